@@ -1,16 +1,15 @@
-#![feature(test)]
+use std::hint::black_box;
+use std::time::Instant;
 
-extern crate test;
+const NITER: u32 = 100;
 
-const NITER: usize = 100;
-
-#[bench]
-fn bench_read(bencher: &mut test::Bencher) {
+fn main() {
     let gpx_bytes = include_bytes!("../tests/fixtures/wikipedia_example.gpx");
 
-    bencher.iter(|| {
-        for _ in 0..NITER {
-            test::black_box(gpx::read(&gpx_bytes[..]).unwrap());
-        }
-    });
+    let start = Instant::now();
+    for _ in 0..NITER {
+        black_box(gpx::read(&gpx_bytes[..]).unwrap());
+    }
+
+    println!("bench_read: {:?}/iter", start.elapsed() / NITER);
 }
@@ -0,0 +1,79 @@
+//! Reads GPX from a `tokio::io::AsyncRead`, with the `async` feature.
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::errors::*;
+use crate::reader::{read, read_with_options, ParserOptions};
+use crate::Gpx;
+
+/// Reads an activity in GPX format from an asynchronous reader.
+///
+/// The document is read without blocking on the reader, then parsed exactly
+/// like `read` does.
+///
+/// ```
+/// use gpx::read_async;
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let data = "<gpx version=\"1.1\"><wpt lat=\"37.24\" lon=\"-121.97\"/></gpx>";
+///
+/// let gpx = read_async(data.as_bytes()).await.unwrap();
+/// assert_eq!(gpx.waypoints.len(), 1);
+/// # });
+/// ```
+pub async fn read_async<R: AsyncRead + Unpin>(reader: R) -> Result<Gpx> {
+    read(read_all(reader).await?.as_slice())
+}
+
+/// Reads an activity in GPX format from an asynchronous reader, according
+/// to `options`, like `read_with_options` does.
+pub async fn read_async_with_options<R: AsyncRead + Unpin>(
+    reader: R,
+    options: &ParserOptions,
+) -> Result<(Gpx, Vec<Error>)> {
+    read_with_options(read_all(reader).await?.as_slice(), options)
+}
+
+async fn read_all<R: AsyncRead + Unpin>(mut reader: R) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    reader
+        .read_to_end(&mut buffer)
+        .await
+        .chain_err(|| "error while reading gpx")?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_async, read_async_with_options};
+    use crate::{read, ParserOptions};
+
+    const DATA: &str = "<gpx version=\"1.1\"><trk><trkseg>
+        <trkpt lat=\"37.24\" lon=\"-121.97\"><ele>553.21</ele></trkpt>
+        <trkpt lat=\"37.25\" lon=\"east\"/>
+    </trkseg></trk></gpx>";
+
+    #[tokio::test]
+    async fn same_as_sync_reader() {
+        let data = std::fs::read("tests/fixtures/garmin-activity.gpx").unwrap();
+
+        let gpx = read_async(data.as_slice()).await.unwrap();
+
+        assert_eq!(gpx, read(data.as_slice()).unwrap());
+    }
+
+    #[tokio::test]
+    async fn read_with_options() {
+        let options = ParserOptions {
+            lenient: true,
+            ..Default::default()
+        };
+
+        assert!(read_async(DATA.as_bytes()).await.is_err());
+        let (gpx, skipped) = read_async_with_options(DATA.as_bytes(), &options)
+            .await
+            .unwrap();
+        assert_eq!(gpx.tracks[0].segments[0].points.len(), 1);
+        assert_eq!(skipped.len(), 1);
+    }
+}
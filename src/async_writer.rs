@@ -0,0 +1,146 @@
+//! Writes GPX to a `tokio::io::AsyncWrite`, with the `async` feature.
+
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::errors::*;
+use crate::writer::{Chunks, WriterOptions};
+use crate::Gpx;
+
+/// Writes an activity to GPX format on an asynchronous writer.
+///
+/// The document is serialized exactly like `write` does, and written out as
+/// it's serialized, in chunks of about 8 KiB. Only a chunk is held in memory
+/// at once, besides the document itself, and serializing a chunk is the only
+/// work done without yielding to the runtime.
+///
+/// ```
+/// use gpx::{write_async, Gpx, GpxVersion};
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let gpx = Gpx::builder(GpxVersion::Gpx11).creator("my-app").build();
+/// let mut buffer = Vec::new();
+/// write_async(&gpx, &mut buffer).await.unwrap();
+///
+/// assert_eq!(gpx::read(buffer.as_slice()).unwrap().creator, gpx.creator);
+/// # });
+/// ```
+pub async fn write_async<W: AsyncWrite + Unpin>(gpx: &Gpx, writer: W) -> Result<()> {
+    write_async_with_options(gpx, writer, &WriterOptions::default()).await
+}
+
+/// Writes an activity to GPX format on an asynchronous writer, formatted
+/// according to `options`.
+///
+/// Like `write_async`, this writes the document in chunks as it's
+/// serialized.
+pub async fn write_async_with_options<W: AsyncWrite + Unpin>(
+    gpx: &Gpx,
+    mut writer: W,
+    options: &WriterOptions,
+) -> Result<()> {
+    for chunk in Chunks::new(gpx, options) {
+        writer
+            .write_all(&chunk?)
+            .await
+            .chain_err(|| "error while writing gpx")?;
+    }
+    writer
+        .flush()
+        .await
+        .chain_err(|| "error while flushing gpx writer")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use tokio::io::AsyncWrite;
+
+    use super::{write_async, write_async_with_options};
+    use crate::{write_with_options, Gpx, GpxVersion, Route, Track, Waypoint, WriterOptions};
+
+    fn example() -> Gpx {
+        let point = Waypoint::builder().lat(37.24).lon(-121.97).build().unwrap();
+        let track = Track::builder().name("Walk").points(vec![point; 3]).build();
+        Gpx::builder(GpxVersion::Gpx11).track(track).build()
+    }
+
+    #[tokio::test]
+    async fn same_as_sync_writer() {
+        let gpx = example();
+        let options = WriterOptions {
+            indent: None,
+            coordinate_precision: Some(1),
+            ..Default::default()
+        };
+
+        let mut expected = Vec::new();
+        write_with_options(&gpx, &mut expected, &options).unwrap();
+        let mut written = Vec::new();
+        write_async_with_options(&gpx, &mut written, &options)
+            .await
+            .unwrap();
+
+        assert_eq!(written, expected);
+    }
+
+    /// Records every write it's given.
+    #[derive(Default)]
+    struct Recorder {
+        writes: Vec<Vec<u8>>,
+    }
+
+    impl AsyncWrite for Recorder {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.writes.push(buf.to_vec());
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn writes_in_chunks() {
+        let point = Waypoint::builder().lat(37.24).lon(-121.97).build().unwrap();
+        let mut route = Route::new();
+        route.points = vec![point.clone(); 100];
+        let track = Track::builder().points(vec![point.clone(); 1000]).build();
+        let gpx = Gpx::builder(GpxVersion::Gpx11)
+            .waypoint(point)
+            .route(route)
+            .track(track)
+            .build();
+
+        let mut expected = Vec::new();
+        write_with_options(&gpx, &mut expected, &WriterOptions::default()).unwrap();
+        let mut recorder = Recorder::default();
+        write_async(&gpx, &mut recorder).await.unwrap();
+
+        assert!(recorder.writes.len() > 1);
+        assert!(recorder.writes.iter().all(|write| write.len() < 2 * 8192));
+        assert_eq!(recorder.writes.concat(), expected);
+    }
+
+    #[tokio::test]
+    async fn round_trip_gpx() {
+        let gpx = example();
+
+        let mut written = Vec::new();
+        write_async(&gpx, &mut written).await.unwrap();
+        let read_gpx = crate::read(written.as_slice()).unwrap();
+
+        assert_eq!(read_gpx.tracks, gpx.tracks);
+    }
+}
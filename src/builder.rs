@@ -0,0 +1,330 @@
+//! Fluent builders for documents, tracks and waypoints.
+//!
+//! Fields the GPX schema requires are enforced by the builders' types where
+//! possible: a document's version is given up front, and a waypoint can only
+//! be built once both its latitude and longitude are set.
+
+use chrono::{DateTime, Utc};
+use geo_types::Point;
+
+use crate::errors::*;
+use crate::{
+    Degrees, DgpsStationId, Fix, Gpx, GpxVersion, Link, Metadata, Route, Track,
+    TrackPointExtension, TrackSegment, Waypoint,
+};
+
+// Generates the setters for optional fields, each of which sets the field of
+// the value being built.
+macro_rules! optional_setters {
+    ($target:ident; $($(#[$attr:meta])* $field:ident: $ty:ty;)*) => {
+        $(
+            $(#[$attr])*
+            pub fn $field(mut self, $field: impl Into<$ty>) -> Self {
+                self.$target.$field = Some($field.into());
+                self
+            }
+        )*
+    };
+}
+
+/// Marks a coordinate of a `WaypointBuilder` which has not been set yet.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Unset;
+
+/// WaypointBuilder builds a `Waypoint`, created with `Waypoint::builder`.
+///
+/// `Lat` and `Lon` track whether the latitude and longitude have been set;
+/// `build` is only available once both are `f64`.
+#[derive(Clone, Debug)]
+pub struct WaypointBuilder<Lat = Unset, Lon = Unset> {
+    latitude: Lat,
+    longitude: Lon,
+    waypoint: Waypoint,
+}
+
+impl Waypoint {
+    /// Starts building a waypoint.
+    ///
+    /// ```
+    /// extern crate gpx;
+    ///
+    /// use gpx::Waypoint;
+    ///
+    /// fn main() {
+    ///     let wpt = Waypoint::builder()
+    ///         .lat(37.24)
+    ///         .lon(-121.97)
+    ///         .elevation(553.21)
+    ///         .name("Lexington Reservoir")
+    ///         .build()
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(wpt.point().lng(), -121.97);
+    ///     assert_eq!(wpt.name, Some(String::from("Lexington Reservoir")));
+    /// }
+    /// ```
+    pub fn builder() -> WaypointBuilder {
+        WaypointBuilder {
+            latitude: Unset,
+            longitude: Unset,
+            waypoint: Waypoint::default(),
+        }
+    }
+}
+
+impl<Lat, Lon> WaypointBuilder<Lat, Lon> {
+    /// Sets the latitude, which must be in [-90, 90].
+    pub fn lat(self, latitude: f64) -> WaypointBuilder<f64, Lon> {
+        WaypointBuilder {
+            latitude,
+            longitude: self.longitude,
+            waypoint: self.waypoint,
+        }
+    }
+
+    /// Sets the longitude, which must be in [-180, 180].
+    pub fn lon(self, longitude: f64) -> WaypointBuilder<Lat, f64> {
+        WaypointBuilder {
+            latitude: self.latitude,
+            longitude,
+            waypoint: self.waypoint,
+        }
+    }
+
+    /// Adds a link to additional information about the waypoint.
+    pub fn link(mut self, link: Link) -> Self {
+        self.waypoint.links.push(link);
+        self
+    }
+
+    optional_setters! {
+        waypoint;
+        /// Sets the elevation (in meters).
+        elevation: f64;
+        /// Sets the speed (in meters per second). Only written in GPX 1.0.
+        speed: f64;
+        /// Sets the course. Only written in GPX 1.0.
+        course: Degrees;
+        /// Sets the timestamp.
+        time: DateTime<Utc>;
+        /// Sets the offset (in seconds east of UTC) to write the timestamp with.
+        time_offset: i32;
+        /// Sets the GPS name.
+        name: String;
+        /// Sets the GPS comment.
+        comment: String;
+        /// Sets the description.
+        description: String;
+        /// Sets the source of the data.
+        source: String;
+        /// Sets the GPS symbol name.
+        symbol: String;
+        /// Sets the type (classification).
+        _type: String;
+        /// Sets the magnetic variation.
+        magvar: Degrees;
+        /// Sets the height of the geoid (in meters) above WGS 84.
+        geoidheight: f64;
+        /// Sets the type of GPS fix.
+        fix: Fix;
+        /// Sets the number of satellites used.
+        sat: u64;
+        /// Sets the horizontal dilution of precision.
+        hdop: f64;
+        /// Sets the vertical dilution of precision.
+        vdop: f64;
+        /// Sets the positional dilution of precision.
+        pdop: f64;
+        /// Sets the number of seconds since the last DGPS update.
+        dgps_age: f64;
+        /// Sets the ID of the DGPS station used.
+        dgpsid: DgpsStationId;
+        /// Sets the raw XML content of the extensions.
+        extensions: String;
+        /// Sets the Garmin TrackPointExtension sensor data.
+        track_point_extension: TrackPointExtension;
+    }
+}
+
+impl WaypointBuilder<f64, f64> {
+    /// Builds the waypoint, failing if its coordinates are out of range.
+    pub fn build(self) -> Result<Waypoint> {
+        let mut waypoint = self.waypoint;
+        waypoint.set_point(Point::new(self.longitude, self.latitude))?;
+        Ok(waypoint)
+    }
+}
+
+/// TrackBuilder builds a `Track`, created with `Track::builder`.
+#[derive(Clone, Debug, Default)]
+pub struct TrackBuilder {
+    track: Track,
+}
+
+impl Track {
+    /// Starts building a track.
+    ///
+    /// ```
+    /// extern crate gpx;
+    ///
+    /// use gpx::{Track, Waypoint};
+    ///
+    /// fn main() {
+    ///     let start = Waypoint::builder().lat(37.24).lon(-121.97).build().unwrap();
+    ///     let end = Waypoint::builder().lat(37.25).lon(-121.96).build().unwrap();
+    ///
+    ///     let track = Track::builder()
+    ///         .name("Morning ride")
+    ///         .points(vec![start, end])
+    ///         .build();
+    ///
+    ///     assert_eq!(track.segments[0].points.len(), 2);
+    /// }
+    /// ```
+    pub fn builder() -> TrackBuilder {
+        TrackBuilder::default()
+    }
+}
+
+impl TrackBuilder {
+    /// Adds a link to external information about the track.
+    pub fn link(mut self, link: Link) -> Self {
+        self.track.links.push(link);
+        self
+    }
+
+    /// Adds a segment to the track.
+    pub fn segment(mut self, segment: TrackSegment) -> Self {
+        self.track.segments.push(segment);
+        self
+    }
+
+    /// Adds a segment made of the given points to the track.
+    pub fn points<I: IntoIterator<Item = Waypoint>>(self, points: I) -> Self {
+        let mut segment = TrackSegment::new();
+        segment.points.extend(points);
+        self.segment(segment)
+    }
+
+    optional_setters! {
+        track;
+        /// Sets the GPS name.
+        name: String;
+        /// Sets the GPS comment.
+        comment: String;
+        /// Sets the description.
+        description: String;
+        /// Sets the source of the data.
+        source: String;
+        /// Sets the GPS track number.
+        number: u32;
+        /// Sets the type (classification).
+        _type: String;
+        /// Sets the raw XML content of the extensions.
+        extensions: String;
+    }
+
+    /// Builds the track.
+    pub fn build(self) -> Track {
+        self.track
+    }
+}
+
+/// GpxBuilder builds a `Gpx` document, created with `Gpx::builder`.
+#[derive(Clone, Debug)]
+pub struct GpxBuilder {
+    gpx: Gpx,
+}
+
+impl Gpx {
+    /// Starts building a document of the given version.
+    ///
+    /// ```
+    /// extern crate gpx;
+    ///
+    /// use gpx::{Gpx, GpxVersion, Track, Waypoint};
+    ///
+    /// fn main() {
+    ///     let summit = Waypoint::builder().lat(37.74).lon(-119.53).build().unwrap();
+    ///
+    ///     let gpx = Gpx::builder(GpxVersion::Gpx11)
+    ///         .creator("my-app")
+    ///         .waypoint(summit)
+    ///         .track(Track::builder().name("Approach").build())
+    ///         .build();
+    ///
+    ///     assert_eq!(gpx.version, GpxVersion::Gpx11);
+    ///     assert_eq!(gpx.waypoints.len(), 1);
+    /// }
+    /// ```
+    pub fn builder(version: GpxVersion) -> GpxBuilder {
+        GpxBuilder {
+            gpx: Gpx {
+                version,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl GpxBuilder {
+    /// Adds a waypoint to the document.
+    pub fn waypoint(mut self, waypoint: Waypoint) -> Self {
+        self.gpx.waypoints.push(waypoint);
+        self
+    }
+
+    /// Adds a track to the document.
+    pub fn track(mut self, track: Track) -> Self {
+        self.gpx.tracks.push(track);
+        self
+    }
+
+    /// Adds a route to the document.
+    pub fn route(mut self, route: Route) -> Self {
+        self.gpx.routes.push(route);
+        self
+    }
+
+    optional_setters! {
+        gpx;
+        /// Sets the name or URL of the software creating the document.
+        creator: String;
+        /// Sets the metadata about the document.
+        metadata: Metadata;
+    }
+
+    /// Builds the document.
+    pub fn build(self) -> Gpx {
+        self.gpx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Link, Waypoint};
+
+    #[test]
+    fn build_waypoint_out_of_range() {
+        let result = Waypoint::builder().lon(1.234).lat(91.).build();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn build_waypoint_links() {
+        let link = Link {
+            href: String::from("https://example.com"),
+            ..Default::default()
+        };
+        let waypoint = Waypoint::builder()
+            .link(link.clone())
+            .lat(2.345)
+            .lon(1.234)
+            .link(link.clone())
+            .build()
+            .unwrap();
+
+        assert_eq!(waypoint.links, vec![link.clone(), link]);
+    }
+}
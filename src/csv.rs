@@ -0,0 +1,198 @@
+//! Reads points from CSV or TSV, with the `csv` feature.
+
+use std::io::Read;
+
+use chrono::{DateTime, NaiveDateTime, ParseResult, TimeZone, Utc};
+
+use crate::errors::*;
+use crate::{Gpx, GpxVersion, Track, TrackSegment, Waypoint};
+
+/// CsvOptions controls how CSV is read: its delimiter, which columns hold
+/// what, and how times are written.
+///
+/// Columns are found by the names in the header row. The default reads
+/// comma-separated `lat`, `lon`, `ele` and `time` columns, with times in RFC
+/// 3339; the elevation and time are left out if there is no such column.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsvOptions {
+    /// Byte separating the fields, like `b','` or `b'\t'` for TSV.
+    pub delimiter: u8,
+
+    /// Name of the latitude column.
+    pub latitude: String,
+
+    /// Name of the longitude column.
+    pub longitude: String,
+
+    /// Name of the elevation column, or `None` to not read elevations.
+    pub elevation: Option<String>,
+
+    /// Name of the time column, or `None` to not read times.
+    pub time: Option<String>,
+
+    /// Format of the times, as for `chrono`'s `parse_from_str`, or `None` for
+    /// RFC 3339. Times without an offset are taken as UTC.
+    pub time_format: Option<String>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> CsvOptions {
+        CsvOptions {
+            delimiter: b',',
+            latitude: String::from("lat"),
+            longitude: String::from("lon"),
+            elevation: Some(String::from("ele")),
+            time: Some(String::from("time")),
+            time_format: None,
+        }
+    }
+}
+
+/// Reads the rows of CSV as the points of a single track, like logged by GPS
+/// loggers.
+///
+/// ```
+/// use gpx::read_csv;
+///
+/// let data = "lat,lon,ele,time\n37.24,-121.97,553.21,2020-06-01T12:00:00Z\n37.25,-121.96,,\n";
+///
+/// let gpx = read_csv(data.as_bytes()).unwrap();
+/// let points = &gpx.tracks[0].segments[0].points;
+/// assert_eq!(points.len(), 2);
+/// assert_eq!(points[0].elevation, Some(553.21));
+/// assert_eq!(points[1].time, None);
+/// ```
+pub fn read_csv<R: Read>(reader: R) -> Result<Gpx> {
+    read_csv_with_options(reader, &CsvOptions::default())
+}
+
+/// Reads the rows of CSV as the points of a single track, laid out according
+/// to `options`.
+///
+/// ```
+/// use gpx::{read_csv_with_options, CsvOptions};
+///
+/// let data = "when\tlatitude\tlongitude\n01/06/2020 12:00:00\t37.24\t-121.97\n";
+/// let options = CsvOptions {
+///     delimiter: b'\t',
+///     latitude: String::from("latitude"),
+///     longitude: String::from("longitude"),
+///     elevation: None,
+///     time: Some(String::from("when")),
+///     time_format: Some(String::from("%d/%m/%Y %H:%M:%S")),
+/// };
+///
+/// let gpx = read_csv_with_options(data.as_bytes(), &options).unwrap();
+/// assert!(gpx.tracks[0].segments[0].points[0].time.is_some());
+/// ```
+pub fn read_csv_with_options<R: Read>(reader: R, options: &CsvOptions) -> Result<Gpx> {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .delimiter(options.delimiter)
+        .from_reader(reader);
+    let headers = reader
+        .headers()
+        .chain_err(|| "error while reading csv header")?
+        .clone();
+    let column = |name: &str| -> Result<usize> {
+        headers
+            .iter()
+            .position(|header| header.trim() == name)
+            .ok_or_else(|| Error::from(format!("csv has no {} column", name)))
+    };
+    let latitude = column(&options.latitude)?;
+    let longitude = column(&options.longitude)?;
+    // Optional columns are only read if the file has them.
+    let elevation = options
+        .elevation
+        .as_deref()
+        .and_then(|name| column(name).ok());
+    let time = options.time.as_deref().and_then(|name| column(name).ok());
+
+    let mut segment = TrackSegment::new();
+    for (index, record) in reader.records().enumerate() {
+        let record = record.chain_err(|| "error while reading csv")?;
+        let field = |column: Option<usize>| {
+            column
+                .and_then(|column| record.get(column))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+        let number = |column: usize, name: &str| -> Result<f64> {
+            field(Some(column))
+                .unwrap_or_default()
+                .parse()
+                .chain_err(|| format!("error while parsing {} of csv row {}", name, index + 1))
+        };
+        let mut waypoint = Waypoint::try_new(
+            number(latitude, "latitude")?,
+            number(longitude, "longitude")?,
+        )?;
+        if let Some(column) = elevation.filter(|&column| field(Some(column)).is_some()) {
+            waypoint.elevation = Some(number(column, "elevation")?);
+        }
+        if let Some(value) = field(time) {
+            waypoint.time = Some(
+                parse_time(value, options.time_format.as_deref())
+                    .chain_err(|| format!("error while parsing time of csv row {}", index + 1))?,
+            );
+        }
+        segment.points.push(waypoint);
+    }
+
+    let mut track = Track::new();
+    track.segments.push(segment);
+    Ok(Gpx {
+        version: GpxVersion::Gpx11,
+        tracks: vec![track],
+        ..Default::default()
+    })
+}
+
+fn parse_time(value: &str, format: Option<&str>) -> ParseResult<DateTime<Utc>> {
+    let time = match format {
+        None => DateTime::parse_from_rfc3339(value)?,
+        Some(format) => match DateTime::parse_from_str(value, format) {
+            Ok(time) => time,
+            Err(_) => {
+                let time = NaiveDateTime::parse_from_str(value, format)?;
+                return Ok(Utc.from_utc_datetime(&time));
+            }
+        },
+    };
+    Ok(time.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_csv, read_csv_with_options, CsvOptions};
+
+    #[test]
+    fn read_without_optional_columns() {
+        let gpx = read_csv("lon,lat\n-121.97,37.24\n".as_bytes()).unwrap();
+
+        let point = &gpx.tracks[0].segments[0].points[0];
+        assert_eq!(point.point().lat(), 37.24);
+        assert_eq!(point.elevation, None);
+    }
+
+    #[test]
+    fn read_time_with_offset() {
+        let options = CsvOptions {
+            time_format: Some(String::from("%Y-%m-%d %H:%M:%S %z")),
+            ..Default::default()
+        };
+        let data = "lat,lon,time\n37.24,-121.97,2020-06-01 14:00:00 +0200\n";
+
+        let gpx = read_csv_with_options(data.as_bytes(), &options).unwrap();
+        let time = gpx.tracks[0].segments[0].points[0].time.unwrap();
+        assert_eq!(time.to_rfc3339(), "2020-06-01T12:00:00+00:00");
+    }
+
+    #[test]
+    fn read_invalid_rows() {
+        assert!(read_csv("lat\n37.24\n".as_bytes()).is_err());
+        assert!(read_csv("lat,lon\n37.24,west\n".as_bytes()).is_err());
+        assert!(read_csv("lat,lon\n121.97,37.24\n".as_bytes()).is_err());
+        assert!(read_csv("lat,lon,time\n37.24,-121.97,noon\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,101 @@
+//! Reads directories of GPX files in parallel, with the `rayon` feature.
+
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+
+use crate::errors::*;
+use crate::reader::read;
+use crate::Gpx;
+
+/// Reads every file ending in `.gpx` or `.gpx.gz` in the directory at
+/// `path` and the directories within it, in parallel. Gives each file's path
+/// along with what reading it gave, sorted by path, failing only if a
+/// directory can't be listed.
+///
+/// Compressed files are only read with the `gzip` feature.
+///
+/// ```
+/// let files = gpx::read_dir("tests/fixtures").unwrap();
+/// for (path, gpx) in &files {
+///     assert!(gpx.is_ok(), "{}", path.display());
+/// }
+/// ```
+pub fn read_dir<P: AsRef<Path>>(path: P) -> Result<Vec<(PathBuf, Result<Gpx>)>> {
+    let mut paths = Vec::new();
+    find(path.as_ref(), &mut paths)?;
+    paths.sort();
+    Ok(paths
+        .into_par_iter()
+        .map(|path| {
+            let gpx = read_file(&path);
+            (path, gpx)
+        })
+        .collect())
+}
+
+/// Adds the paths of the GPX files in the directory at `path`, and in the
+/// directories within it, to `paths`.
+fn find(path: &Path, paths: &mut Vec<PathBuf>) -> Result<()> {
+    let entries =
+        fs::read_dir(path).chain_err(|| format!("error while listing {}", path.display()))?;
+    for entry in entries {
+        let entry = entry.chain_err(|| format!("error while listing {}", path.display()))?;
+        let path = entry.path();
+        if path.is_dir() {
+            find(&path, paths)?;
+        } else if is_gpx(&path) {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_gpx(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| {
+            let name = name.to_ascii_lowercase();
+            name.ends_with(".gpx") || name.ends_with(".gpx.gz")
+        })
+        .unwrap_or(false)
+}
+
+fn read_file(path: &Path) -> Result<Gpx> {
+    let file = File::open(path).chain_err(|| format!("error while opening {}", path.display()))?;
+    read(BufReader::new(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{is_gpx, read_dir};
+
+    #[test]
+    fn reads_fixtures() {
+        let files = read_dir("tests/fixtures").unwrap();
+
+        let find = |name: &str| {
+            files
+                .iter()
+                .find(|(path, _)| path == &Path::new("tests/fixtures").join(name))
+        };
+        let (_, gpx) = find("wikipedia_example.gpx").unwrap();
+        assert_eq!(gpx.as_ref().unwrap().tracks.len(), 1);
+        assert!(find("badcharacter.xml").is_none());
+        assert!(files.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+        assert!(read_dir("tests/missing").is_err());
+    }
+
+    #[test]
+    fn gpx_names() {
+        assert!(is_gpx(Path::new("a/run.gpx")));
+        assert!(is_gpx(Path::new("RIDE.GPX.gz")));
+        assert!(!is_gpx(Path::new("ride.gpx.zip")));
+        assert!(!is_gpx(Path::new("gpx")));
+    }
+}
@@ -1,7 +1,49 @@
 //! errors provides error generics for the gpx parser.
 
+// error_chain emits cfgs that are unknown to the current compiler.
+#![allow(unexpected_cfgs)]
+
+use std::fmt;
+
 use error_chain::*;
 
+/// Location tells where in a document an error was found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    /// Number of bytes read from the document when the error was found. This
+    /// is the end of the last XML event read, or a few bytes past it, rather
+    /// than where the faulty content starts.
+    pub offset: u64,
+
+    /// Line of the last XML event read, counting from 1.
+    pub line: u64,
+
+    /// Column of the last XML event read, counting from 1.
+    pub column: u64,
+
+    /// Path of the element the error was found in, like
+    /// `gpx/trk[2]/trkseg[0]/trkpt[913]/ele[0]`, where the numbers are the
+    /// indices of elements among their siblings of the same name.
+    pub path: String,
+
+    /// The last text read, like the value that failed to parse.
+    pub text: Option<String>,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} (line {}, column {}, byte {})",
+            self.path, self.line, self.column, self.offset
+        )?;
+        if let Some(ref text) = self.text {
+            write!(f, " near {:?}", text)?;
+        }
+        Ok(())
+    }
+}
+
 // This gives us our error boilerplate macros.
 error_chain! {
     errors {
@@ -31,5 +73,55 @@ error_chain! {
             description("invalid element, lacks required attribute")
             display("invalid element, {} lacks required attribute {}", parent, attr)
         }
+
+        /// Cancelled signifies that reading was stopped through a
+        /// `Cancellation`.
+        Cancelled {
+            description("reading cancelled")
+            display("reading cancelled")
+        }
+
+        /// LimitExceeded signifies that a document goes over a limit set in
+        /// `ParserOptions`, like on its number of points.
+        LimitExceeded(what: &'static str, limit: u64) {
+            description("document over a limit")
+            display("document over the limit of {} {}", limit, what)
+        }
+
+        /// NonConforming signifies that a document read in strict mode
+        /// doesn't conform to the GPX schema.
+        NonConforming(reason: String) {
+            description("document doesn't conform to the GPX schema")
+            display("document doesn't conform to the GPX schema: {}", reason)
+        }
+
+        /// InvalidDocument tells where in a document the error it is the
+        /// cause of was found, and is chained to that error's own cause.
+        InvalidDocument(location: Location) {
+            description("invalid document")
+            display("invalid document at {}", location)
+        }
+    }
+}
+
+impl Error {
+    /// Gives where in the document the error was found, if it was found
+    /// while reading one.
+    pub fn location(&self) -> Option<&Location> {
+        let mut cause = std::error::Error::source(self);
+        while let Some(error) = cause {
+            if let Some(Error(ErrorKind::InvalidDocument(location), _)) = error.downcast_ref() {
+                return Some(location);
+            }
+            cause = error.source();
+        }
+        None
+    }
+
+    /// Gives the error located in the document, keeping its kind, with an
+    /// `InvalidDocument` between it and its cause.
+    pub(crate) fn located(self, location: Location) -> Error {
+        let Error(kind, state) = self;
+        Error::with_chain(Error(ErrorKind::InvalidDocument(location), state), kind)
     }
 }
@@ -0,0 +1,220 @@
+//! Reads activities recorded in the FIT format, with the `fit` feature.
+
+use std::fmt::Write as _;
+use std::io::Read;
+
+use chrono::Utc;
+use fitparser::profile::MesgNum;
+use fitparser::{FitDataRecord, Value};
+
+use crate::errors::*;
+use crate::{Gpx, GpxVersion, Track, TrackPointExtension, TrackSegment, Waypoint};
+
+/// Namespace the sensor readings of points are written in.
+const TRACK_POINT_EXTENSION_NS: &str = "http://www.garmin.com/xmlschemas/TrackPointExtension/v2";
+
+/// Degrees per semicircle, the unit of FIT positions.
+const DEGREES_PER_SEMICIRCLE: f64 = 180. / 2_147_483_648.;
+
+/// Reads an activity in FIT format, as recorded by Garmin, Wahoo and other
+/// devices.
+///
+/// The `record` messages become the points of a single track, typed after
+/// the sport of the session. Records without a position, like those of
+/// indoor activities, are skipped, and a file with no positions at all has
+/// no track. Heart rate, cadence, power and temperature are kept in the
+/// points' `TrackPointExtension`, and written to their extensions (apart
+/// from the power, which Garmin's schema has no room for).
+///
+/// ```no_run
+/// use std::fs::File;
+/// use std::io::BufReader;
+///
+/// let file = File::open("activity.fit").unwrap();
+/// let gpx = gpx::read_fit(BufReader::new(file)).unwrap();
+/// println!("{} points", gpx.tracks[0].segments[0].points.len());
+/// ```
+pub fn read_fit<R: Read>(mut reader: R) -> Result<Gpx> {
+    let records = fitparser::from_reader(&mut reader).chain_err(|| "error while parsing fit")?;
+    from_records(&records)
+}
+
+fn from_records(records: &[FitDataRecord]) -> Result<Gpx> {
+    let mut track = Track::new();
+    let mut segment = TrackSegment::new();
+    for record in records {
+        match record.kind() {
+            MesgNum::Record => {
+                if let Some(point) = waypoint(record)? {
+                    segment.points.push(point);
+                }
+            }
+            MesgNum::Session if track._type.is_none() => {
+                if let Some(Value::String(sport)) = field(record, "sport") {
+                    track._type = Some(sport.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut gpx = Gpx {
+        version: GpxVersion::Gpx11,
+        ..Default::default()
+    };
+    if !segment.points.is_empty() {
+        track.segments.push(segment);
+        gpx.tracks.push(track);
+    }
+    gpx.register_namespace("gpxtpx", TRACK_POINT_EXTENSION_NS);
+    Ok(gpx)
+}
+
+/// Makes a waypoint of a `record` message, if it has a position.
+fn waypoint(record: &FitDataRecord) -> Result<Option<Waypoint>> {
+    let (latitude, longitude) = match (
+        number(record, "position_lat"),
+        number(record, "position_long"),
+    ) {
+        (Some(latitude), Some(longitude)) => (
+            latitude * DEGREES_PER_SEMICIRCLE,
+            longitude * DEGREES_PER_SEMICIRCLE,
+        ),
+        _ => return Ok(None),
+    };
+    let mut waypoint = Waypoint::try_new(latitude, longitude)?;
+    if let Some(Value::Timestamp(time)) = field(record, "timestamp") {
+        waypoint.time = Some(time.with_timezone(&Utc));
+    }
+    waypoint.elevation = number(record, "enhanced_altitude").or_else(|| number(record, "altitude"));
+
+    let extension = TrackPointExtension {
+        atemp: number(record, "temperature"),
+        hr: number(record, "heart_rate").map(|hr| hr as u8),
+        cad: number(record, "cadence").map(|cad| cad as u8),
+        power: number(record, "power").map(|power| power as u16),
+        ..Default::default()
+    };
+    if extension != TrackPointExtension::default() {
+        waypoint.extensions = Some(extension_xml(&extension));
+        waypoint.track_point_extension = Some(extension);
+    }
+    Ok(Some(waypoint))
+}
+
+/// Gives the raw XML of the sensor readings, in the schema's order.
+fn extension_xml(extension: &TrackPointExtension) -> String {
+    let mut xml = String::from("<gpxtpx:TrackPointExtension>");
+    if let Some(atemp) = extension.atemp {
+        let _ = write!(xml, "<gpxtpx:atemp>{}</gpxtpx:atemp>", atemp);
+    }
+    if let Some(hr) = extension.hr {
+        let _ = write!(xml, "<gpxtpx:hr>{}</gpxtpx:hr>", hr);
+    }
+    if let Some(cad) = extension.cad {
+        let _ = write!(xml, "<gpxtpx:cad>{}</gpxtpx:cad>", cad);
+    }
+    xml.push_str("</gpxtpx:TrackPointExtension>");
+    xml
+}
+
+fn field<'a>(record: &'a FitDataRecord, name: &str) -> Option<&'a Value> {
+    record
+        .fields()
+        .iter()
+        .find(|field| field.name() == name)
+        .map(|field| field.value())
+}
+
+/// Gives the value of a numeric field, if the record has it.
+fn number(record: &FitDataRecord, name: &str) -> Option<f64> {
+    match *field(record, name)? {
+        Value::SInt8(value) => Some(value.into()),
+        Value::UInt8(value) | Value::UInt8z(value) | Value::Byte(value) => Some(value.into()),
+        Value::SInt16(value) => Some(value.into()),
+        Value::UInt16(value) | Value::UInt16z(value) => Some(value.into()),
+        Value::SInt32(value) => Some(value.into()),
+        Value::UInt32(value) | Value::UInt32z(value) => Some(value.into()),
+        Value::Float32(value) => Some(value.into()),
+        Value::Float64(value) => Some(value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::BufReader;
+
+    use fitparser::profile::MesgNum;
+    use fitparser::{FitDataField, FitDataRecord, Value};
+
+    use super::{extension_xml, from_records, read_fit, DEGREES_PER_SEMICIRCLE};
+    use crate::TrackPointExtension;
+
+    #[test]
+    fn read_activity() {
+        let file = File::open("tests/fixtures/activity.fit").unwrap();
+        let gpx = read_fit(BufReader::new(file)).unwrap();
+
+        assert_eq!(gpx.tracks.len(), 1);
+        assert_eq!(gpx.tracks[0]._type.as_deref(), Some("cycling"));
+        // The record without a position is skipped.
+        let points = &gpx.tracks[0].segments[0].points;
+        assert_eq!(points.len(), 2);
+        assert!((points[0].point().lat() - 37.24).abs() < 1e-6);
+        assert!((points[0].point().lng() + 121.97).abs() < 1e-6);
+        assert!((points[0].elevation.unwrap() - 553.2).abs() < 1e-6);
+        assert_eq!(
+            points[0].time.unwrap().to_rfc3339(),
+            "2020-06-01T12:00:00+00:00"
+        );
+        let extension = points[0].track_point_extension.as_ref().unwrap();
+        assert_eq!(
+            (extension.hr, extension.cad, extension.power),
+            (Some(142), Some(88), Some(250))
+        );
+        // Readings the device didn't have are left out.
+        let extension = points[1].track_point_extension.as_ref().unwrap();
+        assert_eq!(
+            (extension.hr, extension.cad, extension.power),
+            (Some(144), None, None)
+        );
+    }
+
+    #[test]
+    fn no_track_without_positions() {
+        let field = |name: &str, number, value| {
+            FitDataField::new(name.to_string(), number, None, value, String::new())
+        };
+        let mut record = FitDataRecord::new(MesgNum::Record);
+        record.push(field("heart_rate", 3, Value::UInt8(142)));
+        let mut session = FitDataRecord::new(MesgNum::Session);
+        session.push(field("sport", 5, Value::String("training".to_string())));
+
+        let gpx = from_records(&[record.clone(), record, session]).unwrap();
+
+        assert!(gpx.tracks.is_empty());
+    }
+
+    #[test]
+    fn semicircles_to_degrees() {
+        assert_eq!(1_073_741_824. * DEGREES_PER_SEMICIRCLE, 90.);
+        assert_eq!(-2_147_483_648. * DEGREES_PER_SEMICIRCLE, -180.);
+    }
+
+    #[test]
+    fn sensor_readings_as_xml() {
+        let extension = TrackPointExtension {
+            hr: Some(142),
+            cad: Some(88),
+            power: Some(250),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            extension_xml(&extension),
+            "<gpxtpx:TrackPointExtension><gpxtpx:hr>142</gpxtpx:hr><gpxtpx:cad>88</gpxtpx:cad></gpxtpx:TrackPointExtension>"
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Builds GPX from GeoJSON, with the `geojson` feature.
+
+use std::convert::TryFrom;
+use std::io::Read;
+
+use ::geojson::{Feature, FeatureCollection, GeoJson, Geometry, Value};
+
+use crate::errors::*;
+use crate::{Gpx, GpxVersion, Track, TrackSegment, Waypoint};
+
+/// Reads a GeoJSON `FeatureCollection` into a GPX document, like
+/// `from_geojson` does.
+///
+/// ```
+/// use gpx::read_geojson;
+///
+/// let data = r#"{
+///     "type": "FeatureCollection",
+///     "features": [{
+///         "type": "Feature",
+///         "properties": { "name": "Lunch" },
+///         "geometry": { "type": "Point", "coordinates": [-121.97, 37.24, 553.21] }
+///     }]
+/// }"#;
+///
+/// let gpx = read_geojson(data.as_bytes()).unwrap();
+/// assert_eq!(gpx.waypoints[0].name.as_deref(), Some("Lunch"));
+/// assert_eq!(gpx.waypoints[0].elevation, Some(553.21));
+/// ```
+pub fn read_geojson<R: Read>(mut reader: R) -> Result<Gpx> {
+    let mut text = String::new();
+    reader
+        .read_to_string(&mut text)
+        .chain_err(|| "error while reading geojson")?;
+    let geojson = text
+        .parse::<GeoJson>()
+        .chain_err(|| "error while parsing geojson")?;
+    let collection =
+        FeatureCollection::try_from(geojson).chain_err(|| "geojson is not a FeatureCollection")?;
+    from_geojson(&collection)
+}
+
+/// Builds a GPX 1.1 document from a GeoJSON `FeatureCollection`.
+///
+/// Points become waypoints, and each line string or multi line string
+/// becomes a track, with a segment per line. The `name` and `description`
+/// properties of a feature are kept as the name and description of what it
+/// becomes, and a third coordinate as the elevation. Polygons, which GPX has
+/// no room for, are skipped.
+pub fn from_geojson(collection: &FeatureCollection) -> Result<Gpx> {
+    let mut gpx = Gpx {
+        version: GpxVersion::Gpx11,
+        ..Default::default()
+    };
+    for feature in &collection.features {
+        if let Some(ref geometry) = feature.geometry {
+            add_geometry(&mut gpx, feature, geometry)?;
+        }
+    }
+    Ok(gpx)
+}
+
+fn add_geometry(gpx: &mut Gpx, feature: &Feature, geometry: &Geometry) -> Result<()> {
+    match geometry.value {
+        Value::Point(ref position) => gpx.waypoints.push(waypoint(feature, position)?),
+        Value::MultiPoint(ref positions) => {
+            for position in positions {
+                gpx.waypoints.push(waypoint(feature, position)?);
+            }
+        }
+        Value::LineString(ref line) => gpx.tracks.push(track(feature, std::slice::from_ref(line))?),
+        Value::MultiLineString(ref lines) => gpx.tracks.push(track(feature, lines)?),
+        Value::GeometryCollection(ref geometries) => {
+            for geometry in geometries {
+                add_geometry(gpx, feature, geometry)?;
+            }
+        }
+        // Polygons have no equivalent in GPX.
+        _ => {}
+    }
+    Ok(())
+}
+
+fn waypoint(feature: &Feature, position: &[f64]) -> Result<Waypoint> {
+    let mut waypoint = point(position)?;
+    waypoint.name = string_property(feature, "name");
+    waypoint.description = string_property(feature, "description");
+    Ok(waypoint)
+}
+
+fn track(feature: &Feature, lines: &[Vec<Vec<f64>>]) -> Result<Track> {
+    let mut track = Track::new();
+    track.name = string_property(feature, "name");
+    track.description = string_property(feature, "description");
+    for line in lines {
+        let mut segment = TrackSegment::new();
+        for position in line {
+            segment.points.push(point(position)?);
+        }
+        track.segments.push(segment);
+    }
+    Ok(track)
+}
+
+/// Makes a waypoint of a GeoJSON position, `[longitude, latitude]` with an
+/// optional elevation.
+fn point(position: &[f64]) -> Result<Waypoint> {
+    match *position {
+        [longitude, latitude, ref rest @ ..] => {
+            let mut waypoint = Waypoint::try_new(latitude, longitude)?;
+            waypoint.elevation = rest.first().cloned();
+            Ok(waypoint)
+        }
+        _ => Err(Error::from(format!(
+            "geojson position {:?} lacks a coordinate",
+            position
+        ))),
+    }
+}
+
+fn string_property(feature: &Feature, name: &str) -> Option<String> {
+    feature
+        .property(name)
+        .and_then(|value| value.as_str())
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_geojson;
+
+    #[test]
+    fn read_lines_as_tracks() {
+        let data = r#"{
+            "type": "FeatureCollection",
+            "features": [
+                {
+                    "type": "Feature",
+                    "properties": { "name": "Loop" },
+                    "geometry": {
+                        "type": "MultiLineString",
+                        "coordinates": [[[-121.97, 37.24], [-121.96, 37.25]], [[-121.95, 37.26]]]
+                    }
+                },
+                {
+                    "type": "Feature",
+                    "properties": null,
+                    "geometry": { "type": "LineString", "coordinates": [[-121.97, 37.24, 12.5]] }
+                },
+                {
+                    "type": "Feature",
+                    "properties": null,
+                    "geometry": { "type": "Polygon", "coordinates": [] }
+                }
+            ]
+        }"#;
+
+        let gpx = read_geojson(data.as_bytes()).unwrap();
+
+        assert!(gpx.waypoints.is_empty());
+        assert_eq!(gpx.tracks.len(), 2);
+        assert_eq!(gpx.tracks[0].name.as_deref(), Some("Loop"));
+        assert_eq!(gpx.tracks[0].segments.len(), 2);
+        assert_eq!(gpx.tracks[0].segments[0].points[1].point().lat(), 37.25);
+        assert_eq!(gpx.tracks[1].segments[0].points[0].elevation, Some(12.5));
+    }
+
+    #[test]
+    fn read_out_of_range() {
+        let data = r#"{
+            "type": "FeatureCollection",
+            "features": [{
+                "type": "Feature",
+                "properties": null,
+                "geometry": { "type": "Point", "coordinates": [37.24, -121.97] }
+            }]
+        }"#;
+
+        assert!(read_geojson(data.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn read_not_feature_collection() {
+        let data = r#"{ "type": "Point", "coordinates": [-121.97, 37.24] }"#;
+
+        assert!(read_geojson(data.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+//! Reads and writes gzip-compressed GPX (`.gpx.gz`), with the `gzip` feature.
+
+use std::io::{self, Chain, Cursor, Read, Write};
+
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::errors::*;
+use crate::reader::read;
+use crate::writer::{write_with_options, WriterOptions};
+use crate::Gpx;
+
+/// Bytes gzip streams start with.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Reads an activity in gzip-compressed GPX format.
+///
+/// ```
+/// use gpx::{read_gzip, write_gzip, Gpx, GpxVersion};
+///
+/// let gpx = Gpx::builder(GpxVersion::Gpx11).creator("my-app").build();
+/// let mut compressed = Vec::new();
+/// write_gzip(&gpx, &mut compressed).unwrap();
+///
+/// let read_gpx = read_gzip(compressed.as_slice()).unwrap();
+/// assert_eq!(read_gpx.creator, gpx.creator);
+/// ```
+pub fn read_gzip<R: Read>(reader: R) -> Result<Gpx> {
+    read(MultiGzDecoder::new(reader))
+}
+
+/// Input reads GPX which may be gzip-compressed, decompressing it if it is.
+pub(crate) enum Input<R: Read> {
+    Plain(Chain<Cursor<Vec<u8>>, R>),
+    Gzip(Box<MultiGzDecoder<Chain<Cursor<Vec<u8>>, R>>>),
+}
+
+impl<R: Read> Read for Input<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Input::Plain(reader) => reader.read(buf),
+            Input::Gzip(reader) => reader.read(buf),
+        }
+    }
+}
+
+/// Tells from its first bytes whether `reader` is gzip-compressed, and
+/// gives a reader of its decompressed data.
+pub(crate) fn decompress<R: Read>(mut reader: R) -> Result<Input<R>> {
+    let mut magic = Vec::with_capacity(GZIP_MAGIC.len());
+    (&mut reader)
+        .take(GZIP_MAGIC.len() as u64)
+        .read_to_end(&mut magic)
+        .chain_err(|| "error while reading gpx")?;
+    let gzip = magic == GZIP_MAGIC;
+    let reader = Cursor::new(magic).chain(reader);
+    Ok(if gzip {
+        Input::Gzip(Box::new(MultiGzDecoder::new(reader)))
+    } else {
+        Input::Plain(reader)
+    })
+}
+
+/// Tells from its first bytes whether `bytes` are gzip-compressed.
+pub(crate) fn is_compressed(bytes: &[u8]) -> bool {
+    bytes.starts_with(&GZIP_MAGIC)
+}
+
+/// Writes an activity in gzip-compressed GPX format.
+pub fn write_gzip<W: Write>(gpx: &Gpx, writer: W) -> Result<()> {
+    write_gzip_with_options(gpx, writer, &WriterOptions::default())
+}
+
+/// Writes an activity in gzip-compressed GPX format, formatted according to
+/// `options`.
+pub fn write_gzip_with_options<W: Write>(
+    gpx: &Gpx,
+    writer: W,
+    options: &WriterOptions,
+) -> Result<()> {
+    let mut encoder = GzEncoder::new(writer, Compression::default());
+    write_with_options(gpx, &mut encoder, options)?;
+    encoder
+        .finish()
+        .chain_err(|| "error while finishing gzip stream")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_gzip, write_gzip};
+    use crate::{Gpx, GpxVersion, Track, Waypoint};
+
+    #[test]
+    fn round_trip_gpx() {
+        let point = Waypoint::builder().lat(37.24).lon(-121.97).build().unwrap();
+        let track = Track::builder()
+            .name("Walk")
+            .points(vec![point; 1000])
+            .build();
+        let gpx = Gpx::builder(GpxVersion::Gpx11).track(track).build();
+
+        let mut compressed = Vec::new();
+        write_gzip(&gpx, &mut compressed).unwrap();
+        let read_gpx = read_gzip(compressed.as_slice()).unwrap();
+
+        assert_eq!(read_gpx.tracks, gpx.tracks);
+    }
+
+    #[test]
+    fn read_detects_gzip() {
+        let gpx = Gpx::builder(GpxVersion::Gpx11).creator("my-app").build();
+        let mut compressed = Vec::new();
+        write_gzip(&gpx, &mut compressed).unwrap();
+
+        assert_eq!(
+            crate::read(compressed.as_slice()).unwrap().creator,
+            gpx.creator
+        );
+        assert_eq!(Gpx::from_bytes(&compressed).unwrap().creator, gpx.creator);
+        assert!(crate::read("<gpx version=\"1.1\"></gpx>".as_bytes()).is_ok());
+        assert!(crate::read(&[0x1f][..]).is_err());
+    }
+
+    #[test]
+    fn read_not_gzip() {
+        assert!(read_gzip("<gpx version=\"1.1\"></gpx>".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn read_truncated() {
+        let gpx = Gpx::builder(GpxVersion::Gpx11).build();
+        let mut compressed = Vec::new();
+        write_gzip(&gpx, &mut compressed).unwrap();
+
+        assert!(read_gzip(&compressed[..compressed.len() / 2]).is_err());
+    }
+}
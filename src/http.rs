@@ -0,0 +1,108 @@
+//! Downloads and reads GPX from a URL, with the `http` feature.
+
+use std::io;
+use std::pin::pin;
+
+use futures_util::{stream, TryStreamExt};
+use tokio_util::io::StreamReader;
+
+use crate::async_reader::read_async;
+use crate::errors::*;
+use crate::reader::read;
+use crate::Gpx;
+
+impl Gpx {
+    /// Downloads and reads an activity in GPX format from `url`, blocking
+    /// until it's done.
+    ///
+    /// The response body is parsed as it's received. Responses with a gzip
+    /// content encoding are decompressed, and error statuses fail.
+    ///
+    /// This must not be called from within an asynchronous runtime; use
+    /// `from_url_async` there instead.
+    ///
+    /// ```no_run
+    /// use gpx::Gpx;
+    ///
+    /// let gpx = Gpx::from_url("https://example.com/ride.gpx").unwrap();
+    /// println!("{} tracks", gpx.tracks.len());
+    /// ```
+    pub fn from_url(url: &str) -> Result<Gpx> {
+        let response = reqwest::blocking::get(url)
+            .and_then(|response| response.error_for_status())
+            .chain_err(|| format!("error while downloading {}", url))?;
+        read(response)
+    }
+
+    /// Downloads and reads an activity in GPX format from `url` without
+    /// blocking, like `from_url` does, as `read_async` reads.
+    pub async fn from_url_async(url: &str) -> Result<Gpx> {
+        let response = reqwest::get(url)
+            .await
+            .and_then(|response| response.error_for_status())
+            .chain_err(|| format!("error while downloading {}", url))?;
+        let chunks = stream::try_unfold(response, |mut response| async move {
+            let chunk = response.chunk().await?;
+            Ok(chunk.map(|chunk| (chunk, response)))
+        });
+        let body = pin!(StreamReader::new(
+            chunks.map_err(io::Error::other::<reqwest::Error>)
+        ));
+        read_async(body).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use crate::Gpx;
+
+    /// Serves one response of `status` and `body` on a local port, giving
+    /// the URL of it.
+    fn serve(status: &'static str, body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/ride.gpx", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            assert!(stream.read(&mut request).unwrap() > 0);
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .unwrap();
+        });
+        url
+    }
+
+    #[test]
+    fn from_url() {
+        let url = serve(
+            "200 OK",
+            "<gpx version=\"1.1\"><wpt lat=\"1\" lon=\"2\"/></gpx>",
+        );
+        assert_eq!(Gpx::from_url(&url).unwrap().waypoints.len(), 1);
+
+        let url = serve("404 Not Found", "<gpx version=\"1.1\"></gpx>");
+        assert!(Gpx::from_url(&url).is_err());
+    }
+
+    #[tokio::test]
+    async fn from_url_async() {
+        let url = serve(
+            "200 OK",
+            "<gpx version=\"1.1\"><wpt lat=\"1\" lon=\"2\"/></gpx>",
+        );
+        let gpx = Gpx::from_url_async(&url).await.unwrap();
+        assert_eq!(gpx.waypoints.len(), 1);
+
+        let url = serve("404 Not Found", "<gpx version=\"1.1\"></gpx>");
+        assert!(Gpx::from_url_async(&url).await.is_err());
+    }
+}
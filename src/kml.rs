@@ -0,0 +1,525 @@
+//! Reads and writes KML, with the `kml` feature, and reads KMZ with the
+//! `kmz` feature.
+
+#[cfg(feature = "kmz")]
+use std::io::Seek;
+use std::io::{Read, Write};
+
+use chrono::{DateTime, Utc};
+use error_chain::ensure;
+use xml::reader::{EventReader, ParserConfig, XmlEvent as ReaderEvent};
+use xml::writer::{EmitterConfig, EventWriter, XmlEvent};
+#[cfg(feature = "kmz")]
+use zip::ZipArchive;
+
+use crate::errors::*;
+use crate::writer::{write_string, write_string_if_exists, write_xml_event};
+use crate::{Gpx, GpxVersion, Metadata, Route, Track, TrackSegment, Waypoint};
+
+/// Namespace of KML 2.2.
+const KML_NAMESPACE: &str = "http://www.opengis.net/kml/2.2";
+
+/// Namespace of Google's extensions to KML, for `gx:Track`.
+const GX_NAMESPACE: &str = "http://www.google.com/kml/ext/2.2";
+
+/// KmlOptions controls how an activity is written as KML.
+///
+/// The default writes tracks as plain line strings.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KmlOptions {
+    /// Whether to write tracks as `gx:Track`, which keeps the time of every
+    /// point, instead of line strings. Tracks with points without a time are
+    /// still written as line strings.
+    pub gx_track: bool,
+}
+
+/// Writes an activity to KML, for example to view it in Google Earth.
+///
+/// Waypoints become placemarks with a point, and routes and tracks become
+/// placemarks with a line string (or one per segment). The document is named
+/// after the metadata.
+///
+/// ```
+/// use gpx::{write_kml, Gpx, GpxVersion, Waypoint};
+///
+/// let wpt = Waypoint::builder().lat(37.24).lon(-121.97).name("Lunch").build().unwrap();
+/// let gpx = Gpx::builder(GpxVersion::Gpx11).waypoint(wpt).build();
+///
+/// let mut buffer = Vec::new();
+/// write_kml(&gpx, &mut buffer).unwrap();
+///
+/// let kml = String::from_utf8(buffer).unwrap();
+/// assert!(kml.contains("<coordinates>-121.97,37.24</coordinates>"));
+/// ```
+pub fn write_kml<W: Write>(gpx: &Gpx, writer: W) -> Result<()> {
+    write_kml_with_options(gpx, writer, &KmlOptions::default())
+}
+
+/// Writes an activity to KML, formatted according to `options`.
+pub fn write_kml_with_options<W: Write>(gpx: &Gpx, writer: W, options: &KmlOptions) -> Result<()> {
+    let mut writer = EmitterConfig::new()
+        .perform_indent(true)
+        .create_writer(writer);
+    let mut kml = XmlEvent::start_element("kml").default_ns(KML_NAMESPACE);
+    if options.gx_track {
+        kml = kml.ns("gx", GX_NAMESPACE);
+    }
+    write_xml_event(kml, &mut writer)?;
+    write_xml_event(XmlEvent::start_element("Document"), &mut writer)?;
+    if let Some(ref metadata) = gpx.metadata {
+        write_string_if_exists("name", &metadata.name, &mut writer)?;
+        write_string_if_exists("description", &metadata.description, &mut writer)?;
+    }
+    for waypoint in &gpx.waypoints {
+        write_placemark_start(&waypoint.name, &waypoint.description, &mut writer)?;
+        write_xml_event(XmlEvent::start_element("Point"), &mut writer)?;
+        write_string("coordinates", &coordinates(waypoint, ","), &mut writer)?;
+        write_xml_event(XmlEvent::end_element(), &mut writer)?;
+        write_xml_event(XmlEvent::end_element(), &mut writer)?;
+    }
+    for route in &gpx.routes {
+        write_route(route, &mut writer)?;
+    }
+    for track in &gpx.tracks {
+        write_track(track, options, &mut writer)?;
+    }
+    write_xml_event(XmlEvent::end_element(), &mut writer)?;
+    write_xml_event(XmlEvent::end_element(), &mut writer)?;
+    Ok(())
+}
+
+fn write_placemark_start<W: Write>(
+    name: &Option<String>,
+    description: &Option<String>,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
+    write_xml_event(XmlEvent::start_element("Placemark"), writer)?;
+    write_string_if_exists("name", name, writer)?;
+    write_string_if_exists("description", description, writer)?;
+    Ok(())
+}
+
+fn write_route<W: Write>(route: &Route, writer: &mut EventWriter<W>) -> Result<()> {
+    write_placemark_start(&route.name, &route.description, writer)?;
+    write_line_string(&route.points, writer)?;
+    write_xml_event(XmlEvent::end_element(), writer)?;
+    Ok(())
+}
+
+fn write_track<W: Write>(
+    track: &Track,
+    options: &KmlOptions,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
+    write_placemark_start(&track.name, &track.description, writer)?;
+    let gx_track = options.gx_track
+        && track
+            .segments
+            .iter()
+            .flat_map(|segment| &segment.points)
+            .all(|point| point.time.is_some());
+    let multiple = track.segments.len() > 1;
+    if multiple {
+        let element = if gx_track {
+            "gx:MultiTrack"
+        } else {
+            "MultiGeometry"
+        };
+        write_xml_event(XmlEvent::start_element(element), writer)?;
+    }
+    for segment in &track.segments {
+        if gx_track {
+            write_gx_track(segment, writer)?;
+        } else {
+            write_line_string(&segment.points, writer)?;
+        }
+    }
+    if multiple {
+        write_xml_event(XmlEvent::end_element(), writer)?;
+    }
+    write_xml_event(XmlEvent::end_element(), writer)?;
+    Ok(())
+}
+
+fn write_line_string<W: Write>(points: &[Waypoint], writer: &mut EventWriter<W>) -> Result<()> {
+    let coordinates = points
+        .iter()
+        .map(|point| coordinates(point, ","))
+        .collect::<Vec<_>>()
+        .join(" ");
+    write_xml_event(XmlEvent::start_element("LineString"), writer)?;
+    write_string("coordinates", &coordinates, writer)?;
+    write_xml_event(XmlEvent::end_element(), writer)?;
+    Ok(())
+}
+
+/// Writes a segment as a `gx:Track`, all times followed by all coordinates.
+/// Its points all have a time.
+fn write_gx_track<W: Write>(segment: &TrackSegment, writer: &mut EventWriter<W>) -> Result<()> {
+    write_xml_event(XmlEvent::start_element("gx:Track"), writer)?;
+    for time in segment.points.iter().filter_map(|point| point.time) {
+        write_string("when", &time.to_rfc3339(), writer)?;
+    }
+    for point in &segment.points {
+        write_string("gx:coord", &coordinates(point, " "), writer)?;
+    }
+    write_xml_event(XmlEvent::end_element(), writer)?;
+    Ok(())
+}
+
+/// Gives the longitude, latitude and elevation (if any) of a point, joined by
+/// `separator`.
+fn coordinates(waypoint: &Waypoint, separator: &str) -> String {
+    let point = waypoint.point();
+    let mut coordinates = format!("{}{}{}", point.lng(), separator, point.lat());
+    if let Some(elevation) = waypoint.elevation {
+        coordinates.push_str(separator);
+        coordinates.push_str(&elevation.to_string());
+    }
+    coordinates
+}
+
+/// Reads an activity from KML, the inverse of `write_kml`.
+///
+/// Placemarks with a point become waypoints, and placemarks with line strings
+/// or `gx:Track`s become tracks, with a segment per line. Placemarks keep
+/// their name and description, and the document its name. Polygons, which
+/// GPX has no room for, are skipped.
+///
+/// ```
+/// use gpx::read_kml;
+///
+/// let data = r#"<kml xmlns="http://www.opengis.net/kml/2.2"><Document>
+///     <Placemark>
+///         <name>Walk</name>
+///         <LineString><coordinates>-121.97,37.24,553.21 -121.96,37.25</coordinates></LineString>
+///     </Placemark>
+/// </Document></kml>"#;
+///
+/// let gpx = read_kml(data.as_bytes()).unwrap();
+/// assert_eq!(gpx.tracks[0].name.as_deref(), Some("Walk"));
+/// assert_eq!(gpx.tracks[0].segments[0].points[0].elevation, Some(553.21));
+/// ```
+pub fn read_kml<R: Read>(reader: R) -> Result<Gpx> {
+    let config = ParserConfig {
+        cdata_to_characters: true,
+        ..ParserConfig::new()
+    };
+    let mut gpx = Gpx {
+        version: GpxVersion::Gpx11,
+        ..Default::default()
+    };
+    // Local names of the open elements, and the text of the innermost one.
+    let mut elements: Vec<String> = Vec::new();
+    let mut text = String::new();
+    let mut placemark = Placemark::default();
+
+    for event in EventReader::new_with_config(reader, config) {
+        match event.chain_err(|| "error while parsing kml")? {
+            ReaderEvent::StartElement { name, .. } => {
+                elements.push(name.local_name);
+                text.clear();
+            }
+            ReaderEvent::Characters(characters) => text.push_str(&characters),
+            ReaderEvent::EndElement { .. } => {
+                let element = elements.pop().unwrap_or_default();
+                let parent = elements.last().map(String::as_str).unwrap_or_default();
+                match (element.as_str(), parent) {
+                    ("name", "Document") if gpx.metadata.is_none() => {
+                        gpx.metadata = Some(Metadata {
+                            name: Some(text.trim().to_string()),
+                            ..Default::default()
+                        });
+                    }
+                    ("name", "Placemark") => placemark.name = Some(text.trim().to_string()),
+                    ("description", "Placemark") => {
+                        placemark.description = Some(text.trim().to_string());
+                    }
+                    ("coordinates", "Point") => {
+                        placemark.points.extend(parse_coordinates(&text)?);
+                    }
+                    ("coordinates", "LineString") => {
+                        let mut segment = TrackSegment::new();
+                        segment.points = parse_coordinates(&text)?;
+                        placemark.segments.push(segment);
+                    }
+                    ("when", "Track") => placemark.times.push(parse_time(&text)?),
+                    ("coord", "Track") => placemark.coords.push(parse_coord(&text)?),
+                    ("Track", _) => placemark.end_track()?,
+                    ("Placemark", _) => placemark.end(&mut gpx),
+                    _ => {}
+                }
+                text.clear();
+            }
+            _ => {}
+        }
+    }
+    Ok(gpx)
+}
+
+/// Reads an activity from KMZ, a zip archive of a KML document and the files
+/// it refers to. The document is `doc.kml` by convention, or else the first
+/// KML file in the archive.
+#[cfg(feature = "kmz")]
+pub fn read_kmz<R: Read + Seek>(reader: R) -> Result<Gpx> {
+    let mut archive = ZipArchive::new(reader).chain_err(|| "error while reading kmz archive")?;
+    let mut names = Vec::new();
+    for index in 0..archive.len() {
+        let file = archive
+            .by_index(index)
+            .chain_err(|| "error while reading kmz archive")?;
+        names.push(file.name().to_string());
+    }
+    let name = names
+        .iter()
+        .find(|name| *name == "doc.kml")
+        .or_else(|| names.iter().find(|name| name.ends_with(".kml")))
+        .ok_or_else(|| Error::from("kmz archive has no kml document"))?;
+    let file = archive
+        .by_name(name)
+        .chain_err(|| "error while reading kmz archive")?;
+    read_kml(file)
+}
+
+/// The geometries of a placemark being read.
+#[derive(Default)]
+struct Placemark {
+    name: Option<String>,
+    description: Option<String>,
+    points: Vec<Waypoint>,
+    segments: Vec<TrackSegment>,
+    // The times and coordinates of the gx:Track being read.
+    times: Vec<Option<DateTime<Utc>>>,
+    coords: Vec<Waypoint>,
+}
+
+impl Placemark {
+    /// Makes a segment of the times and coordinates of a `gx:Track`.
+    fn end_track(&mut self) -> Result<()> {
+        ensure!(
+            self.times.len() == self.coords.len(),
+            "gx:Track has {} times but {} coordinates",
+            self.times.len(),
+            self.coords.len()
+        );
+        let mut segment = TrackSegment::new();
+        for (time, mut point) in self.times.drain(..).zip(self.coords.drain(..)) {
+            point.time = time;
+            segment.points.push(point);
+        }
+        self.segments.push(segment);
+        Ok(())
+    }
+
+    /// Adds what the placemark holds to `gpx`, and starts over.
+    fn end(&mut self, gpx: &mut Gpx) {
+        let placemark = std::mem::take(self);
+        for mut point in placemark.points {
+            point.name = placemark.name.clone();
+            point.description = placemark.description.clone();
+            gpx.waypoints.push(point);
+        }
+        if !placemark.segments.is_empty() {
+            let mut track = Track::new();
+            track.name = placemark.name;
+            track.description = placemark.description;
+            track.segments = placemark.segments;
+            gpx.tracks.push(track);
+        }
+    }
+}
+
+/// Parses the tuples of a `coordinates` element, `longitude,latitude` with
+/// an optional altitude, separated by whitespace.
+fn parse_coordinates(text: &str) -> Result<Vec<Waypoint>> {
+    text.split_whitespace()
+        .map(|tuple| parse_point(tuple.split(',')))
+        .collect()
+}
+
+/// Parses a `gx:coord`, `longitude latitude altitude`.
+fn parse_coord(text: &str) -> Result<Waypoint> {
+    parse_point(text.split_whitespace())
+}
+
+fn parse_point<'a>(mut values: impl Iterator<Item = &'a str>) -> Result<Waypoint> {
+    let mut next = || -> Result<Option<f64>> {
+        values
+            .next()
+            .map(|value| {
+                value
+                    .parse::<f64>()
+                    .chain_err(|| "error while parsing kml coordinate")
+            })
+            .transpose()
+    };
+    let (longitude, latitude) = match (next()?, next()?) {
+        (Some(longitude), Some(latitude)) => (longitude, latitude),
+        _ => return Err(Error::from("kml coordinate lacks a latitude")),
+    };
+    let mut waypoint = Waypoint::try_new(latitude, longitude)?;
+    waypoint.elevation = next()?;
+    Ok(waypoint)
+}
+
+/// Parses the time of a `when` element, which may be left empty.
+fn parse_time(text: &str) -> Result<Option<DateTime<Utc>>> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    let time =
+        DateTime::parse_from_rfc3339(text).chain_err(|| "error while parsing time as RFC3339")?;
+    Ok(Some(time.with_timezone(&Utc)))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::{read_kml, write_kml, write_kml_with_options, KmlOptions};
+    use crate::{Gpx, GpxVersion, Track, TrackSegment, Waypoint};
+
+    fn example() -> Gpx {
+        let mut first = Waypoint::builder()
+            .lat(37.24)
+            .lon(-121.97)
+            .elevation(553.21)
+            .build()
+            .unwrap();
+        first.time = Some(Utc.with_ymd_and_hms(2020, 6, 1, 12, 0, 0).unwrap());
+        let mut second = Waypoint::builder().lat(37.25).lon(-121.96).build().unwrap();
+        second.time = Some(Utc.with_ymd_and_hms(2020, 6, 1, 12, 1, 0).unwrap());
+        let track = Track::builder()
+            .name("Walk")
+            .points(vec![first, second])
+            .build();
+        Gpx::builder(GpxVersion::Gpx11).track(track).build()
+    }
+
+    #[test]
+    fn write_line_string() {
+        let mut buffer = Vec::new();
+        write_kml(&example(), &mut buffer).unwrap();
+
+        let kml = String::from_utf8(buffer).unwrap();
+        assert!(kml.contains("<name>Walk</name>"));
+        assert!(kml.contains("<coordinates>-121.97,37.24,553.21 -121.96,37.25</coordinates>"));
+        assert!(!kml.contains("gx:"));
+    }
+
+    #[test]
+    fn write_gx_track() {
+        let mut buffer = Vec::new();
+        let options = KmlOptions { gx_track: true };
+        write_kml_with_options(&example(), &mut buffer, &options).unwrap();
+
+        let kml = String::from_utf8(buffer).unwrap();
+        assert!(kml.contains("xmlns:gx=\"http://www.google.com/kml/ext/2.2\""));
+        assert!(kml.contains("<when>2020-06-01T12:00:00+00:00</when>"));
+        assert!(kml.contains("<gx:coord>-121.96 37.25</gx:coord>"));
+
+        let mut gpx = example();
+        gpx.tracks[0].segments[0].points[1].time = None;
+        let mut buffer = Vec::new();
+        write_kml_with_options(&gpx, &mut buffer, &options).unwrap();
+
+        let kml = String::from_utf8(buffer).unwrap();
+        assert!(!kml.contains("<gx:Track>"));
+        assert!(kml.contains("<coordinates>-121.97,37.24,553.21 -121.96,37.25</coordinates>"));
+    }
+
+    #[test]
+    fn write_segments_as_multi_geometry() {
+        let mut gpx = example();
+        gpx.tracks[0].segments.push(TrackSegment::new());
+
+        let mut buffer = Vec::new();
+        write_kml(&gpx, &mut buffer).unwrap();
+
+        let kml = String::from_utf8(buffer).unwrap();
+        assert_eq!(kml.matches("<LineString>").count(), 2);
+        assert!(kml.contains("<MultiGeometry>"));
+    }
+
+    #[test]
+    fn read_written_kml() {
+        let gpx = example();
+        for gx_track in [false, true] {
+            let mut buffer = Vec::new();
+            write_kml_with_options(&gpx, &mut buffer, &KmlOptions { gx_track }).unwrap();
+
+            let read_gpx = read_kml(buffer.as_slice()).unwrap();
+            assert_eq!(read_gpx.tracks[0].name, gpx.tracks[0].name);
+            let points = &read_gpx.tracks[0].segments[0].points;
+            assert_eq!(points.len(), 2);
+            assert_eq!(
+                points[0].point(),
+                gpx.tracks[0].segments[0].points[0].point()
+            );
+            assert_eq!(points[0].elevation, Some(553.21));
+            assert_eq!(points[1].elevation, None);
+            // Only a gx:Track keeps the times.
+            assert_eq!(points[0].time.is_some(), gx_track);
+        }
+    }
+
+    #[test]
+    fn read_placemarks() {
+        let data = r#"<kml xmlns="http://www.opengis.net/kml/2.2"><Document>
+            <name>Trip</name>
+            <Placemark>
+                <name>Lunch</name>
+                <description><![CDATA[<b>Good</b> food]]></description>
+                <Point><coordinates> -121.97,37.24 </coordinates></Point>
+            </Placemark>
+            <Placemark>
+                <Polygon><outerBoundaryIs><LinearRing>
+                    <coordinates>0,0 1,0 1,1 0,0</coordinates>
+                </LinearRing></outerBoundaryIs></Polygon>
+            </Placemark>
+        </Document></kml>"#;
+
+        let gpx = read_kml(data.as_bytes()).unwrap();
+        assert_eq!(gpx.metadata.unwrap().name.as_deref(), Some("Trip"));
+        assert_eq!(gpx.waypoints.len(), 1);
+        assert_eq!(gpx.waypoints[0].name.as_deref(), Some("Lunch"));
+        assert_eq!(
+            gpx.waypoints[0].description.as_deref(),
+            Some("<b>Good</b> food")
+        );
+        assert!(gpx.tracks.is_empty());
+    }
+
+    #[test]
+    fn read_invalid_kml() {
+        let out_of_range = "<kml><Placemark><Point><coordinates>37.24,-121.97</coordinates></Point></Placemark></kml>";
+        assert!(read_kml(out_of_range.as_bytes()).is_err());
+
+        let mismatched = "<kml><Placemark><Track><when></when></Track></Placemark></kml>";
+        assert!(read_kml(mismatched.as_bytes()).is_err());
+    }
+
+    #[cfg(feature = "kmz")]
+    #[test]
+    fn read_kmz() {
+        use std::io::{Cursor, Write};
+        use zip::write::{FileOptions, ZipWriter};
+
+        let mut kml = Vec::new();
+        write_kml(&example(), &mut kml).unwrap();
+        let mut archive = ZipWriter::new(Cursor::new(Vec::new()));
+        archive
+            .start_file("files/notes.txt", FileOptions::default())
+            .unwrap();
+        archive.write_all(b"notes").unwrap();
+        archive
+            .start_file("doc.kml", FileOptions::default())
+            .unwrap();
+        archive.write_all(&kml).unwrap();
+        let kmz = archive.finish().unwrap().into_inner();
+
+        let gpx = super::read_kmz(Cursor::new(kmz)).unwrap();
+        assert_eq!(gpx.tracks[0].segments[0].points.len(), 2);
+    }
+}
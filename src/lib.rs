@@ -30,15 +30,107 @@
 //! assert_eq!(segment.points[1].elevation, Some(4.94));
 //! assert_eq!(segment.points[2].elevation, Some(6.87));
 //! ```
+//!
+//! # Comparing documents
+//!
+//! All types are `Clone` and `PartialEq`. Comparisons are exact: coordinates,
+//! elevations and other numbers must be bit-for-bit equal (apart from `0.0`
+//! and `-0.0`), with no epsilon. Values read from the same text always compare
+//! equal, but values computed along different paths may not; compare those
+//! field by field with a tolerance instead.
+//!
+//! The one exception is `NaN`, which is read like any other number and never
+//! compares equal, not even to itself: a document with a `NaN` in it isn't
+//! equal to its own clone.
+//!
+//! ```
+//! use std::io::BufReader;
+//! use std::fs::File;
+//!
+//! use gpx::read;
+//!
+//! let file = File::open("tests/fixtures/wikipedia_example.gpx").unwrap();
+//! let gpx = read(BufReader::new(file)).unwrap();
+//!
+//! let mut edited = gpx.clone();
+//! assert_eq!(gpx, edited);
+//!
+//! edited.tracks[0].segments[0].points[0].elevation = Some(4.46 + 1e-12);
+//! assert_ne!(gpx, edited);
+//! ```
 
 // Export our type structs in the root, along with the read and write functions.
-pub use crate::reader::read;
+#[cfg(feature = "async")]
+pub use crate::async_reader::{read_async, read_async_with_options};
+#[cfg(feature = "async")]
+pub use crate::async_writer::{write_async, write_async_with_options};
+pub use crate::builder::*;
+#[cfg(feature = "csv")]
+pub use crate::csv::{read_csv, read_csv_with_options, CsvOptions};
+#[cfg(feature = "rayon")]
+pub use crate::dir::read_dir;
+#[cfg(feature = "fit")]
+pub use crate::fit::read_fit;
+#[cfg(feature = "geojson")]
+pub use crate::geojson::{from_geojson, read_geojson};
+#[cfg(feature = "gzip")]
+pub use crate::gzip::{read_gzip, write_gzip, write_gzip_with_options};
+#[cfg(feature = "kmz")]
+pub use crate::kml::read_kmz;
+#[cfg(feature = "kml")]
+pub use crate::kml::{read_kml, write_kml, write_kml_with_options, KmlOptions};
+#[cfg(feature = "mmap")]
+pub use crate::mmap::{read_mmap, read_mmap_with_options};
+pub use crate::push_parser::{ParsedElement, PushParser};
+pub use crate::reader::{
+    read, read_documents, read_documents_with_options, read_with_options, read_with_warnings,
+    Cancellation, Coercion, Documents, DocumentsWithOptions, Fields, GpxReader, ParserOptions,
+    Progress, Waypoints,
+};
+pub use crate::simplify::Simplification;
+pub use crate::stats::{
+    Activity, ActivityGuess, DistanceMethod, Effort, GpxSummary, MovingTime, PaceUnit, RouteShape,
+    Section, Sensor, SensorSummary, ShapeOptions, Split, Stop, TrackSummary,
+    DEFAULT_ELEVATION_THRESHOLD,
+};
+#[cfg(feature = "tcx")]
+pub use crate::tcx::write_tcx;
 pub use crate::types::*;
-pub use crate::writer::write;
+pub use crate::warning::{ParseWarning, WarningKind};
+pub use crate::writer::{write, write_with_options, StreamingWriter, WriterOptions};
 
+#[cfg(feature = "async")]
+mod async_reader;
+#[cfg(feature = "async")]
+mod async_writer;
+mod builder;
+#[cfg(feature = "csv")]
+mod csv;
+#[cfg(feature = "rayon")]
+mod dir;
+#[cfg(feature = "fit")]
+mod fit;
+#[cfg(feature = "geojson")]
+mod geojson;
+#[cfg(feature = "gzip")]
+mod gzip;
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "kml")]
+mod kml;
+#[cfg(feature = "mmap")]
+mod mmap;
 mod parser;
+mod polyline;
+mod push_parser;
 mod reader;
+mod simplify;
+mod stats;
+#[cfg(feature = "tcx")]
+mod tcx;
 mod types;
+mod warning;
+mod wkt;
 mod writer;
 
 // Errors should be namespaced away.
@@ -0,0 +1,56 @@
+//! Reads GPX files by mapping them into memory, with the `mmap` feature.
+
+use std::fs::File;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::errors::*;
+use crate::reader::{read, read_with_options, ParserOptions};
+use crate::Gpx;
+
+/// Reads an activity in GPX format from the file at `path`, mapped into
+/// memory instead of read through a buffer.
+///
+/// This is the fastest way to read large files from disk. The file must not
+/// be changed by another process while it is read.
+///
+/// ```
+/// let gpx = gpx::read_mmap("tests/fixtures/wikipedia_example.gpx").unwrap();
+/// assert_eq!(gpx.tracks.len(), 1);
+/// ```
+pub fn read_mmap<P: AsRef<Path>>(path: P) -> Result<Gpx> {
+    read(&map(path.as_ref())?[..])
+}
+
+/// Reads an activity in GPX format from the file at `path`, mapped into
+/// memory, according to `options`.
+pub fn read_mmap_with_options<P: AsRef<Path>>(
+    path: P,
+    options: &ParserOptions,
+) -> Result<(Gpx, Vec<Error>)> {
+    read_with_options(&map(path.as_ref())?[..], options)
+}
+
+fn map(path: &Path) -> Result<Mmap> {
+    let file = File::open(path).chain_err(|| format!("error while opening {}", path.display()))?;
+    // Safety: the map is only read while parsing, which the file must not
+    // change during, as documented.
+    let map = unsafe { Mmap::map(&file) };
+    map.chain_err(|| format!("error while mapping {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_mmap;
+    use crate::read;
+
+    #[test]
+    fn same_as_read() {
+        let path = "tests/fixtures/garmin-activity.gpx";
+        let data = std::fs::read(path).unwrap();
+
+        assert_eq!(read_mmap(path).unwrap(), read(data.as_slice()).unwrap());
+        assert!(read_mmap("tests/fixtures/missing.gpx").is_err());
+    }
+}
@@ -1,3 +1,5 @@
+//! bounds handles parsing of GPX-spec bounds.
+
 use std::io::Read;
 
 use error_chain::{bail, ensure};
@@ -115,4 +117,14 @@ mod tests {
 
         assert!(bounds.is_err());
     }
+
+    #[test]
+    fn consume_inverted_bounds() {
+        let bounds = consume!(
+            "<bounds minlat=\"45.7\" minlon=\"-74.0\" maxlat=\"45.4\" maxlon=\"-73.5\"/>",
+            GpxVersion::Gpx11
+        );
+
+        assert!(bounds.is_err());
+    }
 }
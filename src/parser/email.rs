@@ -7,11 +7,12 @@ use xml::reader::XmlEvent;
 
 use crate::errors::*;
 use crate::parser::{verify_starting_tag, Context};
+use crate::Email;
 
 /// consume consumes a GPX email from the `reader` until it ends.
 /// When it returns, the reader will be at the element after the end GPX email
 /// tag.
-pub fn consume<R: Read>(context: &mut Context<R>) -> Result<String> {
+pub fn consume<R: Read>(context: &mut Context<R>) -> Result<Email> {
     let attributes = verify_starting_tag(context, "email")?;
     // get required id and domain attributes
     let id = attributes
@@ -24,9 +25,12 @@ pub fn consume<R: Read>(context: &mut Context<R>) -> Result<String> {
         .find(|attr| attr.name.local_name == "domain")
         .ok_or(ErrorKind::InvalidElementLacksAttribute("domain", "email"))?;
 
-    let email = format!("{id}@{domain}", id = &id.value, domain = &domain.value);
+    let email = Email {
+        id: id.value.clone(),
+        domain: domain.value.clone(),
+    };
 
-    while let Some(event) = context.reader.next() {
+    for event in context.reader() {
         match event.chain_err(|| "error while parsing XML")? {
             XmlEvent::StartElement { ref name, .. } => {
                 bail!(ErrorKind::InvalidChildElement(
@@ -66,7 +70,9 @@ mod tests {
 
         let email = email.unwrap();
 
-        assert_eq!(email, "me@example.com");
+        assert_eq!(email.id, "me");
+        assert_eq!(email.domain, "example.com");
+        assert_eq!(email.to_string(), "me@example.com");
     }
 
     #[test]
@@ -80,7 +86,9 @@ mod tests {
 
         let email = email.unwrap();
 
-        assert_eq!(email, "me@example.com");
+        assert_eq!(email.id, "me");
+        assert_eq!(email.domain, "example.com");
+        assert_eq!(email.to_string(), "me@example.com");
     }
 
     #[test]
@@ -0,0 +1,404 @@
+//! events gives the XML events of a document to the parsers, keeping track
+//! of where in the document they are.
+
+use std::collections::HashMap;
+use std::io::{self, Read};
+
+use xml::common::{Position, TextPosition};
+use xml::name::OwnedName;
+use xml::reader::{Error as XmlError, Result as XmlResult, XmlEvent};
+use xml::EventReader;
+
+use crate::errors::{Error, Location};
+use crate::parser::schema::{Conformance, GPX_NAMESPACE};
+use crate::reader::Fields;
+
+/// Counted counts the bytes read from a reader, and tells whether it ended.
+/// It fails rather than read past a limit, if it has one.
+pub struct Counted<R: Read> {
+    reader: R,
+    count: u64,
+    end: bool,
+    limit: Option<u64>,
+    exceeded: bool,
+}
+
+impl<R: Read> Counted<R> {
+    pub fn with_limit(reader: R, limit: Option<u64>) -> Counted<R> {
+        Counted {
+            reader,
+            count: 0,
+            end: false,
+            limit,
+            exceeded: false,
+        }
+    }
+}
+
+impl<R: Read> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let over = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "document over the limit of bytes",
+            )
+        };
+        if self.exceeded {
+            return Err(over());
+        }
+        // One byte past the limit is enough to tell it's exceeded.
+        let room = match self.limit {
+            Some(limit) => {
+                let left = (limit - self.count).saturating_add(1);
+                if left < buf.len() as u64 {
+                    left as usize
+                } else {
+                    buf.len()
+                }
+            }
+            None => buf.len(),
+        };
+        let count = self.reader.read(&mut buf[..room])?;
+        self.count += count as u64;
+        if self.limit.map(|limit| self.count > limit).unwrap_or(false) {
+            self.exceeded = true;
+            return Err(over());
+        }
+        self.end = count == 0 && !buf.is_empty();
+        Ok(count)
+    }
+}
+
+/// An element the events consumed so far are in.
+struct Frame {
+    name: OwnedName,
+    index: usize,
+    // How many children of each name were started so far.
+    children: HashMap<String, usize>,
+}
+
+impl Frame {
+    fn segment(&self, root: bool) -> String {
+        if root {
+            self.name.local_name.clone()
+        } else {
+            format!("{}[{}]", self.name.local_name, self.index)
+        }
+    }
+}
+
+/// Events gives the XML events of a document and lets the next one be
+/// peeked at, like a `Peekable`. It keeps track of the elements the events
+/// consumed so far are in, and of where the last one is in the document.
+pub struct Events<R: Read> {
+    reader: EventReader<Counted<R>>,
+    peeked: Option<Option<(XmlResult<XmlEvent>, TextPosition, u64)>>,
+    finished: bool,
+    position: TextPosition,
+    offset: u64,
+    frames: Vec<Frame>,
+    // The element which just ended, and the last text read.
+    closed: Option<String>,
+    text: Option<String>,
+    // Whether to close the elements left open by a truncated document,
+    // rather than failing, and where it was truncated if it was.
+    partial: bool,
+    truncation: Option<(XmlError, Location)>,
+    closing: Vec<OwnedName>,
+    // Optional elements to leave out of the events, and the namespace of
+    // the root, which holds GPX elements whatever it is.
+    skip: Fields,
+    root_namespace: Option<String>,
+    // How deep elements may be nested, and the limit the events broke off
+    // at if one was exceeded.
+    max_depth: Option<usize>,
+    exceeded: Option<(&'static str, u64)>,
+    // The check of elements against the schema in strict mode, and why the
+    // events broke off if one didn't conform.
+    conformance: Option<Conformance>,
+    violation: Option<String>,
+}
+
+impl<R: Read> Events<R> {
+    pub fn new(reader: EventReader<Counted<R>>) -> Events<R> {
+        Events {
+            reader,
+            peeked: None,
+            finished: false,
+            position: TextPosition::new(),
+            offset: 0,
+            frames: Vec::new(),
+            closed: None,
+            text: None,
+            partial: false,
+            truncation: None,
+            closing: Vec::new(),
+            skip: Fields::NONE,
+            root_namespace: None,
+            max_depth: None,
+            exceeded: None,
+            conformance: None,
+            violation: None,
+        }
+    }
+
+    pub fn set_partial(&mut self, partial: bool) {
+        self.partial = partial;
+    }
+
+    pub fn set_skip(&mut self, skip: Fields) {
+        self.skip = skip;
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: Option<usize>) {
+        self.max_depth = max_depth;
+    }
+
+    /// Gives the limit the events broke off at, and what it is of, if one
+    /// was exceeded.
+    pub fn take_exceeded(&mut self) -> Option<(&'static str, u64)> {
+        let source = self.reader.source();
+        let bytes = source.limit.filter(|_| source.exceeded);
+        self.exceeded
+            .take()
+            .or_else(|| bytes.map(|limit| ("bytes", limit)))
+    }
+
+    pub fn set_strict(&mut self, strict: bool) {
+        self.conformance = if strict {
+            Some(Conformance::new())
+        } else {
+            None
+        };
+    }
+
+    /// Gives why an element didn't conform to the schema in strict mode, if
+    /// one didn't.
+    pub fn take_violation(&mut self) -> Option<String> {
+        self.violation.take()
+    }
+
+    /// Tells whether the document was truncated, and the elements it left
+    /// open are being closed.
+    pub fn truncated(&self) -> bool {
+        self.truncation.is_some()
+    }
+
+    /// Gives the error of where the document was truncated, if it was.
+    pub fn take_truncation(&mut self) -> Option<Error> {
+        let (error, location) = self.truncation.take()?;
+        Some(Error::with_chain(error, "error while parsing XML").located(location))
+    }
+
+    pub fn peek(&mut self) -> Option<&XmlResult<XmlEvent>> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.pull());
+        }
+        match self.peeked {
+            Some(Some((ref event, ..))) => Some(event),
+            _ => None,
+        }
+    }
+
+    /// Gives how many elements the events consumed so far are in.
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Gives the reader of the document.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader.source_mut().reader
+    }
+
+    /// Gives the number of bytes read from the document.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Gives where the last event consumed is in the document.
+    pub fn location(&self) -> Location {
+        let mut path: Vec<String> = self
+            .frames
+            .iter()
+            .enumerate()
+            .map(|(depth, frame)| frame.segment(depth == 0))
+            .collect();
+        path.extend(self.closed.clone());
+        Location {
+            offset: self.offset,
+            line: self.position.row + 1,
+            column: self.position.column + 1,
+            path: path.join("/"),
+            text: self.text.clone(),
+        }
+    }
+
+    fn pull(&mut self) -> Option<(XmlResult<XmlEvent>, TextPosition, u64)> {
+        if self.finished {
+            return None;
+        }
+        if self.truncation.is_some() {
+            let event = match self.closing.pop() {
+                Some(name) => XmlEvent::EndElement { name },
+                None => {
+                    self.finished = true;
+                    XmlEvent::EndDocument
+                }
+            };
+            return Some((Ok(event), self.position, self.offset));
+        }
+
+        loop {
+            match self.reader.next() {
+                Err(error) => return self.fail(error),
+                Ok(XmlEvent::StartElement { ref name, .. }) if self.skips(name) => {
+                    if let Err(error) = self.skip_element() {
+                        return self.fail(error);
+                    }
+                }
+                event => {
+                    self.finished = matches!(event, Ok(XmlEvent::EndDocument));
+                    return Some((event, self.reader.position(), self.reader.source().count));
+                }
+            }
+        }
+    }
+
+    fn fail(&mut self, error: XmlError) -> Option<(XmlResult<XmlEvent>, TextPosition, u64)> {
+        // The document ended early: the elements open are closed instead.
+        if self.partial && self.reader.source().end {
+            self.truncation = Some((error, self.location()));
+            self.closing = self.frames.iter().map(|frame| frame.name.clone()).collect();
+            return self.pull();
+        }
+        self.finished = true;
+        Some((
+            Err(error),
+            self.reader.position(),
+            self.reader.source().count,
+        ))
+    }
+
+    /// Tells whether an element starting is a GPX one to leave out. The root
+    /// never is, nor anything within extensions, which are given as they are.
+    /// Elements without a namespace, or in the one of the root, are taken as
+    /// GPX ones, as written by exporters which get the namespace wrong.
+    fn skips(&self, name: &OwnedName) -> bool {
+        let gpx = match name.namespace {
+            Some(ref namespace) => {
+                namespace.starts_with(GPX_NAMESPACE)
+                    || self.root_namespace.as_ref() == Some(namespace)
+            }
+            None => true,
+        };
+        gpx && !self.frames.is_empty()
+            && !self
+                .frames
+                .iter()
+                .any(|frame| frame.name.local_name == "extensions")
+            && self.skip.contains(Fields::of_element(&name.local_name))
+    }
+
+    /// Reads up to the end of the element which just started.
+    fn skip_element(&mut self) -> XmlResult<()> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.reader.next()? {
+                XmlEvent::StartElement { .. } => depth += 1,
+                XmlEvent::EndElement { .. } => depth -= 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for Events<R> {
+    type Item = XmlResult<XmlEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let peeked = self.peeked.take();
+        let (event, position, offset) = peeked.unwrap_or_else(|| self.pull())?;
+        self.position = position;
+        self.offset = offset;
+        match event {
+            Ok(XmlEvent::StartElement {
+                ref name,
+                ref attributes,
+                ..
+            }) => {
+                if let Some(ref mut conformance) = self.conformance {
+                    if let Err(reason) = conformance.start(name, attributes) {
+                        self.finished = true;
+                        self.closed = None;
+                        self.violation = Some(reason.clone());
+                        return Some(Err(XmlError::from((&position, reason))));
+                    }
+                }
+                if let Some(max_depth) = self.max_depth {
+                    if self.frames.len() >= max_depth {
+                        self.finished = true;
+                        self.closed = None;
+                        self.exceeded = Some(("levels of nesting", max_depth as u64));
+                        let reason = format!("elements nested over {} deep", max_depth);
+                        return Some(Err(XmlError::from((&position, reason))));
+                    }
+                }
+                if self.frames.is_empty() {
+                    self.root_namespace = name.namespace.clone();
+                }
+                let index = match self.frames.last_mut() {
+                    Some(parent) => {
+                        let count = parent.children.entry(name.local_name.clone()).or_insert(0);
+                        *count += 1;
+                        *count - 1
+                    }
+                    None => 0,
+                };
+                self.frames.push(Frame {
+                    name: name.clone(),
+                    index,
+                    children: HashMap::new(),
+                });
+                self.closed = None;
+                self.text = None;
+            }
+            Ok(XmlEvent::EndElement { .. }) => {
+                if let Some(ref mut conformance) = self.conformance {
+                    conformance.end();
+                }
+                let root = self.frames.len() == 1;
+                self.closed = self.frames.pop().map(|frame| frame.segment(root));
+            }
+            Ok(XmlEvent::Characters(ref text)) if !text.trim().is_empty() => {
+                self.text = Some(text.trim().to_string());
+            }
+            _ => {}
+        }
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use xml::EventReader;
+
+    use super::{Counted, Events};
+
+    #[test]
+    fn location_of_events() {
+        let data = "<gpx>\n  <trk/>\n  <trk><trkseg/><trkseg><trkpt><ele>high</ele>";
+        let mut events = Events::new(EventReader::new(Counted::with_limit(data.as_bytes(), None)));
+
+        // Up to the end of the ele element.
+        for _ in 0..14 {
+            events.next();
+        }
+
+        let location = events.location();
+        assert_eq!(location.path, "gpx/trk[1]/trkseg[1]/trkpt[0]/ele[0]");
+        assert_eq!(location.text.as_deref(), Some("high"));
+        assert_eq!(location.line, 3);
+        assert_eq!(events.depth(), 4);
+    }
+}
@@ -1,47 +1,224 @@
 //! extensions handles parsing of GPX-spec extensions.
-
-// TODO: extensions are not implemented
+//!
+//! Extensions are generally not interpreted; their content is kept as raw XML
+//! so that applications can inspect it themselves. The one exception is
+//! Garmin's widespread TrackPointExtension, which is also parsed into a
+//! `TrackPointExtension` for waypoints.
 
 use std::io::Read;
 
-use error_chain::ensure;
+use error_chain::{bail, ensure};
+use xml::attribute::OwnedAttribute;
+use xml::escape::{escape_str_attribute, escape_str_pcdata};
+use xml::name::OwnedName;
+use xml::namespace::Namespace;
 use xml::reader::XmlEvent;
 
 use crate::errors::*;
 use crate::parser::Context;
+use crate::TrackPointExtension;
+
+/// Namespace prefix shared by all versions of the Garmin TrackPointExtension.
+const TRACK_POINT_EXTENSION_NS: &str = "http://www.garmin.com/xmlschemas/TrackPointExtension/";
+
+/// consume consumes an extensions element, returning its inner XML.
+///
+/// Namespace declarations are only emitted where they were introduced inside
+/// the extensions element; prefixes bound further up in the document (e.g. on
+/// the `gpx` root) are kept as they are.
+pub fn consume<R: Read>(context: &mut Context<R>) -> Result<String> {
+    Ok(consume_waypoint(context)?.0)
+}
+
+/// consume_waypoint consumes the extensions element of a waypoint, returning
+/// its inner XML and the Garmin TrackPointExtension found inside, if any.
+pub fn consume_waypoint<R: Read>(
+    context: &mut Context<R>,
+) -> Result<(String, Option<TrackPointExtension>)> {
+    let mut content = String::new();
+    // The namespaces in scope for every currently open element, starting with
+    // the extensions element itself.
+    let mut scopes: Vec<Namespace> = Vec::new();
 
-/// consume consumes a single string as tag content.
-pub fn consume<R: Read>(context: &mut Context<R>) -> Result<()> {
-    let mut started = false;
+    let mut track_point_extension: Option<TrackPointExtension> = None;
+    let mut in_track_point_extension = false;
+    // The TrackPointExtension child currently being read, with its text.
+    let mut field: Option<(String, String)> = None;
 
     for event in context.reader() {
         match event.chain_err(|| "error while parsing XML")? {
-            XmlEvent::StartElement { name, .. } => {
-                // flip started depending on conditions
-                if &name.local_name == "extensions" {
-                    ensure!(!started, "extensions tag opened twice");
-
-                    started = true;
+            XmlEvent::StartElement {
+                name,
+                attributes,
+                namespace,
+            } => {
+                match scopes.last() {
+                    None => ensure!(
+                        name.local_name == "extensions",
+                        ErrorKind::InvalidChildElement(name.local_name, "extensions")
+                    ),
+                    Some(parent) => {
+                        write_start_element(&mut content, &name, &attributes, &namespace, parent)
+                    }
+                }
+                match scopes.len() {
+                    1 if is_track_point_extension(&name) => {
+                        track_point_extension.get_or_insert_with(Default::default);
+                        in_track_point_extension = true;
+                    }
+                    2 if in_track_point_extension => {
+                        field = Some((name.local_name.clone(), String::new()));
+                    }
+                    _ => {}
                 }
+                scopes.push(namespace);
             }
-
-            XmlEvent::EndElement { name, .. } => {
-                if &name.local_name == "extensions" {
-                    return Ok(());
+            XmlEvent::EndElement { name } => {
+                scopes.pop();
+                match scopes.len() {
+                    0 => return Ok((content.trim().to_string(), track_point_extension)),
+                    1 => in_track_point_extension = false,
+                    2 => {
+                        if let (Some(extension), Some((field, value))) =
+                            (track_point_extension.as_mut(), field.take())
+                        {
+                            set_track_point_field(extension, &field, value.trim())?;
+                        }
+                    }
+                    _ => {}
                 }
+                content.push_str("</");
+                content.push_str(&qualified_name(&name));
+                content.push('>');
+            }
+            XmlEvent::Characters(chars) if !scopes.is_empty() => {
+                if let Some((_, ref mut value)) = field {
+                    value.push_str(&chars);
+                }
+                content.push_str(&escape_str_pcdata(&chars));
             }
-
             _ => {}
         }
     }
 
-    Err("no end tag for extensions".into())
+    bail!(ErrorKind::MissingClosingTag("extensions"));
+}
+
+fn is_track_point_extension(name: &OwnedName) -> bool {
+    name.local_name == "TrackPointExtension"
+        && name
+            .namespace_ref()
+            .is_some_and(|ns| ns.starts_with(TRACK_POINT_EXTENSION_NS))
+}
+
+fn set_track_point_field(
+    extension: &mut TrackPointExtension,
+    field: &str,
+    value: &str,
+) -> Result<()> {
+    match field {
+        "atemp" => {
+            extension.atemp = Some(
+                value
+                    .parse()
+                    .chain_err(|| "error while casting air temperature (atemp) to f64")?,
+            )
+        }
+        "wtemp" => {
+            extension.wtemp = Some(
+                value
+                    .parse()
+                    .chain_err(|| "error while casting water temperature (wtemp) to f64")?,
+            )
+        }
+        "depth" => {
+            extension.depth = Some(
+                value
+                    .parse()
+                    .chain_err(|| "error while casting depth to f64")?,
+            )
+        }
+        "hr" => {
+            extension.hr = Some(
+                value
+                    .parse()
+                    .chain_err(|| "error while casting heart rate (hr) to u8")?,
+            )
+        }
+        "cad" => {
+            extension.cad = Some(
+                value
+                    .parse()
+                    .chain_err(|| "error while casting cadence (cad) to u8")?,
+            )
+        }
+        "power" => {
+            extension.power = Some(
+                value
+                    .parse()
+                    .chain_err(|| "error while casting power to u16")?,
+            )
+        }
+        _ => {} // everything else is only kept in the raw XML
+    }
+    Ok(())
+}
+
+fn qualified_name(name: &OwnedName) -> String {
+    match name.prefix_ref() {
+        Some(prefix) => format!("{}:{}", prefix, name.local_name),
+        None => name.local_name.clone(),
+    }
+}
+
+fn write_start_element(
+    content: &mut String,
+    name: &OwnedName,
+    attributes: &[OwnedAttribute],
+    namespace: &Namespace,
+    parent: &Namespace,
+) {
+    content.push('<');
+    content.push_str(&qualified_name(name));
+    for (prefix, uri) in namespace {
+        if parent.get(prefix) == Some(uri) {
+            continue;
+        }
+        if prefix.is_empty() {
+            content.push_str(" xmlns=\"");
+        } else {
+            content.push_str(" xmlns:");
+            content.push_str(prefix);
+            content.push_str("=\"");
+        }
+        content.push_str(&escape_str_attribute(uri));
+        content.push('"');
+    }
+    for attr in attributes {
+        content.push(' ');
+        content.push_str(&qualified_name(&attr.name));
+        content.push_str("=\"");
+        content.push_str(&escape_str_attribute(&attr.value));
+        content.push('"');
+    }
+    content.push('>');
 }
 
 #[cfg(test)]
 mod tests {
-    use super::consume;
-    use crate::GpxVersion;
+    use super::{consume, consume_waypoint};
+    use crate::{GpxVersion, TrackPointExtension};
+
+    macro_rules! consume_waypoint {
+        ($xml:expr, $version:expr) => {{
+            use crate::parser::create_context;
+            use std::io::BufReader;
+            consume_waypoint(&mut create_context(
+                BufReader::new($xml.as_bytes()),
+                $version,
+            ))
+        }};
+    }
 
     #[test]
     fn consume_arbitrary_extensions() {
@@ -55,5 +232,92 @@ mod tests {
         );
 
         assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap(),
+            "hello world
+                <a><b cond=\"no\"><c>derp</c></b></a>
+                <tag>yadda yadda we dont care</tag>"
+        );
+    }
+
+    #[test]
+    fn consume_empty_extensions() {
+        let result = consume!("<extensions/>", GpxVersion::Gpx11);
+
+        assert_eq!(result.unwrap(), "");
+    }
+
+    #[test]
+    fn consume_namespaced_extensions() {
+        let result = consume!(
+            "<extensions xmlns:x=\"urn:outer\"><x:a xmlns:y=\"urn:inner\"><y:b>1 &lt; 2</y:b></x:a></extensions>",
+            GpxVersion::Gpx11
+        );
+
+        // Only the namespace introduced inside the extensions is declared.
+        assert_eq!(
+            result.unwrap(),
+            "<x:a xmlns:y=\"urn:inner\"><y:b>1 &lt; 2</y:b></x:a>"
+        );
+    }
+
+    #[test]
+    fn consume_unclosed_extensions() {
+        let result = consume!("<extensions><a></a>", GpxVersion::Gpx11);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn consume_track_point_extension() {
+        let result = consume_waypoint!(
+            "<extensions xmlns:gpxtpx=\"http://www.garmin.com/xmlschemas/TrackPointExtension/v1\">
+                <gpxtpx:TrackPointExtension>
+                    <gpxtpx:atemp>21.5</gpxtpx:atemp>
+                    <gpxtpx:hr>142</gpxtpx:hr>
+                    <gpxtpx:cad>88</gpxtpx:cad>
+                    <gpxtpx:power>250</gpxtpx:power>
+                </gpxtpx:TrackPointExtension>
+            </extensions>",
+            GpxVersion::Gpx11
+        );
+
+        let (content, extension) = result.unwrap();
+        assert!(content.starts_with("<gpxtpx:TrackPointExtension>"));
+        assert_eq!(
+            extension.unwrap(),
+            TrackPointExtension {
+                atemp: Some(21.5),
+                hr: Some(142),
+                cad: Some(88),
+                power: Some(250),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn consume_foreign_track_point_extension() {
+        // Same element name, but not in the Garmin namespace.
+        let result = consume_waypoint!(
+            "<extensions xmlns:x=\"urn:example\">
+                <x:TrackPointExtension><x:hr>142</x:hr></x:TrackPointExtension>
+            </extensions>",
+            GpxVersion::Gpx11
+        );
+
+        assert!(result.unwrap().1.is_none());
+    }
+
+    #[test]
+    fn consume_bad_track_point_extension() {
+        let result = consume_waypoint!(
+            "<extensions xmlns:gpxtpx=\"http://www.garmin.com/xmlschemas/TrackPointExtension/v2\">
+                <gpxtpx:TrackPointExtension><gpxtpx:hr>fast</gpxtpx:hr></gpxtpx:TrackPointExtension>
+            </extensions>",
+            GpxVersion::Gpx11
+        );
+
+        assert!(result.is_err());
     }
 }
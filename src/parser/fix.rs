@@ -51,4 +51,10 @@ mod tests {
         let result = consume!("<fix>KF_4SV_OR_MORE</fix>", GpxVersion::Gpx11);
         assert_eq!(result.unwrap(), Fix::Other("KF_4SV_OR_MORE".to_owned()));
     }
+
+    #[test]
+    fn consume_empty_fix() {
+        let result = consume!("<fix></fix>", GpxVersion::Gpx11);
+        assert!(result.is_err());
+    }
 }
@@ -5,142 +5,255 @@ use std::io::Read;
 use chrono::{DateTime, Utc};
 use error_chain::{bail, ensure};
 use geo_types::Rect;
+use xml::namespace::{NS_NO_PREFIX, NS_XMLNS_PREFIX, NS_XML_PREFIX};
 use xml::reader::XmlEvent;
 
 use crate::errors::*;
 use crate::parser::{
-    bounds, metadata, route, string, time, track, verify_starting_tag, waypoint, Context,
+    bounds, extensions, metadata, route, string, time, track, verify_starting_element, waypoint,
+    Context,
 };
-use crate::{Gpx, GpxVersion, Link, Metadata, Person};
+use crate::{Email, Gpx, GpxVersion, Link, Metadata, ParsedElement, Person, WarningKind};
 
 /// Convert the version string to the version enum
-fn version_string_to_version(version_str: &str) -> Result<GpxVersion> {
+pub fn version_string_to_version(version_str: &str) -> GpxVersion {
     match version_str {
-        "1.0" => Ok(GpxVersion::Gpx10),
-        "1.1" => Ok(GpxVersion::Gpx11),
-        version => Err(Error::from(format!("Unknown version {}", version))),
+        "1.0" => GpxVersion::Gpx10,
+        "1.1" => GpxVersion::Gpx11,
+        version => GpxVersion::Unknown(String::from(version)),
     }
 }
 
 /// consume consumes an entire GPX element.
 pub fn consume<R: Read>(context: &mut Context<R>) -> Result<Gpx> {
-    let mut gpx: Gpx = Default::default();
+    start(context)?.finish(context)
+}
 
-    let mut author: Option<String> = None;
-    let mut url: Option<String> = None;
-    let mut urlname: Option<String> = None;
-    let mut email: Option<String> = None;
-    let mut time: Option<DateTime<Utc>> = None;
-    let mut bounds: Option<Rect<f64>> = None;
-    let mut gpx_name: Option<String> = None;
-    let mut description: Option<String> = None;
-    let mut keywords: Option<String> = None;
+/// Root is what was read so far of a GPX element read child by child, like
+/// by a `PushParser`.
+pub struct Root {
+    gpx: Gpx,
+    // Metadata of GPX 1.0, which is on the root element.
+    author: Option<String>,
+    url: Option<String>,
+    urlname: Option<String>,
+    email: Option<Email>,
+    time: Option<DateTime<Utc>>,
+    bounds: Option<Rect<f64>>,
+    name: Option<String>,
+    description: Option<String>,
+    keywords: Option<String>,
+    // Whether the end tag was read.
+    ended: bool,
+}
+
+/// start consumes the start tag of a GPX element, to read its children.
+pub fn start<R: Read>(context: &mut Context<R>) -> Result<Root> {
+    let mut gpx: Gpx = Default::default();
 
     // First we consume the gpx tag and its attributes
-    let attributes = verify_starting_tag(context, "gpx")?;
+    let (attributes, namespace) = verify_starting_element(context, "gpx")?;
     let version = attributes
         .iter()
         .find(|attr| attr.name.local_name == "version")
         .ok_or(ErrorKind::InvalidElementLacksAttribute("version", "gpx"))?;
-    gpx.version = version_string_to_version(&version.value)?;
-    context.version = gpx.version;
-
-    loop {
-        let next_event = {
-            if let Some(next) = context.reader.peek() {
-                match next {
-                    Ok(n) => n,
-                    Err(_) => bail!("error while parsing gpx event"),
-                }
-            } else {
-                break;
-            }
-        };
+    gpx.version = version_string_to_version(&version.value);
+    context.version = gpx.version.clone();
 
-        match next_event {
-            XmlEvent::StartElement { ref name, .. } => match name.local_name.as_ref() {
-                "metadata" if context.version != GpxVersion::Gpx10 => {
-                    gpx.metadata = Some(metadata::consume(context)?);
+    gpx.creator = attributes
+        .iter()
+        .find(|attr| attr.name.local_name == "creator")
+        .map(|attr| attr.value.clone());
+    gpx.schema_location = attributes
+        .iter()
+        .find(|attr| attr.name.local_name == "schemaLocation")
+        .map(|attr| attr.value.clone());
+
+    // Keep the namespaces declared on the root, but not the predefined ones.
+    gpx.namespaces = namespace
+        .into_iter()
+        .filter(|(prefix, uri)| match *prefix {
+            NS_XML_PREFIX | NS_XMLNS_PREFIX => false,
+            NS_NO_PREFIX => !uri.is_empty(),
+            _ => true,
+        })
+        .map(|(prefix, uri)| (prefix.to_string(), uri.to_string()))
+        .collect();
+
+    Ok(Root {
+        gpx,
+        author: None,
+        url: None,
+        urlname: None,
+        email: None,
+        time: None,
+        bounds: None,
+        name: None,
+        description: None,
+        keywords: None,
+        ended: false,
+    })
+}
+
+impl Root {
+    /// Consumes the next child of the GPX element, giving it if it's a
+    /// waypoint, route or track, or its end tag.
+    pub fn next<R: Read>(&mut self, context: &mut Context<R>) -> Result<Option<ParsedElement>> {
+        loop {
+            let next_event = {
+                if let Some(next) = context.reader.peek() {
+                    match next {
+                        Ok(n) => n,
+                        Err(_) => bail!("error while parsing gpx event"),
+                    }
+                } else {
+                    bail!(ErrorKind::MissingClosingTag("gpx"));
+                }
+            };
+
+            let name = match next_event {
+                XmlEvent::StartElement { ref name, .. } => name.local_name.clone(),
+                XmlEvent::EndElement { name } => {
+                    ensure!(
+                        name.local_name == "gpx",
+                        ErrorKind::InvalidClosingTag(name.local_name.clone(), "gpx")
+                    );
+                    context.reader.next();
+                    self.ended = true;
+                    return Ok(None);
                 }
-                "trk" => {
-                    gpx.tracks.push(track::consume(context)?);
+                _ => {
+                    context.reader.next(); //consume and ignore this event
+                    continue;
                 }
-                "rte" => {
-                    gpx.routes.push(route::consume(context)?);
+            };
+            let element = match name.as_str() {
+                "metadata" if context.version != GpxVersion::Gpx10 => {
+                    self.gpx.metadata = context.consume_or_drop(metadata::consume)?;
+                    None
                 }
-                "wpt" => {
-                    gpx.waypoints.push(waypoint::consume(context, "wpt")?);
+                "trk" => context
+                    .consume_or_drop(track::consume)?
+                    .map(ParsedElement::Track),
+                "rte" => context
+                    .consume_or_drop(route::consume)?
+                    .map(ParsedElement::Route),
+                "wpt" => context
+                    .consume_or_skip(|context| waypoint::consume(context, "wpt"))?
+                    .map(|wpt| ParsedElement::Waypoint(Box::new(wpt))),
+                "extensions" if context.version != GpxVersion::Gpx10 => {
+                    self.gpx.extensions = Some(extensions::consume(context)?);
+                    None
                 }
                 "time" if context.version == GpxVersion::Gpx10 => {
-                    time = Some(time::consume(context)?);
+                    self.time = Some(time::consume(context)?);
+                    None
                 }
                 "bounds" if context.version == GpxVersion::Gpx10 => {
-                    bounds = Some(bounds::consume(context)?);
+                    self.bounds = Some(bounds::consume(context)?);
+                    None
                 }
                 "author" if context.version == GpxVersion::Gpx10 => {
-                    author = Some(string::consume(context, "author", false)?);
+                    self.author = Some(string::consume(context, "author", false)?);
+                    None
                 }
                 "email" if context.version == GpxVersion::Gpx10 => {
-                    email = Some(string::consume(context, "email", false)?);
+                    // An address which can't be split is left out, rather
+                    // than failing the read over metadata.
+                    let address = string::consume(context, "email", false)?;
+                    match address.parse() {
+                        Ok(address) => self.email = Some(address),
+                        Err(_) => context.warn(WarningKind::InvalidEmail(address)),
+                    }
+                    None
                 }
                 "url" if context.version == GpxVersion::Gpx10 => {
-                    url = Some(string::consume(context, "url", false)?);
+                    self.url = Some(string::consume(context, "url", false)?);
+                    None
                 }
                 "urlname" if context.version == GpxVersion::Gpx10 => {
-                    urlname = Some(string::consume(context, "urlname", false)?);
+                    self.urlname = Some(string::consume(context, "urlname", false)?);
+                    None
                 }
                 "name" if context.version == GpxVersion::Gpx10 => {
-                    gpx_name = Some(string::consume(context, "name", false)?);
+                    self.name = Some(string::consume(context, "name", false)?);
+                    None
+                }
+                "desc" if context.version == GpxVersion::Gpx10 => {
+                    self.description = Some(string::consume(context, "desc", true)?);
+                    None
                 }
+                // Older versions of this crate wrote "description" instead of "desc".
                 "description" if context.version == GpxVersion::Gpx10 => {
-                    description = Some(string::consume(context, "description", true)?);
+                    self.description = Some(string::consume(context, "description", true)?);
+                    None
                 }
                 "keywords" if context.version == GpxVersion::Gpx10 => {
-                    keywords = Some(string::consume(context, "keywords", true)?);
+                    self.keywords = Some(string::consume(context, "keywords", true)?);
+                    None
                 }
                 child => {
                     bail!(ErrorKind::InvalidChildElement(String::from(child), "gpx"));
                 }
-            },
-            XmlEvent::EndElement { name } => {
-                ensure!(
-                    name.local_name == "gpx",
-                    ErrorKind::InvalidClosingTag(name.local_name.clone(), "gpx")
-                );
-                if gpx.version == GpxVersion::Gpx10 {
-                    let mut metadata: Metadata = Default::default();
-                    metadata.name = gpx_name;
-                    metadata.time = time;
-                    metadata.bounds = bounds;
-                    let mut person: Person = Default::default();
-                    person.name = author;
-                    if let Some(url) = url {
-                        let mut link: Link = Default::default();
-                        link.href = url;
-                        link.text = urlname;
-                        person.link = Some(link);
-                    }
-                    person.email = email;
-                    if person != Default::default() {
-                        metadata.author = Some(person);
-                    }
-                    metadata.keywords = keywords;
-                    metadata.description = description;
-                    if metadata != Default::default() {
-                        gpx.metadata = Some(metadata);
-                    }
-                }
-                context.reader.next();
+            };
+            return Ok(element);
+        }
+    }
 
-                return Ok(gpx);
-            }
-            _ => {
-                context.reader.next(); //consume and ignore this event
+    /// Consumes the rest of the GPX element, giving the document read.
+    pub fn finish<R: Read>(mut self, context: &mut Context<R>) -> Result<Gpx> {
+        while !self.ended {
+            match self.next(context)? {
+                Some(ParsedElement::Waypoint(waypoint)) => self.gpx.waypoints.push(*waypoint),
+                Some(ParsedElement::Route(route)) => self.gpx.routes.push(route),
+                Some(ParsedElement::Track(track)) => self.gpx.tracks.push(track),
+                None => {}
             }
         }
+        Ok(self.end())
     }
 
-    bail!(ErrorKind::MissingClosingTag("gpx"));
+    fn end(self) -> Gpx {
+        let Root {
+            mut gpx,
+            author,
+            url,
+            urlname,
+            email,
+            time,
+            bounds,
+            name,
+            description,
+            keywords,
+            ..
+        } = self;
+        if gpx.version == GpxVersion::Gpx10 {
+            let mut metadata = Metadata {
+                name,
+                description,
+                keywords,
+                time,
+                bounds,
+                ..Default::default()
+            };
+            let person = Person {
+                name: author,
+                email,
+                link: url.map(|href| Link {
+                    href,
+                    text: urlname,
+                    ..Default::default()
+                }),
+            };
+            if person != Default::default() {
+                metadata.author = Some(person);
+            }
+            if metadata != Default::default() {
+                gpx.metadata = Some(metadata);
+            }
+        }
+        gpx
+    }
 }
 
 #[cfg(test)]
@@ -152,23 +265,107 @@ mod tests {
 
     #[test]
     fn consume_gpx() {
-        let gpx = consume!("<gpx version=\"1.1\"></gpx>", GpxVersion::Unknown);
+        let gpx = consume!("<gpx version=\"1.1\"></gpx>", GpxVersion::default());
 
         assert!(gpx.is_ok());
+        assert!(gpx.unwrap().creator.is_none());
+    }
+
+    #[test]
+    fn consume_gpx_creator() {
+        let gpx = consume!(
+            "<gpx version=\"1.1\" creator=\"Garmin Connect\"></gpx>",
+            GpxVersion::default()
+        );
+
+        assert_eq!(gpx.unwrap().creator.unwrap(), "Garmin Connect");
+    }
+
+    #[test]
+    fn consume_gpx_namespaces() {
+        let gpx = consume!(
+            "<gpx version=\"1.1\"
+                xmlns=\"http://www.topografix.com/GPX/1/1\"
+                xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\"
+                xsi:schemaLocation=\"http://www.topografix.com/GPX/1/1 http://www.topografix.com/GPX/1/1/gpx.xsd\">
+            </gpx>",
+            GpxVersion::default()
+        );
+
+        let gpx = gpx.unwrap();
+        assert_eq!(
+            gpx.namespaces,
+            vec![
+                (
+                    String::from(""),
+                    String::from("http://www.topografix.com/GPX/1/1")
+                ),
+                (
+                    String::from("xsi"),
+                    String::from("http://www.w3.org/2001/XMLSchema-instance")
+                ),
+            ]
+        );
+        assert_eq!(
+            gpx.schema_location.unwrap(),
+            "http://www.topografix.com/GPX/1/1 http://www.topografix.com/GPX/1/1/gpx.xsd"
+        );
+    }
+
+    #[test]
+    fn consume_gpx_no_namespaces() {
+        let gpx = consume!("<gpx version=\"1.1\"></gpx>", GpxVersion::default()).unwrap();
+
+        assert!(gpx.namespaces.is_empty());
+        assert!(gpx.schema_location.is_none());
+    }
+
+    #[test]
+    fn consume_gpx_extensions() {
+        let gpx = consume!(
+            "<gpx version=\"1.1\"><extensions><app>1</app></extensions></gpx>",
+            GpxVersion::default()
+        );
+
+        assert_eq!(gpx.unwrap().extensions.unwrap(), "<app>1</app>");
     }
 
     #[test]
     fn consume_gpx_no_version() {
-        let gpx = consume!("<gpx></gpx>", GpxVersion::Unknown);
+        let gpx = consume!("<gpx></gpx>", GpxVersion::default());
 
         assert!(gpx.is_err());
     }
 
     #[test]
-    fn consume_gpx_version_error() {
-        let gpx = consume!("<gpx version=\"1.2\"></gpx>", GpxVersion::Unknown);
+    fn consume_gpx_versions() {
+        let gpx = consume!("<gpx version=\"1.0\"></gpx>", GpxVersion::default());
+        assert_eq!(gpx.unwrap().version, GpxVersion::Gpx10);
 
-        assert!(gpx.is_err());
+        let gpx = consume!("<gpx version=\"1.1\"></gpx>", GpxVersion::default());
+        assert_eq!(gpx.unwrap().version, GpxVersion::Gpx11);
+    }
+
+    #[test]
+    fn consume_gpx_unknown_version() {
+        let gpx = consume!("<gpx version=\"1.2\"></gpx>", GpxVersion::default());
+
+        assert_eq!(
+            gpx.unwrap().version,
+            GpxVersion::Unknown(String::from("1.2"))
+        );
+    }
+
+    #[test]
+    fn consume_gpx10_bad_email() {
+        let gpx = consume!(
+            "<gpx version=\"1.0\"><author>Nobody</author><email>nobody</email></gpx>",
+            GpxVersion::default()
+        );
+
+        let author = gpx.unwrap().metadata.unwrap().author.unwrap();
+        assert_eq!(author.name.as_deref(), Some("Nobody"));
+        assert_eq!(author.email, None);
     }
 
     #[test]
@@ -186,7 +383,7 @@ mod tests {
                 <rte></rte>
             </gpx>
             ",
-            GpxVersion::Unknown
+            GpxVersion::default()
         );
 
         assert!(gpx.is_ok());
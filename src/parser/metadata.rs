@@ -6,7 +6,9 @@ use error_chain::{bail, ensure};
 use xml::reader::XmlEvent;
 
 use crate::errors::*;
-use crate::parser::{bounds, extensions, link, person, string, time, verify_starting_tag, Context};
+use crate::parser::{
+    bounds, copyright, extensions, link, person, string, time, verify_starting_tag, Context,
+};
 use crate::Metadata;
 
 pub fn consume<R: Read>(context: &mut Context<R>) -> Result<Metadata> {
@@ -30,12 +32,19 @@ pub fn consume<R: Read>(context: &mut Context<R>) -> Result<Metadata> {
                 "name" => {
                     metadata.name = Some(string::consume(context, "name", false)?);
                 }
+                "desc" => {
+                    metadata.description = Some(string::consume(context, "desc", true)?);
+                }
+                // Older versions of this crate wrote "description" instead of "desc".
                 "description" => {
                     metadata.description = Some(string::consume(context, "description", true)?);
                 }
                 "author" => {
                     metadata.author = Some(person::consume(context, "author")?);
                 }
+                "copyright" => {
+                    metadata.copyright = Some(copyright::consume(context)?);
+                }
                 "keywords" => {
                     metadata.keywords = Some(string::consume(context, "keywords", true)?);
                 }
@@ -49,7 +58,7 @@ pub fn consume<R: Read>(context: &mut Context<R>) -> Result<Metadata> {
                     metadata.bounds = Some(bounds::consume(context)?);
                 }
                 "extensions" => {
-                    extensions::consume(context)?;
+                    metadata.extensions = Some(extensions::consume(context)?);
                 }
                 child => {
                     bail!(ErrorKind::InvalidChildElement(
@@ -77,7 +86,7 @@ pub fn consume<R: Read>(context: &mut Context<R>) -> Result<Metadata> {
 
 #[cfg(test)]
 mod tests {
-    use chrono::{TimeZone, Utc};
+    use chrono::{Duration, TimeZone, Utc};
 
     use super::consume;
     use crate::GpxVersion;
@@ -92,8 +101,20 @@ mod tests {
         assert!(result.name.is_none());
         assert!(result.description.is_none());
         assert!(result.author.is_none());
+        assert!(result.copyright.is_none());
         assert!(result.keywords.is_none());
         assert!(result.time.is_none());
+        assert!(result.bounds.is_none());
+    }
+
+    #[test]
+    fn consume_legacy_description() {
+        let result = consume!(
+            "<metadata><description>xxdescription</description></metadata>",
+            GpxVersion::Gpx11
+        );
+
+        assert_eq!(result.unwrap().description.unwrap(), "xxdescription");
     }
 
     #[test]
@@ -103,7 +124,7 @@ mod tests {
             <metadata>
                 <link href=\"example.com\" />
                 <name>xxname</name>
-                <description>xxdescription</description>
+                <desc>xxdescription</desc>
                 <author>
                     <name>John Doe</name>
                     <email id=\"john.doe\" domain=\"example.com\" />
@@ -112,8 +133,12 @@ mod tests {
                         <type>some type</type>
                     </link>
                 </author>
+                <copyright author=\"John Doe\">
+                    <year>2017</year>
+                </copyright>
                 <keywords>some keywords here</keywords>
                 <time>2017-08-16T04:03:33.735Z</time>
+                <bounds minlat=\"45.487064362\" minlon=\"-74.031837463\" maxlat=\"45.701225281\" maxlon=\"-73.586273193\"/>
             </metadata>
             ",
             GpxVersion::Gpx11
@@ -133,18 +158,28 @@ mod tests {
         let author = result.author.unwrap();
 
         assert_eq!(author.name.unwrap(), "John Doe");
-        assert_eq!(author.email.unwrap(), "john.doe@example.com");
+        assert_eq!(author.email.unwrap().to_string(), "john.doe@example.com");
         assert_eq!(author.link.unwrap().href, "example.com");
 
+        let copyright = result.copyright.unwrap();
+        assert_eq!(copyright.author, "John Doe");
+        assert_eq!(copyright.year, Some(2017));
+
         assert!(result.keywords.is_some());
         assert_eq!(result.keywords.unwrap(), "some keywords here");
 
         assert!(result.time.is_some());
         assert_eq!(
             result.time.unwrap(),
-            Utc.ymd(2017, 8, 16).and_hms_micro(4, 3, 33, 735_000)
+            Utc.with_ymd_and_hms(2017, 8, 16, 4, 3, 33).unwrap() + Duration::milliseconds(735)
         );
 
         assert_eq!(result.links.len(), 1);
+
+        let bounds = result.bounds.unwrap();
+        assert_eq!(bounds.min().x, -74.031837463);
+        assert_eq!(bounds.min().y, 45.487064362);
+        assert_eq!(bounds.max().x, -73.586273193);
+        assert_eq!(bounds.max().y, 45.701225281);
     }
 }
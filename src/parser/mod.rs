@@ -6,7 +6,7 @@
 macro_rules! consume {
     ($xml:expr, $version:expr) => {{
         use std::io::BufReader;
-        use crate::parser::create_context;
+        use $crate::parser::create_context;
         consume(&mut create_context(
             BufReader::new($xml.as_bytes()),
             $version,
@@ -14,7 +14,7 @@ macro_rules! consume {
     }};
     ($xml:expr, $version:expr, $tagname:expr) => {{
         use std::io::BufReader;
-        use crate::parser::create_context;
+        use $crate::parser::create_context;
         consume(
             &mut create_context(BufReader::new($xml.as_bytes()), $version),
             $tagname,
@@ -22,7 +22,7 @@ macro_rules! consume {
     }};
     ($xml:expr, $version:expr, $tagname:expr, $allow_empty:expr) => {{
         use std::io::BufReader;
-        use crate::parser::create_context;
+        use $crate::parser::create_context;
         consume(
             &mut create_context(BufReader::new($xml.as_bytes()), $version),
             $tagname,
@@ -32,7 +32,9 @@ macro_rules! consume {
 }
 
 pub mod bounds;
+pub mod copyright;
 pub mod email;
+pub mod events;
 pub mod extensions;
 pub mod fix;
 pub mod gpx;
@@ -40,6 +42,7 @@ pub mod link;
 pub mod metadata;
 pub mod person;
 pub mod route;
+pub mod schema;
 pub mod string;
 pub mod time;
 pub mod track;
@@ -47,48 +50,209 @@ pub mod tracksegment;
 pub mod waypoint;
 
 use std::io::Read;
-use std::iter::Peekable;
 
 use error_chain::{bail, ensure};
 use xml::attribute::OwnedAttribute;
-use xml::reader::{Events, XmlEvent};
-use xml::{EventReader, ParserConfig};
+use xml::namespace::Namespace;
+use xml::reader::ParserConfig2;
+use xml::reader::XmlEvent;
+use xml::{Encoding, EventReader, ParserConfig};
 
 use crate::errors::*;
+use crate::parser::events::{Counted, Events};
+use crate::reader::{ParserOptions, Progress};
 use crate::types::GpxVersion;
+use crate::warning::{ParseWarning, WarningKind};
+
+/// How many points are read between reports of the progress.
+const PROGRESS_INTERVAL: usize = 1000;
 
 pub struct Context<R: Read> {
-    reader: Peekable<Events<R>>,
+    reader: Events<R>,
     version: GpxVersion,
+    options: ParserOptions,
+    skipped: Vec<Error>,
+    points: usize,
+    // Warnings found so far, if they are collected.
+    warnings: Option<Vec<ParseWarning>>,
 }
 
 impl<R: Read> Context<R> {
-    pub fn new(reader: Peekable<Events<R>>, version: GpxVersion) -> Context<R> {
-        Context { reader, version }
+    pub fn new(reader: Events<R>, version: GpxVersion) -> Context<R> {
+        Context {
+            reader,
+            version,
+            options: Default::default(),
+            skipped: Vec::new(),
+            points: 0,
+            warnings: None,
+        }
     }
 
-    pub fn reader(&mut self) -> &mut Peekable<Events<R>> {
+    pub fn reader(&mut self) -> &mut Events<R> {
         &mut self.reader
     }
+
+    pub fn set_version(&mut self, version: GpxVersion) {
+        self.version = version;
+    }
+
+    pub fn set_options(&mut self, options: ParserOptions) {
+        self.reader.set_partial(options.partial);
+        self.reader.set_skip(options.skip);
+        self.reader.set_strict(options.strict);
+        self.reader.set_max_depth(options.max_nesting_depth);
+        self.options = options;
+    }
+
+    /// Starts collecting warnings, which are otherwise not looked for.
+    pub fn collect_warnings(&mut self) {
+        self.warnings.get_or_insert_with(Vec::new);
+    }
+
+    /// Records a warning about what was just read, if they are collected.
+    pub fn warn(&mut self, kind: WarningKind) {
+        let location = self.reader.location();
+        if let Some(ref mut warnings) = self.warnings {
+            warnings.push(ParseWarning { kind, location });
+        }
+    }
+
+    pub fn take_warnings(&mut self) -> Vec<ParseWarning> {
+        self.warnings.take().unwrap_or_default()
+    }
+
+    /// locate gives an error where in the document it was found.
+    pub fn locate(&mut self, error: Error) -> Error {
+        if let ErrorKind::Cancelled = error.kind() {
+            return error;
+        }
+        // The parsers only see that the events broke off there.
+        let error = match (self.reader.take_violation(), self.reader.take_exceeded()) {
+            (Some(reason), _) => Error::from(ErrorKind::NonConforming(reason)),
+            (None, Some((what, limit))) => Error::from(ErrorKind::LimitExceeded(what, limit)),
+            (None, None) => error,
+        };
+        error.located(self.reader.location())
+    }
+
+    /// Gives the errors of the elements skipped in lenient mode, and of
+    /// where the document ends if it was truncated.
+    pub fn take_skipped(&mut self) -> Vec<Error> {
+        let mut skipped = std::mem::take(&mut self.skipped);
+        skipped.extend(self.reader.take_truncation());
+        skipped
+    }
+
+    /// consume_or_skip consumes the element about to start with `consume`.
+    /// In lenient mode, an invalid element is skipped and its error recorded
+    /// instead of being returned, unless the XML itself is broken.
+    pub fn consume_or_skip<T>(
+        &mut self,
+        consume: impl FnOnce(&mut Context<R>) -> Result<T>,
+    ) -> Result<Option<T>> {
+        let lenient = self.options.lenient;
+        let element = self.consume_skipping(consume, lenient)?;
+        // Progress is counted in points, which are all read through here.
+        self.points += 1;
+        if let Some(max_points) = self.options.max_points {
+            ensure!(
+                self.points <= max_points,
+                ErrorKind::LimitExceeded("points", max_points as u64)
+            );
+        }
+        if self.points.is_multiple_of(PROGRESS_INTERVAL) {
+            self.report_progress();
+        }
+        if let Some(ref cancellation) = self.options.cancellation {
+            ensure!(!cancellation.is_cancelled(), ErrorKind::Cancelled);
+        }
+        Ok(element)
+    }
+
+    /// Calls the progress callback, if there is one.
+    pub fn report_progress(&self) {
+        if let Some(ref on_progress) = self.options.on_progress {
+            on_progress(Progress {
+                bytes: self.reader.offset(),
+                points: self.points,
+            });
+        }
+    }
+
+    /// consume_or_drop consumes the element about to start with `consume`,
+    /// but drops it if a truncated document ends before it does.
+    pub fn consume_or_drop<T>(
+        &mut self,
+        consume: impl FnOnce(&mut Context<R>) -> Result<T>,
+    ) -> Result<Option<T>> {
+        self.consume_skipping(consume, false)
+    }
+
+    fn consume_skipping<T>(
+        &mut self,
+        consume: impl FnOnce(&mut Context<R>) -> Result<T>,
+        lenient: bool,
+    ) -> Result<Option<T>> {
+        let depth = self.reader.depth();
+        let error = match consume(self) {
+            Ok(element) => return Ok(Some(element)),
+            Err(error) if skippable(&error) && (lenient || self.reader.truncated()) => error,
+            Err(error) => return Err(error),
+        };
+        let location = self.reader.location();
+        // Whatever is left of the element, up to its closing tag.
+        while self.reader.depth() > depth {
+            match self.reader.next() {
+                Some(Ok(_)) => {}
+                Some(Err(_)) | None => return Err(error),
+            }
+        }
+        // Elements cut off are only reported as the truncation.
+        if !self.reader.truncated() {
+            self.skipped.push(error.located(location));
+        }
+        Ok(None)
+    }
+}
+
+/// Tells whether an error can be skipped past, unlike cancellation or going
+/// over a limit.
+fn skippable(error: &Error) -> bool {
+    !matches!(
+        error.kind(),
+        ErrorKind::Cancelled | ErrorKind::LimitExceeded(..)
+    )
 }
 
 pub fn verify_starting_tag<R: Read>(
     context: &mut Context<R>,
     local_name: &'static str,
 ) -> Result<Vec<OwnedAttribute>> {
+    Ok(verify_starting_element(context, local_name)?.0)
+}
+
+/// verify_starting_element works like `verify_starting_tag`, but also gives
+/// the namespace mappings in scope for the element.
+pub fn verify_starting_element<R: Read>(
+    context: &mut Context<R>,
+    local_name: &'static str,
+) -> Result<(Vec<OwnedAttribute>, Namespace)> {
     //makes sure the specified starting tag is the next tag on the stream
     //we ignore and skip all xmlevents except StartElement, Characters and EndElement
     loop {
         let next = context.reader.next();
         match next {
             Some(Ok(XmlEvent::StartElement {
-                name, attributes, ..
+                name,
+                attributes,
+                namespace,
             })) => {
                 ensure!(
                     name.local_name == local_name,
                     ErrorKind::InvalidChildElement(name.local_name, local_name)
                 );
-                return Ok(attributes);
+                return Ok((attributes, namespace));
             }
             Some(Ok(XmlEvent::EndElement { name, .. })) => {
                 bail!(ErrorKind::InvalidChildElement(name.local_name, local_name));
@@ -103,12 +267,61 @@ pub fn verify_starting_tag<R: Read>(
 }
 
 pub(crate) fn create_context<R: Read>(reader: R, version: GpxVersion) -> Context<R> {
-    let parser_config = ParserConfig {
+    create_context_with_options(reader, version, &Default::default())
+}
+
+/// create_context_with_options works like `create_context`, but reads
+/// according to `options`.
+pub(crate) fn create_context_with_options<R: Read>(
+    reader: R,
+    version: GpxVersion,
+    options: &ParserOptions,
+) -> Context<R> {
+    let parser_config = parser_config(options);
+    create_context_with_config(reader, version, options, parser_config)
+}
+
+/// create_str_context works like `create_context`, for text which is
+/// already decoded, whatever encoding its XML declaration names.
+pub(crate) fn create_str_context(text: &str, version: GpxVersion) -> Context<&[u8]> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let options = Default::default();
+    let mut parser_config = parser_config(&options);
+    parser_config.override_encoding = Some(Encoding::Utf8);
+    parser_config.ignore_invalid_encoding_declarations = true;
+    create_context_with_config(text.as_bytes(), version, &options, parser_config)
+}
+
+/// Gives the configuration of the XML parser reading according to `options`.
+fn parser_config(options: &ParserOptions) -> ParserConfig2 {
+    let mut parser_config = ParserConfig2::from(ParserConfig {
         whitespace_to_characters: true, //convert Whitespace event to Characters
         cdata_to_characters: true,      //convert CData event to Characters
         ..ParserConfig::new()
-    };
-    let parser = EventReader::new_with_config(reader, parser_config);
-    let events = parser.into_iter().peekable();
-    Context::new(events, version)
+    });
+    if let Some(length) = options.max_entity_expansion {
+        parser_config.max_entity_expansion_length = length;
+    }
+    if let Some(length) = options.max_attribute_length {
+        parser_config.max_attribute_length = length;
+    }
+    if let Some(length) = options.max_text_length {
+        parser_config.max_data_length = length;
+    }
+    parser_config
+}
+
+fn create_context_with_config<R: Read>(
+    reader: R,
+    version: GpxVersion,
+    options: &ParserOptions,
+    parser_config: ParserConfig2,
+) -> Context<R> {
+    let parser = EventReader::new_with_config(
+        Counted::with_limit(reader, options.max_bytes),
+        parser_config,
+    );
+    let mut context = Context::new(Events::new(parser), version);
+    context.set_options(options.clone());
+    context
 }
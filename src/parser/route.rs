@@ -6,14 +6,18 @@ use error_chain::{bail, ensure};
 use xml::reader::XmlEvent;
 
 use crate::errors::*;
-use crate::parser::{link, string, verify_starting_tag, waypoint, Context};
-use crate::Route;
+use crate::parser::{extensions, link, string, verify_starting_tag, waypoint, Context};
+use crate::{GpxVersion, Link, Route};
 
 /// consume consumes a GPX route from the `reader` until it ends.
 pub fn consume<R: Read>(context: &mut Context<R>) -> Result<Route> {
     let mut route: Route = Default::default();
     verify_starting_tag(context, "rte")?;
 
+    // GPX 1.0 has a single url/urlname pair instead of links.
+    let mut url: Option<String> = None;
+    let mut urlname: Option<String> = None;
+
     loop {
         let next_event = {
             if let Some(next) = context.reader.peek() {
@@ -51,11 +55,24 @@ pub fn consume<R: Read>(context: &mut Context<R>) -> Result<Route> {
                     route._type = Some(string::consume(context, "type", false)?);
                 }
                 "rtept" => {
-                    route.points.push(waypoint::consume(context, "rtept")?);
+                    if let Some(point) =
+                        context.consume_or_skip(|context| waypoint::consume(context, "rtept"))?
+                    {
+                        route.points.push(point);
+                    }
                 }
                 "link" => {
                     route.links.push(link::consume(context)?);
                 }
+                "url" if context.version == GpxVersion::Gpx10 => {
+                    url = Some(string::consume(context, "url", false)?);
+                }
+                "urlname" if context.version == GpxVersion::Gpx10 => {
+                    urlname = Some(string::consume(context, "urlname", false)?);
+                }
+                "extensions" => {
+                    route.extensions = Some(extensions::consume(context)?);
+                }
                 child => {
                     bail!(ErrorKind::InvalidChildElement(String::from(child), "route"));
                 }
@@ -65,6 +82,13 @@ pub fn consume<R: Read>(context: &mut Context<R>) -> Result<Route> {
                     name.local_name == "rte",
                     ErrorKind::InvalidClosingTag(name.local_name.clone(), "route")
                 );
+                if let Some(href) = url {
+                    route.links.push(Link {
+                        href,
+                        text: urlname,
+                        ..Default::default()
+                    });
+                }
                 context.reader.next(); //consume the end tag
                 return Ok(route);
             }
@@ -110,6 +134,52 @@ mod tests {
         assert_eq!(route._type.unwrap(), "route type");
     }
 
+    #[test]
+    fn consume_route_points() {
+        let route = consume!(
+            "
+            <rte>
+                <rtept lon=\"-77.0365\" lat=\"38.8977\">
+                    <name>The White House</name>
+                </rtept>
+                <rtept lon=\"-71.063611\" lat=\"42.358056\">
+                    <name>Boston, Massachusetts</name>
+                </rtept>
+            </rte>
+            ",
+            GpxVersion::Gpx11
+        );
+
+        assert!(route.is_ok());
+        let route = route.unwrap();
+
+        assert_eq!(route.points.len(), 2);
+        assert_eq!(
+            route.points[1].name.as_ref().unwrap(),
+            "Boston, Massachusetts"
+        );
+
+        let linestring = route.linestring();
+        assert_eq!(linestring.0.len(), 2);
+        assert_eq!(linestring.0[0].x, -77.0365);
+        assert_eq!(linestring.0[0].y, 38.8977);
+    }
+
+    #[test]
+    fn consume_gpx10_url() {
+        let route = consume!(
+            "<rte><url>http://example.com</url><urlname>Example</urlname></rte>",
+            GpxVersion::Gpx10
+        );
+
+        assert!(route.is_ok());
+        let route = route.unwrap();
+
+        assert_eq!(route.links.len(), 1);
+        assert_eq!(route.links[0].href, "http://example.com");
+        assert_eq!(route.links[0].text.as_ref().unwrap(), "Example");
+    }
+
     #[test]
     fn consume_empty() {
         let route = consume!("<rte></rte>", GpxVersion::Gpx11);
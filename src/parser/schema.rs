@@ -0,0 +1,412 @@
+//! schema checks that a document conforms to the GPX schema, beyond what is
+//! needed to read it: required attributes, and the order and number of
+//! elements.
+
+use xml::attribute::OwnedAttribute;
+use xml::name::OwnedName;
+
+use crate::parser::gpx::version_string_to_version;
+use crate::GpxVersion;
+
+/// Namespaces of GPX 1.0 and 1.1 start with this.
+pub const GPX_NAMESPACE: &str = "http://www.topografix.com/GPX/";
+
+/// Gives the namespace of a version of GPX, if it's a known one.
+fn namespace(version: &GpxVersion) -> Option<&'static str> {
+    match version {
+        GpxVersion::Gpx10 => Some("http://www.topografix.com/GPX/1/0"),
+        GpxVersion::Gpx11 => Some("http://www.topografix.com/GPX/1/1"),
+        _ => None,
+    }
+}
+
+/// A child element of a sequence, and whether it may be repeated.
+type Child = (&'static str, bool);
+
+const WAYPOINT_10: &[Child] = &[
+    ("ele", false),
+    ("time", false),
+    ("course", false),
+    ("speed", false),
+    ("magvar", false),
+    ("geoidheight", false),
+    ("name", false),
+    ("cmt", false),
+    ("desc", false),
+    ("src", false),
+    ("url", false),
+    ("urlname", false),
+    ("sym", false),
+    ("type", false),
+    ("fix", false),
+    ("sat", false),
+    ("hdop", false),
+    ("vdop", false),
+    ("pdop", false),
+    ("ageofdgpsdata", false),
+    ("dgpsid", false),
+];
+
+const WAYPOINT_11: &[Child] = &[
+    ("ele", false),
+    ("time", false),
+    ("magvar", false),
+    ("geoidheight", false),
+    ("name", false),
+    ("cmt", false),
+    ("desc", false),
+    ("src", false),
+    ("link", true),
+    ("sym", false),
+    ("type", false),
+    ("fix", false),
+    ("sat", false),
+    ("hdop", false),
+    ("vdop", false),
+    ("pdop", false),
+    ("ageofdgpsdata", false),
+    ("dgpsid", false),
+    ("extensions", false),
+];
+
+/// Gives the sequence of children an element of the GPX namespace can have,
+/// if it's one with children.
+fn children(version: &GpxVersion, element: &str) -> Option<&'static [Child]> {
+    let children: &[Child] = match (version, element) {
+        (GpxVersion::Gpx10, "gpx") => &[
+            ("name", false),
+            ("desc", false),
+            ("author", false),
+            ("email", false),
+            ("url", false),
+            ("urlname", false),
+            ("time", false),
+            ("keywords", false),
+            ("bounds", false),
+            ("wpt", true),
+            ("rte", true),
+            ("trk", true),
+        ],
+        (GpxVersion::Gpx10, "wpt")
+        | (GpxVersion::Gpx10, "rtept")
+        | (GpxVersion::Gpx10, "trkpt") => WAYPOINT_10,
+        (GpxVersion::Gpx10, "rte") => &[
+            ("name", false),
+            ("cmt", false),
+            ("desc", false),
+            ("src", false),
+            ("url", false),
+            ("urlname", false),
+            ("number", false),
+            ("rtept", true),
+        ],
+        (GpxVersion::Gpx10, "trk") => &[
+            ("name", false),
+            ("cmt", false),
+            ("desc", false),
+            ("src", false),
+            ("url", false),
+            ("urlname", false),
+            ("number", false),
+            ("trkseg", true),
+        ],
+        (GpxVersion::Gpx10, "trkseg") => &[("trkpt", true)],
+        (GpxVersion::Gpx11, "gpx") => &[
+            ("metadata", false),
+            ("wpt", true),
+            ("rte", true),
+            ("trk", true),
+            ("extensions", false),
+        ],
+        (GpxVersion::Gpx11, "metadata") => &[
+            ("name", false),
+            ("desc", false),
+            ("author", false),
+            ("copyright", false),
+            ("link", true),
+            ("time", false),
+            ("keywords", false),
+            ("bounds", false),
+            ("extensions", false),
+        ],
+        (GpxVersion::Gpx11, "author") => &[("name", false), ("email", false), ("link", false)],
+        (GpxVersion::Gpx11, "copyright") => &[("year", false), ("license", false)],
+        (GpxVersion::Gpx11, "link") => &[("text", false), ("type", false)],
+        (GpxVersion::Gpx11, "wpt")
+        | (GpxVersion::Gpx11, "rtept")
+        | (GpxVersion::Gpx11, "trkpt") => WAYPOINT_11,
+        (GpxVersion::Gpx11, "rte") => &[
+            ("name", false),
+            ("cmt", false),
+            ("desc", false),
+            ("src", false),
+            ("link", true),
+            ("number", false),
+            ("type", false),
+            ("extensions", false),
+            ("rtept", true),
+        ],
+        (GpxVersion::Gpx11, "trk") => &[
+            ("name", false),
+            ("cmt", false),
+            ("desc", false),
+            ("src", false),
+            ("link", true),
+            ("number", false),
+            ("type", false),
+            ("extensions", false),
+            ("trkseg", true),
+        ],
+        (GpxVersion::Gpx11, "trkseg") => &[("trkpt", true), ("extensions", false)],
+        _ => return None,
+    };
+    Some(children)
+}
+
+/// Gives the attributes an element of the GPX namespace must have.
+fn required_attributes(version: &GpxVersion, element: &str) -> &'static [&'static str] {
+    match (version, element) {
+        (_, "gpx") => &["version", "creator"],
+        (_, "wpt") | (_, "rtept") | (_, "trkpt") => &["lat", "lon"],
+        (_, "bounds") => &["minlat", "minlon", "maxlat", "maxlon"],
+        (GpxVersion::Gpx11, "link") => &["href"],
+        (GpxVersion::Gpx11, "email") => &["id", "domain"],
+        (GpxVersion::Gpx11, "copyright") => &["author"],
+        _ => &[],
+    }
+}
+
+/// An element the checked events are in.
+struct Open {
+    children: Option<&'static [Child]>,
+    // Index in `children` of the last child started, if any was.
+    last: Option<usize>,
+    // Whether anything inside is left unchecked, like extensions.
+    unchecked: bool,
+}
+
+/// Conformance checks elements against the schema as they start, and gives
+/// why one doesn't conform.
+pub struct Conformance {
+    version: GpxVersion,
+    open: Vec<Open>,
+}
+
+impl Conformance {
+    pub fn new() -> Conformance {
+        Conformance {
+            version: GpxVersion::default(),
+            open: Vec::new(),
+        }
+    }
+
+    /// Checks an element which starts, in the elements started before.
+    pub fn start(&mut self, name: &OwnedName, attributes: &[OwnedAttribute]) -> Result<(), String> {
+        let gpx = match name.namespace {
+            Some(ref namespace) => namespace.starts_with(GPX_NAMESPACE),
+            None => false,
+        };
+        let element = name.local_name.as_str();
+
+        let parent = match self.open.last_mut() {
+            Some(parent) => parent,
+            None => {
+                if let Some(version) = attributes
+                    .iter()
+                    .find(|attribute| attribute.name.local_name == "version")
+                {
+                    self.version = version_string_to_version(&version.value);
+                }
+                self.check_attributes(element, attributes)?;
+                // Documents of other versions are read like GPX 1.1, but
+                // there's no schema to check them against.
+                if let GpxVersion::Unknown(ref version) = self.version {
+                    return Err(format!(
+                        "attribute version of element {} is {:?}, not 1.0 or 1.1",
+                        element, version
+                    ));
+                }
+                // Unlike in tolerant reading, elements of GPX must be in its
+                // namespace.
+                if let Some(expected) = namespace(&self.version) {
+                    match name.namespace {
+                        Some(ref namespace) if namespace == expected => {}
+                        Some(ref namespace) => {
+                            return Err(format!(
+                                "element {} in namespace {} rather than {}",
+                                element, namespace, expected
+                            ))
+                        }
+                        None => {
+                            return Err(format!("element {} lacks namespace {}", element, expected))
+                        }
+                    }
+                }
+                self.open.push(Open {
+                    children: children(&self.version, element),
+                    last: None,
+                    unchecked: false,
+                });
+                return Ok(());
+            }
+        };
+        if parent.unchecked {
+            self.open.push(Open {
+                children: None,
+                last: None,
+                unchecked: true,
+            });
+            return Ok(());
+        }
+
+        if !gpx {
+            // GPX 1.0 allows elements of other namespaces anywhere, 1.1 only
+            // in extensions.
+            if self.version == GpxVersion::Gpx11 {
+                return Err(format!(
+                    "element {} of another namespace outside of extensions",
+                    element
+                ));
+            }
+        } else if let Some(children) = parent.children {
+            if let Some(index) = children.iter().position(|&(child, _)| child == element) {
+                match parent.last {
+                    Some(last) if index < last => {
+                        return Err(format!("element {} after {}", element, children[last].0));
+                    }
+                    Some(last) if index == last && !children[index].1 => {
+                        return Err(format!("element {} more than once", element));
+                    }
+                    _ => parent.last = Some(index),
+                }
+            }
+            // Elements which don't belong at all fail to read anyway.
+        }
+        self.check_attributes(element, attributes)?;
+
+        let unchecked = !gpx || element == "extensions";
+        self.open.push(Open {
+            children: if unchecked {
+                None
+            } else {
+                children(&self.version, element)
+            },
+            last: None,
+            unchecked,
+        });
+        Ok(())
+    }
+
+    /// Checks that an element ends.
+    pub fn end(&mut self) {
+        self.open.pop();
+    }
+
+    fn check_attributes(&self, element: &str, attributes: &[OwnedAttribute]) -> Result<(), String> {
+        for &required in required_attributes(&self.version, element) {
+            if !attributes
+                .iter()
+                .any(|attribute| attribute.name.local_name == required)
+            {
+                return Err(format!("element {} lacks attribute {}", element, required));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use xml::reader::XmlEvent;
+    use xml::EventReader;
+
+    use super::Conformance;
+
+    const GPX_11: &str =
+        "<gpx version=\"1.1\" creator=\"a\" xmlns=\"http://www.topografix.com/GPX/1/1\"";
+
+    fn check(document: &str) -> Result<(), String> {
+        let mut conformance = Conformance::new();
+        for event in EventReader::new(document.as_bytes()) {
+            match event.unwrap() {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => conformance.start(&name, &attributes)?,
+                XmlEvent::EndElement { .. } => conformance.end(),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn conforming() {
+        assert!(check(&format!(
+            "{}><metadata><name>a</name><link href=\"b\"/><link href=\"c\"/></metadata>
+                <wpt lat=\"1\" lon=\"2\"><ele>3</ele><name>d</name></wpt><trk><trkseg/><trkseg/></trk>
+                <extensions><name>e</name><name>f</name></extensions></gpx>",
+            GPX_11
+        ))
+        .is_ok());
+        assert!(check(
+            "<gpx version=\"1.0\" creator=\"a\" xmlns=\"http://www.topografix.com/GPX/1/0\" xmlns:x=\"urn:x\"><name>a</name><x:b/><time>2020-01-01T00:00:00Z</time></gpx>"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn not_conforming() {
+        assert_eq!(
+            check("<gpx version=\"1.1\"></gpx>"),
+            Err(String::from("element gpx lacks attribute creator"))
+        );
+        assert_eq!(
+            check(&format!(
+                "{}><trk/><wpt lat=\"1\" lon=\"2\"/></gpx>",
+                GPX_11
+            )),
+            Err(String::from("element wpt after trk"))
+        );
+        assert_eq!(
+            check(&format!(
+                "{}><trk><name>a</name><name>b</name></trk></gpx>",
+                GPX_11
+            )),
+            Err(String::from("element name more than once"))
+        );
+        assert_eq!(
+            check(&format!("{} xmlns:x=\"urn:x\"><x:b/></gpx>", GPX_11)),
+            Err(String::from(
+                "element b of another namespace outside of extensions"
+            ))
+        );
+        assert_eq!(
+            check(&format!("{}><metadata><link/></metadata></gpx>", GPX_11)),
+            Err(String::from("element link lacks attribute href"))
+        );
+        assert_eq!(
+            check("<gpx version=\"1.1\" creator=\"a\"></gpx>"),
+            Err(String::from(
+                "element gpx lacks namespace http://www.topografix.com/GPX/1/1"
+            ))
+        );
+        assert_eq!(
+            check("<gpx version=\"1.0\" creator=\"a\" xmlns=\"http://www.topografix.com/GPX/1/1\"></gpx>"),
+            Err(String::from(
+                "element gpx in namespace http://www.topografix.com/GPX/1/1 rather than http://www.topografix.com/GPX/1/0"
+            ))
+        );
+        assert_eq!(
+            check("<gpx version=\"9.9\" creator=\"a\"><trk/><wpt lat=\"1\" lon=\"2\"/></gpx>"),
+            Err(String::from(
+                "attribute version of element gpx is \"9.9\", not 1.0 or 1.1"
+            ))
+        );
+        assert_eq!(
+            check("<gpx version=\"\" creator=\"a\"></gpx>"),
+            Err(String::from(
+                "attribute version of element gpx is \"\", not 1.0 or 1.1"
+            ))
+        );
+    }
+}
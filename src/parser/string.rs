@@ -1,6 +1,7 @@
 //! string handles parsing of GPX-spec strings.
 
 use std::io::Read;
+use std::str::FromStr;
 
 use error_chain::{bail, ensure};
 use xml::reader::XmlEvent;
@@ -42,6 +43,29 @@ pub fn consume<R: Read>(
     bail!(ErrorKind::MissingClosingTag(tagname));
 }
 
+/// consume_number consumes a single number as tag content, coerced as the
+/// options say, or `None` for a placeholder they take as a missing value.
+pub fn consume_number<R: Read, T>(
+    context: &mut Context<R>,
+    tagname: &'static str,
+) -> Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + 'static,
+{
+    let coercion = context.options.coercion;
+    // Empty numbers are only read to be taken as missing.
+    let number = consume(context, tagname, coercion.placeholders_as_missing)?;
+    match coercion.coerce(&number) {
+        Some(number) => Ok(Some(
+            number
+                .parse()
+                .chain_err(|| format!("invalid number {:?}", number))?,
+        )),
+        None => Ok(None),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::consume;
@@ -60,6 +84,18 @@ mod tests {
         assert_eq!(result.unwrap(), "hello world");
     }
 
+    #[test]
+    fn consume_escaped_and_cdata_string() {
+        let result = consume!(
+            "<desc>Fish &amp; <![CDATA[<b>Chips</b>]]]]><![CDATA[>]]>\nend</desc>",
+            GpxVersion::Gpx11,
+            "desc",
+            false
+        );
+
+        assert_eq!(result.unwrap(), "Fish & <b>Chips</b>]]>\nend");
+    }
+
     #[test]
     fn consume_new_tag() {
         // cannot start new tag inside string
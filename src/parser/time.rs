@@ -4,19 +4,30 @@
 use std::io::Read;
 
 use chrono::prelude::Utc;
-use chrono::DateTime;
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone};
 
 use crate::errors::*;
 use crate::parser::{string, Context};
 
-/// consume consumes an element as a time.
+/// consume consumes an element as a time, converted to UTC.
 pub fn consume<R: Read>(context: &mut Context<R>) -> Result<DateTime<Utc>> {
-    let time = string::consume(context, "time", false)?;
+    Ok(consume_with_offset(context)?.0)
+}
 
-    let time =
-        DateTime::parse_from_rfc3339(&time).chain_err(|| "error while parsing time as RFC3339")?;
+/// consume_with_offset consumes an element as a time converted to UTC, also
+/// giving the offset it was written with, if it has one.
+pub fn consume_with_offset<R: Read>(
+    context: &mut Context<R>,
+) -> Result<(DateTime<Utc>, Option<FixedOffset>)> {
+    let time = string::consume(context, "time", false)?;
 
-    Ok(DateTime::from_utc(time.naive_utc(), Utc))
+    if let Ok(time) = DateTime::parse_from_rfc3339(&time) {
+        return Ok((time.with_timezone(&Utc), Some(*time.offset())));
+    }
+    // Times without an offset are taken as UTC, which all should be in.
+    let time = NaiveDateTime::parse_from_str(&time, "%Y-%m-%dT%H:%M:%S%.f")
+        .chain_err(|| "error while parsing time as RFC3339")?;
+    Ok((Utc.from_utc_datetime(&time), None))
 }
 
 #[cfg(test)]
@@ -31,13 +42,13 @@ mod tests {
 
         // The following examples are taken from the xsd:dateTime examples.
 
-        // TODO, we currently don't allow dates which don't specify timezones,
-        // while the spec considers these to be "undetermined".
-        // let result = consume!("<time>2001-10-26T21:32:52</time>");
-        // assert!(result.is_ok());
+        // Dates which don't specify timezones are taken as UTC, while the
+        // spec considers these to be "undetermined".
+        let result = consume!("<time>2001-10-26T21:32:52</time>", GpxVersion::Gpx11);
+        assert_eq!(result.unwrap().to_rfc3339(), "2001-10-26T21:32:52+00:00");
 
         let result = consume!("<time>2001-10-26T21:32:52+02:00</time>", GpxVersion::Gpx11);
-        assert!(result.is_ok());
+        assert_eq!(result.unwrap().to_rfc3339(), "2001-10-26T19:32:52+00:00");
 
         let result = consume!("<time>2001-10-26T19:32:52Z</time>", GpxVersion::Gpx11);
         assert!(result.is_ok());
@@ -48,8 +59,8 @@ mod tests {
         // let result = consume!("<time>-2001-10-26T21:32:52</time>", GpxVersion::Gpx11);
         // assert!(result.is_ok());
 
-        // let result = consume!("<time>2001-10-26T21:32:52.12679</time>", GpxVersion::Gpx11);
-        // assert!(result.is_ok());
+        let result = consume!("<time>2001-10-26T21:32:52.12679</time>", GpxVersion::Gpx11);
+        assert!(result.is_ok());
 
         // These are invalid, again, from xsd:dateTime examples.
         let result = consume!("<time>2001-10-26</time>", GpxVersion::Gpx11);
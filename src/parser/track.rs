@@ -6,14 +6,19 @@ use error_chain::{bail, ensure};
 use xml::reader::XmlEvent;
 
 use crate::errors::*;
-use crate::parser::{link, string, tracksegment, verify_starting_tag, Context};
-use crate::Track;
+use crate::parser::{extensions, link, string, tracksegment, verify_starting_tag, Context};
+use crate::warning::WarningKind;
+use crate::{GpxVersion, Link, Track};
 
 /// consume consumes a GPX track from the `reader` until it ends.
 pub fn consume<R: Read>(context: &mut Context<R>) -> Result<Track> {
     let mut track: Track = Default::default();
     verify_starting_tag(context, "trk")?;
 
+    // GPX 1.0 has a single url/urlname pair instead of links.
+    let mut url: Option<String> = None;
+    let mut urlname: Option<String> = None;
+
     loop {
         let next_event = {
             if let Some(next) = context.reader.peek() {
@@ -40,15 +45,36 @@ pub fn consume<R: Read>(context: &mut Context<R>) -> Result<Track> {
                 "src" => {
                     track.source = Some(string::consume(context, "src", true)?);
                 }
+                "number" => {
+                    track.number = Some(
+                        string::consume(context, "number", false)?
+                            .parse()
+                            .chain_err(|| "error while casting track number (number) to u32")?,
+                    )
+                }
                 "type" => {
                     track._type = Some(string::consume(context, "type", false)?);
                 }
                 "trkseg" => {
-                    track.segments.push(tracksegment::consume(context)?);
+                    if let Some(segment) = context.consume_or_drop(tracksegment::consume)? {
+                        if segment.points.is_empty() {
+                            context.warn(WarningKind::EmptySegment);
+                        }
+                        track.segments.push(segment);
+                    }
                 }
                 "link" => {
                     track.links.push(link::consume(context)?);
                 }
+                "url" if context.version == GpxVersion::Gpx10 => {
+                    url = Some(string::consume(context, "url", false)?);
+                }
+                "urlname" if context.version == GpxVersion::Gpx10 => {
+                    urlname = Some(string::consume(context, "urlname", false)?);
+                }
+                "extensions" => {
+                    track.extensions = Some(extensions::consume(context)?);
+                }
                 child => {
                     bail!(ErrorKind::InvalidChildElement(String::from(child), "track"));
                 }
@@ -58,6 +84,13 @@ pub fn consume<R: Read>(context: &mut Context<R>) -> Result<Track> {
                     name.local_name == "trk",
                     ErrorKind::InvalidClosingTag(name.local_name.clone(), "track")
                 );
+                if let Some(href) = url {
+                    track.links.push(Link {
+                        href,
+                        text: urlname,
+                        ..Default::default()
+                    });
+                }
                 context.reader.next(); //consume the end tag
                 return Ok(track);
             }
@@ -84,6 +117,7 @@ mod tests {
                 <cmt>track comment</cmt>
                 <desc>track description</desc>
                 <src>track source</src>
+                <number>3</number>
                 <type>track type</type>
             </trk>
             ",
@@ -98,12 +132,62 @@ mod tests {
         assert_eq!(track.comment.unwrap(), "track comment");
         assert_eq!(track.description.unwrap(), "track description");
         assert_eq!(track.source.unwrap(), "track source");
+        assert_eq!(track.number.unwrap(), 3);
         assert_eq!(track._type.unwrap(), "track type");
     }
 
+    #[test]
+    fn consume_track_extensions() {
+        let track = consume!(
+            "
+            <trk>
+                <name>track name</name>
+                <extensions><color>red</color></extensions>
+                <trkseg>
+                    <extensions><lap>1</lap></extensions>
+                </trkseg>
+            </trk>
+            ",
+            GpxVersion::Gpx11
+        );
+
+        assert!(track.is_ok());
+
+        let track = track.unwrap();
+
+        assert_eq!(track.extensions.unwrap(), "<color>red</color>");
+        assert_eq!(track.segments.len(), 1);
+        assert_eq!(
+            track.segments[0].extensions.as_ref().unwrap(),
+            "<lap>1</lap>"
+        );
+    }
+
+    #[test]
+    fn consume_bad_number() {
+        let track = consume!("<trk><number>-1</number></trk>", GpxVersion::Gpx11);
+        assert!(track.is_err());
+    }
+
+    #[test]
+    fn consume_gpx10_url() {
+        let track = consume!(
+            "<trk><url>http://example.com</url><urlname>Example</urlname></trk>",
+            GpxVersion::Gpx10
+        );
+
+        assert!(track.is_ok());
+        let track = track.unwrap();
+
+        assert_eq!(track.links.len(), 1);
+        assert_eq!(track.links[0].href, "http://example.com");
+        assert_eq!(track.links[0].text.as_ref().unwrap(), "Example");
+    }
+
     #[test]
     fn consume_empty() {
         let track = consume!("<trk></trk>", GpxVersion::Gpx11);
         assert!(track.is_ok());
+        assert!(track.unwrap().extensions.is_none());
     }
 }
@@ -6,7 +6,8 @@ use error_chain::{bail, ensure};
 use xml::reader::XmlEvent;
 
 use crate::errors::*;
-use crate::parser::{verify_starting_tag, waypoint, Context};
+use crate::parser::{extensions, verify_starting_tag, waypoint, Context};
+use crate::warning::WarningKind;
 use crate::TrackSegment;
 
 /// consume consumes a GPX track segment from the `reader` until it ends.
@@ -28,7 +29,18 @@ pub fn consume<R: Read>(context: &mut Context<R>) -> Result<TrackSegment> {
 
         match next_event {
             XmlEvent::StartElement { ref name, .. } => match name.local_name.as_ref() {
-                "trkpt" => segment.points.push(waypoint::consume(context, "trkpt")?),
+                "trkpt" => {
+                    if let Some(point) =
+                        context.consume_or_skip(|context| waypoint::consume(context, "trkpt"))?
+                    {
+                        let previous = segment.points.last().and_then(|point| point.time);
+                        if let Some(time) = point.time.filter(|&time| previous == Some(time)) {
+                            context.warn(WarningKind::DuplicateTime(time));
+                        }
+                        segment.points.push(point);
+                    }
+                }
+                "extensions" => segment.extensions = Some(extensions::consume(context)?),
                 child => {
                     bail!(ErrorKind::InvalidChildElement(
                         String::from(child),
@@ -1,14 +1,15 @@
 //! waypoint handles parsing of GPX-spec waypoints.
 
+use std::convert::TryFrom;
 use std::io::Read;
 
 use error_chain::{bail, ensure};
-use geo_types::Point;
 use xml::reader::XmlEvent;
 
 use crate::errors::*;
 use crate::parser::{extensions, fix, link, string, time, verify_starting_tag, Context};
-use crate::{GpxVersion, Waypoint};
+use crate::warning::{WarningKind, MAX_DILUTION};
+use crate::{Degrees, DgpsStationId, GpxVersion, Link, Waypoint};
 
 /// consume consumes a GPX waypoint from the `reader` until it ends.
 pub fn consume<R: Read>(context: &mut Context<R>, tagname: &'static str) -> Result<Waypoint> {
@@ -22,8 +23,11 @@ pub fn consume<R: Read>(context: &mut Context<R>, tagname: &'static str) -> Resu
             "latitude", "waypoint",
         ))?;
 
-    let latitude: f64 = latitude
-        .value
+    let latitude: f64 = context
+        .options
+        .coercion
+        .coerce(&latitude.value)
+        .unwrap_or_default()
         .parse()
         .chain_err(|| "error while casting latitude to f64")?;
 
@@ -35,12 +39,31 @@ pub fn consume<R: Read>(context: &mut Context<R>, tagname: &'static str) -> Resu
             "waypoint",
         ))?;
 
-    let longitude: f64 = longitude
-        .value
+    let longitude: f64 = context
+        .options
+        .coercion
+        .coerce(&longitude.value)
+        .unwrap_or_default()
         .parse()
         .chain_err(|| "error while casting longitude to f64")?;
 
-    let mut waypoint: Waypoint = Waypoint::new(Point::new(longitude, latitude));
+    let mut waypoint: Waypoint = match Waypoint::try_new(latitude, longitude) {
+        Ok(waypoint) => waypoint,
+        Err(error) if swapped(latitude, longitude) => {
+            if !context.options.repair_swapped {
+                return Err(error).chain_err(|| {
+                    "invalid waypoint position, with latitude and longitude likely swapped"
+                });
+            }
+            context.warn(WarningKind::SwappedCoordinates(latitude, longitude));
+            Waypoint::try_new(longitude, latitude).chain_err(|| "invalid waypoint position")?
+        }
+        Err(error) => return Err(error).chain_err(|| "invalid waypoint position"),
+    };
+
+    // GPX 1.0 has a single url/urlname pair instead of links.
+    let mut url: Option<String> = None;
+    let mut urlname: Option<String> = None;
 
     loop {
         let next_event = {
@@ -59,82 +82,96 @@ pub fn consume<R: Read>(context: &mut Context<R>, tagname: &'static str) -> Resu
                 match name.local_name.as_ref() {
                     "ele" => {
                         // Cast the elevation to an f64, from a string.
-                        waypoint.elevation = Some(
-                            string::consume(context, "ele", false)?
-                                .parse()
-                                .chain_err(|| "error while casting elevation to f64")?,
-                        )
+                        waypoint.elevation = string::consume_number(context, "ele")
+                            .chain_err(|| "error while casting elevation to f64")?
                     }
                     "speed" if context.version == GpxVersion::Gpx10 => {
                         // Speed is from GPX 1.0
-                        waypoint.speed = Some(
-                            string::consume(context, "speed", false)?
-                                .parse()
-                                .chain_err(|| "error while casting speed to f64")?,
-                        );
+                        waypoint.speed = string::consume_number(context, "speed")
+                            .chain_err(|| "error while casting speed to f64")?;
+                    }
+                    "course" if context.version == GpxVersion::Gpx10 => {
+                        // Course is from GPX 1.0
+                        let course: Option<f64> = string::consume_number(context, "course")
+                            .chain_err(|| "error while casting course to f64")?;
+                        waypoint.course = course.map(Degrees::try_from).transpose()?;
+                    }
+                    "time" => {
+                        let (time, offset) = time::consume_with_offset(context)?;
+                        waypoint.time = Some(time);
+                        if context.options.preserve_offsets {
+                            waypoint.time_offset = offset.map(|offset| offset.local_minus_utc());
+                        }
                     }
-                    "time" => waypoint.time = Some(time::consume(context)?),
                     "name" => waypoint.name = Some(string::consume(context, "name", false)?),
                     "cmt" => waypoint.comment = Some(string::consume(context, "cmt", true)?),
                     "desc" => waypoint.description = Some(string::consume(context, "desc", true)?),
                     "src" => waypoint.source = Some(string::consume(context, "src", true)?),
                     "link" => waypoint.links.push(link::consume(context)?),
+                    "url" if context.version == GpxVersion::Gpx10 => {
+                        url = Some(string::consume(context, "url", false)?)
+                    }
+                    "urlname" if context.version == GpxVersion::Gpx10 => {
+                        urlname = Some(string::consume(context, "urlname", false)?)
+                    }
                     "sym" => waypoint.symbol = Some(string::consume(context, "sym", false)?),
                     "type" => waypoint._type = Some(string::consume(context, "type", false)?),
 
                     // Optional accuracy information
                     "fix" => waypoint.fix = Some(fix::consume(context)?),
+                    "magvar" => {
+                        let magvar: Option<f64> = string::consume_number(context, "magvar")
+                            .chain_err(|| {
+                                "error while casting magnetic variation (magvar) to f64"
+                            })?;
+                        waypoint.magvar = magvar.map(Degrees::try_from).transpose()?;
+                    }
                     "geoidheight" => {
-                        waypoint.geoidheight = Some(
-                            string::consume(context, "geoidheight", false)?
-                                .parse()
-                                .chain_err(|| "error while casting geoid (geoidheight) to f64")?,
-                        )
+                        waypoint.geoidheight = string::consume_number(context, "geoidheight")
+                            .chain_err(|| "error while casting geoid (geoidheight) to f64")?
                     }
                     "sat" => {
-                        waypoint.sat =
-                            Some(string::consume(context, "sat", false)?.parse().chain_err(
-                                || "error while casting number of satellites (sat) to u64",
-                            )?)
-                    }
-                    "hdop" => waypoint.hdop = Some(
-                        string::consume(context, "hdop", false)?
-                            .parse()
-                            .chain_err(|| {
+                        waypoint.sat = string::consume_number(context, "sat")
+                            .chain_err(|| "error while casting number of satellites (sat) to u64")?
+                    }
+                    "hdop" => {
+                        waypoint.hdop =
+                            string::consume_number(context, "hdop").chain_err(|| {
                                 "error while casting horizontal dilution of precision (hdop) to f64"
-                            })?,
-                    ),
-                    "vdop" => waypoint.vdop = Some(
-                        string::consume(context, "vdop", false)?
-                            .parse()
-                            .chain_err(|| {
+                            })?;
+                        check_dilution(context, "hdop", waypoint.hdop);
+                    }
+                    "vdop" => {
+                        waypoint.vdop =
+                            string::consume_number(context, "vdop").chain_err(|| {
                                 "error while casting vertical dilution of precision (vdop) to f64"
-                            })?,
-                    ),
-                    "pdop" => waypoint.pdop = Some(
-                        string::consume(context, "pdop", false)?
-                            .parse()
-                            .chain_err(|| {
+                            })?;
+                        check_dilution(context, "vdop", waypoint.vdop);
+                    }
+                    "pdop" => {
+                        waypoint.pdop =
+                            string::consume_number(context, "pdop").chain_err(|| {
                                 "error while casting position dilution of precision (pdop) to f64"
-                            })?,
-                    ),
+                            })?;
+                        check_dilution(context, "pdop", waypoint.pdop);
+                    }
                     "ageofdgpsdata" => {
-                        waypoint.dgps_age = Some(
-                            string::consume(context, "ageofdgpsdata", false)?
-                                .parse()
-                                .chain_err(|| "error while casting age of DGPS data to f64")?,
-                        )
+                        waypoint.dgps_age = string::consume_number(context, "ageofdgpsdata")
+                            .chain_err(|| "error while casting age of DGPS data to f64")?
                     }
                     "dgpsid" => {
-                        waypoint.dgpsid = Some(
-                            string::consume(context, "dgpsid", false)?
-                                .parse()
-                                .chain_err(|| "error while casting DGPS station ID to u16")?,
-                        )
+                        let dgpsid: Option<u16> = string::consume_number(context, "dgpsid")
+                            .chain_err(|| "error while casting DGPS station ID to u16")?;
+                        waypoint.dgpsid = dgpsid.map(DgpsStationId::try_from).transpose()?;
                     }
 
                     // Finally the GPX 1.1 extensions
-                    "extensions" => extensions::consume(context)?,
+                    "extensions" => {
+                        let (content, track_point_extension) =
+                            extensions::consume_waypoint(context)?;
+                        waypoint.extensions = Some(content);
+                        waypoint.track_point_extension = track_point_extension;
+                    }
                     child => {
                         bail!(ErrorKind::InvalidChildElement(
                             String::from(child),
@@ -148,6 +185,13 @@ pub fn consume<R: Read>(context: &mut Context<R>, tagname: &'static str) -> Resu
                     name.local_name == tagname,
                     ErrorKind::InvalidClosingTag(name.local_name.clone(), "waypoint")
                 );
+                if let Some(href) = url {
+                    waypoint.links.push(Link {
+                        href,
+                        text: urlname,
+                        ..Default::default()
+                    });
+                }
                 context.reader.next(); //consume the end tag
                 return Ok(waypoint);
             }
@@ -160,6 +204,23 @@ pub fn consume<R: Read>(context: &mut Context<R>, tagname: &'static str) -> Resu
     bail!(ErrorKind::MissingClosingTag("waypoint"));
 }
 
+/// Tells whether a position out of range is one with its latitude and
+/// longitude swapped, as some broken exporters write them.
+fn swapped(latitude: f64, longitude: f64) -> bool {
+    latitude.abs() > 90. && latitude.abs() <= 180. && longitude.abs() <= 90.
+}
+
+/// Warns of a dilution of precision which can't be right.
+fn check_dilution<R: Read>(context: &mut Context<R>, element: &'static str, value: Option<f64>) {
+    let value = match value {
+        Some(value) => value,
+        None => return,
+    };
+    if !(value > 0. && value <= MAX_DILUTION) {
+        context.warn(WarningKind::DilutionOutOfRange(element, value));
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use geo_types::Point;
@@ -181,7 +242,10 @@ mod tests {
                 <fix>dgps</fix>
                 <sat>4</sat>
                 <hdop>6.058</hdop>
-                <speed>0.0000</speed>
+                <vdop>2.5</vdop>
+                <pdop>6.55</pdop>
+                <course>182.3</course>
+                <speed>1.5</speed>
             </wpt>
             ",
             GpxVersion::Gpx10,
@@ -208,6 +272,10 @@ mod tests {
         assert_eq!(waypoint.fix.unwrap(), Fix::DGPS);
         assert_eq!(waypoint.sat.unwrap(), 4);
         assert_eq!(waypoint.hdop.unwrap(), 6.058);
+        assert_eq!(waypoint.vdop.unwrap(), 2.5);
+        assert_eq!(waypoint.pdop.unwrap(), 6.55);
+        assert_eq!(waypoint.course.unwrap().value(), 182.3);
+        assert_eq!(waypoint.speed.unwrap(), 1.5);
     }
 
     #[test]
@@ -226,6 +294,169 @@ mod tests {
         assert_eq!(waypoint.point().lat(), 2.345);
     }
 
+    #[test]
+    fn consume_out_of_range() {
+        let waypoint = consume!(
+            "<trkpt lat=\"91\" lon=\"1.234\"></trkpt>",
+            GpxVersion::Gpx11,
+            "trkpt"
+        );
+        assert!(waypoint.is_err());
+
+        let waypoint = consume!(
+            "<trkpt lat=\"2.345\" lon=\"-180.5\"></trkpt>",
+            GpxVersion::Gpx11,
+            "trkpt"
+        );
+        assert!(waypoint.is_err());
+    }
+
+    #[test]
+    fn consume_waypoint_extensions() {
+        let waypoint = consume!(
+            "
+            <trkpt lat=\"2.345\" lon=\"1.234\">
+                <ele>104</ele>
+                <extensions><hr>74</hr></extensions>
+            </trkpt>
+            ",
+            GpxVersion::Gpx11,
+            "trkpt"
+        );
+
+        assert!(waypoint.is_ok());
+        let waypoint = waypoint.unwrap();
+
+        assert_eq!(waypoint.elevation, Some(104.));
+        assert_eq!(waypoint.extensions.unwrap(), "<hr>74</hr>");
+    }
+
+    #[test]
+    fn consume_bad_dop() {
+        let waypoint = consume!(
+            "<wpt lat=\"32.4\" lon=\"1.234\"><hdop>good</hdop></wpt>",
+            GpxVersion::Gpx11,
+            "wpt"
+        );
+
+        assert!(waypoint.is_err());
+    }
+
+    #[test]
+    fn consume_gpx10_url() {
+        let waypoint = consume!(
+            "
+            <wpt lat=\"32.4\" lon=\"1.234\">
+                <url>http://example.com</url>
+                <urlname>Example</urlname>
+            </wpt>
+            ",
+            GpxVersion::Gpx10,
+            "wpt"
+        );
+
+        assert!(waypoint.is_ok());
+        let waypoint = waypoint.unwrap();
+
+        assert_eq!(waypoint.links.len(), 1);
+        assert_eq!(waypoint.links[0].href, "http://example.com");
+        assert_eq!(waypoint.links[0].text.as_ref().unwrap(), "Example");
+    }
+
+    #[test]
+    fn consume_gpx11_speed_and_course() {
+        // speed and course are not part of GPX 1.1
+        let waypoint = consume!(
+            "<wpt lat=\"32.4\" lon=\"1.234\"><course>12</course></wpt>",
+            GpxVersion::Gpx11,
+            "wpt"
+        );
+        assert!(waypoint.is_err());
+
+        let waypoint = consume!(
+            "<wpt lat=\"32.4\" lon=\"1.234\"><speed>1.5</speed></wpt>",
+            GpxVersion::Gpx11,
+            "wpt"
+        );
+        assert!(waypoint.is_err());
+    }
+
+    #[test]
+    fn consume_gpx11_url() {
+        // url is not part of GPX 1.1
+        let waypoint = consume!(
+            "<wpt lat=\"32.4\" lon=\"1.234\"><url>http://example.com</url></wpt>",
+            GpxVersion::Gpx11,
+            "wpt"
+        );
+
+        assert!(waypoint.is_err());
+    }
+
+    #[test]
+    fn consume_magvar_and_geoidheight() {
+        let waypoint = consume!(
+            "
+            <wpt lat=\"32.4\" lon=\"1.234\">
+                <magvar>359.5</magvar>
+                <geoidheight>-23.4</geoidheight>
+            </wpt>
+            ",
+            GpxVersion::Gpx11,
+            "wpt"
+        );
+
+        assert!(waypoint.is_ok());
+        let waypoint = waypoint.unwrap();
+
+        assert_eq!(waypoint.magvar.unwrap().value(), 359.5);
+        assert_eq!(waypoint.geoidheight, Some(-23.4));
+    }
+
+    #[test]
+    fn consume_magvar_out_of_range() {
+        let waypoint = consume!(
+            "<wpt lat=\"32.4\" lon=\"1.234\"><magvar>-3</magvar></wpt>",
+            GpxVersion::Gpx11,
+            "wpt"
+        );
+
+        assert!(waypoint.is_err());
+    }
+
+    #[test]
+    fn consume_dgps() {
+        let waypoint = consume!(
+            "
+            <wpt lat=\"32.4\" lon=\"1.234\">
+                <sat>12</sat>
+                <ageofdgpsdata>2.5</ageofdgpsdata>
+                <dgpsid>1023</dgpsid>
+            </wpt>
+            ",
+            GpxVersion::Gpx11,
+            "wpt"
+        );
+
+        assert!(waypoint.is_ok());
+        let waypoint = waypoint.unwrap();
+
+        assert_eq!(waypoint.sat, Some(12));
+        assert_eq!(waypoint.dgps_age, Some(2.5));
+        assert_eq!(waypoint.dgpsid.unwrap().value(), 1023);
+    }
+
+    #[test]
+    fn consume_dgpsid_out_of_range() {
+        let waypoint = consume!(
+            "<wpt lat=\"32.4\" lon=\"1.234\"><dgpsid>1024</dgpsid></wpt>",
+            GpxVersion::Gpx11,
+            "wpt"
+        );
+
+        assert!(waypoint.is_err());
+    }
+
     #[test]
     fn consume_bad_waypoint() {
         let waypoint = consume!(
@@ -0,0 +1,294 @@
+//! Encodes and decodes Google's encoded polyline format.
+//!
+//! Each point is written as the difference of its latitude and longitude to
+//! the previous point, rounded to a number of decimal places: 5 for Google's
+//! own APIs, 6 for others like OSRM and Valhalla.
+//!
+//! Elevations aren't part of the format, but the `_with_elevation` functions
+//! add them as a third value of each point, as some encoders do. Times and
+//! everything else are dropped either way; write GPX itself with `write` to
+//! keep them.
+
+use crate::errors::*;
+use crate::{Track, TrackSegment, Waypoint};
+
+impl TrackSegment {
+    /// Encodes the points of the segment as a polyline, with `precision`
+    /// decimal places.
+    ///
+    /// ```
+    /// use gpx::{TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// for (lat, lon) in vec![(38.5, -120.2), (40.7, -120.95), (43.252, -126.453)] {
+    ///     segment.points.push(Waypoint::try_new(lat, lon).unwrap());
+    /// }
+    ///
+    /// assert_eq!(segment.to_polyline(5), "_p~iF~ps|U_ulLnnqC_mqNvxq`@");
+    /// ```
+    pub fn to_polyline(&self, precision: u32) -> String {
+        encode(&self.points, precision, None)
+    }
+
+    /// Decodes a segment from a polyline with `precision` decimal places,
+    /// failing if it's malformed or its coordinates are out of range.
+    ///
+    /// ```
+    /// use gpx::TrackSegment;
+    ///
+    /// let segment = TrackSegment::from_polyline("_p~iF~ps|U_ulLnnqC_mqNvxq`@", 5).unwrap();
+    /// assert_eq!(segment.points.len(), 3);
+    /// assert_eq!(segment.points[1].point().lat(), 40.7);
+    /// ```
+    pub fn from_polyline(polyline: &str, precision: u32) -> Result<TrackSegment> {
+        Ok(TrackSegment {
+            points: decode(polyline, precision, None)?,
+            ..Default::default()
+        })
+    }
+
+    /// Encodes the points of the segment as a polyline with their elevations,
+    /// with `precision` decimal places for coordinates and
+    /// `elevation_precision` for elevations.
+    ///
+    /// Points without an elevation are encoded at an elevation of 0.
+    ///
+    /// ```
+    /// use gpx::{TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// let mut point = Waypoint::try_new(38.5, -120.2).unwrap();
+    /// point.elevation = Some(100.5);
+    /// segment.points.push(point);
+    ///
+    /// let polyline = segment.to_polyline_with_elevation(5, 1);
+    /// let decoded = TrackSegment::from_polyline_with_elevation(&polyline, 5, 1).unwrap();
+    /// assert_eq!(decoded.points[0].elevation, Some(100.5));
+    /// ```
+    pub fn to_polyline_with_elevation(&self, precision: u32, elevation_precision: u32) -> String {
+        encode(&self.points, precision, Some(elevation_precision))
+    }
+
+    /// Decodes a segment from a polyline with elevations, with `precision`
+    /// decimal places for coordinates and `elevation_precision` for
+    /// elevations.
+    pub fn from_polyline_with_elevation(
+        polyline: &str,
+        precision: u32,
+        elevation_precision: u32,
+    ) -> Result<TrackSegment> {
+        Ok(TrackSegment {
+            points: decode(polyline, precision, Some(elevation_precision))?,
+            ..Default::default()
+        })
+    }
+}
+
+impl Track {
+    /// Encodes the points of all segments of the track as a single polyline,
+    /// with `precision` decimal places.
+    pub fn to_polyline(&self, precision: u32) -> String {
+        encode(&self.points(), precision, None)
+    }
+
+    /// Decodes a track of a single segment from a polyline with `precision`
+    /// decimal places.
+    pub fn from_polyline(polyline: &str, precision: u32) -> Result<Track> {
+        let mut track = Track::new();
+        track
+            .segments
+            .push(TrackSegment::from_polyline(polyline, precision)?);
+        Ok(track)
+    }
+
+    /// Encodes the points of all segments of the track as a single polyline
+    /// with their elevations, like `TrackSegment::to_polyline_with_elevation`.
+    pub fn to_polyline_with_elevation(&self, precision: u32, elevation_precision: u32) -> String {
+        encode(&self.points(), precision, Some(elevation_precision))
+    }
+
+    /// Decodes a track of a single segment from a polyline with elevations,
+    /// like `TrackSegment::from_polyline_with_elevation`.
+    pub fn from_polyline_with_elevation(
+        polyline: &str,
+        precision: u32,
+        elevation_precision: u32,
+    ) -> Result<Track> {
+        let mut track = Track::new();
+        track
+            .segments
+            .push(TrackSegment::from_polyline_with_elevation(
+                polyline,
+                precision,
+                elevation_precision,
+            )?);
+        Ok(track)
+    }
+
+    fn points(&self) -> Vec<Waypoint> {
+        self.segments
+            .iter()
+            .flat_map(|segment| segment.points.iter().cloned())
+            .collect()
+    }
+}
+
+/// Encodes `points`, with their elevations if `elevation_precision` is set.
+fn encode(points: &[Waypoint], precision: u32, elevation_precision: Option<u32>) -> String {
+    let factor = 10f64.powi(precision as i32);
+    let elevation_factor = elevation_precision.map(|precision| 10f64.powi(precision as i32));
+    let mut polyline = String::new();
+    let mut previous = (0, 0, 0);
+    for waypoint in points {
+        let point = waypoint.point();
+        let current = (
+            (point.lat() * factor).round() as i64,
+            (point.lng() * factor).round() as i64,
+            elevation_factor.map_or(0, |factor| {
+                (waypoint.elevation.unwrap_or(0.) * factor).round() as i64
+            }),
+        );
+        encode_value(current.0 - previous.0, &mut polyline);
+        encode_value(current.1 - previous.1, &mut polyline);
+        if elevation_factor.is_some() {
+            encode_value(current.2 - previous.2, &mut polyline);
+        }
+        previous = current;
+    }
+    polyline
+}
+
+fn encode_value(value: i64, polyline: &mut String) {
+    // The sign goes into the lowest bit, inverting the others if negative.
+    let mut value = if value < 0 { !(value << 1) } else { value << 1 };
+    // Then five bits at a time, with 0x20 flagging that more follow.
+    while value >= 0x20 {
+        polyline.push((((value & 0x1f) | 0x20) as u8 + 63) as char);
+        value >>= 5;
+    }
+    polyline.push((value as u8 + 63) as char);
+}
+
+/// Decodes the points of a polyline, with a third value for their elevation
+/// if `elevation_precision` is set.
+fn decode(
+    polyline: &str,
+    precision: u32,
+    elevation_precision: Option<u32>,
+) -> Result<Vec<Waypoint>> {
+    let factor = 10f64.powi(precision as i32);
+    let elevation_factor = elevation_precision.map(|precision| 10f64.powi(precision as i32));
+    let mut bytes = polyline.bytes();
+    let mut points = Vec::new();
+    let (mut latitude, mut longitude, mut elevation) = (0, 0, 0);
+    while let Some(value) = decode_value(&mut bytes)? {
+        latitude += value;
+        longitude += decode_value(&mut bytes)?
+            .ok_or_else(|| Error::from("polyline ends with a latitude but no longitude"))?;
+        let mut point = Waypoint::try_new(latitude as f64 / factor, longitude as f64 / factor)?;
+        if let Some(elevation_factor) = elevation_factor {
+            elevation += decode_value(&mut bytes)?
+                .ok_or_else(|| Error::from("polyline ends with a longitude but no elevation"))?;
+            point.elevation = Some(elevation as f64 / elevation_factor);
+        }
+        points.push(point);
+    }
+    Ok(points)
+}
+
+/// Decodes the next value of a polyline, or gives `None` at its end.
+fn decode_value(bytes: &mut impl Iterator<Item = u8>) -> Result<Option<i64>> {
+    let mut value: i64 = 0;
+    let mut shift = 0;
+    loop {
+        let byte = match bytes.next() {
+            Some(byte) => byte,
+            None if shift == 0 => return Ok(None),
+            None => return Err(Error::from("polyline ends in the middle of a value")),
+        };
+        if !(63..=126).contains(&byte) || shift > 60 {
+            return Err(Error::from(format!(
+                "invalid character {:?} in polyline",
+                byte as char
+            )));
+        }
+        let chunk = i64::from(byte - 63);
+        value |= (chunk & 0x1f) << shift;
+        shift += 5;
+        if chunk < 0x20 {
+            break;
+        }
+    }
+    Ok(Some(if value & 1 == 1 {
+        !(value >> 1)
+    } else {
+        value >> 1
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Track, TrackSegment, Waypoint};
+
+    fn segment(points: &[(f64, f64)]) -> TrackSegment {
+        let mut segment = TrackSegment::new();
+        for &(lat, lon) in points {
+            segment.points.push(Waypoint::try_new(lat, lon).unwrap());
+        }
+        segment
+    }
+
+    #[test]
+    fn round_trip_precision_6() {
+        let segment = segment(&[(37.241234, -121.971234), (-33.868, 151.209), (0., 0.)]);
+
+        let polyline = segment.to_polyline(6);
+        let decoded = TrackSegment::from_polyline(&polyline, 6).unwrap();
+
+        assert_eq!(decoded.linestring(), segment.linestring());
+    }
+
+    #[test]
+    fn track_joins_segments() {
+        let mut track = Track::new();
+        track.segments.push(segment(&[(38.5, -120.2)]));
+        track
+            .segments
+            .push(segment(&[(40.7, -120.95), (43.252, -126.453)]));
+
+        assert_eq!(track.to_polyline(5), "_p~iF~ps|U_ulLnnqC_mqNvxq`@");
+        assert_eq!(
+            Track::from_polyline("_p~iF~ps|U_ulLnnqC_mqNvxq`@", 5)
+                .unwrap()
+                .segments[0]
+                .points
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn round_trip_elevation() {
+        let mut segment = segment(&[(37.241234, -121.971234), (-33.868, 151.209), (0., 0.)]);
+        segment.points[0].elevation = Some(-12.5);
+        segment.points[1].elevation = Some(1234.56);
+
+        let polyline = segment.to_polyline_with_elevation(6, 2);
+        let decoded = TrackSegment::from_polyline_with_elevation(&polyline, 6, 2).unwrap();
+
+        assert_eq!(decoded.linestring(), segment.linestring());
+        let elevations: Vec<_> = decoded.points.iter().map(|point| point.elevation).collect();
+        assert_eq!(elevations, vec![Some(-12.5), Some(1234.56), Some(0.)]);
+        assert!(TrackSegment::from_polyline(&polyline, 6).is_err());
+    }
+
+    #[test]
+    fn decode_invalid() {
+        // Truncated in the middle of a value, and after a latitude.
+        assert!(TrackSegment::from_polyline("_p~", 5).is_err());
+        assert!(TrackSegment::from_polyline("_p~iF", 5).is_err());
+        assert!(TrackSegment::from_polyline("_p~iF ps|U", 5).is_err());
+        // A latitude of 385 degrees.
+        assert!(TrackSegment::from_polyline("_p~iF~ps|U", 4).is_err());
+    }
+}
@@ -0,0 +1,301 @@
+//! Parses GPX fed in chunks, like the body of an HTTP request.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::errors::*;
+use crate::parser::gpx::{self, Root};
+use crate::parser::{create_context, Context};
+use crate::{Gpx, GpxVersion, Route, Track, Waypoint};
+
+/// ParsedElement is a child of the root element, parsed as soon as its
+/// closing tag was fed to a `PushParser`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParsedElement {
+    /// A `wpt` element.
+    Waypoint(Box<Waypoint>),
+    /// An `rte` element.
+    Route(Route),
+    /// A `trk` element.
+    Track(Track),
+}
+
+/// PushParser parses GPX from chunks of bytes pushed to it, instead of
+/// pulling them from a reader.
+///
+/// Waypoints, routes and tracks are given out as soon as they are complete,
+/// and forgotten; the rest of the document is kept until `finish`.
+///
+/// ```
+/// use gpx::{ParsedElement, PushParser};
+///
+/// let mut parser = PushParser::new();
+/// let mut tracks = Vec::new();
+/// for chunk in &["<gpx version=\"1.1\"><trk><trkseg><trkpt lat=\"1\"", " lon=\"2\"/></trkseg></trk>", "</gpx>"] {
+///     for element in parser.feed(chunk.as_bytes()).unwrap() {
+///         if let ParsedElement::Track(track) = element {
+///             tracks.push(track);
+///         }
+///     }
+/// }
+/// let gpx = parser.finish().unwrap();
+///
+/// assert_eq!(tracks.len(), 1);
+/// assert!(gpx.tracks.is_empty());
+/// ```
+pub struct PushParser {
+    // Bytes fed and not passed on to the XML parser yet, scanned up to
+    // `scanned`, and how many elements are open there.
+    buffer: Vec<u8>,
+    scanned: usize,
+    depth: usize,
+    // How many children of the root were passed on whole and not parsed.
+    children: usize,
+    // The parser of the whole document, only passed bytes up to the end of
+    // the root's start tag or of one of its children, and what it read of
+    // the root.
+    context: Context<VecDeque<u8>>,
+    root: Option<Root>,
+}
+
+impl PushParser {
+    /// Creates a parser, to be fed a whole document.
+    pub fn new() -> PushParser {
+        PushParser {
+            buffer: Vec::new(),
+            scanned: 0,
+            depth: 0,
+            children: 0,
+            context: create_context(VecDeque::new(), GpxVersion::default()),
+            root: None,
+        }
+    }
+
+    /// Feeds the next bytes of the document to the parser, giving the
+    /// waypoints, routes and tracks they complete.
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<Vec<ParsedElement>> {
+        self.buffer.extend_from_slice(bytes);
+        let mut elements = Vec::new();
+        while let Some((start, end)) = self.next_markup() {
+            self.scanned = end;
+            let tag = &self.buffer[start..end];
+            if tag.starts_with(b"<?") || tag.starts_with(b"<!") {
+                continue;
+            }
+            let start_tag = !tag.starts_with(b"</");
+            let end_tag = !start_tag || tag.ends_with(b"/>");
+            if start_tag {
+                self.depth += 1;
+                if self.depth == 1 {
+                    self.pass_on(end);
+                    self.root = Some(self.start()?);
+                }
+            }
+            if end_tag {
+                self.depth = self.depth.saturating_sub(1);
+                if self.depth == 1 {
+                    self.pass_on(end);
+                    self.children += 1;
+                }
+            }
+        }
+
+        let context = &mut self.context;
+        if let Some(ref mut root) = self.root {
+            while self.children > 0 {
+                self.children -= 1;
+                let element = root.next(context).map_err(|error| context.locate(error))?;
+                elements.extend(element);
+            }
+        }
+        Ok(elements)
+    }
+
+    /// Ends the document, giving all of it but the waypoints, routes and
+    /// tracks already given out by `feed`, or an error if it's incomplete.
+    pub fn finish(mut self) -> Result<Gpx> {
+        self.pass_on(self.buffer.len());
+        let root = match self.root.take() {
+            Some(root) => root,
+            None => self.start()?,
+        };
+        let context = &mut self.context;
+        root.finish(context).map_err(|error| context.locate(error))
+    }
+
+    /// Finds the next complete tag, comment or other markup to scan, if it
+    /// was fed yet.
+    fn next_markup(&self) -> Option<(usize, usize)> {
+        let start = self.scanned
+            + self.buffer[self.scanned..]
+                .iter()
+                .position(|&b| b == b'<')?;
+        let length = markup_length(&self.buffer[start..])?;
+        Some((start, start + length))
+    }
+
+    /// Passes the bytes fed up to `end` on to the XML parser.
+    fn pass_on(&mut self, end: usize) {
+        self.context
+            .reader()
+            .get_mut()
+            .extend(self.buffer.drain(..end));
+        self.scanned -= self.scanned.min(end);
+    }
+
+    /// Reads the start tag of the root, passed on whole.
+    fn start(&mut self) -> Result<Root> {
+        let context = &mut self.context;
+        gpx::start(context).map_err(|error| context.locate(error))
+    }
+}
+
+impl Default for PushParser {
+    fn default() -> PushParser {
+        PushParser::new()
+    }
+}
+
+impl fmt::Debug for PushParser {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PushParser")
+            .field("buffered", &self.buffer.len())
+            .field("depth", &self.depth)
+            .finish()
+    }
+}
+
+/// Gives the length of the markup `bytes` start with, if all of it is
+/// there.
+fn markup_length(bytes: &[u8]) -> Option<usize> {
+    for &(open, close) in &[("<!--", "-->"), ("<![CDATA[", "]]>"), ("<?", "?>")] {
+        let open = open.as_bytes();
+        if bytes.starts_with(open) {
+            return find(&bytes[open.len()..], close.as_bytes())
+                .map(|index| open.len() + index + close.len());
+        }
+        if open.starts_with(bytes) {
+            // Too short to tell what it is yet.
+            return None;
+        }
+    }
+
+    // Tags end at the first '>' out of quotes, doctypes out of brackets.
+    let mut quote = None;
+    let mut brackets = 0;
+    for (index, &byte) in bytes.iter().enumerate() {
+        match (quote, byte) {
+            (Some(q), _) if q == byte => quote = None,
+            (Some(_), _) => {}
+            (None, b'"') | (None, b'\'') => quote = Some(byte),
+            (None, b'[') => brackets += 1,
+            (None, b']') => brackets -= 1,
+            (None, b'>') if brackets <= 0 => return Some(index + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{markup_length, ParsedElement, PushParser};
+    use crate::read;
+
+    #[test]
+    fn same_as_read_in_any_chunks() {
+        let data = std::fs::read("tests/fixtures/garmin-activity.gpx").unwrap();
+        let expected = read(data.as_slice()).unwrap();
+
+        for size in &[1, 7, 1024, data.len()] {
+            let mut parser = PushParser::new();
+            let mut routes = Vec::new();
+            let mut tracks = Vec::new();
+            for chunk in data.chunks(*size) {
+                for element in parser.feed(chunk).unwrap() {
+                    match element {
+                        ParsedElement::Route(route) => routes.push(route),
+                        ParsedElement::Track(track) => tracks.push(track),
+                        element => panic!("unexpected {:?}", element),
+                    }
+                }
+            }
+            let gpx = parser.finish().unwrap();
+
+            assert_eq!(routes, expected.routes);
+            assert_eq!(tracks, expected.tracks);
+            assert_eq!(gpx.metadata, expected.metadata);
+            assert!(gpx.routes.is_empty() && gpx.tracks.is_empty());
+        }
+    }
+
+    #[test]
+    fn elements_as_they_end() {
+        let mut parser = PushParser::new();
+
+        let elements = parser
+            .feed(b"<?xml version=\"1.0\"?><!-- <wpt> --><gpx version=\"1.0\"><wpt lat=\"1\" lon=\"2\"/><rte><rtept")
+            .unwrap();
+        assert_eq!(elements.len(), 1);
+        let elements = parser
+            .feed(b" lat=\"1\" lon=\"2\"><name><![CDATA[</rte>]]></name></rtept></rte><trk>")
+            .unwrap();
+        match &elements[..] {
+            [ParsedElement::Route(route)] => {
+                assert_eq!(route.points[0].name.as_deref(), Some("</rte>"))
+            }
+            elements => panic!("unexpected {:?}", elements),
+        }
+        assert!(parser.feed(b"</trk></gpx>").unwrap().len() == 1);
+        assert!(parser.finish().is_ok());
+    }
+
+    #[test]
+    fn entities_of_the_doctype() {
+        let mut parser = PushParser::new();
+
+        parser
+            .feed(b"<!DOCTYPE gpx [<!ENTITY camp \"Camp\">]><gpx version=\"1.1\">")
+            .unwrap();
+        let elements = parser
+            .feed(b"<wpt lat=\"1\" lon=\"2\"><name>&camp;</name></wpt>")
+            .unwrap();
+        match &elements[..] {
+            [ParsedElement::Waypoint(waypoint)] => {
+                assert_eq!(waypoint.name.as_deref(), Some("Camp"))
+            }
+            elements => panic!("unexpected {:?}", elements),
+        }
+        parser.feed(b"</gpx>").unwrap();
+        assert!(parser.finish().is_ok());
+    }
+
+    #[test]
+    fn invalid_or_truncated() {
+        let mut parser = PushParser::new();
+        assert!(parser.feed(b"<gpx creator=\"a\">").is_err());
+
+        let mut parser = PushParser::new();
+        parser.feed(b"<gpx version=\"1.1\"><trk>").unwrap();
+        assert!(parser.finish().is_err());
+
+        let mut parser = PushParser::new();
+        parser.feed(b"<gpx version=\"1.1\">").unwrap();
+        assert!(parser.feed(b"<wpt lat=\"north\" lon=\"2\"/>").is_err());
+    }
+
+    #[test]
+    fn markup_lengths() {
+        assert_eq!(markup_length(b"<a b='>'>c"), Some(9));
+        assert_eq!(markup_length(b"<!-- > -->"), Some(10));
+        assert_eq!(markup_length(b"<!-"), None);
+        assert_eq!(markup_length(b"<!DOCTYPE gpx [<!ENTITY a 'b'>]>"), Some(32));
+        assert_eq!(markup_length(b"<a b=\">"), None);
+    }
+}
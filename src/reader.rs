@@ -1,15 +1,29 @@
 //! Reads an activity from GPX format.
 
-use std::io::Read;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read};
+use std::ops::{BitOr, BitOrAssign};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use error_chain::bail;
+use xml::reader::XmlEvent;
 
 use crate::errors::*;
-use crate::parser::{create_context, gpx};
-use crate::{Gpx, GpxVersion};
+use crate::parser::gpx::version_string_to_version;
+use crate::parser::{
+    create_context, create_context_with_options, create_str_context, gpx, waypoint, Context,
+};
+use crate::warning::ParseWarning;
+use crate::{Gpx, GpxVersion, Waypoint};
 
 /// Reads an activity in GPX format.
 ///
 /// Takes any `std::io::Read` as its reader, and returns a
-/// `Result<Gpx>`.
+/// `Result<Gpx>`. Documents in UTF-16 with a byte order mark, or declared
+/// to be in Latin-1, are read as well as UTF-8, and with the `gzip`
+/// feature, gzip-compressed GPX is detected and decompressed.
 ///
 /// ```
 /// use std::io::BufReader;
@@ -33,5 +47,1534 @@ use crate::{Gpx, GpxVersion};
 /// }
 /// ```
 pub fn read<R: Read>(reader: R) -> Result<Gpx> {
-    gpx::consume(&mut create_context(reader, GpxVersion::Unknown))
+    let mut context = create_context(decompress(reader)?, GpxVersion::default());
+    gpx::consume(&mut context).map_err(|error| context.locate(error))
+}
+
+impl Gpx {
+    /// Reads an activity in GPX format from any `std::io::Read`, like `read`.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Gpx> {
+        read(reader)
+    }
+
+    /// Reads an activity in GPX format from bytes in memory, like `read`,
+    /// but parsing the slice itself rather than through a buffer of its
+    /// first bytes, kept to tell whether it's compressed.
+    ///
+    /// ```
+    /// use gpx::Gpx;
+    ///
+    /// let gpx = Gpx::from_bytes(b"<gpx version=\"1.1\"></gpx>").unwrap();
+    /// assert!(gpx.tracks.is_empty());
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Result<Gpx> {
+        #[cfg(feature = "gzip")]
+        {
+            if crate::gzip::is_compressed(bytes) {
+                return crate::gzip::read_gzip(bytes);
+            }
+        }
+        let mut context = create_context(bytes, GpxVersion::default());
+        gpx::consume(&mut context).map_err(|error| context.locate(error))
+    }
+}
+
+/// Reads an activity in GPX format from a string, so that it can be
+/// `parse`d. The string is parsed as it is, already decoded, whatever
+/// encoding the XML declaration names.
+///
+/// ```
+/// use gpx::Gpx;
+///
+/// let gpx: Gpx = "<gpx version=\"1.1\"><wpt lat=\"1\" lon=\"2\"/></gpx>".parse().unwrap();
+/// assert_eq!(gpx.waypoints.len(), 1);
+/// ```
+impl FromStr for Gpx {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Gpx> {
+        let mut context = create_str_context(s, GpxVersion::default());
+        gpx::consume(&mut context).map_err(|error| context.locate(error))
+    }
+}
+
+/// Gives the GPX in `reader`, decompressed if needed.
+#[cfg(feature = "gzip")]
+fn decompress<R: Read>(reader: R) -> Result<impl Read> {
+    crate::gzip::decompress(reader)
+}
+
+#[cfg(not(feature = "gzip"))]
+fn decompress<R: Read>(reader: R) -> Result<R> {
+    Ok(reader)
+}
+
+/// ParserOptions controls how GPX is read.
+///
+/// The default is as strict as `read`. External entities and DTDs are never
+/// loaded, whatever the options, and entities declared in the document are
+/// expanded within limits, against "billion laughs" attacks.
+///
+/// GPX elements are told by their local name, so that documents without the
+/// GPX namespace, or with a wrong one, as some exporters write them, are read
+/// all the same.
+#[derive(Clone, Default)]
+pub struct ParserOptions {
+    /// Whether to skip invalid waypoints, route points and track points,
+    /// like those with a mangled coordinate or time, instead of failing to
+    /// read the whole document. Broken XML still fails.
+    pub lenient: bool,
+
+    /// Whether to give what was read of a truncated document, like one cut
+    /// off by a crashed logger, instead of failing. Elements cut off are
+    /// left out.
+    pub partial: bool,
+
+    /// Function called with the progress of reading, every thousand points
+    /// and once done, like to update a progress bar.
+    pub on_progress: Option<Arc<dyn Fn(Progress) + Send + Sync>>,
+
+    /// Cancellation checked after every point, to stop reading once
+    /// cancelled with a `Cancelled` error.
+    pub cancellation: Option<Cancellation>,
+
+    /// Optional elements to skip without parsing them, wherever they are,
+    /// when only some of them are needed.
+    pub skip: Fields,
+
+    /// Most characters entities declared in the document may expand to, or
+    /// `None` for the XML parser's own limit.
+    pub max_entity_expansion: Option<usize>,
+
+    /// Most characters of an attribute value, or `None` for the XML parser's
+    /// own limit.
+    pub max_attribute_length: Option<usize>,
+
+    /// Most characters of text, like a name or a description, or `None` for
+    /// the XML parser's own limit.
+    pub max_text_length: Option<usize>,
+
+    /// Most waypoints, route points and track points to read, failing with
+    /// a `LimitExceeded` error past them.
+    pub max_points: Option<usize>,
+
+    /// How deep elements may be nested, the root counting as 1 and track
+    /// points as 4, failing with a `LimitExceeded` error deeper.
+    pub max_nesting_depth: Option<usize>,
+
+    /// Most bytes of the document to read, once decompressed, failing with a
+    /// `LimitExceeded` error past them.
+    pub max_bytes: Option<u64>,
+
+    /// Whether to reject documents which don't conform to the GPX schema,
+    /// though they can be read: those lacking required attributes like
+    /// `creator`, with elements out of order, or repeating elements which
+    /// can only appear once, or without the namespace of their version of
+    /// GPX, which must be 1.0 or 1.1. Extensions are not checked.
+    pub strict: bool,
+
+    /// Whether to swap back the coordinates of points which only make sense
+    /// swapped, with a latitude beyond ±90° but a longitude within, as some
+    /// broken exporters write them. `read_with_warnings` reports each point
+    /// repaired. Otherwise, these points are invalid.
+    pub repair_swapped: bool,
+
+    /// Whether to keep the offset from UTC the times of points were written
+    /// with, as `Waypoint::time_offset`, to write them with it again. Times
+    /// are converted to UTC either way, and those without an offset are
+    /// taken to be in UTC.
+    pub preserve_offsets: bool,
+
+    /// How malformed numbers, like elevations and dilutions of precision,
+    /// are coerced into valid ones or missing values instead of failing.
+    pub coercion: Coercion,
+}
+
+impl fmt::Debug for ParserOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ParserOptions")
+            .field("lenient", &self.lenient)
+            .field("partial", &self.partial)
+            .field("on_progress", &self.on_progress.as_ref().map(|_| "Fn"))
+            .field("cancellation", &self.cancellation)
+            .field("skip", &self.skip)
+            .field("max_entity_expansion", &self.max_entity_expansion)
+            .field("max_attribute_length", &self.max_attribute_length)
+            .field("max_text_length", &self.max_text_length)
+            .field("max_points", &self.max_points)
+            .field("max_nesting_depth", &self.max_nesting_depth)
+            .field("max_bytes", &self.max_bytes)
+            .field("strict", &self.strict)
+            .field("repair_swapped", &self.repair_swapped)
+            .field("preserve_offsets", &self.preserve_offsets)
+            .field("coercion", &self.coercion)
+            .finish()
+    }
+}
+
+/// Options are equal whatever their progress callbacks.
+impl PartialEq for ParserOptions {
+    fn eq(&self, other: &ParserOptions) -> bool {
+        let ParserOptions {
+            lenient,
+            partial,
+            on_progress: _,
+            cancellation,
+            skip,
+            max_entity_expansion,
+            max_attribute_length,
+            max_text_length,
+            max_points,
+            max_nesting_depth,
+            max_bytes,
+            strict,
+            repair_swapped,
+            preserve_offsets,
+            coercion,
+        } = self;
+        *lenient == other.lenient
+            && *partial == other.partial
+            && *cancellation == other.cancellation
+            && *skip == other.skip
+            && *max_entity_expansion == other.max_entity_expansion
+            && *max_attribute_length == other.max_attribute_length
+            && *max_text_length == other.max_text_length
+            && *max_points == other.max_points
+            && *max_nesting_depth == other.max_nesting_depth
+            && *max_bytes == other.max_bytes
+            && *strict == other.strict
+            && *repair_swapped == other.repair_swapped
+            && *preserve_offsets == other.preserve_offsets
+            && *coercion == other.coercion
+    }
+}
+
+/// Values taken as missing numbers by `Coercion::placeholders_as_missing`.
+const PLACEHOLDERS: &[&str] = &["", "-", "--", "nan", "n/a", "null", "none"];
+
+/// Coercion tells how to read malformed numbers found in the wild, as
+/// `ParserOptions::coercion`. The default reads them as they are, failing
+/// if they are not valid.
+///
+/// ```
+/// use gpx::{read_with_options, Coercion, ParserOptions};
+///
+/// let data = "<gpx version=\"1.1\">
+///     <wpt lat=\"37,24\" lon=\"-121,97\"><ele>-</ele><hdop> 1,5 </hdop></wpt>
+/// </gpx>";
+/// let options = ParserOptions {
+///     coercion: Coercion {
+///         placeholders_as_missing: true,
+///         decimal_comma: true,
+///         trim_whitespace: true,
+///     },
+///     ..Default::default()
+/// };
+///
+/// let (gpx, _) = read_with_options(data.as_bytes(), &options).unwrap();
+/// assert_eq!(gpx.waypoints[0].point().lat(), 37.24);
+/// assert_eq!(gpx.waypoints[0].elevation, None);
+/// assert_eq!(gpx.waypoints[0].hdop, Some(1.5));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Coercion {
+    /// Whether to take empty numbers, `NaN`, and placeholders like `-`,
+    /// `N/A` or `null` as missing values, rather than invalid ones.
+    pub placeholders_as_missing: bool,
+
+    /// Whether to read a comma as the decimal separator, as in `553,21`,
+    /// in numbers without a period.
+    pub decimal_comma: bool,
+
+    /// Whether to ignore whitespace around numbers, as in `<ele> 553.21
+    /// </ele>`.
+    pub trim_whitespace: bool,
+}
+
+impl Coercion {
+    /// Gives a number to parse, or `None` if it's taken as missing.
+    pub(crate) fn coerce(self, number: &str) -> Option<String> {
+        let number = if self.trim_whitespace {
+            number.trim()
+        } else {
+            number
+        };
+        if self.placeholders_as_missing
+            && PLACEHOLDERS
+                .iter()
+                .any(|placeholder| number.eq_ignore_ascii_case(placeholder))
+        {
+            return None;
+        }
+        if self.decimal_comma && !number.contains('.') {
+            return Some(number.replacen(',', ".", 1));
+        }
+        Some(number.to_string())
+    }
+}
+
+/// Fields is a set of optional GPX elements, which reading can skip.
+///
+/// ```
+/// use gpx::{read_with_options, Fields, ParserOptions};
+///
+/// let data = "<gpx version=\"1.1\"><wpt lat=\"1\" lon=\"2\">
+///     <time>2020-06-01T12:00:00Z</time><name>Camp</name><sym>Tent</sym>
+/// </wpt></gpx>";
+/// let options = ParserOptions {
+///     skip: Fields::NAMES | Fields::SYMBOLS,
+///     ..Default::default()
+/// };
+///
+/// let (gpx, _) = read_with_options(data.as_bytes(), &options).unwrap();
+/// assert!(gpx.waypoints[0].time.is_some());
+/// assert_eq!(gpx.waypoints[0].name, None);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Fields(u32);
+
+impl Fields {
+    /// No elements.
+    pub const NONE: Fields = Fields(0);
+    /// `ele` and `geoidheight`.
+    pub const ELEVATIONS: Fields = Fields(1);
+    /// `time`.
+    pub const TIMES: Fields = Fields(1 << 1);
+    /// `name`.
+    pub const NAMES: Fields = Fields(1 << 2);
+    /// `cmt`.
+    pub const COMMENTS: Fields = Fields(1 << 3);
+    /// `desc`, and `keywords` of GPX 1.0.
+    pub const DESCRIPTIONS: Fields = Fields(1 << 4);
+    /// `src`.
+    pub const SOURCES: Fields = Fields(1 << 5);
+    /// `link`, and `url` and `urlname` of GPX 1.0.
+    pub const LINKS: Fields = Fields(1 << 6);
+    /// `sym`.
+    pub const SYMBOLS: Fields = Fields(1 << 7);
+    /// `type`.
+    pub const TYPES: Fields = Fields(1 << 8);
+    /// `fix`, `sat`, `hdop`, `vdop`, `pdop`, `ageofdgpsdata` and `dgpsid`.
+    pub const ACCURACY: Fields = Fields(1 << 9);
+    /// `magvar`, and `speed` and `course` of GPX 1.0.
+    pub const MOTION: Fields = Fields(1 << 10);
+    /// `extensions`.
+    pub const EXTENSIONS: Fields = Fields(1 << 11);
+
+    /// Tells whether all of `other` is in the set.
+    pub fn contains(self, other: Fields) -> bool {
+        other != Fields::NONE && self.0 & other.0 == other.0
+    }
+
+    /// Gives the field a GPX element holds, if it's an optional one.
+    pub(crate) fn of_element(name: &str) -> Fields {
+        match name {
+            "ele" | "geoidheight" => Fields::ELEVATIONS,
+            "time" => Fields::TIMES,
+            "name" => Fields::NAMES,
+            "cmt" => Fields::COMMENTS,
+            "desc" | "description" | "keywords" => Fields::DESCRIPTIONS,
+            "src" => Fields::SOURCES,
+            "link" | "url" | "urlname" => Fields::LINKS,
+            "sym" => Fields::SYMBOLS,
+            "type" => Fields::TYPES,
+            "fix" | "sat" | "hdop" | "vdop" | "pdop" | "ageofdgpsdata" | "dgpsid" => {
+                Fields::ACCURACY
+            }
+            "magvar" | "speed" | "course" => Fields::MOTION,
+            "extensions" => Fields::EXTENSIONS,
+            _ => Fields::NONE,
+        }
+    }
+}
+
+impl BitOr for Fields {
+    type Output = Fields;
+
+    fn bitor(self, other: Fields) -> Fields {
+        Fields(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for Fields {
+    fn bitor_assign(&mut self, other: Fields) {
+        self.0 |= other.0;
+    }
+}
+
+/// Progress tells how far reading a document got.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Progress {
+    /// Number of bytes of the document read.
+    pub bytes: u64,
+
+    /// Number of waypoints, route points and track points read.
+    pub points: usize,
+}
+
+/// Cancellation lets reading a document be stopped from another thread, or
+/// from a progress callback.
+///
+/// ```
+/// use std::sync::Arc;
+/// use gpx::{read_with_options, Cancellation, ParserOptions};
+///
+/// let cancellation = Cancellation::new();
+/// let cancel = cancellation.clone();
+/// let options = ParserOptions {
+///     on_progress: Some(Arc::new(move |_| cancel.cancel())),
+///     cancellation: Some(cancellation),
+///     ..Default::default()
+/// };
+///
+/// let point = "<wpt lat=\"1\" lon=\"2\"/>";
+/// let data = format!("<gpx version=\"1.1\">{}</gpx>", point.repeat(5000));
+/// assert!(read_with_options(data.as_bytes(), &options).is_err());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Cancellation {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Cancellation {
+    /// Creates a cancellation, not cancelled yet.
+    pub fn new() -> Cancellation {
+        Default::default()
+    }
+
+    /// Cancels reading, for all clones of the cancellation.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Tells whether reading was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Cancellations are equal if they are clones of each other.
+impl PartialEq for Cancellation {
+    fn eq(&self, other: &Cancellation) -> bool {
+        Arc::ptr_eq(&self.cancelled, &other.cancelled)
+    }
+}
+
+/// Reads an activity in GPX format, according to `options`.
+///
+/// Gives the errors of the points skipped in lenient mode along with the
+/// activity, followed by the error of where a partial document ends.
+///
+/// ```
+/// use gpx::{read_with_options, ParserOptions};
+///
+/// let data = "<gpx version=\"1.1\"><trk><trkseg>
+///     <trkpt lat=\"37.24\" lon=\"-121.97\"/>
+///     <trkpt lat=\"37.25\" lon=\"-121.96\"><time>noon</time></trkpt>
+/// </trkseg></trk></gpx>";
+/// let options = ParserOptions {
+///     lenient: true,
+///     ..Default::default()
+/// };
+///
+/// let (gpx, skipped) = read_with_options(data.as_bytes(), &options).unwrap();
+/// assert_eq!(gpx.tracks[0].segments[0].points.len(), 1);
+/// assert_eq!(skipped.len(), 1);
+/// ```
+pub fn read_with_options<R: Read>(reader: R, options: &ParserOptions) -> Result<(Gpx, Vec<Error>)> {
+    let mut context =
+        create_context_with_options(decompress(reader)?, GpxVersion::default(), options);
+    let gpx = gpx::consume(&mut context).map_err(|error| context.locate(error))?;
+    context.report_progress();
+    Ok((gpx, context.take_skipped()))
+}
+
+/// Reads an activity in GPX format according to `options`, like
+/// `read_with_options` does, also giving warnings about suspicious content
+/// which was read anyway: track points with the same time as the previous
+/// one, segments without points, and dilutions of precision out of range.
+///
+/// ```
+/// use gpx::{read_with_warnings, WarningKind};
+///
+/// let data = "<gpx version=\"1.1\"><trk><trkseg></trkseg></trk></gpx>";
+///
+/// let (gpx, _, warnings) = read_with_warnings(data.as_bytes(), &Default::default()).unwrap();
+/// assert_eq!(gpx.tracks[0].segments.len(), 1);
+/// assert_eq!(warnings[0].kind, WarningKind::EmptySegment);
+/// assert_eq!(warnings[0].location.path, "gpx/trk[0]/trkseg[0]");
+/// ```
+pub fn read_with_warnings<R: Read>(
+    reader: R,
+    options: &ParserOptions,
+) -> Result<(Gpx, Vec<Error>, Vec<ParseWarning>)> {
+    let mut context =
+        create_context_with_options(decompress(reader)?, GpxVersion::default(), options);
+    context.collect_warnings();
+    let gpx = gpx::consume(&mut context).map_err(|error| context.locate(error))?;
+    context.report_progress();
+    Ok((gpx, context.take_skipped(), context.take_warnings()))
+}
+
+/// Reads the GPX documents written one after the other in `reader`, as by
+/// loggers which append a document to the same file each session. The
+/// documents are read one at a time, as they are iterated over.
+///
+/// ```
+/// use gpx::read_documents;
+///
+/// let data = "<?xml version=\"1.0\"?><gpx version=\"1.1\"><wpt lat=\"1\" lon=\"2\"/></gpx>
+/// <?xml version=\"1.0\"?><gpx version=\"1.1\"></gpx>
+/// ";
+///
+/// let documents: Vec<_> = read_documents(data.as_bytes()).unwrap().collect();
+/// assert_eq!(documents.len(), 2);
+/// assert_eq!(documents[0].as_ref().unwrap().waypoints.len(), 1);
+/// ```
+pub fn read_documents<R: Read>(reader: R) -> Result<Documents<impl Read>> {
+    Ok(Documents(read_documents_with_options(
+        reader,
+        &Default::default(),
+    )?))
+}
+
+/// Reads the GPX documents written one after the other in `reader`, like
+/// `read_documents`, but each according to `options`, as `read_with_options`
+/// does. Limits like `max_bytes` hold for each document, and errors are
+/// located within their document.
+///
+/// ```
+/// use gpx::{read_documents_with_options, ParserOptions};
+///
+/// let data = "<gpx version=\"1.1\"><wpt lat=\"1\" lon=\"2\"/><wpt lat=\"a\" lon=\"2\"/></gpx>
+/// <gpx version=\"1.1\"><wpt lat=\"3\" lon=\"2\"/>";
+/// let options = ParserOptions {
+///     lenient: true,
+///     partial: true,
+///     ..Default::default()
+/// };
+///
+/// let documents: Vec<_> = read_documents_with_options(data.as_bytes(), &options)
+///     .unwrap()
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(documents[0].0.waypoints.len(), 1);
+/// assert_eq!(documents[0].1.len(), 1);
+/// assert_eq!(documents[1].0.waypoints.len(), 1);
+/// ```
+pub fn read_documents_with_options<R: Read>(
+    reader: R,
+    options: &ParserOptions,
+) -> Result<DocumentsWithOptions<impl Read>> {
+    Ok(DocumentsWithOptions {
+        reader: BufReader::new(decompress(reader)?),
+        options: options.clone(),
+        done: false,
+    })
+}
+
+/// Documents iterates over GPX documents written one after the other.
+/// Created by `read_documents`.
+///
+/// The iteration ends after the first error.
+pub struct Documents<R: Read>(DocumentsWithOptions<R>);
+
+impl<R: Read> Iterator for Documents<R> {
+    type Item = Result<Gpx>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|result| result.map(|(gpx, _)| gpx))
+    }
+}
+
+/// DocumentsWithOptions iterates over GPX documents written one after the
+/// other, with the errors skipped in each. Created by
+/// `read_documents_with_options`.
+///
+/// The iteration ends after the first error.
+pub struct DocumentsWithOptions<R: Read> {
+    reader: BufReader<R>,
+    options: ParserOptions,
+    done: bool,
+}
+
+impl<R: Read> Iterator for DocumentsWithOptions<R> {
+    type Item = Result<(Gpx, Vec<Error>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match skip_whitespace(&mut self.reader) {
+            Ok(true) => {}
+            Ok(false) => {
+                self.done = true;
+                return None;
+            }
+            Err(error) => {
+                self.done = true;
+                return Some(Err(error).chain_err(|| "error while reading gpx"));
+            }
+        }
+        // The XML parser reads no further than the end tag of the root, so
+        // that a new one reads the next document from there.
+        let mut context =
+            create_context_with_options(&mut self.reader, GpxVersion::default(), &self.options);
+        let result = gpx::consume(&mut context).map_err(|error| context.locate(error));
+        self.done = result.is_err();
+        Some(result.map(|gpx| {
+            context.report_progress();
+            (gpx, context.take_skipped())
+        }))
+    }
+}
+
+/// Skips whitespace, telling whether anything else follows.
+fn skip_whitespace<R: BufRead>(reader: &mut R) -> io::Result<bool> {
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            return Ok(false);
+        }
+        let whitespace = buffer
+            .iter()
+            .take_while(|byte| byte.is_ascii_whitespace())
+            .count();
+        let more = whitespace < buffer.len();
+        reader.consume(whitespace);
+        if more {
+            return Ok(true);
+        }
+    }
+}
+
+/// GpxReader reads a GPX document piece by piece, for documents too large to
+/// be read into a `Gpx` all at once.
+///
+/// ```
+/// use gpx::GpxReader;
+///
+/// let data = "<gpx version=\"1.1\"><trk><trkseg>
+///     <trkpt lat=\"37.24\" lon=\"-121.97\"/>
+///     <trkpt lat=\"37.25\" lon=\"-121.96\"/>
+/// </trkseg></trk></gpx>";
+///
+/// for point in GpxReader::new(data.as_bytes()).waypoints() {
+///     let (track, segment, waypoint) = point.unwrap();
+///     println!("track {}, segment {}: {:?}", track, segment, waypoint.point());
+/// }
+/// ```
+pub struct GpxReader<R: Read> {
+    context: Context<R>,
+}
+
+impl<R: Read> GpxReader<R> {
+    /// Creates a reader of the GPX document in `reader`.
+    pub fn new(reader: R) -> GpxReader<R> {
+        GpxReader {
+            context: create_context(reader, GpxVersion::default()),
+        }
+    }
+
+    /// Gives an iterator over the track points of the document, along with
+    /// the index of their track and of their segment in the track.
+    ///
+    /// Points are parsed as they are iterated over, and only one is held in
+    /// memory at a time. Everything else in the document is skipped without
+    /// being checked.
+    pub fn waypoints(self) -> Waypoints<R> {
+        Waypoints {
+            context: self.context,
+            level: Level::Document,
+            tracks: 0,
+            segments: 0,
+            done: false,
+        }
+    }
+}
+
+/// The innermost element `Waypoints` is in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Level {
+    Document,
+    Gpx,
+    Track,
+    Segment,
+    End,
+}
+
+/// Waypoints iterates over the track points of a document, as
+/// `(track_index, segment_index, waypoint)`. Created by
+/// `GpxReader::waypoints`.
+///
+/// The iteration ends after the first error.
+pub struct Waypoints<R: Read> {
+    context: Context<R>,
+    level: Level,
+    // The number of tracks so far, and of segments in the current track.
+    tracks: usize,
+    segments: usize,
+    done: bool,
+}
+
+impl<R: Read> Waypoints<R> {
+    fn next_point(&mut self) -> Result<Option<(usize, usize, Waypoint)>> {
+        loop {
+            let is_point = match self.context.reader().peek() {
+                Some(Ok(XmlEvent::StartElement { name, .. })) => {
+                    self.level == Level::Segment && name.local_name == "trkpt"
+                }
+                _ => false,
+            };
+            if is_point {
+                let point = waypoint::consume(&mut self.context, "trkpt")?;
+                return Ok(Some((self.tracks - 1, self.segments - 1, point)));
+            }
+
+            let event = match self.context.reader().next() {
+                Some(event) => event.chain_err(|| "error while parsing XML")?,
+                None if self.level == Level::End => return Ok(None),
+                None => bail!(ErrorKind::MissingClosingTag("gpx")),
+            };
+            match event {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => match (self.level, name.local_name.as_str()) {
+                    (Level::Document, "gpx") => {
+                        let version = attributes
+                            .iter()
+                            .find(|attr| attr.name.local_name == "version")
+                            .ok_or(ErrorKind::InvalidElementLacksAttribute("version", "gpx"))?;
+                        self.context
+                            .set_version(version_string_to_version(&version.value));
+                        self.level = Level::Gpx;
+                    }
+                    (Level::Document, child) => {
+                        bail!(ErrorKind::InvalidChildElement(
+                            String::from(child),
+                            "document"
+                        ))
+                    }
+                    (Level::Gpx, "trk") => {
+                        self.tracks += 1;
+                        self.segments = 0;
+                        self.level = Level::Track;
+                    }
+                    (Level::Track, "trkseg") => {
+                        self.segments += 1;
+                        self.level = Level::Segment;
+                    }
+                    _ => self.skip_element()?,
+                },
+                XmlEvent::EndElement { .. } => {
+                    self.level = match self.level {
+                        Level::Segment => Level::Track,
+                        Level::Track => Level::Gpx,
+                        _ => Level::End,
+                    };
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Skips the rest of the element just started.
+    fn skip_element(&mut self) -> Result<()> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.context.reader().next() {
+                Some(event) => match event.chain_err(|| "error while parsing XML")? {
+                    XmlEvent::StartElement { .. } => depth += 1,
+                    XmlEvent::EndElement { .. } => depth -= 1,
+                    _ => {}
+                },
+                None => bail!(ErrorKind::MissingClosingTag("gpx")),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for Waypoints<R> {
+    type Item = Result<(usize, usize, Waypoint)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self
+            .next_point()
+            .map_err(|error| self.context.locate(error));
+        self.done = !matches!(result, Ok(Some(_)));
+        result.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read};
+    use std::sync::{Arc, Mutex};
+
+    use super::{
+        read, read_documents, read_documents_with_options, read_with_options, read_with_warnings,
+        Cancellation, Coercion, Fields, GpxReader, ParserOptions,
+    };
+    use crate::errors::ErrorKind;
+    use crate::WarningKind;
+    use crate::{write, Gpx, GpxVersion};
+
+    fn lenient() -> ParserOptions {
+        ParserOptions {
+            lenient: true,
+            ..Default::default()
+        }
+    }
+
+    fn partial() -> ParserOptions {
+        ParserOptions {
+            partial: true,
+            ..Default::default()
+        }
+    }
+
+    fn waypoints(count: usize) -> String {
+        let point = "<wpt lat=\"1\" lon=\"2\"/>";
+        format!("<gpx version=\"1.1\">{}</gpx>", point.repeat(count))
+    }
+
+    #[test]
+    fn progress_every_thousand_points() {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let progress = reports.clone();
+        let options = ParserOptions {
+            on_progress: Some(Arc::new(move |report| {
+                progress.lock().unwrap().push(report)
+            })),
+            ..Default::default()
+        };
+        let data = waypoints(2500);
+
+        read_with_options(data.as_bytes(), &options).unwrap();
+
+        let reports = reports.lock().unwrap();
+        let points: Vec<usize> = reports.iter().map(|report| report.points).collect();
+        assert_eq!(points, vec![1000, 2000, 2500]);
+        assert!(reports[0].bytes < reports[1].bytes);
+        assert_eq!(reports[2].bytes, data.len() as u64);
+    }
+
+    #[test]
+    fn skip_fields() {
+        let data = "<gpx version=\"1.1\" xmlns:x=\"urn:x\">
+            <metadata><name>Ride</name><time>2020-06-01T12:00:00Z</time></metadata>
+            <trk><name>Ride</name><link href=\"a\"><text>A</text></link><trkseg>
+                <trkpt lat=\"1\" lon=\"2\">
+                    <ele>not a number</ele><time>2020-06-01T12:00:00Z</time>
+                    <extensions><x:name>kept</x:name></extensions>
+                </trkpt>
+            </trkseg></trk>
+        </gpx>";
+        let options = ParserOptions {
+            skip: Fields::ELEVATIONS | Fields::NAMES | Fields::LINKS,
+            ..Default::default()
+        };
+
+        let (gpx, skipped) = read_with_options(data.as_bytes(), &options).unwrap();
+
+        let metadata = gpx.metadata.unwrap();
+        assert_eq!(metadata.name, None);
+        assert!(metadata.time.is_some());
+        assert_eq!(gpx.tracks[0].name, None);
+        assert!(gpx.tracks[0].links.is_empty());
+        let point = &gpx.tracks[0].segments[0].points[0];
+        assert_eq!(point.elevation, None);
+        assert!(point.time.is_some());
+        assert!(point.extensions.as_ref().unwrap().contains("kept"));
+        assert!(skipped.is_empty());
+    }
+
+    #[test]
+    fn strict() {
+        let strict = ParserOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let data = std::fs::read("tests/fixtures/wikipedia_example.gpx").unwrap();
+        assert!(read_with_options(data.as_slice(), &strict).is_ok());
+
+        let data = "<gpx version=\"1.1\" creator=\"a\" xmlns=\"http://www.topografix.com/GPX/1/1\"><trk><trkseg>
+            <trkpt lat=\"1\" lon=\"2\"><time>2020-06-01T12:00:00Z</time><ele>3</ele></trkpt>
+        </trkseg></trk></gpx>";
+        assert!(read(data.as_bytes()).is_ok());
+        let error = read_with_options(data.as_bytes(), &strict).unwrap_err();
+        assert_eq!(
+            error.location().unwrap().path,
+            "gpx/trk[0]/trkseg[0]/trkpt[0]"
+        );
+        assert_eq!(
+            error.to_string(),
+            "document doesn't conform to the GPX schema: element ele after time"
+        );
+    }
+
+    #[test]
+    fn strict_rejects_unknown_versions() {
+        let strict = ParserOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let documents = [
+            (
+                "9.9",
+                "<gpx version=\"9.9\" creator=\"a\"><trk/><wpt lat=\"1\" lon=\"2\"/></gpx>",
+            ),
+            (
+                "",
+                "<gpx version=\"\" creator=\"a\"><metadata><name>a</name><name>b</name></metadata></gpx>",
+            ),
+        ];
+
+        for &(version, data) in &documents {
+            let gpx = read(data.as_bytes()).unwrap();
+            assert_eq!(gpx.version, GpxVersion::Unknown(version.to_string()));
+            let error = read_with_options(data.as_bytes(), &strict).unwrap_err();
+            assert_eq!(error.location().unwrap().line, 1);
+            assert_eq!(
+                error.to_string(),
+                format!(
+                    "document doesn't conform to the GPX schema: attribute version of element gpx is {:?}, not 1.0 or 1.1",
+                    version
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn repair_swapped() {
+        let data = "<gpx version=\"1.1\"><trk><trkseg>
+            <trkpt lat=\"37.24\" lon=\"-121.97\"/><trkpt lat=\"-121.96\" lon=\"37.25\"/>
+        </trkseg></trk></gpx>";
+        let options = ParserOptions {
+            repair_swapped: true,
+            ..Default::default()
+        };
+
+        let error = read(data.as_bytes()).unwrap_err();
+        assert!(error
+            .iter()
+            .any(|error| error.to_string().contains("swapped")));
+
+        let (gpx, _, warnings) = read_with_warnings(data.as_bytes(), &options).unwrap();
+        let point = gpx.tracks[0].segments[0].points[1].point();
+        assert_eq!((point.lat(), point.lng()), (37.25, -121.96));
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0].kind,
+            WarningKind::SwappedCoordinates(-121.96, 37.25)
+        );
+        assert_eq!(warnings[0].location.path, "gpx/trk[0]/trkseg[0]/trkpt[1]");
+    }
+
+    #[test]
+    fn preserve_offsets() {
+        let data = "<gpx version=\"1.1\" creator=\"a\">
+            <wpt lat=\"1\" lon=\"2\"><time>2020-06-01T14:00:00+02:00</time></wpt>
+            <wpt lat=\"1\" lon=\"2\"><time>2020-06-01T12:00:00</time></wpt>
+        </gpx>";
+        let options = ParserOptions {
+            preserve_offsets: true,
+            ..Default::default()
+        };
+
+        let gpx = read(data.as_bytes()).unwrap();
+        assert_eq!(gpx.waypoints[0].time, gpx.waypoints[1].time);
+        assert_eq!(gpx.waypoints[0].time_offset, None);
+
+        let (gpx, _) = read_with_options(data.as_bytes(), &options).unwrap();
+        assert_eq!(gpx.waypoints[0].time_offset, Some(7200));
+        assert_eq!(gpx.waypoints[1].time_offset, None);
+        let mut written = Vec::new();
+        write(&gpx, &mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(written.contains("<time>2020-06-01T14:00:00+02:00</time>"));
+        assert!(written.contains("<time>2020-06-01T12:00:00+00:00</time>"));
+    }
+
+    #[test]
+    fn coercion() {
+        let data = "<gpx version=\"1.1\"><wpt lat=\"1\" lon=\"2\">
+            <ele>NaN</ele><sat></sat><hdop> 1,5 </hdop>
+        </wpt></gpx>";
+        let options = ParserOptions {
+            coercion: Coercion {
+                placeholders_as_missing: true,
+                decimal_comma: true,
+                trim_whitespace: true,
+            },
+            ..Default::default()
+        };
+
+        assert!(read(data.as_bytes()).is_err());
+        let (gpx, _) = read_with_options(data.as_bytes(), &options).unwrap();
+        assert_eq!(gpx.waypoints[0].elevation, None);
+        assert_eq!(gpx.waypoints[0].sat, None);
+        assert_eq!(gpx.waypoints[0].hdop, Some(1.5));
+    }
+
+    #[test]
+    fn numbers_read_as_they_are_by_default() {
+        let number = |ele: &str| {
+            let data = format!(
+                "<gpx version=\"1.1\"><wpt lat=\"1\" lon=\"2\"><ele>{}</ele></wpt></gpx>",
+                ele
+            );
+            read(data.as_bytes()).map(|gpx| gpx.waypoints[0].elevation)
+        };
+
+        assert_eq!(number("553.21").unwrap(), Some(553.21));
+        assert!(number(" 553.21 ").is_err());
+        assert_eq!(
+            number("").unwrap_err().to_string(),
+            "error while casting elevation to f64"
+        );
+    }
+
+    #[test]
+    fn nan_never_compares_equal() {
+        let data = "<gpx version=\"1.1\"><wpt lat=\"1\" lon=\"2\"><ele>NaN</ele></wpt></gpx>";
+
+        let gpx = read(data.as_bytes()).unwrap();
+
+        assert!(gpx.waypoints[0].elevation.unwrap().is_nan());
+        assert_ne!(gpx, read(data.as_bytes()).unwrap());
+        assert_ne!(gpx, gpx.clone());
+    }
+
+    #[test]
+    fn warnings() {
+        let data = "<gpx version=\"1.1\"><trk><trkseg>
+            <trkpt lat=\"1\" lon=\"2\"><time>2020-06-01T12:00:00Z</time></trkpt>
+            <trkpt lat=\"1\" lon=\"2\"><time>2020-06-01T12:00:00Z</time><hdop>0</hdop></trkpt>
+            <trkpt lat=\"1\" lon=\"2\"><time>2020-06-01T12:00:01Z</time><pdop>1.2</pdop></trkpt>
+        </trkseg></trk></gpx>";
+
+        let (gpx, _, warnings) = read_with_warnings(data.as_bytes(), &Default::default()).unwrap();
+
+        assert_eq!(gpx, read(data.as_bytes()).unwrap());
+        let warnings: Vec<_> = warnings
+            .iter()
+            .map(|warning| (warning.kind.clone(), warning.location.path.as_str()))
+            .collect();
+        let time = "2020-06-01T12:00:00Z".parse().unwrap();
+        assert_eq!(
+            warnings,
+            vec![
+                (
+                    WarningKind::DilutionOutOfRange("hdop", 0.),
+                    "gpx/trk[0]/trkseg[0]/trkpt[1]/hdop[0]"
+                ),
+                (
+                    WarningKind::DuplicateTime(time),
+                    "gpx/trk[0]/trkseg[0]/trkpt[1]"
+                ),
+            ]
+        );
+
+        let data = "<gpx version=\"1.0\"><email>nobody</email></gpx>";
+        let (_, _, warnings) = read_with_warnings(data.as_bytes(), &Default::default()).unwrap();
+        assert_eq!(
+            warnings[0].kind,
+            WarningKind::InvalidEmail(String::from("nobody"))
+        );
+    }
+
+    #[test]
+    fn fields_set() {
+        let mut fields = Fields::NAMES | Fields::TIMES;
+        fields |= Fields::TYPES;
+
+        assert!(fields.contains(Fields::NAMES | Fields::TYPES));
+        assert!(!fields.contains(Fields::NAMES | Fields::LINKS));
+        assert!(!fields.contains(Fields::NONE));
+    }
+
+    #[test]
+    fn cancelled() {
+        let cancellation = Cancellation::new();
+        let options = ParserOptions {
+            cancellation: Some(cancellation.clone()),
+            lenient: true,
+            ..Default::default()
+        };
+        cancellation.cancel();
+
+        let error = read_with_options(waypoints(1).as_bytes(), &options).unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::Cancelled));
+        assert!(read_with_options(waypoints(0).as_bytes(), &options).is_ok());
+    }
+
+    #[test]
+    fn options_equal_whatever_callback() {
+        let cancellation = Cancellation::new();
+        let options = ParserOptions {
+            cancellation: Some(cancellation.clone()),
+            ..Default::default()
+        };
+        let with_callback = ParserOptions {
+            on_progress: Some(Arc::new(|_| {})),
+            ..options.clone()
+        };
+
+        assert_eq!(options, with_callback);
+        assert_ne!(
+            options,
+            ParserOptions {
+                cancellation: Some(Cancellation::new()),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn read_from_str_bytes_and_reader() {
+        let data =
+            "<gpx version=\"1.1\"><trk><trkseg><trkpt lat=\"1\" lon=\"2\"/></trkseg></trk></gpx>";
+
+        let gpx: Gpx = data.parse().unwrap();
+        assert_eq!(Gpx::from_bytes(data.as_bytes()).unwrap(), gpx);
+        assert_eq!(Gpx::from_reader(data.as_bytes()).unwrap(), gpx);
+        assert_eq!(gpx.tracks[0].segments[0].points.len(), 1);
+
+        assert!("<gpx>".parse::<Gpx>().is_err());
+    }
+
+    #[test]
+    fn read_from_decoded_str() {
+        let data = "\u{feff}<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>
+            <gpx version=\"1.1\" creator=\"Zürich\"></gpx>";
+
+        let gpx: Gpx = data.parse().unwrap();
+        assert_eq!(gpx.creator.as_deref(), Some("Zürich"));
+    }
+
+    #[test]
+    fn error_location() {
+        let data = "<gpx version=\"1.1\">
+            <trk><trkseg><trkpt lat=\"1\" lon=\"2\"/></trkseg></trk>
+            <trk><trkseg/><trkseg>
+                <trkpt lat=\"1\" lon=\"2\"/>
+                <trkpt lat=\"1\" lon=\"2\"><ele>high</ele></trkpt>
+            </trkseg></trk>
+        </gpx>";
+
+        let error = read(data.as_bytes()).unwrap_err();
+
+        let location = error.location().unwrap();
+        assert_eq!(location.path, "gpx/trk[1]/trkseg[1]/trkpt[1]/ele[0]");
+        assert_eq!(location.text.as_deref(), Some("high"));
+        assert_eq!(location.line, 5);
+        // The error keeps its kind, and its cause after the location.
+        assert!(matches!(error.kind(), ErrorKind::Msg(_)));
+        assert!(error.iter().count() > 2);
+    }
+
+    #[test]
+    fn lenient_skips_invalid_points() {
+        let data = "<gpx version=\"1.1\">
+            <wpt lat=\"91\" lon=\"0\"/>
+            <wpt lat=\"1\" lon=\"0\"/>
+            <rte><rtept lat=\"1\" lon=\"east\"><name>A</name></rtept></rte>
+            <trk><trkseg>
+                <trkpt lat=\"1\" lon=\"2\"><ele>high</ele><link href=\"a\"><text>A</text></link></trkpt>
+                <trkpt lat=\"2\" lon=\"2\"><ele>3</ele></trkpt>
+            </trkseg></trk>
+        </gpx>";
+
+        let (gpx, skipped) = read_with_options(data.as_bytes(), &lenient()).unwrap();
+
+        assert_eq!(gpx.waypoints.len(), 1);
+        assert!(gpx.routes[0].points.is_empty());
+        let points = &gpx.tracks[0].segments[0].points;
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].elevation, Some(3.));
+        assert_eq!(skipped.len(), 3);
+    }
+
+    #[test]
+    fn strict_fails_on_invalid_points() {
+        let data = "<gpx version=\"1.1\"><wpt lat=\"91\" lon=\"0\"/></gpx>";
+
+        assert!(read_with_options(data.as_bytes(), &ParserOptions::default()).is_err());
+    }
+
+    #[test]
+    fn partial_keeps_points_before_truncation() {
+        let data = "<gpx version=\"1.1\"><metadata><name>Ride</name></metadata><trk><trkseg>
+            <trkpt lat=\"1\" lon=\"2\"><ele>3</ele></trkpt>
+            <trkpt lat=\"2\" lon=\"2\"><ele>3</ele></trkpt>
+            <trkpt lat=\"3\" lon=\"2\"><ele>3</ele><time>2020-";
+
+        let (gpx, skipped) = read_with_options(data.as_bytes(), &partial()).unwrap();
+
+        assert_eq!(gpx.metadata.unwrap().name.as_deref(), Some("Ride"));
+        assert_eq!(gpx.tracks[0].segments[0].points.len(), 2);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(
+            skipped[0].location().unwrap().path,
+            "gpx/trk[0]/trkseg[0]/trkpt[2]/time[0]"
+        );
+        assert!(read(data.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn partial_of_every_truncation() {
+        let data = "<?xml version=\"1.0\"?>
+<gpx version=\"1.1\" creator=\"a\" xmlns:x=\"urn:x\"><trk><name>Ride &amp; run</name><trkseg>
+    <trkpt lat=\"1\" lon=\"2\"><ele>3</ele><time>2020-06-01T12:00:00Z</time></trkpt>
+    <trkpt lat=\"1.5\" lon=\"2\"><!-- paused --><extensions><x:hr>120</x:hr></extensions></trkpt>
+    <trkpt lat=\"2\" lon=\"2\"><desc><![CDATA[<b>]]></desc></trkpt>
+</trkseg></trk></gpx>";
+        let gpx = read(data.as_bytes()).unwrap();
+        let data = data.as_bytes();
+
+        // From within the first track point on, something can be read.
+        let first_point = data.windows(6).position(|w| w == b"<trkpt").unwrap();
+        for length in first_point..data.len() {
+            let (partial, skipped) = read_with_options(&data[..length], &partial()).unwrap();
+            assert!(partial.tracks[0].segments[0].points.len() <= 3);
+            assert_eq!(skipped.len(), 1);
+        }
+
+        let (complete, skipped) = read_with_options(data, &partial()).unwrap();
+        assert_eq!(complete, gpx);
+        assert!(skipped.is_empty());
+    }
+
+    #[test]
+    fn lenient_fails_on_broken_xml() {
+        let data = "<gpx version=\"1.1\"><wpt lat=\"1\" lon=\"0\"><ele>1</wpt></gpx>";
+
+        assert!(read_with_options(data.as_bytes(), &lenient()).is_err());
+    }
+
+    #[test]
+    fn waypoints_with_indices() {
+        let data = "<gpx version=\"1.1\">
+            <metadata><name>Tracks</name></metadata>
+            <wpt lat=\"1\" lon=\"1\"/>
+            <trk>
+                <name>First</name>
+                <trkseg><trkpt lat=\"1\" lon=\"2\"><ele>3</ele></trkpt></trkseg>
+                <trkseg></trkseg>
+                <trkseg><trkpt lat=\"2\" lon=\"2\"/><trkpt lat=\"3\" lon=\"2\"/></trkseg>
+            </trk>
+            <trk><trkseg><trkpt lat=\"4\" lon=\"2\"/></trkseg></trk>
+        </gpx>";
+
+        let points: Vec<_> = GpxReader::new(data.as_bytes())
+            .waypoints()
+            .map(|point| point.unwrap())
+            .map(|(track, segment, waypoint)| (track, segment, waypoint.point().lat()))
+            .collect();
+
+        assert_eq!(points, vec![(0, 0, 1.), (0, 2, 2.), (0, 2, 3.), (1, 0, 4.)]);
+    }
+
+    #[test]
+    fn waypoints_stop_at_error() {
+        let data = "<gpx version=\"1.1\"><trk><trkseg>
+            <trkpt lat=\"1\" lon=\"2\"/>
+            <trkpt lat=\"north\" lon=\"2\"/>
+            <trkpt lat=\"3\" lon=\"2\"/>
+        </trkseg></trk></gpx>";
+
+        let points: Vec<_> = GpxReader::new(data.as_bytes()).waypoints().collect();
+
+        assert_eq!(points.len(), 2);
+        assert!(points[0].is_ok());
+        assert!(points[1].is_err());
+    }
+
+    #[test]
+    fn waypoints_of_truncated_document() {
+        let data = "<gpx version=\"1.1\"><trk><trkseg><trkpt lat=\"1\" lon=\"2\"/>";
+
+        let points: Vec<_> = GpxReader::new(data.as_bytes()).waypoints().collect();
+
+        assert!(points[0].is_ok());
+        assert!(points[1].is_err());
+    }
+
+    #[test]
+    fn entity_expansion() {
+        let laughs = (1..10)
+            .map(|level| {
+                let previous = format!("&lol{};", level - 1);
+                format!("<!ENTITY lol{} \"{}\">", level, previous.repeat(10))
+            })
+            .collect::<String>();
+        let data = format!(
+            "<!DOCTYPE gpx [<!ENTITY lol0 \"lol\">{}]><gpx version=\"1.1\"><metadata><name>&lol9;</name></metadata></gpx>",
+            laughs
+        );
+        assert!(read(data.as_bytes()).is_err());
+
+        // Expanding the nested entity is what is limited.
+        let data = format!(
+            "<!DOCTYPE gpx [<!ENTITY a \"a\"><!ENTITY long \"&a;{}\">]><gpx version=\"1.1\"><metadata><name>&long;</name></metadata></gpx>",
+            "a".repeat(99)
+        );
+        let gpx = read(data.as_bytes()).unwrap();
+        assert_eq!(gpx.metadata.unwrap().name.unwrap().len(), 100);
+        let options = ParserOptions {
+            max_entity_expansion: Some(50),
+            ..Default::default()
+        };
+        assert!(read_with_options(data.as_bytes(), &options).is_err());
+    }
+
+    #[test]
+    fn external_entities_not_loaded() {
+        let data = "<!DOCTYPE gpx [<!ENTITY passwd SYSTEM \"file:///etc/passwd\">]>
+            <gpx version=\"1.1\"><metadata><name>&passwd;</name></metadata></gpx>";
+
+        // The entity expands to nothing, leaving the name empty.
+        let error = read(data.as_bytes()).unwrap_err();
+        assert_eq!(error.to_string(), "no content inside string");
+        assert_eq!(error.location().unwrap().path, "gpx/metadata[0]/name[0]");
+    }
+
+    #[test]
+    fn attribute_and_text_lengths() {
+        let data = format!(
+            "<gpx version=\"1.1\"><wpt lat=\"1.{}\" lon=\"2\"><name>{}</name></wpt></gpx>",
+            "0".repeat(100),
+            "a".repeat(100)
+        );
+        assert!(read(data.as_bytes()).is_ok());
+
+        let options = ParserOptions {
+            max_attribute_length: Some(50),
+            ..Default::default()
+        };
+        assert!(read_with_options(data.as_bytes(), &options).is_err());
+        let options = ParserOptions {
+            max_text_length: Some(50),
+            ..Default::default()
+        };
+        assert!(read_with_options(data.as_bytes(), &options).is_err());
+        let options = ParserOptions {
+            max_attribute_length: Some(200),
+            max_text_length: Some(200),
+            ..Default::default()
+        };
+        assert!(read_with_options(data.as_bytes(), &options).is_ok());
+    }
+
+    #[test]
+    fn resource_limits() {
+        let data = format!(
+            "<gpx version=\"1.1\"><trk><trkseg>{}</trkseg></trk></gpx>",
+            "<trkpt lat=\"1\" lon=\"2\"/>".repeat(10)
+        );
+        let limit = |options: ParserOptions| -> Option<String> {
+            let error = read_with_options(data.as_bytes(), &options).err()?;
+            assert!(error.location().is_some());
+            Some(error.to_string())
+        };
+
+        assert_eq!(
+            limit(ParserOptions {
+                max_points: Some(10),
+                max_nesting_depth: Some(4),
+                max_bytes: Some(data.len() as u64),
+                ..Default::default()
+            }),
+            None
+        );
+        assert_eq!(
+            limit(ParserOptions {
+                max_points: Some(9),
+                ..Default::default()
+            })
+            .as_deref(),
+            Some("document over the limit of 9 points")
+        );
+        assert_eq!(
+            limit(ParserOptions {
+                max_nesting_depth: Some(3),
+                ..Default::default()
+            })
+            .as_deref(),
+            Some("document over the limit of 3 levels of nesting")
+        );
+        assert_eq!(
+            limit(ParserOptions {
+                max_bytes: Some(data.len() as u64 - 1),
+                ..Default::default()
+            })
+            .as_deref(),
+            Some(format!("document over the limit of {} bytes", data.len() - 1).as_str())
+        );
+        // Going over a limit isn't skipped past, even when lenient.
+        let options = ParserOptions {
+            max_points: Some(9),
+            ..lenient()
+        };
+        assert!(read_with_options(data.as_bytes(), &options).is_err());
+    }
+
+    #[test]
+    fn namespace_tolerance() {
+        let strict = ParserOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let skip_names = ParserOptions {
+            skip: Fields::NAMES,
+            ..Default::default()
+        };
+        for &namespace in &["", " xmlns=\"http://example.com/gpx\""] {
+            let data = format!(
+                "<gpx version=\"1.1\" creator=\"a\"{}><wpt lat=\"1\" lon=\"2\"><name>Camp</name></wpt></gpx>",
+                namespace
+            );
+
+            let gpx = read(data.as_bytes()).unwrap();
+            assert_eq!(gpx.waypoints[0].name.as_deref(), Some("Camp"));
+            let (gpx, _) = read_with_options(data.as_bytes(), &skip_names).unwrap();
+            assert_eq!(gpx.waypoints[0].name, None);
+            let error = read_with_options(data.as_bytes(), &strict).unwrap_err();
+            assert!(error
+                .iter()
+                .any(|error| error.to_string().contains("namespace")));
+        }
+    }
+
+    #[test]
+    fn concatenated_documents() {
+        let data = "
+            <?xml version=\"1.0\"?><gpx version=\"1.1\"><wpt lat=\"1\" lon=\"2\"/></gpx >
+            <gpx version=\"1.0\"><!-- </gpx> --><wpt lat=\"3\" lon=\"2\"/><wpt lat=\"4\" lon=\"2\"/></gpx>
+            <g:gpx xmlns:g=\"http://www.topografix.com/GPX/1/1\" version=\"1.1\">
+                <g:wpt lat=\"5\" lon=\"2\"><g:name><![CDATA[</gpx>]]></g:name></g:wpt>
+            </g:gpx>
+            <gpx version=\"1.1\"><wpt lat=\"6\" lon=\"2\"/>
+            <gpx version=\"1.1\"></gpx>";
+
+        let documents: Vec<_> = read_documents(data.as_bytes()).unwrap().collect();
+
+        assert_eq!(documents.len(), 4);
+        let first = documents[0].as_ref().unwrap();
+        assert_eq!(first.waypoints[0].point().lat(), 1.);
+        let second = documents[1].as_ref().unwrap();
+        assert_eq!(second.version, GpxVersion::Gpx10);
+        assert_eq!(second.waypoints.len(), 2);
+        let third = documents[2].as_ref().unwrap();
+        assert_eq!(third.waypoints[0].name.as_deref(), Some("</gpx>"));
+        // The fourth isn't closed before the fifth starts.
+        assert!(documents[3].is_err());
+
+        assert_eq!(read_documents("".as_bytes()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn concatenated_documents_with_options() {
+        let data = "<gpx version=\"1.1\"><wpt lat=\"1\" lon=\"2\"/></gpx>
+            <gpx version=\"1.1\"><wpt lat=\"2\" lon=\"2\"/><wpt lat=\"3\"";
+
+        let documents: Vec<_> = read_documents_with_options(data.as_bytes(), &partial())
+            .unwrap()
+            .collect();
+
+        assert_eq!(documents.len(), 2);
+        let (first, skipped) = documents[0].as_ref().unwrap();
+        assert_eq!(first.waypoints.len(), 1);
+        assert!(skipped.is_empty());
+        let (second, skipped) = documents[1].as_ref().unwrap();
+        assert_eq!(second.waypoints.len(), 1);
+        assert_eq!(skipped.len(), 1);
+        assert!(read_documents(data.as_bytes())
+            .unwrap()
+            .nth(1)
+            .unwrap()
+            .is_err());
+    }
+
+    fn utf16(text: &str, little_endian: bool) -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|unit| {
+                if little_endian {
+                    unit.to_le_bytes()
+                } else {
+                    unit.to_be_bytes()
+                }
+            })
+            .collect()
+    }
+
+    /// Gives a byte at a time, to split characters across reads.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.split_first() {
+                Some((&byte, rest)) if !buf.is_empty() => {
+                    buf[0] = byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn read_utf16() {
+        let document = "<?xml version=\"1.0\" encoding=\"UTF-16\"?><gpx version=\"1.1\" creator=\"Zürich 🚲\"></gpx>";
+
+        let mut little_endian = vec![0xff, 0xfe];
+        little_endian.extend(utf16(document, true));
+        assert_eq!(
+            read(little_endian.as_slice()).unwrap().creator.as_deref(),
+            Some("Zürich 🚲")
+        );
+
+        let mut big_endian = vec![0xfe, 0xff];
+        big_endian.extend(utf16(document, false));
+        assert_eq!(
+            read(Trickle(&big_endian)).unwrap().creator.as_deref(),
+            Some("Zürich 🚲")
+        );
+    }
+
+    #[test]
+    fn read_latin1() {
+        let mut document =
+            b"<?xml version='1.0' encoding='ISO-8859-1'?>\n<gpx version=\"1.1\" creator=\""
+                .to_vec();
+        document.extend_from_slice(&[b'Z', 0xfc, b'r', b'i', b'c', b'h']);
+        document.extend_from_slice(b"\"></gpx>");
+
+        let gpx = read(document.as_slice()).unwrap();
+        assert_eq!(gpx.creator.as_deref(), Some("Zürich"));
+    }
+
+    #[test]
+    fn read_utf8_with_bom() {
+        let mut document = vec![0xef, 0xbb, 0xbf];
+        document.extend_from_slice("<gpx version=\"1.1\" creator=\"Zürich\"></gpx>".as_bytes());
+
+        assert_eq!(
+            read(document.as_slice()).unwrap().creator.as_deref(),
+            Some("Zürich")
+        );
+    }
+
+    #[test]
+    fn read_unsupported_encoding() {
+        let document = "<?xml version=\"1.0\" encoding=\"EBCDIC\"?><gpx version=\"1.1\"></gpx>";
+
+        assert!(read(document.as_bytes()).is_err());
+    }
 }
@@ -0,0 +1,1025 @@
+//! Simplifies tracks and routes, dropping the points which hardly change
+//! their shape, and thins or resamples them to a steady rate.
+//!
+//! Points are projected onto a plane around their average latitude, so
+//! tolerances are in meters, or square meters for areas.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use chrono::Duration;
+
+use crate::stats::{haversine_distance, interpolate, EARTH_RADIUS};
+use crate::{Route, Track, TrackSegment, Waypoint};
+
+/// Simplification is an algorithm to simplify lines of points with, and its
+/// tolerance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Simplification {
+    /// Ramer–Douglas–Peucker: keeps the points further than the tolerance
+    /// (in meters) from the line between the points kept around them. It
+    /// keeps corners sharp.
+    RamerDouglasPeucker(f64),
+
+    /// Ramer–Douglas–Peucker counting elevations too, with the tolerance in
+    /// meters in three dimensions: points which stray up or down are kept
+    /// like those which stray aside, so climbs keep their shape. Missing
+    /// elevations are taken as the last one known.
+    RamerDouglasPeuckerWithElevation(f64),
+
+    /// Visvalingam–Whyatt: drops the points which make triangles smaller
+    /// than the tolerance (in square meters) with the points around them,
+    /// the smallest first. It gives smoother lines, better fit for display.
+    VisvalingamWhyatt(f64),
+}
+
+impl TrackSegment {
+    /// Gives the segment with only the points kept by `simplification`. The
+    /// first and the last point are always kept.
+    ///
+    /// ```
+    /// use gpx::{Simplification, TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// // A line which strays a meter at most, then turns a corner.
+    /// for &(latitude, longitude) in &[(0., 0.), (0.00001, 0.001), (0., 0.002), (0.001, 0.002)] {
+    ///     segment.points.push(Waypoint::try_new(latitude, longitude).unwrap());
+    /// }
+    ///
+    /// let simplified = segment.simplify(Simplification::RamerDouglasPeucker(5.));
+    /// assert_eq!(simplified.points.len(), 3);
+    /// let simplified = segment.simplify(Simplification::VisvalingamWhyatt(1000.));
+    /// assert_eq!(simplified.points.len(), 3);
+    /// ```
+    pub fn simplify(&self, simplification: Simplification) -> TrackSegment {
+        self.simplify_with_anchors(simplification, |_| false)
+    }
+
+    /// Gives the segment simplified like `simplify`, but keeping the points
+    /// `anchor` tells are anchors, like named points or lap markers. The
+    /// others are simplified between them.
+    ///
+    /// ```
+    /// use gpx::{Simplification, TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// for step in 0..10 {
+    ///     segment.points.push(Waypoint::try_new(0., f64::from(step) / 1000.).unwrap());
+    /// }
+    /// segment.points[4].name = Some(String::from("Turn left"));
+    ///
+    /// let simplified = segment.simplify_with_anchors(
+    ///     Simplification::RamerDouglasPeucker(5.),
+    ///     |point| point.name.is_some(),
+    /// );
+    /// assert_eq!(simplified.points.len(), 3);
+    /// assert_eq!(simplified.points[1].name.as_deref(), Some("Turn left"));
+    /// ```
+    pub fn simplify_with_anchors(
+        &self,
+        simplification: Simplification,
+        anchor: impl Fn(&Waypoint) -> bool,
+    ) -> TrackSegment {
+        TrackSegment {
+            points: simplify(&self.points, simplification, &anchor),
+            extensions: self.extensions.clone(),
+        }
+    }
+
+    /// Gives the segment simplified to at most `max_points` points, with the
+    /// smallest tolerance for the algorithm of `simplification` which does
+    /// so, up to its own: an infinite one puts no limit. The segment keeps
+    /// more points only if even that tolerance does, as it always keeps its
+    /// first and last point.
+    ///
+    /// ```
+    /// use gpx::{Simplification, TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// for step in 0..1000 {
+    ///     let longitude = f64::from(step) / 10000.;
+    ///     let latitude = (longitude * 100.).sin() / 1000.;
+    ///     segment.points.push(Waypoint::try_new(latitude, longitude).unwrap());
+    /// }
+    ///
+    /// let simplified =
+    ///     segment.simplify_to(100, Simplification::RamerDouglasPeucker(f64::INFINITY));
+    /// assert!(simplified.points.len() <= 100);
+    /// ```
+    pub fn simplify_to(&self, max_points: usize, simplification: Simplification) -> TrackSegment {
+        self.simplify_to_with_anchors(max_points, simplification, |_| false)
+    }
+
+    /// Gives the segment simplified like `simplify_to`, but keeping the
+    /// points `anchor` tells are anchors, like `simplify_with_anchors`. They
+    /// count towards `max_points`, but are kept even past it.
+    pub fn simplify_to_with_anchors(
+        &self,
+        max_points: usize,
+        simplification: Simplification,
+        anchor: impl Fn(&Waypoint) -> bool,
+    ) -> TrackSegment {
+        TrackSegment {
+            points: simplify_to(
+                &[self.points.as_slice()],
+                max_points,
+                simplification,
+                &anchor,
+            )
+            .remove(0),
+            extensions: self.extensions.clone(),
+        }
+    }
+
+    /// Gives the segment with at most one point in each `interval` of time
+    /// from its first point, the first of them, for thinning logs recorded
+    /// every second to a steady rate. The last point is kept too, as are
+    /// points without a time, as there's no telling where they fall.
+    ///
+    /// ```
+    /// use chrono::{Duration, TimeZone, Utc};
+    /// use gpx::{TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// for second in 0..60 {
+    ///     let mut point = Waypoint::try_new(0., f64::from(second) / 10000.).unwrap();
+    ///     point.time = Some(Utc.timestamp_opt(second.into(), 0).unwrap());
+    ///     segment.points.push(point);
+    /// }
+    ///
+    /// // A point at 0, 10, 20, 30, 40 and 50 seconds, and the last at 59.
+    /// let downsampled = segment.downsample_by_time(Duration::seconds(10));
+    /// assert_eq!(downsampled.points.len(), 7);
+    /// ```
+    pub fn downsample_by_time(&self, interval: Duration) -> TrackSegment {
+        TrackSegment {
+            points: downsample_by_time(&self.points, interval),
+            extensions: self.extensions.clone(),
+        }
+    }
+
+    /// Gives the segment resampled to a point every `spacing` meters along
+    /// it from its first point, with coordinates, times and elevations
+    /// interpolated between the points around, and its last point. Tracks
+    /// resampled alike can be compared point by point. Gives the segment as
+    /// it is for a spacing which isn't positive.
+    ///
+    /// ```
+    /// use gpx::{TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// // About 111 and 222 meters apart.
+    /// for &(latitude, elevation) in &[(0., 100.), (0.001, 110.), (0.003, 90.)] {
+    ///     let mut point = Waypoint::try_new(latitude, 0.).unwrap();
+    ///     point.elevation = Some(elevation);
+    ///     segment.points.push(point);
+    /// }
+    ///
+    /// // Points at 0, 100, 200 and 300 meters, and the last at 334.
+    /// let resampled = segment.resample_by_distance(100.);
+    /// assert_eq!(resampled.points.len(), 5);
+    /// assert!((resampled.points[2].elevation.unwrap() - 102.).abs() < 0.1);
+    /// ```
+    pub fn resample_by_distance(&self, spacing: f64) -> TrackSegment {
+        TrackSegment {
+            points: resample_by_distance(&self.points, spacing),
+            extensions: self.extensions.clone(),
+        }
+    }
+}
+
+impl Track {
+    /// Gives the track with each of its segments simplified, like
+    /// `TrackSegment::simplify`.
+    pub fn simplify(&self, simplification: Simplification) -> Track {
+        self.simplify_with_anchors(simplification, |_| false)
+    }
+
+    /// Gives the track with each of its segments simplified, like
+    /// `TrackSegment::simplify_with_anchors`. The boundaries of segments are
+    /// always kept.
+    pub fn simplify_with_anchors(
+        &self,
+        simplification: Simplification,
+        anchor: impl Fn(&Waypoint) -> bool,
+    ) -> Track {
+        let mut track = Track {
+            segments: Vec::new(),
+            ..self.clone()
+        };
+        track.segments = self
+            .segments
+            .iter()
+            .map(|segment| segment.simplify_with_anchors(simplification, &anchor))
+            .collect();
+        track
+    }
+
+    /// Gives the track simplified to at most `max_points` points in all its
+    /// segments, with a tolerance found like `TrackSegment::simplify_to`.
+    pub fn simplify_to(&self, max_points: usize, simplification: Simplification) -> Track {
+        self.simplify_to_with_anchors(max_points, simplification, |_| false)
+    }
+
+    /// Gives the track simplified like `simplify_to`, but keeping the points
+    /// `anchor` tells are anchors, like
+    /// `TrackSegment::simplify_to_with_anchors`.
+    pub fn simplify_to_with_anchors(
+        &self,
+        max_points: usize,
+        simplification: Simplification,
+        anchor: impl Fn(&Waypoint) -> bool,
+    ) -> Track {
+        let lines: Vec<&[Waypoint]> = self
+            .segments
+            .iter()
+            .map(|segment| segment.points.as_slice())
+            .collect();
+        let mut track = Track {
+            segments: Vec::new(),
+            ..self.clone()
+        };
+        track.segments = self
+            .segments
+            .iter()
+            .zip(simplify_to(&lines, max_points, simplification, &anchor))
+            .map(|(segment, points)| TrackSegment {
+                points,
+                extensions: segment.extensions.clone(),
+            })
+            .collect();
+        track
+    }
+
+    /// Gives the track with each of its segments downsampled, like
+    /// `TrackSegment::downsample_by_time`.
+    pub fn downsample_by_time(&self, interval: Duration) -> Track {
+        let mut track = Track {
+            segments: Vec::new(),
+            ..self.clone()
+        };
+        track.segments = self
+            .segments
+            .iter()
+            .map(|segment| segment.downsample_by_time(interval))
+            .collect();
+        track
+    }
+
+    /// Gives the track with each of its segments resampled, like
+    /// `TrackSegment::resample_by_distance`.
+    pub fn resample_by_distance(&self, spacing: f64) -> Track {
+        let mut track = Track {
+            segments: Vec::new(),
+            ..self.clone()
+        };
+        track.segments = self
+            .segments
+            .iter()
+            .map(|segment| segment.resample_by_distance(spacing))
+            .collect();
+        track
+    }
+}
+
+impl Route {
+    /// Gives the route with only the points kept by `simplification`, like
+    /// `TrackSegment::simplify`.
+    pub fn simplify(&self, simplification: Simplification) -> Route {
+        self.simplify_with_anchors(simplification, |_| false)
+    }
+
+    /// Gives the route simplified like `simplify`, but keeping the points
+    /// `anchor` tells are anchors, like `TrackSegment::simplify_with_anchors`.
+    pub fn simplify_with_anchors(
+        &self,
+        simplification: Simplification,
+        anchor: impl Fn(&Waypoint) -> bool,
+    ) -> Route {
+        let mut route = Route {
+            points: Vec::new(),
+            ..self.clone()
+        };
+        route.points = simplify(&self.points, simplification, &anchor);
+        route
+    }
+
+    /// Gives the route simplified to at most `max_points` points, with a
+    /// tolerance found like `TrackSegment::simplify_to`.
+    pub fn simplify_to(&self, max_points: usize, simplification: Simplification) -> Route {
+        self.simplify_to_with_anchors(max_points, simplification, |_| false)
+    }
+
+    /// Gives the route simplified like `simplify_to`, but keeping the points
+    /// `anchor` tells are anchors, like
+    /// `TrackSegment::simplify_to_with_anchors`.
+    pub fn simplify_to_with_anchors(
+        &self,
+        max_points: usize,
+        simplification: Simplification,
+        anchor: impl Fn(&Waypoint) -> bool,
+    ) -> Route {
+        let mut route = Route {
+            points: Vec::new(),
+            ..self.clone()
+        };
+        route.points = simplify_to(
+            &[self.points.as_slice()],
+            max_points,
+            simplification,
+            &anchor,
+        )
+        .remove(0);
+        route
+    }
+}
+
+impl Simplification {
+    fn tolerance(self) -> f64 {
+        match self {
+            Simplification::RamerDouglasPeucker(tolerance)
+            | Simplification::RamerDouglasPeuckerWithElevation(tolerance)
+            | Simplification::VisvalingamWhyatt(tolerance) => tolerance,
+        }
+    }
+
+    fn with_tolerance(self, tolerance: f64) -> Simplification {
+        match self {
+            Simplification::RamerDouglasPeucker(_) => {
+                Simplification::RamerDouglasPeucker(tolerance)
+            }
+            Simplification::RamerDouglasPeuckerWithElevation(_) => {
+                Simplification::RamerDouglasPeuckerWithElevation(tolerance)
+            }
+            Simplification::VisvalingamWhyatt(_) => Simplification::VisvalingamWhyatt(tolerance),
+        }
+    }
+
+    fn elevation(self) -> bool {
+        matches!(self, Simplification::RamerDouglasPeuckerWithElevation(_))
+    }
+}
+
+/// Tells whether a point is an anchor, to be kept by simplification.
+type Anchor<'a> = &'a dyn Fn(&Waypoint) -> bool;
+
+/// Gives the points kept by `simplification`, besides anchors.
+fn simplify(points: &[Waypoint], simplification: Simplification, anchor: Anchor) -> Vec<Waypoint> {
+    let projected = project(points, simplification.elevation());
+    select(
+        points,
+        &kept(&projected, anchors(points, anchor), simplification),
+    )
+}
+
+/// Gives the points of lines kept with the smallest tolerance, up to the one
+/// of `simplification`, which keeps at most `max_points` of them in all,
+/// besides anchors.
+fn simplify_to(
+    lines: &[&[Waypoint]],
+    max_points: usize,
+    simplification: Simplification,
+    anchor: Anchor,
+) -> Vec<Vec<Waypoint>> {
+    if lines.iter().map(|points| points.len()).sum::<usize>() <= max_points {
+        return lines.iter().map(|points| points.to_vec()).collect();
+    }
+    let projected: Vec<Vec<Projected>> = lines
+        .iter()
+        .map(|points| project(points, simplification.elevation()))
+        .collect();
+    let anchors: Vec<Vec<bool>> = lines.iter().map(|points| anchors(points, anchor)).collect();
+    let keep_all = |tolerance: f64| -> Vec<Vec<bool>> {
+        let simplification = simplification.with_tolerance(tolerance);
+        projected
+            .iter()
+            .zip(&anchors)
+            .map(|(points, anchors)| kept(points, anchors.clone(), simplification))
+            .collect()
+    };
+    let count = |keep: &[Vec<bool>]| keep.iter().flatten().filter(|&&keep| keep).count();
+
+    // No point is further from the others than the extent of its line, nor
+    // makes a triangle larger than its square, so larger tolerances keep the
+    // same points.
+    let largest = projected
+        .iter()
+        .map(|points| extent(points))
+        .fold(0., f64::max);
+    let bound = match simplification {
+        Simplification::RamerDouglasPeucker(_)
+        | Simplification::RamerDouglasPeuckerWithElevation(_) => largest,
+        Simplification::VisvalingamWhyatt(_) => largest * largest,
+    };
+    let (mut low, mut high) = (0., simplification.tolerance().min(bound));
+    let mut best = keep_all(high);
+    if count(&best) <= max_points {
+        for _ in 0..64 {
+            let tolerance = (low + high) / 2.;
+            let keep = keep_all(tolerance);
+            let kept = count(&keep);
+            if kept <= max_points {
+                best = keep;
+                high = tolerance;
+            } else {
+                low = tolerance;
+            }
+            if kept == max_points {
+                break;
+            }
+        }
+    }
+    lines
+        .iter()
+        .zip(best)
+        .map(|(points, keep)| select(points, &keep))
+        .collect()
+}
+
+/// Tells which points are anchors: those `anchor` tells are, and the first
+/// and the last.
+fn anchors(points: &[Waypoint], anchor: Anchor) -> Vec<bool> {
+    let last = points.len().saturating_sub(1);
+    points
+        .iter()
+        .enumerate()
+        .map(|(index, point)| index == 0 || index == last || anchor(point))
+        .collect()
+}
+
+/// Tells which points `simplification` keeps, starting from those in `keep`.
+fn kept(points: &[Projected], mut keep: Vec<bool>, simplification: Simplification) -> Vec<bool> {
+    match simplification {
+        Simplification::RamerDouglasPeucker(tolerance)
+        | Simplification::RamerDouglasPeuckerWithElevation(tolerance) => {
+            ramer_douglas_peucker(points, tolerance, &mut keep)
+        }
+        Simplification::VisvalingamWhyatt(tolerance) => {
+            visvalingam_whyatt(points, tolerance, &mut keep)
+        }
+    }
+    keep
+}
+
+fn select(points: &[Waypoint], keep: &[bool]) -> Vec<Waypoint> {
+    points
+        .iter()
+        .zip(keep)
+        .filter(|&(_, &keep)| keep)
+        .map(|(point, _)| point.clone())
+        .collect()
+}
+
+/// A point projected onto a plane, in meters, with its elevation or 0.
+type Projected = [f64; 3];
+
+/// Projects points onto a plane around their average latitude, with their
+/// elevations if `elevation`.
+fn project(points: &[Waypoint], elevation: bool) -> Vec<Projected> {
+    let latitude =
+        points.iter().map(|point| point.point().lat()).sum::<f64>() / points.len().max(1) as f64;
+    let scale = latitude.to_radians().cos();
+    let mut last = points
+        .iter()
+        .find_map(|point| point.elevation)
+        .filter(|_| elevation)
+        .unwrap_or(0.);
+    points
+        .iter()
+        .map(|waypoint| {
+            if elevation {
+                last = waypoint.elevation.unwrap_or(last);
+            }
+            let point = waypoint.point();
+            [
+                point.lng().to_radians() * scale * EARTH_RADIUS,
+                point.lat().to_radians() * EARTH_RADIUS,
+                last,
+            ]
+        })
+        .collect()
+}
+
+/// Keeps the points further than `tolerance` from the line between the
+/// points kept around them, besides those kept already.
+fn ramer_douglas_peucker(points: &[Projected], tolerance: f64, keep: &mut [bool]) {
+    let kept: Vec<usize> = (0..points.len()).filter(|&index| keep[index]).collect();
+    let mut spans: Vec<(usize, usize)> = kept.windows(2).map(|pair| (pair[0], pair[1])).collect();
+    while let Some((start, end)) = spans.pop() {
+        let furthest = (start + 1..end)
+            .map(|index| {
+                let distance = distance_to_segment(points[index], points[start], points[end]);
+                (index, distance)
+            })
+            .fold(
+                None,
+                |furthest: Option<(usize, f64)>, (index, distance)| match furthest {
+                    Some((_, further)) if further >= distance => furthest,
+                    _ => Some((index, distance)),
+                },
+            );
+        if let Some((index, distance)) = furthest {
+            if distance > tolerance {
+                keep[index] = true;
+                spans.push((start, index));
+                spans.push((index, end));
+            }
+        }
+    }
+}
+
+/// Drops the points making the smallest triangles with the points around
+/// them while they're smaller than `tolerance`, but for those kept already.
+/// A point's triangle never counts as smaller than the one of a point dropped
+/// next to it before, so that dropping points doesn't make others smaller.
+fn visvalingam_whyatt(points: &[Projected], tolerance: f64, keep: &mut [bool]) {
+    let count = points.len();
+    if count < 3 {
+        keep.iter_mut().for_each(|keep| *keep = true);
+        return;
+    }
+    let mut previous: Vec<usize> = (0..count).map(|index| index.wrapping_sub(1)).collect();
+    let mut next: Vec<usize> = (1..=count).collect();
+    let mut areas = vec![0.; count];
+    let mut dropped = vec![false; count];
+    // Triangles by area, the smallest first. Areas aren't negative, so the
+    // order of their bits is theirs.
+    let mut heap = BinaryHeap::new();
+    for index in 1..count - 1 {
+        areas[index] = triangle_area(points[index - 1], points[index], points[index + 1]);
+        heap.push(Reverse((areas[index].to_bits(), index)));
+    }
+
+    while let Some(Reverse((bits, index))) = heap.pop() {
+        if dropped[index] || bits != areas[index].to_bits() || keep[index] {
+            continue;
+        }
+        let area = f64::from_bits(bits);
+        if area >= tolerance {
+            break;
+        }
+        dropped[index] = true;
+        let (before, after) = (previous[index], next[index]);
+        next[before] = after;
+        previous[after] = before;
+        for &neighbour in &[before, after] {
+            if neighbour == 0 || neighbour == count - 1 {
+                continue;
+            }
+            let triangle = triangle_area(
+                points[previous[neighbour]],
+                points[neighbour],
+                points[next[neighbour]],
+            );
+            areas[neighbour] = triangle.max(area);
+            heap.push(Reverse((areas[neighbour].to_bits(), neighbour)));
+        }
+    }
+    for (keep, dropped) in keep.iter_mut().zip(dropped) {
+        *keep = !dropped;
+    }
+}
+
+/// Gives the length of the diagonal of the box around points.
+fn extent(points: &[Projected]) -> f64 {
+    if points.is_empty() {
+        return 0.;
+    }
+    let low = points.iter().fold([f64::INFINITY; 3], |low, point| {
+        [
+            low[0].min(point[0]),
+            low[1].min(point[1]),
+            low[2].min(point[2]),
+        ]
+    });
+    let high = points.iter().fold([f64::NEG_INFINITY; 3], |high, point| {
+        [
+            high[0].max(point[0]),
+            high[1].max(point[1]),
+            high[2].max(point[2]),
+        ]
+    });
+    norm(difference(high, low))
+}
+
+/// Gives the distance from a point to the segment between two others.
+fn distance_to_segment(point: Projected, start: Projected, end: Projected) -> f64 {
+    let segment = difference(end, start);
+    let offset = difference(point, start);
+    let length = dot(segment, segment);
+    let fraction = if length > 0. {
+        (dot(offset, segment) / length).clamp(0., 1.)
+    } else {
+        0.
+    };
+    norm([
+        offset[0] - segment[0] * fraction,
+        offset[1] - segment[1] * fraction,
+        offset[2] - segment[2] * fraction,
+    ])
+}
+
+/// Gives the area of the triangle between three points.
+fn triangle_area(a: Projected, b: Projected, c: Projected) -> f64 {
+    let (u, v) = (difference(b, a), difference(c, a));
+    norm([
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ]) / 2.
+}
+
+fn difference(a: Projected, b: Projected) -> Projected {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: Projected, b: Projected) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn norm(a: Projected) -> f64 {
+    dot(a, a).sqrt()
+}
+
+/// Gives the first point in each `interval` from the first one, and the
+/// last, and those without a time.
+fn downsample_by_time(points: &[Waypoint], interval: Duration) -> Vec<Waypoint> {
+    let start = points.iter().find_map(|point| point.time);
+    let interval = interval.num_milliseconds();
+    let mut last = None;
+    points
+        .iter()
+        .enumerate()
+        .filter(|&(index, point)| {
+            let (time, start) = match (point.time, start) {
+                (Some(time), Some(start)) if interval > 0 => (time, start),
+                _ => return true,
+            };
+            let slot = (time - start).num_milliseconds().div_euclid(interval);
+            if index + 1 == points.len() || last.map(|last| slot > last).unwrap_or(true) {
+                last = Some(slot);
+                true
+            } else {
+                false
+            }
+        })
+        .map(|(_, point)| point.clone())
+        .collect()
+}
+
+/// Gives points every `spacing` meters along points, interpolated between
+/// them, and the last point.
+fn resample_by_distance(points: &[Waypoint], spacing: f64) -> Vec<Waypoint> {
+    if spacing.is_nan() || spacing <= 0. {
+        return points.to_vec();
+    }
+    let mut along = Vec::with_capacity(points.len());
+    let mut total = 0.;
+    for (index, point) in points.iter().enumerate() {
+        if index > 0 {
+            total += haversine_distance(&points[index - 1], point);
+        }
+        along.push(total);
+    }
+
+    let mut resampled = Vec::new();
+    let mut index = 0;
+    let mut step = 0;
+    let mut end = points.is_empty();
+    while !points.is_empty() && step as f64 * spacing <= total {
+        let distance = step as f64 * spacing;
+        // The first point at or after the distance.
+        while along[index] < distance {
+            index += 1;
+        }
+        resampled.push(if index == 0 || along[index] == distance {
+            points[index].clone()
+        } else {
+            let from = along[index - 1];
+            interpolate(
+                &points[index - 1],
+                &points[index],
+                (distance - from) / (along[index] - from),
+            )
+        });
+        end = index + 1 == points.len() && along[index] == distance;
+        step += 1;
+    }
+    if !end {
+        resampled.extend(points.last().cloned());
+    }
+    resampled
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::Simplification;
+    use crate::{Route, Track, TrackSegment, Waypoint};
+
+    /// A zigzag of about 111 meters a step, straying `stray` degrees of
+    /// latitude every other point.
+    fn zigzag(stray: f64) -> TrackSegment {
+        let mut segment = TrackSegment::new();
+        for step in 0..11 {
+            let latitude = if step % 2 == 1 { stray } else { 0. };
+            segment
+                .points
+                .push(Waypoint::try_new(latitude, step as f64 / 1000.).unwrap());
+        }
+        segment
+    }
+
+    #[test]
+    fn ramer_douglas_peucker() {
+        // Strays of about a meter, and of about 11.
+        let small = zigzag(0.00001);
+        let simplified = small.simplify(Simplification::RamerDouglasPeucker(5.));
+        assert_eq!(simplified.points.len(), 2);
+        assert_eq!(simplified.points[1], small.points[10]);
+        let large = zigzag(0.0001);
+        assert_eq!(
+            large
+                .simplify(Simplification::RamerDouglasPeucker(5.))
+                .points
+                .len(),
+            11
+        );
+        assert_eq!(
+            large
+                .simplify(Simplification::RamerDouglasPeucker(20.))
+                .points
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn visvalingam_whyatt() {
+        // Triangles of about 222 by 1 meter, and 222 by 11.
+        let small = zigzag(0.00001);
+        let simplified = small.simplify(Simplification::VisvalingamWhyatt(1000.));
+        assert_eq!(simplified.points.len(), 2);
+        let large = zigzag(0.0001);
+        assert_eq!(
+            large
+                .simplify(Simplification::VisvalingamWhyatt(1000.))
+                .points
+                .len(),
+            11
+        );
+        // Dropping points makes wider triangles of the others, which are
+        // kept past some.
+        let some = large.simplify(Simplification::VisvalingamWhyatt(2000.));
+        assert_eq!(some.points.len(), 5);
+        assert_eq!(some.points[0], large.points[0]);
+    }
+
+    #[test]
+    fn ramer_douglas_peucker_with_elevation() {
+        // A straight line over a 50 meter peak, 111 meters a step, ending on
+        // a point missing its elevation.
+        let mut segment = TrackSegment::new();
+        for (step, &elevation) in [Some(100.), Some(150.), Some(100.), None]
+            .iter()
+            .enumerate()
+        {
+            let mut point = Waypoint::try_new(0., step as f64 / 1000.).unwrap();
+            point.elevation = elevation;
+            segment.points.push(point);
+        }
+
+        assert_eq!(
+            segment
+                .simplify(Simplification::RamerDouglasPeucker(5.))
+                .points
+                .len(),
+            2
+        );
+        let simplified = segment.simplify(Simplification::RamerDouglasPeuckerWithElevation(30.));
+        assert_eq!(simplified.points.len(), 3);
+        assert_eq!(simplified.points[1].elevation, Some(150.));
+        assert_eq!(
+            segment
+                .simplify(Simplification::RamerDouglasPeuckerWithElevation(100.))
+                .points
+                .len(),
+            2
+        );
+    }
+
+    /// An arc of 21 points, a kilometer across.
+    fn arc() -> TrackSegment {
+        let mut segment = TrackSegment::new();
+        for step in 0..21 {
+            let latitude = (std::f64::consts::PI * step as f64 / 20.).sin() / 1000.;
+            segment
+                .points
+                .push(Waypoint::try_new(latitude, step as f64 / 1000.).unwrap());
+        }
+        segment
+    }
+
+    #[test]
+    fn simplify_to_point_count() {
+        let arc = arc();
+        for &max_points in &[2, 5, 10] {
+            for &simplification in &[
+                Simplification::RamerDouglasPeucker(f64::INFINITY),
+                Simplification::VisvalingamWhyatt(f64::INFINITY),
+            ] {
+                let count = arc.simplify_to(max_points, simplification).points.len();
+                assert!(count <= max_points && count + 2 >= max_points);
+            }
+        }
+        assert_eq!(
+            arc.simplify_to(30, Simplification::RamerDouglasPeucker(f64::INFINITY)),
+            arc
+        );
+        // The first and the last point stay, and so do points further than
+        // the largest tolerance allowed.
+        assert_eq!(
+            arc.simplify_to(1, Simplification::VisvalingamWhyatt(f64::INFINITY))
+                .points
+                .len(),
+            2
+        );
+        assert!(
+            arc.simplify_to(2, Simplification::RamerDouglasPeucker(5.))
+                .points
+                .len()
+                > 2
+        );
+    }
+
+    #[test]
+    fn track_simplifies_to_point_count() {
+        let mut track = Track::new();
+        track.segments.push(arc());
+        track.segments.push(arc());
+
+        let simplified = track.simplify_to(10, Simplification::RamerDouglasPeucker(f64::INFINITY));
+        let counts: Vec<usize> = simplified
+            .segments
+            .iter()
+            .map(|segment| segment.points.len())
+            .collect();
+        assert!(counts[0] + counts[1] <= 10);
+        assert_eq!(counts[0], counts[1]);
+    }
+
+    #[test]
+    fn anchors_are_kept() {
+        let mut segment = zigzag(0.00001);
+        segment.points[3].name = Some(String::from("Summit"));
+        segment.points[6].name = Some(String::from("Lap"));
+        let anchor = |point: &Waypoint| point.name.is_some();
+
+        for &simplification in &[
+            Simplification::RamerDouglasPeucker(5.),
+            Simplification::VisvalingamWhyatt(1000.),
+        ] {
+            let simplified = segment.simplify_with_anchors(simplification, anchor);
+            let names: Vec<Option<&str>> = simplified
+                .points
+                .iter()
+                .map(|point| point.name.as_deref())
+                .collect();
+            assert_eq!(names, [None, Some("Summit"), Some("Lap"), None]);
+        }
+
+        // Anchors are kept past the number of points asked for.
+        let simplified = segment.simplify_to_with_anchors(
+            3,
+            Simplification::RamerDouglasPeucker(f64::INFINITY),
+            anchor,
+        );
+        assert_eq!(simplified.points.len(), 4);
+
+        let mut track = Track::new();
+        track.segments.push(segment.clone());
+        track.segments.push(segment);
+        let simplified =
+            track.simplify_with_anchors(Simplification::RamerDouglasPeucker(5.), anchor);
+        assert_eq!(simplified.segments[1].points.len(), 4);
+    }
+
+    #[test]
+    fn downsample_by_time() {
+        // A point every 3 seconds, one without a time, and one going back in
+        // time.
+        let mut segment = TrackSegment::new();
+        for &second in &[
+            Some(0),
+            Some(3),
+            Some(6),
+            None,
+            Some(9),
+            Some(4),
+            Some(12),
+            Some(13),
+        ] {
+            let mut point = Waypoint::try_new(0., 0.).unwrap();
+            point.time = second.map(|second| Utc.timestamp_opt(second, 0).unwrap());
+            segment.points.push(point);
+        }
+
+        let seconds = |segment: TrackSegment| -> Vec<Option<i64>> {
+            segment
+                .points
+                .iter()
+                .map(|point| point.time.map(|time| time.timestamp()))
+                .collect()
+        };
+        assert_eq!(
+            seconds(segment.downsample_by_time(Duration::seconds(5))),
+            [Some(0), Some(6), None, Some(12), Some(13)]
+        );
+        assert_eq!(
+            seconds(segment.downsample_by_time(Duration::seconds(20))),
+            [Some(0), None, Some(13)]
+        );
+        assert_eq!(segment.downsample_by_time(Duration::zero()), segment);
+
+        let mut track = Track::new();
+        track.segments.push(segment.clone());
+        track.segments.push(segment);
+        let downsampled = track.downsample_by_time(Duration::seconds(20));
+        assert_eq!(downsampled.segments[1].points.len(), 3);
+    }
+
+    #[test]
+    fn resample_by_distance() {
+        // About 111 and 222 meters apart.
+        let mut segment = TrackSegment::new();
+        for &(second, latitude, elevation) in &[(0, 0., 100.), (10, 0.001, 110.), (30, 0.003, 90.)]
+        {
+            let mut point = Waypoint::try_new(latitude, 0.).unwrap();
+            point.time = Some(Utc.timestamp_opt(second, 0).unwrap());
+            point.elevation = Some(elevation);
+            segment.points.push(point);
+        }
+
+        let resampled = segment.resample_by_distance(100.);
+        assert_eq!(resampled.points.len(), 5);
+        assert_eq!(resampled.points[0], segment.points[0]);
+        assert_eq!(resampled.points[4], segment.points[2]);
+        // 200 meters is about 0.4 of the way from the second point to the
+        // last.
+        let point = &resampled.points[2];
+        assert!((point.point().lat() - 0.0017986).abs() < 1e-6);
+        assert!((point.elevation.unwrap() - 102.01).abs() < 0.01);
+        assert_eq!(
+            point.time.unwrap(),
+            Utc.timestamp_opt(17, 986_000_000).unwrap()
+        );
+        assert!((resampled.length_m() - segment.length_m()).abs() < 1e-6);
+
+        // Spacings longer than the segment keep its ends.
+        assert_eq!(segment.resample_by_distance(1000.).points.len(), 2);
+        assert_eq!(segment.resample_by_distance(0.), segment);
+        assert!(TrackSegment::new()
+            .resample_by_distance(100.)
+            .points
+            .is_empty());
+
+        let mut track = Track::new();
+        track.segments.push(segment.clone());
+        track.segments.push(segment);
+        let resampled = track.resample_by_distance(100.);
+        assert_eq!(resampled.segments[1].points.len(), 5);
+    }
+
+    #[test]
+    fn track_and_route() {
+        let mut track = Track::new();
+        track.name = Some(String::from("Zigzag"));
+        track.segments.push(zigzag(0.00001));
+        track.segments.push(TrackSegment::new());
+        let mut single = TrackSegment::new();
+        single.points.push(Waypoint::try_new(1., 1.).unwrap());
+        track.segments.push(single);
+
+        let simplified = track.simplify(Simplification::VisvalingamWhyatt(1000.));
+        assert_eq!(simplified.name.as_deref(), Some("Zigzag"));
+        let lengths: Vec<usize> = simplified
+            .segments
+            .iter()
+            .map(|segment| segment.points.len())
+            .collect();
+        assert_eq!(lengths, [2, 0, 1]);
+
+        let route = Route {
+            points: zigzag(0.00001).points,
+            ..Default::default()
+        };
+        assert_eq!(
+            route
+                .simplify(Simplification::RamerDouglasPeucker(5.))
+                .points
+                .len(),
+            2
+        );
+    }
+}
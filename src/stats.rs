@@ -0,0 +1,2900 @@
+//! Computes statistics of tracks, like their length, duration and climb.
+//!
+//! Distances are great-circle distances on a sphere of the earth's mean
+//! radius, unless geodesic ones on the WGS 84 ellipsoid are asked for with
+//! `DistanceMethod`. Speeds are in meters per second.
+
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Duration, Utc};
+use geo_types::Point;
+
+use crate::{Gpx, Track, TrackSegment, Waypoint};
+
+/// Mean radius of the earth in meters, for distances between points.
+pub(crate) const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Semi-major axis (in meters) and flattening of the WGS 84 ellipsoid.
+const WGS84_A: f64 = 6_378_137.;
+const WGS84_F: f64 = 1. / 298.257_223_563;
+
+/// A threshold (in meters) for `elevation_gain` and `elevation_loss` which
+/// filters out the noise of GPS elevations, while keeping most real climbs.
+/// Barometric elevations are steadier, and do with a lower one.
+pub const DEFAULT_ELEVATION_THRESHOLD: f64 = 3.;
+
+/// Speed (in meters per second) below which one is taken to be standing
+/// still when inferring activities.
+const STOPPED_SPEED: f64 = 0.5;
+
+/// Typical speeds (in meters per second) between walking and running,
+/// running and riding, and riding and driving.
+const ACTIVITY_SPEEDS: [f64; 3] = [2., 4.5, 12.];
+
+/// DistanceMethod selects how distances between points are computed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DistanceMethod {
+    /// Great-circle distances on a sphere, by the haversine formula. Fast,
+    /// and within 0.5% of the geodesic distance.
+    #[default]
+    Haversine,
+
+    /// Geodesic distances on the WGS 84 ellipsoid, by Vincenty's formulae,
+    /// accurate to within a millimeter but several times slower. Nearly
+    /// antipodal points, for which they don't converge, fall back to the
+    /// haversine distance.
+    Geodesic,
+}
+
+impl DistanceMethod {
+    /// Gives the distance between two points, in meters.
+    ///
+    /// ```
+    /// use gpx::{DistanceMethod, Waypoint};
+    ///
+    /// let from = Waypoint::try_new(-37.95103342, 144.42486789).unwrap();
+    /// let to = Waypoint::try_new(-37.65282114, 143.92649554).unwrap();
+    ///
+    /// let distance = DistanceMethod::Geodesic.distance(&from, &to);
+    /// assert!((distance - 54_972.271).abs() < 0.001);
+    /// ```
+    pub fn distance(self, from: &Waypoint, to: &Waypoint) -> f64 {
+        match self {
+            DistanceMethod::Haversine => haversine_distance(from, to),
+            DistanceMethod::Geodesic => {
+                vincenty_distance(from, to).unwrap_or_else(|| haversine_distance(from, to))
+            }
+        }
+    }
+
+    /// Gives the distances between each two of `points`, in meters: the
+    /// distance from the i-th point to the j-th is in the j-th column of the
+    /// i-th row. With the `rayon` feature, rows are computed in parallel.
+    ///
+    /// ```
+    /// use gpx::{DistanceMethod, Waypoint};
+    ///
+    /// let points = vec![
+    ///     Waypoint::try_new(0., 0.).unwrap(),
+    ///     Waypoint::try_new(0., 1.).unwrap(),
+    ///     Waypoint::try_new(1., 1.).unwrap(),
+    /// ];
+    ///
+    /// let matrix = DistanceMethod::Haversine.matrix(&points);
+    /// assert_eq!(matrix[0][0], 0.);
+    /// assert_eq!(matrix[0][1].round(), 111_195.);
+    /// assert_eq!(matrix[2][1], matrix[1][2]);
+    /// ```
+    pub fn matrix(self, points: &[Waypoint]) -> Vec<Vec<f64>> {
+        let row = |from: &Waypoint| -> Vec<f64> {
+            points.iter().map(|to| self.distance(from, to)).collect()
+        };
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            points.par_iter().map(row).collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            points.iter().map(row).collect()
+        }
+    }
+}
+
+/// TrackSummary holds the statistics of a track, or of the tracks of a
+/// document.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrackSummary {
+    /// Length (in meters), summed over the segments but not between them.
+    pub distance: f64,
+
+    /// Time between the first and the last time of the points, if at least
+    /// two have different ones.
+    pub duration: Option<Duration>,
+
+    /// Sum of the climbs (in meters) between points with an elevation, of
+    /// at least `DEFAULT_ELEVATION_THRESHOLD`, like
+    /// `TrackSegment::elevation_gain`.
+    pub elevation_gain: f64,
+
+    /// Sum of the descents (in meters) between points with an elevation, of
+    /// at least `DEFAULT_ELEVATION_THRESHOLD`, like
+    /// `TrackSegment::elevation_loss`.
+    pub elevation_loss: f64,
+
+    /// Lowest elevation of the points, if any has one.
+    pub min_elevation: Option<f64>,
+
+    /// Highest elevation of the points, if any has one.
+    pub max_elevation: Option<f64>,
+
+    /// Distance divided by duration, if the duration isn't zero.
+    pub average_speed: Option<f64>,
+
+    /// Highest speed between two points following each other with times.
+    pub max_speed: Option<f64>,
+
+    /// Number of points.
+    pub points: usize,
+}
+
+/// PaceUnit is the distance a pace is the time to cover.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaceUnit {
+    /// Time per kilometer.
+    Kilometer,
+
+    /// Time per statute mile, of 1609.344 meters.
+    Mile,
+}
+
+impl PaceUnit {
+    /// Gives the length of the unit in meters.
+    pub fn meters(self) -> f64 {
+        match self {
+            PaceUnit::Kilometer => 1000.,
+            PaceUnit::Mile => 1609.344,
+        }
+    }
+
+    /// Gives the time to cover the unit at `speed` meters per second, unless
+    /// standing still.
+    ///
+    /// ```
+    /// use gpx::PaceUnit;
+    ///
+    /// assert_eq!(PaceUnit::Kilometer.pace(4.).unwrap().num_seconds(), 250);
+    /// assert_eq!(PaceUnit::Mile.pace(4.).unwrap().num_milliseconds(), 402_336);
+    /// assert_eq!(PaceUnit::Mile.pace(0.), None);
+    /// ```
+    pub fn pace(self, speed: f64) -> Option<Duration> {
+        if speed > 0. && speed.is_finite() {
+            Some(Duration::milliseconds(
+                (self.meters() / speed * 1000.).round() as i64,
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// Split is the part of a track covering a unit of distance, as in the
+/// splits per kilometer or mile of training logs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Split {
+    /// Distance covered, in meters: a whole unit, but for the last split.
+    pub distance: f64,
+
+    /// Time taken, if the points around the ends of the split have times.
+    pub duration: Option<Duration>,
+
+    /// Time per unit of distance at the pace of the split.
+    pub pace: Option<Duration>,
+
+    /// Elevation change from the start of the split to its end, if the
+    /// points around its ends have elevations.
+    pub elevation_change: Option<f64>,
+}
+
+impl Split {
+    fn between(start: &Waypoint, end: &Waypoint, distance: f64, unit: PaceUnit) -> Split {
+        let duration = match (start.time, end.time) {
+            (Some(start), Some(end)) => Some(end - start),
+            _ => None,
+        };
+        Split {
+            distance,
+            duration,
+            pace: duration.map(|duration| {
+                let milliseconds = duration.num_milliseconds() as f64 * unit.meters() / distance;
+                Duration::milliseconds(milliseconds.round() as i64)
+            }),
+            elevation_change: match (start.elevation, end.elevation) {
+                (Some(start), Some(end)) => Some(end - start),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// RouteShape is the shape of the route a track takes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RouteShape {
+    /// Ends where it starts, without going back the same way.
+    Loop,
+
+    /// Goes back the same way it went.
+    OutAndBack,
+
+    /// Ends somewhere else than where it starts.
+    PointToPoint,
+}
+
+/// ShapeOptions holds the thresholds the shape of routes is told with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapeOptions {
+    /// Distance (in meters) between the start and the end up to which a
+    /// route ends where it starts. Defaults to 200 meters.
+    pub closing_distance: f64,
+
+    /// Distance (in meters) up to which a point of the second half of a
+    /// route retraces one of the first half. Defaults to 50 meters.
+    pub overlap_distance: f64,
+
+    /// Fraction of the points of the second half of a route which retrace
+    /// the first half from which it goes back the same way. Defaults to 0.8.
+    pub overlap_fraction: f64,
+}
+
+impl Default for ShapeOptions {
+    fn default() -> ShapeOptions {
+        ShapeOptions {
+            closing_distance: 200.,
+            overlap_distance: 50.,
+            overlap_fraction: 0.8,
+        }
+    }
+}
+
+/// Stop is a time a track stayed in one place.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stop {
+    /// Time of the first point of the stop.
+    pub start_time: DateTime<Utc>,
+
+    /// Time of the last point of the stop.
+    pub end_time: DateTime<Utc>,
+
+    /// Average location of the points of the stop.
+    pub location: Point<f64>,
+}
+
+impl Stop {
+    /// Gives the time stayed.
+    pub fn duration(&self) -> Duration {
+        self.end_time - self.start_time
+    }
+}
+
+/// Effort is the part of a track covering a distance in the least time, as
+/// in the best efforts of training logs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Effort {
+    /// Index of the first point of the effort, in order across the segments
+    /// of the track.
+    pub start: usize,
+
+    /// Index of the last point of the effort, in order across the segments
+    /// of the track.
+    pub end: usize,
+
+    /// Time of the first point.
+    pub start_time: DateTime<Utc>,
+
+    /// Time of the last point.
+    pub end_time: DateTime<Utc>,
+
+    /// Distance covered from the first point to the last, in meters: at
+    /// least the distance asked for, up to a step between points more.
+    pub distance: f64,
+}
+
+impl Effort {
+    /// Gives the time taken.
+    pub fn duration(&self) -> Duration {
+        self.end_time - self.start_time
+    }
+}
+
+/// Section is a part of a segment, between two of its points.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Section {
+    /// Index of the first point of the section in the segment.
+    pub start: usize,
+
+    /// Index of the last point of the section in the segment.
+    pub end: usize,
+
+    /// Length of the section, in meters.
+    pub length: f64,
+
+    /// Elevation change from the first point to the last, in meters.
+    pub climb: f64,
+
+    /// Climb per meter of the section, negative when descending.
+    pub gradient: f64,
+}
+
+/// SensorSummary holds the statistics of the sensor data of a track, read
+/// from the Garmin TrackPointExtension of its points. Averages are over the
+/// points with a value.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SensorSummary {
+    /// Average heart rate, in beats per minute.
+    pub average_heart_rate: Option<f64>,
+
+    /// Highest heart rate, in beats per minute.
+    pub max_heart_rate: Option<u8>,
+
+    /// Average cadence, in revolutions per minute.
+    pub average_cadence: Option<f64>,
+
+    /// Highest cadence, in revolutions per minute.
+    pub max_cadence: Option<u8>,
+
+    /// Average power, in watts.
+    pub average_power: Option<f64>,
+
+    /// Highest power, in watts.
+    pub max_power: Option<u16>,
+
+    /// Normalized power, in watts: the fourth root of the average of the
+    /// fourth powers of the power averaged over 30 seconds, which weighs
+    /// efforts the way the body feels them. It needs at least 30 seconds of
+    /// points with times and power.
+    pub normalized_power: Option<f64>,
+}
+
+/// Sensor is a kind of sensor data of the Garmin TrackPointExtension of
+/// points.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sensor {
+    /// Heart rate, in beats per minute.
+    HeartRate,
+
+    /// Cadence, in revolutions per minute.
+    Cadence,
+
+    /// Power, in watts.
+    Power,
+}
+
+impl Sensor {
+    /// Gives the value of the sensor at a point, if it has one.
+    fn value(self, point: &Waypoint) -> Option<f64> {
+        let extension = point.track_point_extension.as_ref()?;
+        match self {
+            Sensor::HeartRate => extension.hr.map(f64::from),
+            Sensor::Cadence => extension.cad.map(f64::from),
+            Sensor::Power => extension.power.map(f64::from),
+        }
+    }
+}
+
+/// Activity is a kind of activity a track was recorded during.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Activity {
+    /// Walking or hiking.
+    Walk,
+
+    /// Running.
+    Run,
+
+    /// Riding a bicycle.
+    Ride,
+
+    /// Driving, or riding anything with an engine.
+    Drive,
+}
+
+/// ActivityGuess is the activity a track was most likely recorded during.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ActivityGuess {
+    /// The activity guessed.
+    pub activity: Activity,
+
+    /// How sure the guess is, from 0.5 for a toss-up to 1.
+    pub confidence: f64,
+}
+
+/// MovingTime holds the time spent moving along a track, and the time from
+/// its start to its end.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MovingTime {
+    /// Sum of the times between points following each other in a segment,
+    /// when moving between them at least at the speed threshold.
+    pub moving: Duration,
+
+    /// Time between the first and the last time of the points, stops and
+    /// gaps between segments included.
+    pub elapsed: Duration,
+}
+
+/// GpxSummary holds the statistics of each track of a document, and of all
+/// of them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GpxSummary {
+    /// Statistics of each track, in order.
+    pub tracks: Vec<TrackSummary>,
+
+    /// Statistics of all tracks together, with the duration from the first
+    /// time of the document to the last.
+    pub total: TrackSummary,
+}
+
+impl Waypoint {
+    /// Gives the initial bearing of the great circle from this point to
+    /// another: the direction to head in, in degrees clockwise from true
+    /// north, from 0 to 360.
+    ///
+    /// ```
+    /// use gpx::Waypoint;
+    ///
+    /// let origin = Waypoint::try_new(0., 0.).unwrap();
+    /// assert_eq!(origin.bearing_to(&Waypoint::try_new(1., 0.).unwrap()), 0.);
+    /// assert_eq!(origin.bearing_to(&Waypoint::try_new(0., -1.).unwrap()), 270.);
+    /// ```
+    pub fn bearing_to(&self, other: &Waypoint) -> f64 {
+        let (from, to) = (self.point(), other.point());
+        let (lat1, lat2) = (from.lat().to_radians(), to.lat().to_radians());
+        let delta_lng = (to.lng() - from.lng()).to_radians();
+        let y = delta_lng.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * delta_lng.cos();
+        (y.atan2(x).to_degrees() + 360.) % 360.
+    }
+}
+
+impl TrackSegment {
+    /// Gives the length of the segment in meters: the sum of the
+    /// great-circle distances between its points, by the haversine formula.
+    ///
+    /// ```
+    /// use gpx::{TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// segment.points.push(Waypoint::try_new(0., 0.).unwrap());
+    /// segment.points.push(Waypoint::try_new(0., 1.).unwrap());
+    ///
+    /// assert_eq!(segment.length_m().round(), 111_195.);
+    /// ```
+    pub fn length_m(&self) -> f64 {
+        self.length_m_with(DistanceMethod::Haversine)
+    }
+
+    /// Gives the length of the segment in meters, with distances computed
+    /// by `method`.
+    pub fn length_m_with(&self, method: DistanceMethod) -> f64 {
+        self.points
+            .windows(2)
+            .map(|pair| method.distance(&pair[0], &pair[1]))
+            .sum()
+    }
+
+    /// Gives the sum of the climbs of the segment in meters, counting only
+    /// those of at least `threshold` meters since the last elevation counted
+    /// from, so that the noise of elevations doesn't add up to a climb.
+    /// Points without an elevation are left out.
+    ///
+    /// ```
+    /// use gpx::{TrackSegment, Waypoint, DEFAULT_ELEVATION_THRESHOLD};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// for elevation in vec![100., 101., 100., 102., 101., 105., 104.] {
+    ///     let mut point = Waypoint::try_new(0., 0.).unwrap();
+    ///     point.elevation = Some(elevation);
+    ///     segment.points.push(point);
+    /// }
+    ///
+    /// assert_eq!(segment.elevation_gain(0.), 7.);
+    /// assert_eq!(segment.elevation_gain(DEFAULT_ELEVATION_THRESHOLD), 5.);
+    /// assert_eq!(segment.elevation_loss(DEFAULT_ELEVATION_THRESHOLD), 0.);
+    /// ```
+    pub fn elevation_gain(&self, threshold: f64) -> f64 {
+        climb(&self.points, threshold).0
+    }
+
+    /// Gives the sum of the descents of the segment in meters, counting only
+    /// those of at least `threshold` meters, like `elevation_gain`.
+    pub fn elevation_loss(&self, threshold: f64) -> f64 {
+        climb(&self.points, threshold).1
+    }
+
+    /// Gives the time spent moving along the segment, at least at
+    /// `speed_threshold` meters per second between points with times, and
+    /// the time elapsed from its first point to its last.
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use gpx::{TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// // About 11 meters in 10 seconds, then standing still for a minute.
+    /// for (seconds, latitude) in vec![(0, 0.), (10, 0.0001), (70, 0.0001)] {
+    ///     let mut point = Waypoint::try_new(latitude, 0.).unwrap();
+    ///     point.time = Some(Utc.timestamp_opt(seconds, 0).unwrap());
+    ///     segment.points.push(point);
+    /// }
+    ///
+    /// let time = segment.moving_time(0.5);
+    /// assert_eq!(time.moving.num_seconds(), 10);
+    /// assert_eq!(time.elapsed.num_seconds(), 70);
+    /// ```
+    pub fn moving_time(&self, speed_threshold: f64) -> MovingTime {
+        MovingTime {
+            moving: moving(&self.points, speed_threshold),
+            elapsed: elapsed(self.points.iter()),
+        }
+    }
+
+    /// Gives the speed (in meters per second) at each point of the segment,
+    /// from the last point before it with a time. Points without a time, or
+    /// with the same time as the last one, have none, as does the first.
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use gpx::{TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// for (seconds, latitude) in vec![(0, 0.), (10, 0.001), (20, 0.001)] {
+    ///     let mut point = Waypoint::try_new(latitude, 0.).unwrap();
+    ///     point.time = Some(Utc.timestamp_opt(seconds, 0).unwrap());
+    ///     segment.points.push(point);
+    /// }
+    ///
+    /// let speeds = segment.speeds();
+    /// assert_eq!(speeds.len(), 3);
+    /// assert_eq!(speeds[0], None);
+    /// assert_eq!(speeds[1].unwrap().round(), 11.);
+    /// assert_eq!(speeds[2], Some(0.));
+    /// ```
+    pub fn speeds(&self) -> Vec<Option<f64>> {
+        steps(&self.points)
+            .into_iter()
+            .map(|step| step.map(|step| step.distance / step.seconds))
+            .collect()
+    }
+
+    /// Gives the pace at each point of the segment: the time to cover a
+    /// `unit` of distance at its speed, like `TrackSegment::speeds`. Points
+    /// without a speed, or standing still, have none.
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use gpx::{PaceUnit, TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// for (seconds, latitude) in vec![(0, 0.), (30, 0.001)] {
+    ///     let mut point = Waypoint::try_new(latitude, 0.).unwrap();
+    ///     point.time = Some(Utc.timestamp_opt(seconds, 0).unwrap());
+    ///     segment.points.push(point);
+    /// }
+    ///
+    /// // 111 meters in 30 seconds, about 4:30 per kilometer.
+    /// let pace = segment.paces(PaceUnit::Kilometer)[1].unwrap();
+    /// assert_eq!(pace.num_seconds(), 269);
+    /// ```
+    pub fn paces(&self, unit: PaceUnit) -> Vec<Option<Duration>> {
+        self.speeds()
+            .into_iter()
+            .map(|speed| unit.pace(speed?))
+            .collect()
+    }
+
+    /// Gives the grade-adjusted pace at each point of the segment: the pace
+    /// of the same effort on flat ground, given the slope from the point
+    /// before it. Points without an elevation, or after one without, are
+    /// taken to be on flat ground.
+    ///
+    /// The effort is the energy cost of running on a slope measured by
+    /// Minetti et al. (2002), for slopes from -45% to 45%. Steeper ones are
+    /// taken to be as steep as those.
+    pub fn grade_adjusted_paces(&self, unit: PaceUnit) -> Vec<Option<Duration>> {
+        steps(&self.points)
+            .into_iter()
+            .map(|step| {
+                let step = step?;
+                let grade = match step.climb {
+                    Some(climb) if step.distance > 0. => climb / step.distance,
+                    _ => 0.,
+                };
+                let speed = step.distance / step.seconds;
+                unit.pace(speed * (running_cost(grade) / running_cost(0.)))
+            })
+            .collect()
+    }
+
+    /// Gives the gradient at each point of the segment, as the climb per
+    /// meter (0.1 for a 10% slope) over the last `window` meters up to the
+    /// point, or from its first point for the points closer to it. Points
+    /// without an elevation have none, as do those where no distance was
+    /// covered, and the first. A `window` of zero gives the gradient from the
+    /// point before, a wider one smooths out the noise of elevations.
+    ///
+    /// ```
+    /// use gpx::{TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// for (latitude, elevation) in vec![(0., 0.), (0.001, 11.), (0.002, 11.)] {
+    ///     let mut point = Waypoint::try_new(latitude, 0.).unwrap();
+    ///     point.elevation = Some(elevation);
+    ///     segment.points.push(point);
+    /// }
+    ///
+    /// let gradients = segment.gradients(0.);
+    /// assert_eq!(gradients[0], None);
+    /// assert_eq!((gradients[1].unwrap() * 100.).round(), 10.);
+    /// assert_eq!(gradients[2], Some(0.));
+    /// // Over about 222 meters, back to the first point.
+    /// assert_eq!((segment.gradients(200.)[2].unwrap() * 100.).round(), 5.);
+    /// ```
+    pub fn gradients(&self, window: f64) -> Vec<Option<f64>> {
+        let profile = profile(&self.points);
+        let mut gradients = vec![None; self.points.len()];
+        let mut start = 0;
+        for (position, &(index, distance, elevation)) in profile.iter().enumerate().skip(1) {
+            // The last point at least `window` meters before, if any is.
+            while start + 1 < position && distance - profile[start + 1].1 >= window {
+                start += 1;
+            }
+            let (_, from_distance, from_elevation) = profile[start];
+            if distance > from_distance {
+                gradients[index] = Some((elevation - from_elevation) / (distance - from_distance));
+            }
+        }
+        gradients
+    }
+
+    /// Gives the steepest section of the segment at least `min_length`
+    /// meters long, climbing or descending, between points with elevations.
+    /// Gives none if the segment is shorter than that.
+    ///
+    /// It compares every pair of points, so it's best kept to segments of a
+    /// few thousand points.
+    pub fn steepest_section(&self, min_length: f64) -> Option<Section> {
+        let profile = profile(&self.points);
+        let mut steepest: Option<Section> = None;
+        for (position, &(start, from_distance, from_elevation)) in profile.iter().enumerate() {
+            for &(end, distance, elevation) in &profile[position + 1..] {
+                let length = distance - from_distance;
+                if length <= 0. || length < min_length {
+                    continue;
+                }
+                let climb = elevation - from_elevation;
+                let gradient = climb / length;
+                let steeper = match steepest {
+                    Some(ref section) => gradient.abs() > section.gradient.abs(),
+                    None => true,
+                };
+                if steeper {
+                    steepest = Some(Section {
+                        start,
+                        end,
+                        length,
+                        climb,
+                        gradient,
+                    });
+                }
+            }
+        }
+        steepest
+    }
+
+    /// Gives the vertical speed at each point of the segment, in meters per
+    /// second (negative when sinking), over the last `window` of time up to
+    /// the point, or from its first point for the points closer to it. Only
+    /// points with both a time and an elevation count: the others have none,
+    /// as does the first of them. A `window` of zero gives the vertical speed
+    /// from the point before, a wider one smooths out the noise of
+    /// elevations.
+    ///
+    /// ```
+    /// use chrono::{Duration, TimeZone, Utc};
+    /// use gpx::{TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// for (seconds, elevation) in vec![(0, 100.), (10, 105.), (20, 103.)] {
+    ///     let mut point = Waypoint::try_new(0., 0.).unwrap();
+    ///     point.time = Some(Utc.timestamp_opt(seconds, 0).unwrap());
+    ///     point.elevation = Some(elevation);
+    ///     segment.points.push(point);
+    /// }
+    ///
+    /// let speeds = segment.vertical_speeds(Duration::zero());
+    /// assert_eq!(speeds, vec![None, Some(0.5), Some(-0.2)]);
+    /// // Over the 20 seconds back to the first point.
+    /// assert_eq!(segment.vertical_speeds(Duration::seconds(15))[2], Some(0.15));
+    /// ```
+    pub fn vertical_speeds(&self, window: Duration) -> Vec<Option<f64>> {
+        let series: Vec<(usize, DateTime<Utc>, f64)> = self
+            .points
+            .iter()
+            .enumerate()
+            .filter_map(|(index, point)| Some((index, point.time?, point.elevation?)))
+            .collect();
+        let mut speeds = vec![None; self.points.len()];
+        let mut start = 0;
+        for (position, &(index, time, elevation)) in series.iter().enumerate().skip(1) {
+            // The last point at least `window` before, if any is.
+            while start + 1 < position && time - series[start + 1].1 >= window {
+                start += 1;
+            }
+            let (_, from_time, from_elevation) = series[start];
+            let seconds = (time - from_time).num_milliseconds() as f64 / 1000.;
+            if seconds > 0. {
+                speeds[index] = Some((elevation - from_elevation) / seconds);
+            }
+        }
+        speeds
+    }
+
+    /// Gives the bearing at each point of the segment: the direction of
+    /// travel from the point before it, like `Waypoint::bearing_to`. The
+    /// first point has none, as do those at the same place as the point
+    /// before.
+    pub fn bearings(&self) -> Vec<Option<f64>> {
+        let mut bearings = vec![None; self.points.len()];
+        for (index, pair) in self.points.windows(2).enumerate() {
+            if pair[0].point() != pair[1].point() {
+                bearings[index + 1] = Some(pair[0].bearing_to(&pair[1]));
+            }
+        }
+        bearings
+    }
+
+    /// Gives the direction of travel at the start of the segment, from its
+    /// first point to the next one at another place, if there is one.
+    ///
+    /// ```
+    /// use gpx::{TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// // North, then east.
+    /// for (latitude, longitude) in vec![(0., 0.), (0., 0.), (1., 0.), (1., 1.)] {
+    ///     segment.points.push(Waypoint::try_new(latitude, longitude).unwrap());
+    /// }
+    ///
+    /// assert_eq!(segment.initial_bearing(), Some(0.));
+    /// assert_eq!(segment.final_bearing().unwrap().round(), 90.);
+    /// ```
+    pub fn initial_bearing(&self) -> Option<f64> {
+        let first = self.points.first()?;
+        let next = self
+            .points
+            .iter()
+            .find(|point| point.point() != first.point())?;
+        Some(first.bearing_to(next))
+    }
+
+    /// Gives the direction of travel at the end of the segment, to its last
+    /// point from the one before it at another place, if there is one.
+    pub fn final_bearing(&self) -> Option<f64> {
+        let last = self.points.last()?;
+        let before = self
+            .points
+            .iter()
+            .rev()
+            .find(|point| point.point() != last.point())?;
+        Some(before.bearing_to(last))
+    }
+
+    /// Gives the highest speed of the segment (in meters per second), if any
+    /// point has one. Speeds above `limit` are left out as GPS jitter.
+    pub fn max_speed(&self, limit: Option<f64>) -> Option<f64> {
+        self.speeds()
+            .into_iter()
+            .flatten()
+            .filter(|&speed| speed <= limit.unwrap_or(f64::INFINITY))
+            .fold(None, max)
+    }
+
+    /// Gives the average speed of the segment (in meters per second): the
+    /// distance between points with times divided by the time between them,
+    /// if it isn't zero. Steps between points faster than `limit` are left
+    /// out as GPS jitter.
+    pub fn avg_speed(&self, limit: Option<f64>) -> Option<f64> {
+        let (distance, seconds) = plausible_steps(&self.points, limit);
+        if seconds > 0. {
+            Some(distance / seconds)
+        } else {
+            None
+        }
+    }
+
+    /// Gives the statistics of the heart rate, cadence and power of the
+    /// points of the segment.
+    ///
+    /// ```
+    /// use gpx::{TrackPointExtension, TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// for (hr, cad) in vec![(120, None), (140, Some(80)), (130, Some(90))] {
+    ///     let mut point = Waypoint::try_new(0., 0.).unwrap();
+    ///     point.track_point_extension = Some(TrackPointExtension {
+    ///         hr: Some(hr),
+    ///         cad,
+    ///         ..Default::default()
+    ///     });
+    ///     segment.points.push(point);
+    /// }
+    ///
+    /// let summary = segment.sensor_summary();
+    /// assert_eq!(summary.average_heart_rate, Some(130.));
+    /// assert_eq!(summary.max_heart_rate, Some(140));
+    /// assert_eq!(summary.average_cadence, Some(85.));
+    /// assert_eq!(summary.max_power, None);
+    /// ```
+    pub fn sensor_summary(&self) -> SensorSummary {
+        sensor_summary(self.points.iter())
+    }
+
+    /// Gives the time spent in each zone of a `sensor`, as split by the
+    /// ascending `boundaries` of the zones: the first zone is below the first
+    /// boundary, and the last one from the last boundary up. The time from a
+    /// point with a time and a value to the next point with a time counts in
+    /// the zone of that value.
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use gpx::{Sensor, TrackPointExtension, TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// for (seconds, hr) in vec![(0, 110), (60, 135), (90, 160), (100, 150)] {
+    ///     let mut point = Waypoint::try_new(0., 0.).unwrap();
+    ///     point.time = Some(Utc.timestamp_opt(seconds, 0).unwrap());
+    ///     point.track_point_extension = Some(TrackPointExtension {
+    ///         hr: Some(hr),
+    ///         ..Default::default()
+    ///     });
+    ///     segment.points.push(point);
+    /// }
+    ///
+    /// let zones = segment.time_in_zones(Sensor::HeartRate, &[120., 140.]);
+    /// let seconds: Vec<i64> = zones.iter().map(|zone| zone.num_seconds()).collect();
+    /// assert_eq!(seconds, vec![60, 30, 10]);
+    /// ```
+    pub fn time_in_zones(&self, sensor: Sensor, boundaries: &[f64]) -> Vec<Duration> {
+        let mut zones = vec![Duration::zero(); boundaries.len() + 1];
+        self.add_time_in_zones(sensor, boundaries, &mut zones);
+        zones
+    }
+
+    fn add_time_in_zones(&self, sensor: Sensor, boundaries: &[f64], zones: &mut [Duration]) {
+        let mut timed = self
+            .points
+            .iter()
+            .filter_map(|point| Some((point.time?, sensor.value(point))));
+        let mut previous = match timed.next() {
+            Some(previous) => previous,
+            None => return,
+        };
+        for (time, value) in timed {
+            if let (from, Some(from_value)) = previous {
+                let zone = boundaries
+                    .iter()
+                    .filter(|&&boundary| from_value >= boundary)
+                    .count();
+                zones[zone] += time - from;
+            }
+            previous = (time, value);
+        }
+    }
+
+    /// Gives the statistics of the segment, computed in a single pass over
+    /// its points.
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use gpx::{TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// for (seconds, latitude, elevation) in vec![(0, 0., 10.), (10, 0.001, 15.), (20, 0.002, 12.)] {
+    ///     let mut point = Waypoint::try_new(latitude, 0.).unwrap();
+    ///     point.time = Some(Utc.timestamp_opt(seconds, 0).unwrap());
+    ///     point.elevation = Some(elevation);
+    ///     segment.points.push(point);
+    /// }
+    ///
+    /// let summary = segment.summary();
+    /// assert_eq!(summary.points, 3);
+    /// assert_eq!(summary.distance.round(), 222.);
+    /// assert_eq!(summary.duration.unwrap().num_seconds(), 20);
+    /// assert_eq!((summary.elevation_gain, summary.elevation_loss), (5., 3.));
+    /// ```
+    pub fn summary(&self) -> TrackSummary {
+        self.summary_with(DistanceMethod::Haversine)
+    }
+
+    /// Gives the statistics of the segment, with distances computed by
+    /// `method`.
+    pub fn summary_with(&self, method: DistanceMethod) -> TrackSummary {
+        let mut summary = Summary::new(method);
+        summary.add_segment(self);
+        summary.finish()
+    }
+}
+
+impl Track {
+    /// Gives the length of the track in meters, summed over its segments
+    /// but not between them, like `TrackSegment::length_m`.
+    pub fn length_m(&self) -> f64 {
+        self.length_m_with(DistanceMethod::Haversine)
+    }
+
+    /// Gives the length of the track in meters, with distances computed by
+    /// `method`.
+    pub fn length_m_with(&self, method: DistanceMethod) -> f64 {
+        self.segments
+            .iter()
+            .map(|segment| segment.length_m_with(method))
+            .sum()
+    }
+
+    /// Gives the sum of the climbs of the track in meters, within each of
+    /// its segments, like `TrackSegment::elevation_gain`.
+    pub fn elevation_gain(&self, threshold: f64) -> f64 {
+        self.segments
+            .iter()
+            .map(|segment| segment.elevation_gain(threshold))
+            .sum()
+    }
+
+    /// Gives the sum of the descents of the track in meters, within each of
+    /// its segments, like `TrackSegment::elevation_loss`.
+    pub fn elevation_loss(&self, threshold: f64) -> f64 {
+        self.segments
+            .iter()
+            .map(|segment| segment.elevation_loss(threshold))
+            .sum()
+    }
+
+    /// Gives the time spent moving along the track, within each of its
+    /// segments, and the time elapsed from its first point to its last, like
+    /// `TrackSegment::moving_time`.
+    pub fn moving_time(&self, speed_threshold: f64) -> MovingTime {
+        MovingTime {
+            moving: self
+                .segments
+                .iter()
+                .map(|segment| moving(&segment.points, speed_threshold))
+                .fold(Duration::zero(), |sum, moving| sum + moving),
+            elapsed: elapsed(self.segments.iter().flat_map(|segment| &segment.points)),
+        }
+    }
+
+    /// Gives the vertical speed at each point of the track, in order across
+    /// its segments, like `TrackSegment::vertical_speeds`. The first point of
+    /// each segment has none.
+    pub fn vertical_speeds(&self, window: Duration) -> Vec<Option<f64>> {
+        self.segments
+            .iter()
+            .flat_map(|segment| segment.vertical_speeds(window))
+            .collect()
+    }
+
+    /// Gives the bearing at each point of the track, in order across its
+    /// segments, like `TrackSegment::bearings`. The first point of each
+    /// segment has none.
+    pub fn bearings(&self) -> Vec<Option<f64>> {
+        self.segments
+            .iter()
+            .flat_map(TrackSegment::bearings)
+            .collect()
+    }
+
+    /// Gives the speed at each point of the track, in order across its
+    /// segments, like `TrackSegment::speeds`. The first point of each
+    /// segment has none.
+    pub fn speeds(&self) -> Vec<Option<f64>> {
+        self.segments
+            .iter()
+            .flat_map(TrackSegment::speeds)
+            .collect()
+    }
+
+    /// Gives the pace at each point of the track, in order across its
+    /// segments, like `TrackSegment::paces`.
+    pub fn paces(&self, unit: PaceUnit) -> Vec<Option<Duration>> {
+        self.segments
+            .iter()
+            .flat_map(|segment| segment.paces(unit))
+            .collect()
+    }
+
+    /// Gives the grade-adjusted pace at each point of the track, in order
+    /// across its segments, like `TrackSegment::grade_adjusted_paces`.
+    pub fn grade_adjusted_paces(&self, unit: PaceUnit) -> Vec<Option<Duration>> {
+        self.segments
+            .iter()
+            .flat_map(|segment| segment.grade_adjusted_paces(unit))
+            .collect()
+    }
+
+    /// Gives the splits of the track per `unit` of distance, with their ends
+    /// interpolated between points. Distances are summed over the segments
+    /// but not between them, while the times between them count.
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use gpx::{PaceUnit, Track, TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// // A hundredth of a degree of latitude, about 1.1 kilometers, every
+    /// // five minutes.
+    /// for step in 0..3 {
+    ///     let mut point = Waypoint::try_new(step as f64 / 100., 0.).unwrap();
+    ///     point.time = Some(Utc.timestamp_opt(step * 300, 0).unwrap());
+    ///     segment.points.push(point);
+    /// }
+    /// let mut track = Track::new();
+    /// track.segments.push(segment);
+    ///
+    /// let splits = track.splits(PaceUnit::Kilometer);
+    /// assert_eq!(splits.len(), 3);
+    /// assert_eq!(splits[0].pace.unwrap().num_seconds(), 269);
+    /// assert_eq!(splits[2].distance.round(), 224.);
+    /// ```
+    pub fn splits(&self, unit: PaceUnit) -> Vec<Split> {
+        let along = along(self);
+        let length = unit.meters();
+        let mut previous = match along.first() {
+            Some(&first) => first,
+            None => return Vec::new(),
+        };
+        let mut splits = Vec::new();
+        let (mut start_distance, mut start) = (previous.0, previous.1.clone());
+        for &(distance, point) in &along[1..] {
+            while distance >= start_distance + length && distance > previous.0 {
+                let end_distance = start_distance + length;
+                let fraction = (end_distance - previous.0) / (distance - previous.0);
+                let end = interpolate(previous.1, point, fraction);
+                splits.push(Split::between(&start, &end, length, unit));
+                start = end;
+                start_distance = end_distance;
+            }
+            previous = (distance, point);
+        }
+        if previous.0 > start_distance {
+            splits.push(Split::between(
+                &start,
+                previous.1,
+                previous.0 - start_distance,
+                unit,
+            ));
+        }
+        splits
+    }
+
+    /// Gives the point at half of the length of the track, like
+    /// `point_at_fraction`.
+    pub fn midpoint(&self) -> Option<Waypoint> {
+        self.point_at_fraction(0.5)
+    }
+
+    /// Gives the point at a `fraction` of the length of the track, from 0
+    /// for its start to 1 for its end, with its coordinates, time and
+    /// elevation interpolated between the points around it. When it falls
+    /// on a point of the track, that point is given. Distances are summed
+    /// over the segments but not between them.
+    ///
+    /// Gives none for a track without points, or a fraction outside of 0 to
+    /// 1.
+    ///
+    /// ```
+    /// use gpx::{Track, TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// for latitude in vec![0., 0.001, 0.003] {
+    ///     segment.points.push(Waypoint::try_new(latitude, 0.).unwrap());
+    /// }
+    /// let mut track = Track::new();
+    /// track.segments.push(segment);
+    ///
+    /// let midpoint = track.midpoint().unwrap();
+    /// assert!((midpoint.point().lat() - 0.0015).abs() < 1e-9);
+    /// assert_eq!(track.point_at_fraction(1.).unwrap().point().lat(), 0.003);
+    /// assert_eq!(track.point_at_fraction(1.5), None);
+    /// ```
+    pub fn point_at_fraction(&self, fraction: f64) -> Option<Waypoint> {
+        if !(0. ..=1.).contains(&fraction) {
+            return None;
+        }
+        let along = along(self);
+        let length = along.last()?.0;
+        point_along(&along, fraction * length)
+    }
+
+    /// Gives the point at `distance` meters along the track, with its
+    /// coordinates, time and elevation interpolated between the points
+    /// around it, like `point_at_fraction`. Gives none for a distance
+    /// outside of the track.
+    ///
+    /// ```
+    /// use gpx::{Track, TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// for latitude in vec![0., 0.001, 0.002] {
+    ///     segment.points.push(Waypoint::try_new(latitude, 0.).unwrap());
+    /// }
+    /// let mut track = Track::new();
+    /// track.segments.push(segment);
+    ///
+    /// let distance = track.distance_at_index(1).unwrap();
+    /// assert_eq!(distance.round(), 111.);
+    /// let point = track.point_at_distance(distance / 2.).unwrap();
+    /// assert!((point.point().lat() - 0.0005).abs() < 1e-9);
+    /// assert_eq!(track.point_at_distance(1000.), None);
+    /// ```
+    pub fn point_at_distance(&self, distance: f64) -> Option<Waypoint> {
+        if distance.is_nan() || distance < 0. {
+            return None;
+        }
+        point_along(&along(self), distance)
+    }
+
+    /// Gives the point of the track at `time`, with its coordinates and
+    /// elevation interpolated between the points with times around it, as
+    /// when geotagging photos or aligning sensor data. Gives none for a time
+    /// outside of the recording, or between two of its segments, where
+    /// nothing was recorded.
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use gpx::{Track, TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// for (seconds, latitude) in vec![(0, 0.), (10, 0.001), (30, 0.002)] {
+    ///     let mut point = Waypoint::try_new(latitude, 0.).unwrap();
+    ///     point.time = Some(Utc.timestamp_opt(seconds, 0).unwrap());
+    ///     segment.points.push(point);
+    /// }
+    /// let mut track = Track::new();
+    /// track.segments.push(segment);
+    ///
+    /// let point = track.point_at_time(Utc.timestamp_opt(15, 0).unwrap()).unwrap();
+    /// assert!((point.point().lat() - 0.00125).abs() < 1e-9);
+    /// assert_eq!(track.point_at_time(Utc.timestamp_opt(31, 0).unwrap()), None);
+    /// ```
+    pub fn point_at_time(&self, time: DateTime<Utc>) -> Option<Waypoint> {
+        for segment in &self.segments {
+            let mut previous: Option<(&Waypoint, DateTime<Utc>)> = None;
+            for point in &segment.points {
+                let point_time = match point.time {
+                    Some(point_time) => point_time,
+                    None => continue,
+                };
+                if point_time == time {
+                    return Some(point.clone());
+                }
+                if let Some((from, from_time)) = previous {
+                    if from_time < time && time < point_time {
+                        let elapsed = (time - from_time).num_milliseconds() as f64;
+                        let total = (point_time - from_time).num_milliseconds() as f64;
+                        let fraction = if total > 0. { elapsed / total } else { 0. };
+                        let mut interpolated = interpolate(from, point, fraction);
+                        interpolated.time = Some(time);
+                        return Some(interpolated);
+                    }
+                }
+                previous = Some((point, point_time));
+            }
+        }
+        None
+    }
+
+    /// Gives the distance along the track to the point at `index`, in order
+    /// across its segments, if it has that many points. Distances are summed
+    /// over the segments but not between them.
+    pub fn distance_at_index(&self, index: usize) -> Option<f64> {
+        along(self).get(index).map(|&(distance, _)| distance)
+    }
+
+    /// Gives the elevation profile of the track, as pairs of a distance along
+    /// it and the elevation there, every `sample_distance` meters and at its
+    /// end, ready to be charted. Elevations are interpolated between the
+    /// points with one, and the profile spans from the first of them to the
+    /// last. Distances are summed over the segments but not between them.
+    ///
+    /// Gives an empty profile for a track without elevations, or a sample
+    /// distance that isn't positive.
+    ///
+    /// ```
+    /// use gpx::{Track, TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// // About 111 and 222 meters apart.
+    /// for (latitude, elevation) in vec![(0., 100.), (0.001, 110.), (0.003, 90.)] {
+    ///     let mut point = Waypoint::try_new(latitude, 0.).unwrap();
+    ///     point.elevation = Some(elevation);
+    ///     segment.points.push(point);
+    /// }
+    /// let mut track = Track::new();
+    /// track.segments.push(segment);
+    ///
+    /// let profile = track.elevation_profile(100.);
+    /// assert_eq!(profile.len(), 5);
+    /// assert_eq!(profile[0], (0., 100.));
+    /// assert_eq!(profile[4].1, 90.);
+    /// ```
+    pub fn elevation_profile(&self, sample_distance: f64) -> Vec<(f64, f64)> {
+        let known: Vec<(f64, f64)> = along(self)
+            .into_iter()
+            .filter_map(|(distance, point)| Some((distance, point.elevation?)))
+            .collect();
+        let (first, last) = match (known.first(), known.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return Vec::new(),
+        };
+        if sample_distance.is_nan() || sample_distance <= 0. {
+            return Vec::new();
+        }
+
+        let mut profile = Vec::new();
+        let mut index = 0;
+        let mut sample = (first.0 / sample_distance).ceil();
+        while sample * sample_distance <= last.0 {
+            let distance = sample * sample_distance;
+            // The first point at or after the distance.
+            while known[index].0 < distance {
+                index += 1;
+            }
+            let (to_distance, to_elevation) = known[index];
+            let elevation = if index == 0 || to_distance == distance {
+                to_elevation
+            } else {
+                let (from_distance, from_elevation) = known[index - 1];
+                from_elevation
+                    + (to_elevation - from_elevation) * (distance - from_distance)
+                        / (to_distance - from_distance)
+            };
+            profile.push((distance, elevation));
+            sample += 1.;
+        }
+        if profile.last().map(|&(distance, _)| distance) != Some(last.0) {
+            profile.push(last);
+        }
+        profile
+    }
+
+    /// Gives the fastest part of the track covering at least `distance`
+    /// meters between points with times, if it's that long. Distances are
+    /// summed over the segments but not between them, while the times
+    /// between them count.
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use gpx::{Track, TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// // About 111 meters per point, faster in the middle.
+    /// for (step, seconds) in vec![0, 30, 50, 70, 100].into_iter().enumerate() {
+    ///     let mut point = Waypoint::try_new(step as f64 / 1000., 0.).unwrap();
+    ///     point.time = Some(Utc.timestamp_opt(seconds, 0).unwrap());
+    ///     segment.points.push(point);
+    /// }
+    /// let mut track = Track::new();
+    /// track.segments.push(segment);
+    ///
+    /// let effort = track.best_effort(200.).unwrap();
+    /// assert_eq!((effort.start, effort.end), (1, 3));
+    /// assert_eq!(effort.duration().num_seconds(), 40);
+    /// assert_eq!(track.best_effort(1000.), None);
+    /// ```
+    pub fn best_effort(&self, distance: f64) -> Option<Effort> {
+        let timed: Vec<(usize, f64, DateTime<Utc>)> = along(self)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, (along, point))| Some((index, along, point.time?)))
+            .collect();
+        let mut best: Option<Effort> = None;
+        let mut start = 0;
+        for (end, &(end_index, end_distance, end_time)) in timed.iter().enumerate() {
+            // The last point at least `distance` meters before, if any is.
+            while start + 1 < end && end_distance - timed[start + 1].1 >= distance {
+                start += 1;
+            }
+            let (start_index, start_distance, start_time) = timed[start];
+            if start == end || end_distance - start_distance < distance {
+                continue;
+            }
+            let faster = match best {
+                Some(ref best) => end_time - start_time < best.duration(),
+                None => true,
+            };
+            if faster {
+                best = Some(Effort {
+                    start: start_index,
+                    end: end_index,
+                    start_time,
+                    end_time,
+                    distance: end_distance - start_distance,
+                });
+            }
+        }
+        best
+    }
+
+    /// Gives the gradient at each point of the track, in order across its
+    /// segments, like `TrackSegment::gradients`. The first point of each
+    /// segment has none.
+    pub fn gradients(&self, window: f64) -> Vec<Option<f64>> {
+        self.segments
+            .iter()
+            .flat_map(|segment| segment.gradients(window))
+            .collect()
+    }
+
+    /// Gives the highest speed of the track, like `TrackSegment::max_speed`.
+    pub fn max_speed(&self, limit: Option<f64>) -> Option<f64> {
+        self.segments
+            .iter()
+            .filter_map(|segment| segment.max_speed(limit))
+            .fold(None, max)
+    }
+
+    /// Gives the average speed of the track within its segments, like
+    /// `TrackSegment::avg_speed`. Gaps between segments are left out.
+    pub fn avg_speed(&self, limit: Option<f64>) -> Option<f64> {
+        let (distance, seconds) = self
+            .segments
+            .iter()
+            .map(|segment| plausible_steps(&segment.points, limit))
+            .fold((0., 0.), |(distance, seconds), step| {
+                (distance + step.0, seconds + step.1)
+            });
+        if seconds > 0. {
+            Some(distance / seconds)
+        } else {
+            None
+        }
+    }
+
+    /// Gives the statistics of the heart rate, cadence and power of the
+    /// points of the track, like `TrackSegment::sensor_summary`.
+    pub fn sensor_summary(&self) -> SensorSummary {
+        sensor_summary(self.segments.iter().flat_map(|segment| &segment.points))
+    }
+
+    /// Estimates the energy spent along the track, in kilocalories, by a
+    /// person of `weight` kilograms doing `activity`, from the speed and the
+    /// gradient between each two points with times. Only the time between
+    /// them counts, not the time between segments.
+    ///
+    /// It's an estimate, good to some tens of percent at best: walks and
+    /// runs follow the metabolic equations of the American College of Sports
+    /// Medicine, rides the power needed to overcome rolling resistance,
+    /// gravity and drag on a road bike of 10 kilograms at an efficiency of
+    /// 24%, and drives the energy spent sitting.
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use gpx::{Activity, Track, TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// // About 5 kilometers per hour for an hour.
+    /// for minutes in 0..=60 {
+    ///     let mut point = Waypoint::try_new(minutes as f64 * 0.00075, 0.).unwrap();
+    ///     point.time = Some(Utc.timestamp_opt(minutes * 60, 0).unwrap());
+    ///     segment.points.push(point);
+    /// }
+    /// let mut track = Track::new();
+    /// track.segments.push(segment);
+    ///
+    /// let walking = track.energy_estimate(70., Activity::Walk);
+    /// assert_eq!(walking.round(), 249.);
+    /// assert!(track.energy_estimate(70., Activity::Drive) < walking);
+    /// ```
+    pub fn energy_estimate(&self, weight: f64, activity: Activity) -> f64 {
+        self.segments
+            .iter()
+            .flat_map(|segment| steps(&segment.points))
+            .flatten()
+            .map(|step| {
+                let speed = step.distance / step.seconds;
+                let grade = match step.climb {
+                    Some(climb) if step.distance > 0. => climb / step.distance,
+                    _ => 0.,
+                };
+                energy_rate(activity, weight, speed, grade) * step.seconds
+            })
+            .sum()
+    }
+
+    /// Tells whether the track is a loop, an out-and-back or goes from point
+    /// to point, with the thresholds of `options`, if it has at least two
+    /// points. Routes which go back the same way are out-and-backs first,
+    /// whether they end where they start or not.
+    ///
+    /// It compares the points of either half of the route with each other,
+    /// so it's best kept to tracks of a few thousand points.
+    ///
+    /// ```
+    /// use gpx::{RouteShape, ShapeOptions, Track, TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// // Up a valley and back down it.
+    /// for step in (0..10).chain((0..10).rev()) {
+    ///     segment.points.push(Waypoint::try_new(step as f64 / 1000., 0.).unwrap());
+    /// }
+    /// let mut track = Track::new();
+    /// track.segments.push(segment);
+    ///
+    /// let shape = track.route_shape(&ShapeOptions::default());
+    /// assert_eq!(shape, Some(RouteShape::OutAndBack));
+    /// ```
+    pub fn route_shape(&self, options: &ShapeOptions) -> Option<RouteShape> {
+        let along = along(self);
+        if along.len() < 2 {
+            return None;
+        }
+        let (first, last) = (along[0].1, along[along.len() - 1].1);
+        let half = along[along.len() - 1].0 / 2.;
+        let outward: Vec<&Waypoint> = along
+            .iter()
+            .filter(|&&(distance, _)| distance <= half)
+            .map(|&(_, point)| point)
+            .collect();
+        let back: Vec<&Waypoint> = along
+            .iter()
+            .filter(|&&(distance, _)| distance > half)
+            .map(|&(_, point)| point)
+            .collect();
+        if !back.is_empty() {
+            let retraced = back
+                .iter()
+                .filter(|point| {
+                    outward
+                        .iter()
+                        .any(|other| haversine_distance(point, other) <= options.overlap_distance)
+                })
+                .count();
+            if retraced as f64 / back.len() as f64 >= options.overlap_fraction {
+                return Some(RouteShape::OutAndBack);
+            }
+        }
+        if haversine_distance(first, last) <= options.closing_distance {
+            Some(RouteShape::Loop)
+        } else {
+            Some(RouteShape::PointToPoint)
+        }
+    }
+
+    /// Gives the times the track stayed within `radius` meters of a point
+    /// for at least `min_duration`, in order, as for trip reports. Only
+    /// points with times count, and stops don't span two segments.
+    ///
+    /// Each stop starts at a point and holds the points after it within
+    /// `radius` of it, so GPS jitter while standing still doesn't break it
+    /// up as long as it stays within that.
+    ///
+    /// ```
+    /// use chrono::{Duration, TimeZone, Utc};
+    /// use gpx::{Track, TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// // About 11 meters every 10 seconds, but for a break of two minutes.
+    /// let points = vec![(0, 0.), (10, 0.0001), (70, 0.0001), (130, 0.0001), (140, 0.0002)];
+    /// for (seconds, latitude) in points {
+    ///     let mut point = Waypoint::try_new(latitude, 0.).unwrap();
+    ///     point.time = Some(Utc.timestamp_opt(seconds, 0).unwrap());
+    ///     segment.points.push(point);
+    /// }
+    /// let mut track = Track::new();
+    /// track.segments.push(segment);
+    ///
+    /// let stops = track.stops(Duration::minutes(1), 5.);
+    /// assert_eq!(stops.len(), 1);
+    /// assert_eq!(stops[0].start_time, Utc.timestamp_opt(10, 0).unwrap());
+    /// assert_eq!(stops[0].duration().num_seconds(), 120);
+    /// ```
+    pub fn stops(&self, min_duration: Duration, radius: f64) -> Vec<Stop> {
+        let mut stops = Vec::new();
+        for segment in &self.segments {
+            let timed: Vec<(&Waypoint, DateTime<Utc>)> = segment
+                .points
+                .iter()
+                .filter_map(|point| Some((point, point.time?)))
+                .collect();
+            let mut start = 0;
+            while start < timed.len() {
+                let (anchor, start_time) = timed[start];
+                // The anchor is part of its stop, whatever the radius.
+                let count = 1 + timed[start + 1..]
+                    .iter()
+                    .take_while(|&&(point, _)| haversine_distance(anchor, point) <= radius)
+                    .count();
+                let (_, end_time) = timed[start + count - 1];
+                if count > 1 && end_time - start_time >= min_duration {
+                    let points = &timed[start..start + count];
+                    let (latitude, longitude) =
+                        points
+                            .iter()
+                            .fold((0., 0.), |(latitude, longitude), (point, _)| {
+                                (
+                                    latitude + point.point().lat(),
+                                    longitude + point.point().lng(),
+                                )
+                            });
+                    stops.push(Stop {
+                        start_time,
+                        end_time,
+                        location: Point::new(longitude / count as f64, latitude / count as f64),
+                    });
+                    start += count;
+                } else {
+                    start += 1;
+                }
+            }
+        }
+        stops
+    }
+
+    /// Guesses the activity the track was recorded during, from the speeds
+    /// when moving, whether its points have a cadence, and how much of the
+    /// time was spent standing still. Gives none for a track without speeds.
+    ///
+    /// It's a heuristic, best used to label files which come without one:
+    /// the median speed tells walks (up to 2 m/s), runs (up to 4.5 m/s),
+    /// rides (up to 12 m/s) and drives apart. Top speeds no legs reach, or
+    /// frequent stops at the speeds of rides without a cadence, make drives.
+    /// The guess is less sure the closer the median speed is to another
+    /// activity, and more when a cadence agrees with it.
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use gpx::{Activity, Track, TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// // About 3 meters per second.
+    /// for step in 0..10 {
+    ///     let mut point = Waypoint::try_new(step as f64 * 0.0003, 0.).unwrap();
+    ///     point.time = Some(Utc.timestamp_opt(step * 10, 0).unwrap());
+    ///     segment.points.push(point);
+    /// }
+    /// let mut track = Track::new();
+    /// track.segments.push(segment);
+    ///
+    /// assert_eq!(track.infer_activity().unwrap().activity, Activity::Run);
+    /// ```
+    pub fn infer_activity(&self) -> Option<ActivityGuess> {
+        let mut speeds: Vec<f64> = self
+            .speeds()
+            .into_iter()
+            .flatten()
+            .filter(|&speed| speed >= STOPPED_SPEED)
+            .collect();
+        if speeds.is_empty() {
+            return None;
+        }
+        speeds.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        let median = speeds[speeds.len() / 2];
+        let top = speeds[(speeds.len() - 1) * 95 / 100];
+        let cadence = self
+            .segments
+            .iter()
+            .flat_map(|segment| &segment.points)
+            .any(|point| Sensor::Cadence.value(point).unwrap_or(0.) > 0.);
+        let time = self.moving_time(STOPPED_SPEED);
+        let elapsed = time.elapsed.num_milliseconds() as f64;
+        let stopped = if elapsed > 0. {
+            1. - time.moving.num_milliseconds() as f64 / elapsed
+        } else {
+            0.
+        };
+
+        let [walk, run, ride] = ACTIVITY_SPEEDS;
+        if !cadence && (top > 25. || (median > 8. && median <= ride && stopped >= 0.15)) {
+            return Some(ActivityGuess {
+                activity: Activity::Drive,
+                confidence: 0.9,
+            });
+        }
+        let activity = if median <= walk {
+            Activity::Walk
+        } else if median <= run {
+            Activity::Run
+        } else if median <= ride {
+            Activity::Ride
+        } else {
+            Activity::Drive
+        };
+        let margin = ACTIVITY_SPEEDS
+            .iter()
+            .map(|speed| (median - speed).abs())
+            .fold(f64::INFINITY, f64::min);
+        let mut confidence = 0.5 + 0.4 * (margin / 1.5).min(1.);
+        if cadence {
+            confidence += match activity {
+                Activity::Drive => -0.2,
+                _ => 0.1,
+            };
+        }
+        Some(ActivityGuess {
+            activity,
+            confidence: confidence.clamp(0.5, 1.),
+        })
+    }
+
+    /// Gives the time spent in each zone of a `sensor` within the segments of
+    /// the track, like `TrackSegment::time_in_zones`.
+    pub fn time_in_zones(&self, sensor: Sensor, boundaries: &[f64]) -> Vec<Duration> {
+        let mut zones = vec![Duration::zero(); boundaries.len() + 1];
+        for segment in &self.segments {
+            segment.add_time_in_zones(sensor, boundaries, &mut zones);
+        }
+        zones
+    }
+
+    /// Gives the statistics of the track, computed in a single pass over its
+    /// points.
+    pub fn summary(&self) -> TrackSummary {
+        self.summary_with(DistanceMethod::Haversine)
+    }
+
+    /// Gives the statistics of the track, with distances computed by
+    /// `method`.
+    pub fn summary_with(&self, method: DistanceMethod) -> TrackSummary {
+        let mut summary = Summary::new(method);
+        summary.add_track(self);
+        summary.finish()
+    }
+}
+
+impl Gpx {
+    /// Gives the statistics of the tracks of the document, computed in a
+    /// single pass over their points.
+    pub fn summary(&self) -> GpxSummary {
+        self.summary_with(DistanceMethod::Haversine)
+    }
+
+    /// Gives the statistics of the tracks of the document, with distances
+    /// computed by `method`.
+    pub fn summary_with(&self, method: DistanceMethod) -> GpxSummary {
+        let mut total = Summary::new(method);
+        let tracks = self
+            .tracks
+            .iter()
+            .map(|track| {
+                let mut summary = Summary::new(method);
+                summary.add_track(track);
+                total.merge(&summary);
+                summary.finish()
+            })
+            .collect();
+        GpxSummary {
+            tracks,
+            total: total.finish(),
+        }
+    }
+}
+
+/// Summary sums up points as they are added.
+struct Summary {
+    method: DistanceMethod,
+    summary: TrackSummary,
+    first_time: Option<DateTime<Utc>>,
+    last_time: Option<DateTime<Utc>>,
+}
+
+impl Summary {
+    fn new(method: DistanceMethod) -> Summary {
+        Summary {
+            method,
+            summary: Default::default(),
+            first_time: None,
+            last_time: None,
+        }
+    }
+
+    fn add_track(&mut self, track: &Track) {
+        for segment in &track.segments {
+            self.add_segment(segment);
+        }
+    }
+
+    fn add_segment(&mut self, segment: &TrackSegment) {
+        let summary = &mut self.summary;
+        let (gain, loss) = climb(&segment.points, DEFAULT_ELEVATION_THRESHOLD);
+        summary.elevation_gain += gain;
+        summary.elevation_loss += loss;
+        let mut previous: Option<&Waypoint> = None;
+        for point in &segment.points {
+            summary.points += 1;
+            if let Some(previous) = previous {
+                let distance = self.method.distance(previous, point);
+                summary.distance += distance;
+                if let (Some(from), Some(to)) = (previous.time, point.time) {
+                    let seconds = (to - from).num_milliseconds() as f64 / 1000.;
+                    if seconds > 0. {
+                        summary.max_speed = max(summary.max_speed, distance / seconds);
+                    }
+                }
+            }
+            if let Some(elevation) = point.elevation {
+                summary.min_elevation = min(summary.min_elevation, elevation);
+                summary.max_elevation = max(summary.max_elevation, elevation);
+            }
+            if let Some(time) = point.time {
+                self.first_time = Some(self.first_time.map_or(time, |first| first.min(time)));
+                self.last_time = Some(self.last_time.map_or(time, |last| last.max(time)));
+            }
+            previous = Some(point);
+        }
+    }
+
+    /// Adds up the points summed up by `other`.
+    fn merge(&mut self, other: &Summary) {
+        let (summary, other_summary) = (&mut self.summary, &other.summary);
+        summary.points += other_summary.points;
+        summary.distance += other_summary.distance;
+        summary.elevation_gain += other_summary.elevation_gain;
+        summary.elevation_loss += other_summary.elevation_loss;
+        if let Some(elevation) = other_summary.min_elevation {
+            summary.min_elevation = min(summary.min_elevation, elevation);
+        }
+        if let Some(elevation) = other_summary.max_elevation {
+            summary.max_elevation = max(summary.max_elevation, elevation);
+        }
+        if let Some(speed) = other_summary.max_speed {
+            summary.max_speed = max(summary.max_speed, speed);
+        }
+        self.first_time = match (self.first_time, other.first_time) {
+            (Some(first), Some(other)) => Some(first.min(other)),
+            (first, other) => first.or(other),
+        };
+        self.last_time = match (self.last_time, other.last_time) {
+            (Some(last), Some(other)) => Some(last.max(other)),
+            (last, other) => last.or(other),
+        };
+    }
+
+    fn finish(self) -> TrackSummary {
+        let mut summary = self.summary;
+        if let (Some(first), Some(last)) = (self.first_time, self.last_time) {
+            if first < last {
+                let duration = last - first;
+                let seconds = duration.num_milliseconds() as f64 / 1000.;
+                summary.duration = Some(duration);
+                summary.average_speed = Some(summary.distance / seconds);
+            }
+        }
+        summary
+    }
+}
+
+/// Gives the sums of the climbs and of the descents between points, with
+/// hysteresis: the elevation counted from only moves once another is at
+/// least `threshold` above or below it.
+fn climb(points: &[Waypoint], threshold: f64) -> (f64, f64) {
+    let (mut gain, mut loss) = (0., 0.);
+    let mut reference: Option<f64> = None;
+    for elevation in points.iter().filter_map(|point| point.elevation) {
+        let from = match reference {
+            Some(from) => from,
+            None => {
+                reference = Some(elevation);
+                continue;
+            }
+        };
+        let change = elevation - from;
+        if change.abs() >= threshold {
+            if change > 0. {
+                gain += change;
+            } else {
+                loss -= change;
+            }
+            reference = Some(elevation);
+        }
+    }
+    (gain, loss)
+}
+
+/// Gives the sum of the times between points with times following each
+/// other, when moving between them at least at `speed_threshold`.
+fn moving(points: &[Waypoint], speed_threshold: f64) -> Duration {
+    let mut moving = Duration::zero();
+    let mut timed = points
+        .iter()
+        .filter_map(|point| point.time.map(|time| (point, time)));
+    let mut previous = match timed.next() {
+        Some(previous) => previous,
+        None => return moving,
+    };
+    for (point, time) in timed {
+        let duration = time - previous.1;
+        let seconds = duration.num_milliseconds() as f64 / 1000.;
+        if seconds > 0. && haversine_distance(previous.0, point) / seconds >= speed_threshold {
+            moving += duration;
+        }
+        previous = (point, time);
+    }
+    moving
+}
+
+/// Gives the statistics of the sensor data of points.
+fn sensor_summary<'a>(points: impl Iterator<Item = &'a Waypoint>) -> SensorSummary {
+    let (mut heart_rates, mut cadences, mut powers) = (Vec::new(), Vec::new(), Vec::new());
+    let mut timed_powers = Vec::new();
+    for point in points {
+        let extension = match point.track_point_extension {
+            Some(ref extension) => extension,
+            None => continue,
+        };
+        heart_rates.extend(extension.hr);
+        cadences.extend(extension.cad);
+        if let Some(power) = extension.power {
+            powers.push(power);
+            if let Some(time) = point.time {
+                timed_powers.push((time, f64::from(power)));
+            }
+        }
+    }
+    timed_powers.sort_by_key(|&(time, _)| time);
+    SensorSummary {
+        average_heart_rate: average(heart_rates.iter().map(|&value| f64::from(value))),
+        max_heart_rate: heart_rates.iter().copied().max(),
+        average_cadence: average(cadences.iter().map(|&value| f64::from(value))),
+        max_cadence: cadences.iter().copied().max(),
+        average_power: average(powers.iter().map(|&value| f64::from(value))),
+        max_power: powers.iter().copied().max(),
+        normalized_power: normalized_power(&timed_powers),
+    }
+}
+
+fn average(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0., 0_u32), |(sum, count), value| (sum + value, count + 1));
+    if count > 0 {
+        Some(sum / f64::from(count))
+    } else {
+        None
+    }
+}
+
+/// Gives the normalized power of power samples in order of time, each held
+/// until the next one, if they span at least 30 seconds.
+///
+/// The power of each second is that of the last sample by then. Seconds
+/// past the first 30 of a sample, when the rolling average is its power,
+/// are counted all at once, so that samples far apart cost nothing more.
+fn normalized_power(samples: &[(DateTime<Utc>, f64)]) -> Option<f64> {
+    let first = samples.first()?.0;
+    let seconds = (samples.last()?.0 - first).num_seconds();
+    if seconds < 30 {
+        return None;
+    }
+    // The first second at or after a time.
+    let start = |time: DateTime<Utc>| {
+        let milliseconds = (time - first).num_milliseconds();
+        (milliseconds + 999).div_euclid(1000).min(seconds + 1)
+    };
+    let mut window = VecDeque::with_capacity(30);
+    let (mut sum, mut count) = (0., 0);
+    for (index, &(time, power)) in samples.iter().enumerate() {
+        let end = samples
+            .get(index + 1)
+            .map_or(seconds + 1, |&(next, _)| start(next));
+        let mut second = start(time);
+        let mut held = 0;
+        while second < end {
+            if held >= 30 {
+                sum += (end - second) as f64 * power.powi(4);
+                count += end - second;
+                break;
+            }
+            if window.len() == 30 {
+                window.pop_front();
+            }
+            window.push_back(power);
+            if window.len() == 30 {
+                sum += (window.iter().sum::<f64>() / 30.).powi(4);
+                count += 1;
+            }
+            second += 1;
+            held += 1;
+        }
+    }
+    Some((sum / count as f64).powf(0.25))
+}
+
+/// Gives the distance along the track to each of its points, summed over
+/// its segments but not between them.
+fn along(track: &Track) -> Vec<(f64, &Waypoint)> {
+    let mut distance = 0.;
+    let mut along = Vec::new();
+    for segment in &track.segments {
+        for (index, point) in segment.points.iter().enumerate() {
+            if index > 0 {
+                distance += haversine_distance(&segment.points[index - 1], point);
+            }
+            along.push((distance, point));
+        }
+    }
+    along
+}
+
+/// Gives the point at `distance` meters along points, interpolated between
+/// the points around it, if the points reach that far.
+fn point_along(along: &[(f64, &Waypoint)], distance: f64) -> Option<Waypoint> {
+    let index = along.iter().position(|&(along, _)| along >= distance)?;
+    let (to_distance, to) = along[index];
+    if index == 0 || to_distance == distance {
+        return Some(to.clone());
+    }
+    let (from_distance, from) = along[index - 1];
+    Some(interpolate(
+        from,
+        to,
+        (distance - from_distance) / (to_distance - from_distance),
+    ))
+}
+
+/// Gives a point a `fraction` of the way from one point to another, with
+/// its coordinates, time and elevation interpolated linearly, which is close
+/// enough at the distances between the points of tracks.
+pub(crate) fn interpolate(from: &Waypoint, to: &Waypoint, fraction: f64) -> Waypoint {
+    let (start, end) = (from.point(), to.point());
+    let mut longitude_change = end.lng() - start.lng();
+    // The short way across the antimeridian.
+    if longitude_change > 180. {
+        longitude_change -= 360.;
+    } else if longitude_change < -180. {
+        longitude_change += 360.;
+    }
+    let mut longitude = start.lng() + longitude_change * fraction;
+    if longitude > 180. {
+        longitude -= 360.;
+    } else if longitude < -180. {
+        longitude += 360.;
+    }
+    let latitude = start.lat() + (end.lat() - start.lat()) * fraction;
+
+    let mut point = Waypoint::new(Point::new(longitude, latitude));
+    point.time = match (from.time, to.time) {
+        (Some(from), Some(to)) => {
+            let milliseconds = (to - from).num_milliseconds() as f64 * fraction;
+            Some(from + Duration::milliseconds(milliseconds.round() as i64))
+        }
+        _ => None,
+    };
+    point.elevation = match (from.elevation, to.elevation) {
+        (Some(from), Some(to)) => Some(from + (to - from) * fraction),
+        _ => None,
+    };
+    point
+}
+
+/// Gives the index, the distance along the points and the elevation of each
+/// point with an elevation.
+fn profile(points: &[Waypoint]) -> Vec<(usize, f64, f64)> {
+    let mut distance = 0.;
+    let mut profile = Vec::new();
+    for (index, point) in points.iter().enumerate() {
+        if index > 0 {
+            distance += haversine_distance(&points[index - 1], point);
+        }
+        if let Some(elevation) = point.elevation {
+            profile.push((index, distance, elevation));
+        }
+    }
+    profile
+}
+
+/// A step from a point with a time to the next one with a later time.
+struct Step {
+    distance: f64,
+    seconds: f64,
+    // Elevation change, if both points have an elevation.
+    climb: Option<f64>,
+}
+
+/// Gives the step to each point from the last point before it with a time,
+/// for points with a later time than that one.
+fn steps(points: &[Waypoint]) -> Vec<Option<Step>> {
+    let mut previous: Option<&Waypoint> = None;
+    points
+        .iter()
+        .map(|point| {
+            let time = point.time?;
+            let from = previous.replace(point)?;
+            let seconds = (time - from.time?).num_milliseconds() as f64 / 1000.;
+            if seconds > 0. {
+                Some(Step {
+                    distance: haversine_distance(from, point),
+                    seconds,
+                    climb: point
+                        .elevation
+                        .and_then(|elevation| Some(elevation - from.elevation?)),
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Gives the sums of the distances and of the seconds of the steps between
+/// points, leaving out those faster than `limit`.
+fn plausible_steps(points: &[Waypoint], limit: Option<f64>) -> (f64, f64) {
+    steps(points)
+        .into_iter()
+        .flatten()
+        .filter(|step| step.distance / step.seconds <= limit.unwrap_or(f64::INFINITY))
+        .fold((0., 0.), |(distance, seconds), step| {
+            (distance + step.distance, seconds + step.seconds)
+        })
+}
+
+/// Gives the energy spent (in kilocalories per second) by a person of
+/// `weight` kilograms doing `activity` at `speed` meters per second up a
+/// slope of `grade`.
+fn energy_rate(activity: Activity, weight: f64, speed: f64, grade: f64) -> f64 {
+    // Oxygen uptake in milliliters per kilogram and minute, at about 5
+    // kilocalories per liter, with 3.5 at rest.
+    let oxygen = |uptake: f64| uptake * weight / 1000. * 5. / 60.;
+    let meters_per_minute = speed * 60.;
+    match activity {
+        Activity::Walk => {
+            oxygen(0.1 * meters_per_minute + 1.8 * meters_per_minute * grade.max(0.) + 3.5)
+        }
+        Activity::Run => {
+            oxygen(0.2 * meters_per_minute + 0.9 * meters_per_minute * grade.max(0.) + 3.5)
+        }
+        Activity::Ride => {
+            // Rolling resistance and gravity on the rider and the bike, and
+            // drag in still air.
+            let mass = weight + 10.;
+            let power = mass * 9.81 * speed * (0.005 + grade) + 0.5 * 1.225 * 0.4 * speed.powi(3);
+            oxygen(3.5) + power.max(0.) / 0.24 / 4184.
+        }
+        Activity::Drive => oxygen(1.3 * 3.5),
+    }
+}
+
+/// Gives the energy cost of running (in joules per kilogram and meter) on a
+/// slope of `grade`, by the polynomial fit of Minetti et al. (2002).
+fn running_cost(grade: f64) -> f64 {
+    let i = grade.clamp(-0.45, 0.45);
+    ((((155.4 * i - 30.4) * i - 43.3) * i + 46.3) * i + 19.5) * i + 3.6
+}
+
+/// Gives the time between the first and the last time of points.
+fn elapsed<'a>(points: impl Iterator<Item = &'a Waypoint>) -> Duration {
+    let mut times = points.filter_map(|point| point.time);
+    let first = match times.next() {
+        Some(first) => first,
+        None => return Duration::zero(),
+    };
+    let (first, last) = times.fold((first, first), |(first, last), time| {
+        (first.min(time), last.max(time))
+    });
+    last - first
+}
+
+fn min(current: Option<f64>, value: f64) -> Option<f64> {
+    Some(current.map_or(value, |current| current.min(value)))
+}
+
+fn max(current: Option<f64>, value: f64) -> Option<f64> {
+    Some(current.map_or(value, |current| current.max(value)))
+}
+
+/// Gives the great-circle distance between two points, in meters.
+pub(crate) fn haversine_distance(from: &Waypoint, to: &Waypoint) -> f64 {
+    let (from, to) = (from.point(), to.point());
+    let (lat1, lat2) = (from.lat().to_radians(), to.lat().to_radians());
+    let delta_lat = lat2 - lat1;
+    let delta_lng = (to.lng() - from.lng()).to_radians();
+    let a =
+        (delta_lat / 2.).sin().powi(2) + lat1.cos() * lat2.cos() * (delta_lng / 2.).sin().powi(2);
+    2. * EARTH_RADIUS * a.sqrt().asin()
+}
+
+/// Gives the geodesic distance between two points on the WGS 84 ellipsoid,
+/// in meters, or `None` if Vincenty's inverse formula doesn't converge.
+fn vincenty_distance(from: &Waypoint, to: &Waypoint) -> Option<f64> {
+    let (from, to) = (from.point(), to.point());
+    let b = (1. - WGS84_F) * WGS84_A;
+    let l = (to.lng() - from.lng()).to_radians();
+    // Reduced latitudes, on the auxiliary sphere.
+    let u1 = ((1. - WGS84_F) * from.lat().to_radians().tan()).atan();
+    let u2 = ((1. - WGS84_F) * to.lat().to_radians().tan()).atan();
+    let (sin_u1, cos_u1) = u1.sin_cos();
+    let (sin_u2, cos_u2) = u2.sin_cos();
+
+    let mut lambda = l;
+    for _ in 0..200 {
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+        let sin_sigma = ((cos_u2 * sin_lambda).powi(2)
+            + (cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda).powi(2))
+        .sqrt();
+        if sin_sigma == 0. {
+            // The same point.
+            return Some(0.);
+        }
+        let cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+        let sigma = sin_sigma.atan2(cos_sigma);
+        let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+        let cos2_alpha = 1. - sin_alpha * sin_alpha;
+        // Points on the equator have no midpoint latitude to speak of.
+        let cos_2sigma_m = if cos2_alpha == 0. {
+            0.
+        } else {
+            cos_sigma - 2. * sin_u1 * sin_u2 / cos2_alpha
+        };
+        let c = WGS84_F / 16. * cos2_alpha * (4. + WGS84_F * (4. - 3. * cos2_alpha));
+        let previous = lambda;
+        lambda = l
+            + (1. - c)
+                * WGS84_F
+                * sin_alpha
+                * (sigma
+                    + c * sin_sigma
+                        * (cos_2sigma_m + c * cos_sigma * (-1. + 2. * cos_2sigma_m.powi(2))));
+        if (lambda - previous).abs() < 1e-12 {
+            let u2 = cos2_alpha * (WGS84_A * WGS84_A - b * b) / (b * b);
+            let big_a = 1. + u2 / 16384. * (4096. + u2 * (-768. + u2 * (320. - 175. * u2)));
+            let big_b = u2 / 1024. * (256. + u2 * (-128. + u2 * (74. - 47. * u2)));
+            let delta_sigma = big_b
+                * sin_sigma
+                * (cos_2sigma_m
+                    + big_b / 4.
+                        * (cos_sigma * (-1. + 2. * cos_2sigma_m.powi(2))
+                            - big_b / 6.
+                                * cos_2sigma_m
+                                * (-3. + 4. * sin_sigma.powi(2))
+                                * (-3. + 4. * cos_2sigma_m.powi(2))));
+            return Some(b * big_a * (sigma - delta_sigma));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::{
+        Activity, DistanceMethod, PaceUnit, RouteShape, Sensor, DEFAULT_ELEVATION_THRESHOLD,
+    };
+    use crate::{Gpx, Track, TrackPointExtension, TrackSegment, Waypoint};
+
+    fn segment(points: &[(i64, f64, Option<f64>)]) -> TrackSegment {
+        let mut segment = TrackSegment::new();
+        for &(seconds, latitude, elevation) in points {
+            let mut point = Waypoint::try_new(latitude, 0.).unwrap();
+            point.time = Some(Utc.timestamp_opt(seconds, 0).unwrap());
+            point.elevation = elevation;
+            segment.points.push(point);
+        }
+        segment
+    }
+
+    #[test]
+    fn track_summary_across_segments() {
+        let mut track = Track::new();
+        track
+            .segments
+            .push(segment(&[(0, 0., Some(100.)), (100, 0.01, None)]));
+        track
+            .segments
+            .push(segment(&[(200, 0.05, Some(90.)), (250, 0.06, Some(95.))]));
+
+        let summary = track.summary();
+
+        assert_eq!(summary.points, 4);
+        // Two hundredths of a degree of latitude, without the gap.
+        assert_eq!((summary.distance / 10.).round(), 222.);
+        assert_eq!(summary.duration.unwrap().num_seconds(), 250);
+        assert_eq!(summary.elevation_gain, 5.);
+        assert_eq!(summary.elevation_loss, 0.);
+        assert_eq!(summary.min_elevation, Some(90.));
+        assert_eq!(summary.max_elevation, Some(100.));
+        let max_speed = summary.max_speed.unwrap();
+        assert!((max_speed - 1111.95 / 50.).abs() < 0.01);
+        assert!((summary.average_speed.unwrap() - summary.distance / 250.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn length_m() {
+        let mut track = Track::new();
+        track
+            .segments
+            .push(segment(&[(0, 0., None), (1, 0.01, None)]));
+        track.segments.push(segment(&[
+            (2, 0.05, None),
+            (3, 0.06, None),
+            (4, 0.06, None),
+        ]));
+        track.segments.push(TrackSegment::new());
+
+        assert_eq!(track.segments[2].length_m(), 0.);
+        assert!((track.segments[0].length_m() - 1111.95).abs() < 0.01);
+        assert!((track.length_m() - track.summary().distance).abs() < 1e-9);
+    }
+
+    #[test]
+    fn elevation_threshold() {
+        let mut track = Track::new();
+        track.segments.push(segment(&[
+            (0, 0., Some(10.)),
+            (1, 0., Some(12.)),
+            (2, 0., None),
+            (3, 0., Some(14.)),
+            (4, 0., Some(9.)),
+        ]));
+        // No climb from the end of a segment to the start of the next.
+        track
+            .segments
+            .push(segment(&[(5, 0., Some(20.)), (6, 0., Some(18.))]));
+
+        assert_eq!(track.elevation_gain(0.), 4.);
+        assert_eq!(track.elevation_loss(0.), 7.);
+        assert_eq!(track.elevation_gain(3.), 4.);
+        assert_eq!(track.elevation_loss(3.), 5.);
+        let summary = track.summary();
+        let threshold = DEFAULT_ELEVATION_THRESHOLD;
+        assert_eq!(summary.elevation_gain, track.elevation_gain(threshold));
+        assert_eq!(summary.elevation_loss, track.elevation_loss(threshold));
+        assert_eq!(track.elevation_gain(10.), 0.);
+        assert_eq!(track.elevation_loss(10.), 0.);
+    }
+
+    #[test]
+    fn moving_time() {
+        let mut track = Track::new();
+        // A stop of 20 seconds, and a point without a time.
+        let mut first = segment(&[(0, 0., None), (10, 0.001, None), (30, 0.001, None)]);
+        first
+            .points
+            .insert(1, Waypoint::try_new(0.0005, 0.).unwrap());
+        track.segments.push(first);
+        track
+            .segments
+            .push(segment(&[(100, 0.002, None), (110, 0.003, None)]));
+
+        let time = track.moving_time(0.5);
+        assert_eq!(time.moving.num_seconds(), 20);
+        assert_eq!(time.elapsed.num_seconds(), 110);
+        // Moving at about 11 meters per second at most.
+        assert_eq!(track.moving_time(12.).moving, chrono::Duration::zero());
+        assert_eq!(Track::new().moving_time(0.5).elapsed.num_seconds(), 0);
+    }
+
+    #[test]
+    fn speeds() {
+        let mut track = Track::new();
+        let mut first = segment(&[(0, 0., None), (10, 0.001, None), (10, 0.002, None)]);
+        first.points.push(Waypoint::try_new(0.003, 0.).unwrap());
+        // A jump of over a kilometer in a second.
+        first
+            .points
+            .extend(segment(&[(20, 0.003, None), (21, 0.013, None)]).points);
+        track.segments.push(first);
+        track
+            .segments
+            .push(segment(&[(100, 0.02, None), (110, 0.021, None)]));
+
+        let speeds = track.speeds();
+        assert_eq!(speeds.len(), 8);
+        assert_eq!(speeds[0], None);
+        assert!((speeds[1].unwrap() - 11.1195).abs() < 0.001);
+        // No time since the last point, or no time at all.
+        assert_eq!(speeds[2], None);
+        assert_eq!(speeds[3], None);
+        assert!((speeds[4].unwrap() - 11.1195).abs() < 0.001);
+        assert!(speeds[5].unwrap() > 1000.);
+        assert_eq!(speeds[6], None);
+
+        assert_eq!(track.max_speed(None), speeds[5]);
+        assert!((track.max_speed(Some(50.)).unwrap() - 11.1195).abs() < 0.001);
+        let average = track.avg_speed(Some(50.)).unwrap();
+        assert!((average - 3. * 111.195 / 30.).abs() < 0.001);
+        assert!(track.avg_speed(None).unwrap() > average);
+        assert_eq!(TrackSegment::new().avg_speed(None), None);
+    }
+
+    #[test]
+    fn grade_adjusted_paces() {
+        let mut track = Track::new();
+        // Up a 10% slope, then down it, then without elevations.
+        track.segments.push(segment(&[
+            (0, 0., Some(0.)),
+            (100, 0.001, Some(11.1195)),
+            (200, 0.002, Some(0.)),
+            (300, 0.003, None),
+        ]));
+
+        let paces = track.paces(PaceUnit::Kilometer);
+        let adjusted = track.grade_adjusted_paces(PaceUnit::Kilometer);
+        assert_eq!((paces[0], adjusted[0]), (None, None));
+        assert_eq!(paces[1].unwrap().num_seconds(), 899);
+        let ratio = |index: usize| {
+            adjusted[index].unwrap().num_milliseconds() as f64
+                / paces[index].unwrap().num_milliseconds() as f64
+        };
+        assert!((ratio(1) - 3.6 / 5.968214).abs() < 1e-4);
+        // Down a 10% slope is easier than on flat ground.
+        assert!(ratio(2) > 1.);
+        assert_eq!(adjusted[3], paces[3]);
+        assert_eq!(track.paces(PaceUnit::Mile)[3].unwrap().num_seconds(), 1447);
+    }
+
+    #[test]
+    fn gradients() {
+        let mut segment = segment(&[
+            (0, 0., Some(0.)),
+            (1, 0.001, Some(1.)),
+            (2, 0.002, None),
+            (3, 0.003, Some(12.)),
+            (4, 0.004, Some(12.5)),
+            (5, 0.005, Some(13.)),
+        ]);
+        let step = 111.195;
+
+        let close =
+            |gradient: Option<f64>, expected: f64| (gradient.unwrap() - expected).abs() < 1e-5;
+        let gradients = segment.gradients(0.);
+        assert_eq!((gradients[0], gradients[2]), (None, None));
+        assert!(close(gradients[1], 1. / step));
+        assert!(close(gradients[3], 11. / (2. * step)));
+        assert!(close(gradients[5], 0.5 / step));
+        let smoothed = segment.gradients(300.);
+        assert!(close(smoothed[3], 12. / (3. * step)));
+        assert!(close(smoothed[4], 11.5 / (3. * step)));
+        assert!(close(smoothed[5], 12. / (4. * step)));
+
+        let steepest = segment.steepest_section(200.).unwrap();
+        assert_eq!((steepest.start, steepest.end), (1, 3));
+        assert_eq!(steepest.climb, 11.);
+        assert!((steepest.length - 2. * step).abs() < 0.001);
+        assert_eq!(segment.steepest_section(1000.), None);
+
+        segment.points.reverse();
+        let steepest = segment.steepest_section(200.).unwrap();
+        assert_eq!((steepest.start, steepest.end), (2, 4));
+        assert!(steepest.gradient < 0.);
+    }
+
+    #[test]
+    fn splits() {
+        let mut track = Track::new();
+        // 20 steps of 111.195 meters in 20 seconds each, climbing a meter.
+        let points: Vec<_> = (0..=20)
+            .map(|step| (step * 20, step as f64 / 1000., Some(step as f64)))
+            .collect();
+        track.segments.push(segment(&points));
+        let step = 111.195_08;
+
+        let splits = track.splits(PaceUnit::Kilometer);
+        assert_eq!(splits.len(), 3);
+        assert_eq!((splits[0].distance, splits[1].distance), (1000., 1000.));
+        assert!((splits[2].distance - (20. * step - 2000.)).abs() < 1e-3);
+        for split in &splits {
+            let pace = split.pace.unwrap().num_milliseconds() as f64;
+            assert!((pace - 1000. / step * 20_000.).abs() < 10.);
+            let climb = split.elevation_change.unwrap();
+            assert!((climb - split.distance / step).abs() < 1e-4);
+        }
+        assert_eq!(splits[0].duration.unwrap().num_seconds(), 179);
+
+        // No time for the last point.
+        track.segments[0].points[20].time = None;
+        let splits = track.splits(PaceUnit::Mile);
+        assert_eq!(splits.len(), 2);
+        assert!(splits[0].duration.is_some());
+        assert_eq!((splits[1].duration, splits[1].pace), (None, None));
+        assert!(Track::new().splits(PaceUnit::Mile).is_empty());
+    }
+
+    #[test]
+    fn best_effort() {
+        let mut track = Track::new();
+        // Steps of 111.195 meters in 20 seconds, but for three in 10.
+        let times = [0, 20, 40, 60, 80, 100, 110, 120, 130, 150, 170];
+        let points: Vec<_> = times
+            .iter()
+            .enumerate()
+            .map(|(step, &seconds)| (seconds, step as f64 / 1000., None))
+            .collect();
+        track.segments.push(segment(&points[..4]));
+        track.segments.push(segment(&points[4..]));
+
+        let effort = track.best_effort(200.).unwrap();
+        assert_eq!((effort.start, effort.end), (5, 7));
+        assert_eq!(effort.duration().num_seconds(), 20);
+        assert!((effort.distance - 2. * 111.195).abs() < 0.001);
+        // Five steps, as four fall short, with all of the faster ones and
+        // none across the gap between the segments.
+        let effort = track.best_effort(500.).unwrap();
+        assert_eq!((effort.start, effort.end), (4, 9));
+        assert_eq!(effort.duration().num_seconds(), 70);
+        assert_eq!(track.best_effort(2000.), None);
+    }
+
+    #[test]
+    fn point_at_fraction() {
+        let mut track = Track::new();
+        assert_eq!(track.midpoint(), None);
+        track
+            .segments
+            .push(segment(&[(0, 0., Some(10.)), (10, 0.001, Some(20.))]));
+        // Three times as far in the next segment, after a gap.
+        track
+            .segments
+            .push(segment(&[(60, 0.005, Some(0.)), (90, 0.008, Some(30.))]));
+
+        let midpoint = track.midpoint().unwrap();
+        assert!((midpoint.point().lat() - 0.006).abs() < 1e-9);
+        assert_eq!(midpoint.point().lng(), 0.);
+        assert_eq!(midpoint.time, Some(Utc.timestamp_opt(70, 0).unwrap()));
+        assert!((midpoint.elevation.unwrap() - 10.).abs() < 1e-6);
+
+        let start = track.point_at_fraction(0.).unwrap();
+        assert_eq!(start, track.segments[0].points[0]);
+        let end = track.point_at_fraction(1.).unwrap();
+        assert_eq!(end, track.segments[1].points[1]);
+        assert_eq!(track.point_at_fraction(-0.1), None);
+        assert_eq!(track.point_at_fraction(f64::NAN), None);
+    }
+
+    #[test]
+    fn elevation_profile() {
+        let mut track = Track::new();
+        assert!(track.elevation_profile(100.).is_empty());
+        track.segments.push(segment(&[
+            (0, 0., None),
+            (1, 0.001, Some(10.)),
+            (2, 0.002, None),
+            (3, 0.004, Some(40.)),
+        ]));
+        let step = 111.195_08;
+
+        let profile = track.elevation_profile(100.);
+        let distances: Vec<f64> = profile.iter().map(|&(distance, _)| distance).collect();
+        assert_eq!(distances[..3], [200., 300., 400.]);
+        assert!((distances[3] - 4. * step).abs() < 1e-3);
+        // Across the point without an elevation.
+        let expected = 10. + 30. * (300. - step) / (3. * step);
+        assert!((profile[1].1 - expected).abs() < 1e-6);
+        assert_eq!(profile[3].1, 40.);
+        assert!(track.elevation_profile(0.).is_empty());
+    }
+
+    #[test]
+    fn point_at_distance() {
+        let mut track = Track::new();
+        track
+            .segments
+            .push(segment(&[(0, 0., None), (10, 0.001, Some(5.))]));
+        track
+            .segments
+            .push(segment(&[(20, 0.002, Some(7.)), (40, 0.004, Some(9.))]));
+        let step = 111.195_08;
+
+        assert_eq!(track.distance_at_index(0), Some(0.));
+        // No distance across the gap between the segments.
+        assert_eq!(track.distance_at_index(1), track.distance_at_index(2));
+        assert!((track.distance_at_index(3).unwrap() - 3. * step).abs() < 1e-3);
+        assert_eq!(track.distance_at_index(4), None);
+
+        let point = track.point_at_distance(2. * step).unwrap();
+        assert!((point.point().lat() - 0.003).abs() < 1e-6);
+        assert_eq!(point.time, Some(Utc.timestamp_opt(30, 0).unwrap()));
+        assert!((point.elevation.unwrap() - 8.).abs() < 1e-6);
+        // Without an elevation to interpolate from.
+        assert_eq!(track.point_at_distance(step / 2.).unwrap().elevation, None);
+        assert_eq!(track.point_at_distance(-1.), None);
+        assert_eq!(track.point_at_distance(4. * step), None);
+    }
+
+    #[test]
+    fn point_at_time() {
+        let at = |seconds| Utc.timestamp_opt(seconds, 0).unwrap();
+        let mut track = Track::new();
+        let mut first = segment(&[(0, 0., Some(0.)), (20, 0.002, Some(20.))]);
+        // A point without a time is passed over.
+        first
+            .points
+            .insert(1, Waypoint::try_new(0.005, 0.).unwrap());
+        track.segments.push(first);
+        track
+            .segments
+            .push(segment(&[(60, 0.01, None), (70, 0.011, None)]));
+
+        let point = track.point_at_time(at(5)).unwrap();
+        assert!((point.point().lat() - 0.0005).abs() < 1e-9);
+        assert_eq!(point.elevation, Some(5.));
+        assert_eq!(point.time, Some(at(5)));
+        assert_eq!(
+            track.point_at_time(at(60)).unwrap(),
+            track.segments[1].points[0]
+        );
+        assert!(track.point_at_time(at(65)).unwrap().elevation.is_none());
+        // Between the segments, and after the end.
+        assert_eq!(track.point_at_time(at(40)), None);
+        assert_eq!(track.point_at_time(at(71)), None);
+    }
+
+    #[test]
+    fn vertical_speeds() {
+        let mut track = Track::new();
+        let mut first = segment(&[
+            (0, 0., Some(0.)),
+            (10, 0., Some(2.)),
+            (20, 0., None),
+            (30, 0., Some(6.)),
+            (40, 0., Some(5.)),
+        ]);
+        first.points[1].time = None;
+        track.segments.push(first);
+        track
+            .segments
+            .push(segment(&[(50, 0., Some(100.)), (60, 0., Some(90.))]));
+
+        let speeds = track.vertical_speeds(chrono::Duration::zero());
+        assert_eq!(
+            speeds,
+            [None, None, None, Some(0.2), Some(-0.1), None, Some(-1.)]
+        );
+        let smoothed = track.vertical_speeds(chrono::Duration::seconds(20));
+        assert_eq!(smoothed[3], Some(0.2));
+        assert_eq!(smoothed[4], Some(5. / 40.));
+        assert_eq!(smoothed[6], Some(-1.));
+    }
+
+    #[test]
+    fn sensor_summary() {
+        let sample = |seconds, power| {
+            let mut point = Waypoint::try_new(0., 0.).unwrap();
+            point.time = Some(Utc.timestamp_opt(seconds, 0).unwrap());
+            point.track_point_extension = Some(TrackPointExtension {
+                hr: Some(150),
+                power: Some(power),
+                ..Default::default()
+            });
+            point
+        };
+        let mut track = Track::new();
+        assert_eq!(track.sensor_summary(), Default::default());
+
+        // A steady 200 watts for a minute, a sample every 5 seconds.
+        let mut steady = TrackSegment::new();
+        steady.points = (0..=12).map(|step| sample(step * 5, 200)).collect();
+        let summary = steady.sensor_summary();
+        assert_eq!(summary.average_power, Some(200.));
+        assert!((summary.normalized_power.unwrap() - 200.).abs() < 1e-9);
+        assert_eq!(summary.average_cadence, None);
+
+        // Alternating minutes of 100 and 300 watts weigh more than their
+        // average of 200.
+        let mut intervals = TrackSegment::new();
+        intervals.points = (0..=240)
+            .map(|second| sample(second, if (second / 60) % 2 == 0 { 100 } else { 300 }))
+            .collect();
+        track.segments.push(intervals);
+        let summary = track.sensor_summary();
+        assert_eq!(summary.max_power, Some(300));
+        assert!(summary.normalized_power.unwrap() > 230.);
+        assert_eq!(summary.max_heart_rate, Some(150));
+
+        // Out of order, or decades apart, as from a clock not set yet.
+        let mut shuffled = steady.clone();
+        shuffled.points.reverse();
+        let normalized_power = steady.sensor_summary().normalized_power;
+        assert_eq!(shuffled.sensor_summary().normalized_power, normalized_power);
+        shuffled.points.push(sample(-1_000_000_000, 200));
+        assert!((shuffled.sensor_summary().normalized_power.unwrap() - 200.).abs() < 1e-9);
+
+        // Too short to normalize.
+        steady.points.truncate(6);
+        assert_eq!(steady.sensor_summary().normalized_power, None);
+    }
+
+    #[test]
+    fn time_in_zones() {
+        let sample = |seconds, power: Option<u16>| {
+            let mut point = Waypoint::try_new(0., 0.).unwrap();
+            point.time = Some(Utc.timestamp_opt(seconds, 0).unwrap());
+            point.track_point_extension = Some(TrackPointExtension {
+                power,
+                ..Default::default()
+            });
+            point
+        };
+        let mut track = Track::new();
+        let mut first = TrackSegment::new();
+        first.points = vec![
+            sample(0, Some(150)),
+            sample(10, None),
+            sample(20, Some(250)),
+            sample(50, Some(200)),
+        ];
+        // Without a time, the point is passed over.
+        first.points.insert(3, Waypoint::try_new(0., 0.).unwrap());
+        track.segments.push(first);
+        track.segments.push(TrackSegment {
+            points: vec![sample(100, Some(400)), sample(105, Some(90))],
+            ..Default::default()
+        });
+
+        let zones = track.time_in_zones(Sensor::Power, &[200., 300.]);
+        let seconds: Vec<i64> = zones.iter().map(|zone| zone.num_seconds()).collect();
+        // Nothing for the time without power, nor between the segments.
+        assert_eq!(seconds, [10, 30, 5]);
+        assert_eq!(
+            track.time_in_zones(Sensor::HeartRate, &[]),
+            [chrono::Duration::zero()]
+        );
+    }
+
+    #[test]
+    fn infer_activity() {
+        // A point every 10 seconds at `speed`, standing still at the end for
+        // `stopped` of them.
+        let track = |speed: f64, stopped: i64, cadence: Option<u8>| {
+            let mut segment = TrackSegment::new();
+            let mut latitude = 0.;
+            for step in 0..100 {
+                if step < 100 - stopped {
+                    latitude += speed * 10. / 111_195.08;
+                }
+                let mut point = Waypoint::try_new(latitude, 0.).unwrap();
+                point.time = Some(Utc.timestamp_opt(step * 10, 0).unwrap());
+                point.track_point_extension = Some(TrackPointExtension {
+                    cad: cadence,
+                    ..Default::default()
+                });
+                segment.points.push(point);
+            }
+            let mut track = Track::new();
+            track.segments.push(segment);
+            track
+        };
+        let activity = |track: &Track| track.infer_activity().unwrap().activity;
+
+        assert_eq!(activity(&track(1.3, 0, None)), Activity::Walk);
+        assert_eq!(activity(&track(3.3, 0, Some(85))), Activity::Run);
+        assert_eq!(activity(&track(7., 0, Some(80))), Activity::Ride);
+        assert_eq!(activity(&track(30., 0, None)), Activity::Drive);
+        // Stop and go at the speed of a fast ride, without pedaling.
+        assert_eq!(activity(&track(10., 30, None)), Activity::Drive);
+        assert_eq!(activity(&track(10., 30, Some(90))), Activity::Ride);
+
+        let sure = track(7., 0, Some(80)).infer_activity().unwrap();
+        let unsure = track(4.4, 0, None).infer_activity().unwrap();
+        assert!(sure.confidence > unsure.confidence);
+        assert!((0.5..=1.).contains(&unsure.confidence));
+        assert_eq!(track(1., 100, None).infer_activity(), None);
+    }
+
+    #[test]
+    fn stops() {
+        let minute = chrono::Duration::minutes(1);
+        let mut track = Track::new();
+        // Jitter of a few meters around a stop of 90 seconds, with a point
+        // without a time.
+        let mut first = segment(&[
+            (0, 0., None),
+            (10, 0.001, None),
+            (40, 0.00102, None),
+            (70, 0.00099, None),
+            (100, 0.001, None),
+            (110, 0.002, None),
+        ]);
+        first
+            .points
+            .insert(2, Waypoint::try_new(0.005, 0.).unwrap());
+        track.segments.push(first);
+        // Too short a stop, and one which doesn't carry on into the next
+        // segment.
+        track.segments.push(segment(&[
+            (200, 0.003, None),
+            (230, 0.003, None),
+            (240, 0.004, None),
+            (300, 0.004, None),
+        ]));
+        track.segments.push(segment(&[(400, 0.004, None)]));
+
+        let stops = track.stops(minute, 5.);
+        assert_eq!(stops.len(), 2);
+        assert_eq!(stops[0].start_time, Utc.timestamp_opt(10, 0).unwrap());
+        assert_eq!(stops[0].duration().num_seconds(), 90);
+        assert!((stops[0].location.lat() - 0.0010025).abs() < 1e-9);
+        assert_eq!(stops[0].location.lng(), 0.);
+        assert_eq!(stops[1].duration().num_seconds(), 60);
+        // The jitter reaches further than a meter.
+        assert!(track.stops(minute, 1.).len() < 2);
+        assert!(track.stops(minute * 2, 5.).is_empty());
+        assert!(track.stops(minute, -1.).is_empty());
+        assert!(track.stops(minute, f64::NAN).is_empty());
+    }
+
+    #[test]
+    fn route_shape() {
+        let shape = |points: Vec<(f64, f64)>| {
+            let mut segment = TrackSegment::new();
+            for (latitude, longitude) in points {
+                segment
+                    .points
+                    .push(Waypoint::try_new(latitude, longitude).unwrap());
+            }
+            let mut track = Track::new();
+            track.segments.push(segment);
+            track.route_shape(&Default::default())
+        };
+        let steps = |from: (f64, f64), to: (f64, f64)| {
+            (0..10).map(move |step| {
+                let fraction = step as f64 / 10.;
+                (
+                    from.0 + (to.0 - from.0) * fraction,
+                    from.1 + (to.1 - from.1) * fraction,
+                )
+            })
+        };
+
+        // Around a square of about a kilometer.
+        let square: Vec<_> = steps((0., 0.), (0., 0.01))
+            .chain(steps((0., 0.01), (0.01, 0.01)))
+            .chain(steps((0.01, 0.01), (0.01, 0.)))
+            .chain(steps((0.01, 0.), (0., 0.)))
+            .chain(vec![(0., 0.)])
+            .collect();
+        assert_eq!(shape(square), Some(RouteShape::Loop));
+
+        let mut out_and_back: Vec<_> = steps((0., 0.), (0.01, 0.)).collect();
+        out_and_back.extend(steps((0.01, 0.), (0., 0.)));
+        // Back to a car park a little further.
+        out_and_back.push((-0.005, 0.));
+        assert_eq!(shape(out_and_back), Some(RouteShape::OutAndBack));
+
+        let line: Vec<_> = steps((0., 0.), (0.02, 0.01)).collect();
+        assert_eq!(shape(line), Some(RouteShape::PointToPoint));
+        assert_eq!(shape(vec![(0., 0.)]), None);
+    }
+
+    #[test]
+    fn energy_estimate() {
+        // 100 meters every 75 seconds for 20 minutes, climbing `climb`
+        // meters each time.
+        let track = |climb: f64| {
+            let mut segment = TrackSegment::new();
+            for step in 0..=16 {
+                let mut point = Waypoint::try_new(step as f64 * 100. / 111_195.08, 0.).unwrap();
+                point.time = Some(Utc.timestamp_opt(step * 75, 0).unwrap());
+                point.elevation = Some(step as f64 * climb);
+                segment.points.push(point);
+            }
+            let mut track = Track::new();
+            track.segments.push(segment);
+            track
+        };
+        let flat = track(0.);
+
+        // 80 meters per minute take 11.5 milliliters of oxygen per
+        // kilogram and minute.
+        let walking = flat.energy_estimate(70., Activity::Walk);
+        assert!((walking - 11.5 * 70. / 1000. * 5. * 20.).abs() < 0.01);
+        assert!(flat.energy_estimate(70., Activity::Run) > walking);
+        assert!(track(5.).energy_estimate(70., Activity::Walk) > walking);
+        // Downhill costs no less than on the flat.
+        assert_eq!(track(-5.).energy_estimate(70., Activity::Walk), walking);
+        assert!(
+            track(5.).energy_estimate(70., Activity::Ride)
+                > flat.energy_estimate(70., Activity::Ride)
+        );
+        assert_eq!(Track::new().energy_estimate(70., Activity::Run), 0.);
+    }
+
+    #[test]
+    fn distance_matrix() {
+        let points: Vec<Waypoint> = (0..4)
+            .map(|step| Waypoint::try_new(step as f64, step as f64 * 2.).unwrap())
+            .collect();
+
+        let matrix = DistanceMethod::Geodesic.matrix(&points);
+        assert_eq!(matrix.len(), 4);
+        for (i, row) in matrix.iter().enumerate() {
+            assert_eq!(row.len(), 4);
+            assert_eq!(row[i], 0.);
+            for (j, &distance) in row.iter().enumerate() {
+                assert!((distance - matrix[j][i]).abs() < 1e-6);
+                assert_eq!(
+                    distance,
+                    DistanceMethod::Geodesic.distance(&points[i], &points[j])
+                );
+            }
+        }
+        assert!(DistanceMethod::Haversine.matrix(&[]).is_empty());
+    }
+
+    #[test]
+    fn bearings() {
+        let point = |latitude, longitude| Waypoint::try_new(latitude, longitude).unwrap();
+        let mut segment = TrackSegment::new();
+        for &(latitude, longitude) in &[(0., 0.), (0., 1.), (0., 1.), (-1., 1.), (-1., 0.)] {
+            segment.points.push(point(latitude, longitude));
+        }
+
+        let bearings = segment.bearings();
+        assert_eq!(bearings[..4], [None, Some(90.), None, Some(180.)]);
+        assert!((bearings[4].unwrap() - 270.).abs() < 0.01);
+        assert_eq!(segment.initial_bearing(), Some(90.));
+        assert!((segment.final_bearing().unwrap() - 270.).abs() < 0.01);
+
+        // From Paris to New York, heading north-west.
+        let bearing = point(48.8566, 2.3522).bearing_to(&point(40.7128, -74.006));
+        assert!((bearing - 291.8).abs() < 0.1);
+
+        segment.points.truncate(1);
+        assert_eq!(segment.initial_bearing(), None);
+        assert_eq!(segment.final_bearing(), None);
+    }
+
+    #[test]
+    fn geodesic_distances() {
+        let point = |latitude, longitude| Waypoint::try_new(latitude, longitude).unwrap();
+
+        // Along the equator, and along a meridian from the equator to a pole.
+        let equator = DistanceMethod::Geodesic.distance(&point(0., 0.), &point(0., 1.));
+        assert!((equator - 111_319.491).abs() < 0.001);
+        let meridian = DistanceMethod::Geodesic.distance(&point(0., 0.), &point(90., 0.));
+        assert!((meridian - 10_001_965.729).abs() < 0.001);
+        assert_eq!(
+            DistanceMethod::Geodesic.distance(&point(1., 2.), &point(1., 2.)),
+            0.
+        );
+
+        // Antipodes fall back to the haversine distance.
+        let from = point(0., 0.);
+        let to = point(0.5, 179.7);
+        assert_eq!(
+            DistanceMethod::Geodesic.distance(&from, &to),
+            DistanceMethod::Haversine.distance(&from, &to)
+        );
+
+        let mut track = Track::new();
+        track
+            .segments
+            .push(segment(&[(0, 0., None), (1, 1., None)]));
+        let geodesic = track.length_m_with(DistanceMethod::Geodesic);
+        assert!((geodesic - 110_574.389).abs() < 0.001);
+        assert_eq!(
+            track.summary_with(DistanceMethod::Geodesic).distance,
+            geodesic
+        );
+    }
+
+    #[test]
+    fn gpx_summary() {
+        let mut first = Track::new();
+        first
+            .segments
+            .push(segment(&[(0, 0., None), (10, 0.001, None)]));
+        let mut second = Track::new();
+        second.segments.push(segment(&[(30, 0.002, Some(5.))]));
+        let gpx = Gpx {
+            tracks: vec![first, second, Track::new()],
+            ..Default::default()
+        };
+
+        let summary = gpx.summary();
+
+        assert_eq!(summary.tracks.len(), 3);
+        assert_eq!(summary.tracks[1].duration, None);
+        assert_eq!(summary.tracks[2], Default::default());
+        assert_eq!(summary.total.points, 3);
+        assert_eq!(summary.total.distance, summary.tracks[0].distance);
+        assert_eq!(summary.total.duration.unwrap().num_seconds(), 30);
+        assert_eq!(summary.total.max_elevation, Some(5.));
+    }
+}
@@ -0,0 +1,212 @@
+//! Writes an activity to TCX (Garmin's Training Center XML), with the `tcx`
+//! feature.
+
+use std::io::Write;
+
+use chrono::{DateTime, Utc};
+use xml::writer::{EmitterConfig, EventWriter, XmlEvent};
+
+use crate::errors::*;
+use crate::stats::haversine_distance;
+use crate::writer::{write_string, write_xml_event};
+use crate::{Gpx, Track, TrackSegment, Waypoint};
+
+/// Namespace of TCX version 2.
+const TCX_NAMESPACE: &str = "http://www.garmin.com/xmlschemas/TrainingCenterDatabase/v2";
+
+/// Writes the tracks of an activity to TCX, for platforms which only accept
+/// TCX uploads.
+///
+/// Each track becomes an activity, with a lap per segment. The sport is
+/// taken from the track's type (`running` or `biking`, else `Other`), and
+/// the heart rate and cadence of points from their TrackPointExtension.
+/// Distances are computed from the points. As TCX requires it, every point
+/// must have a time.
+///
+/// ```
+/// use chrono::{TimeZone, Utc};
+/// use gpx::{write_tcx, Gpx, GpxVersion, Track, Waypoint};
+///
+/// let mut wpt = Waypoint::builder().lat(37.24).lon(-121.97).build().unwrap();
+/// wpt.time = Some(Utc.with_ymd_and_hms(2020, 6, 1, 12, 0, 0).unwrap());
+/// let track = Track::builder().points(vec![wpt]).build();
+/// let gpx = Gpx::builder(GpxVersion::Gpx11).track(track).build();
+///
+/// let mut buffer = Vec::new();
+/// write_tcx(&gpx, &mut buffer).unwrap();
+///
+/// let tcx = String::from_utf8(buffer).unwrap();
+/// assert!(tcx.contains("<Id>2020-06-01T12:00:00+00:00</Id>"));
+/// ```
+pub fn write_tcx<W: Write>(gpx: &Gpx, writer: W) -> Result<()> {
+    let mut writer = EmitterConfig::new()
+        .perform_indent(true)
+        .create_writer(writer);
+    write_xml_event(
+        XmlEvent::start_element("TrainingCenterDatabase").default_ns(TCX_NAMESPACE),
+        &mut writer,
+    )?;
+    write_xml_event(XmlEvent::start_element("Activities"), &mut writer)?;
+    for track in &gpx.tracks {
+        write_activity(track, &mut writer)?;
+    }
+    write_xml_event(XmlEvent::end_element(), &mut writer)?;
+    write_xml_event(XmlEvent::end_element(), &mut writer)?;
+    Ok(())
+}
+
+fn write_activity<W: Write>(track: &Track, writer: &mut EventWriter<W>) -> Result<()> {
+    let start = match track
+        .segments
+        .iter()
+        .flat_map(|segment| &segment.points)
+        .next()
+    {
+        Some(point) => time_of(point)?,
+        // An activity without points has no start to be identified by.
+        None => return Ok(()),
+    };
+    write_xml_event(
+        XmlEvent::start_element("Activity").attr("Sport", sport(track)),
+        writer,
+    )?;
+    write_string("Id", &start.to_rfc3339(), writer)?;
+    let mut distance = 0.;
+    for segment in &track.segments {
+        if !segment.points.is_empty() {
+            write_lap(segment, &mut distance, writer)?;
+        }
+    }
+    write_xml_event(XmlEvent::end_element(), writer)?;
+    Ok(())
+}
+
+/// Writes a segment as a lap, adding its length to the `distance` covered
+/// so far by the activity.
+fn write_lap<W: Write>(
+    segment: &TrackSegment,
+    distance: &mut f64,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
+    let points = &segment.points;
+    let start = time_of(&points[0])?;
+    let end = time_of(&points[points.len() - 1])?;
+    let length = segment.length_m();
+    let duration = (end - start).num_milliseconds() as f64 / 1000.;
+
+    write_xml_event(
+        XmlEvent::start_element("Lap").attr("StartTime", &start.to_rfc3339()),
+        writer,
+    )?;
+    write_string("TotalTimeSeconds", &duration.to_string(), writer)?;
+    write_string("DistanceMeters", &length.to_string(), writer)?;
+    write_string("Calories", "0", writer)?;
+    write_string("Intensity", "Active", writer)?;
+    write_string("TriggerMethod", "Manual", writer)?;
+    write_xml_event(XmlEvent::start_element("Track"), writer)?;
+    for (index, point) in points.iter().enumerate() {
+        if index > 0 {
+            *distance += haversine_distance(&points[index - 1], point);
+        }
+        write_trackpoint(point, *distance, writer)?;
+    }
+    write_xml_event(XmlEvent::end_element(), writer)?;
+    write_xml_event(XmlEvent::end_element(), writer)?;
+    Ok(())
+}
+
+fn write_trackpoint<W: Write>(
+    point: &Waypoint,
+    distance: f64,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
+    write_xml_event(XmlEvent::start_element("Trackpoint"), writer)?;
+    write_string("Time", &time_of(point)?.to_rfc3339(), writer)?;
+    write_xml_event(XmlEvent::start_element("Position"), writer)?;
+    write_string("LatitudeDegrees", &point.point().lat().to_string(), writer)?;
+    write_string("LongitudeDegrees", &point.point().lng().to_string(), writer)?;
+    write_xml_event(XmlEvent::end_element(), writer)?;
+    if let Some(elevation) = point.elevation {
+        write_string("AltitudeMeters", &elevation.to_string(), writer)?;
+    }
+    write_string("DistanceMeters", &distance.to_string(), writer)?;
+    if let Some(ref extension) = point.track_point_extension {
+        if let Some(hr) = extension.hr {
+            write_xml_event(XmlEvent::start_element("HeartRateBpm"), writer)?;
+            write_string("Value", &hr.to_string(), writer)?;
+            write_xml_event(XmlEvent::end_element(), writer)?;
+        }
+        if let Some(cad) = extension.cad {
+            write_string("Cadence", &cad.to_string(), writer)?;
+        }
+    }
+    write_xml_event(XmlEvent::end_element(), writer)?;
+    Ok(())
+}
+
+fn time_of(point: &Waypoint) -> Result<DateTime<Utc>> {
+    point
+        .time
+        .ok_or_else(|| Error::from("tcx requires the time of every point"))
+}
+
+/// Gives the TCX sport of a track, from its type.
+fn sport(track: &Track) -> &'static str {
+    let kind = track._type.as_deref().unwrap_or_default().to_lowercase();
+    match kind.as_str() {
+        "running" | "run" => "Running",
+        "biking" | "cycling" | "bike" | "ride" => "Biking",
+        _ => "Other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::write_tcx;
+    use crate::{Gpx, GpxVersion, Track, TrackPointExtension, TrackSegment, Waypoint};
+
+    fn point(lat: f64, seconds: i64) -> Waypoint {
+        let mut point = Waypoint::builder().lat(lat).lon(0.).build().unwrap();
+        let start = Utc.with_ymd_and_hms(2020, 6, 1, 12, 0, 0).unwrap();
+        point.time = Some(start + Duration::seconds(seconds));
+        point
+    }
+
+    #[test]
+    fn write_activity() {
+        let mut first = point(0., 0);
+        first.track_point_extension = Some(TrackPointExtension {
+            hr: Some(140),
+            cad: Some(85),
+            ..Default::default()
+        });
+        let mut track = Track::builder()
+            .points(vec![first, point(0.001, 60)])
+            .build();
+        track._type = Some(String::from("Running"));
+        track.segments.push(TrackSegment::new());
+        let gpx = Gpx::builder(GpxVersion::Gpx11).track(track).build();
+
+        let mut buffer = Vec::new();
+        write_tcx(&gpx, &mut buffer).unwrap();
+
+        let tcx = String::from_utf8(buffer).unwrap();
+        assert!(tcx.contains("<Activity Sport=\"Running\">"));
+        assert_eq!(tcx.matches("<Lap ").count(), 1);
+        assert!(tcx.contains("<TotalTimeSeconds>60</TotalTimeSeconds>"));
+        assert!(tcx.contains("<DistanceMeters>111.19"));
+        assert!(tcx.contains("<Value>140</Value>"));
+        assert!(tcx.contains("<Cadence>85</Cadence>"));
+    }
+
+    #[test]
+    fn write_without_time() {
+        let point = Waypoint::builder().lat(37.24).lon(-121.97).build().unwrap();
+        let track = Track::builder().points(vec![point]).build();
+        let gpx = Gpx::builder(GpxVersion::Gpx11).track(track).build();
+
+        assert!(write_tcx(&gpx, Vec::new()).is_err());
+    }
+}
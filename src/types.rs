@@ -1,28 +1,57 @@
 //! generic types for GPX
 
-use geo_types::{Geometry, LineString, MultiLineString, Point, Rect};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use geo_types::{Coordinate, Geometry, LineString, MultiLineString, Point, Rect};
 
 use chrono::{DateTime, Utc};
+use error_chain::ensure;
+
+use crate::errors::*;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// GpxVersion is the version of the GPX schema a document conforms to.
+///
+/// It is detected from the `version` attribute of the root element when
+/// reading, and selects the flavour of GPX that is emitted when writing.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GpxVersion {
-    Unknown,
+    /// The version is not (yet) known, or is one not supported, given as
+    /// written in the document. Documents of such a version are read like
+    /// GPX 1.1, but cannot be written.
+    Unknown(String),
+    /// GPX 1.0, where metadata lives directly on the root element.
     Gpx10,
+    /// GPX 1.1, the current version of the schema.
     Gpx11,
 }
 
 impl Default for GpxVersion {
     fn default() -> GpxVersion {
-        GpxVersion::Unknown
+        GpxVersion::Unknown(String::new())
     }
 }
 
 /// Gpx is the root element in the XML file.
 #[derive(Clone, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Gpx {
     /// Version of the Gpx file.
     pub version: GpxVersion,
 
+    /// Creator name or URL of the software that created the GPX document.
+    pub creator: Option<String>,
+
+    /// Namespaces declared on the root element, as `(prefix, uri)` pairs. The
+    /// default namespace has an empty prefix.
+    pub namespaces: Vec<(String, String)>,
+
+    /// Value of the root `xsi:schemaLocation` attribute, pairing namespaces
+    /// with the location of their schema.
+    pub schema_location: Option<String>,
+
     /// Metadata about the file.
     pub metadata: Option<Metadata>,
 
@@ -34,6 +63,154 @@ pub struct Gpx {
 
     /// A list of routes with a list of point-by-point directions
     pub routes: Vec<Route>,
+
+    /// Raw XML content of the root `extensions` element, if present.
+    pub extensions: Option<String>,
+}
+
+impl Gpx {
+    /// Binds a namespace prefix for the document's extensions, replacing the
+    /// namespace bound to it before, if any.
+    ///
+    /// The writer only declares the namespaces the extensions actually use.
+    ///
+    /// ```
+    /// use gpx::{Gpx, GpxVersion, Waypoint};
+    ///
+    /// let wpt = Waypoint::builder()
+    ///     .lat(37.24)
+    ///     .lon(-121.97)
+    ///     .extensions("<gpxtpx:TrackPointExtension><gpxtpx:hr>74</gpxtpx:hr></gpxtpx:TrackPointExtension>")
+    ///     .build()
+    ///     .unwrap();
+    /// let mut gpx = Gpx::builder(GpxVersion::Gpx11).waypoint(wpt).build();
+    /// gpx.register_namespace("gpxtpx", "http://www.garmin.com/xmlschemas/TrackPointExtension/v1");
+    /// gpx.register_namespace("gpxx", "http://www.garmin.com/xmlschemas/GpxExtensions/v3");
+    ///
+    /// let xml = gpx.to_string().unwrap();
+    /// assert!(xml.contains("xmlns:gpxtpx="));
+    /// assert!(!xml.contains("xmlns:gpxx="));
+    /// ```
+    pub fn register_namespace(&mut self, prefix: &str, uri: &str) {
+        match self.namespaces.iter_mut().find(|(p, _)| p == prefix) {
+            Some((_, bound)) => *bound = String::from(uri),
+            None => self
+                .namespaces
+                .push((String::from(prefix), String::from(uri))),
+        }
+    }
+
+    /// Splits the document into one document per track, for example to
+    /// export each activity of a file on its own.
+    ///
+    /// Each document keeps the version, creator, namespaces, metadata and
+    /// root extensions of this one, but not its waypoints and routes. The
+    /// metadata bounds are dropped, as they cover all tracks.
+    ///
+    /// ```
+    /// use gpx::{Gpx, GpxVersion, Track};
+    ///
+    /// let gpx = Gpx::builder(GpxVersion::Gpx11)
+    ///     .creator("my-app")
+    ///     .track(Track::builder().name("Morning ride").build())
+    ///     .track(Track::builder().name("Evening run").build())
+    ///     .build();
+    ///
+    /// let documents = gpx.split_tracks();
+    /// assert_eq!(documents.len(), 2);
+    /// assert_eq!(documents[1].tracks[0].name.as_deref(), Some("Evening run"));
+    /// assert_eq!(documents[1].creator.as_deref(), Some("my-app"));
+    /// ```
+    pub fn split_tracks(&self) -> Vec<Gpx> {
+        self.tracks
+            .iter()
+            .map(|track| self.with_track(track.clone()))
+            .collect()
+    }
+
+    /// Splits the document into one document per track segment, like
+    /// `split_tracks` does per track. Each segment is kept in a track with
+    /// the information of the track it belongs to.
+    ///
+    /// ```
+    /// use gpx::{Gpx, GpxVersion, Track, TrackSegment};
+    ///
+    /// let mut track = Track::builder().name("Tour").build();
+    /// track.segments = vec![TrackSegment::new(), TrackSegment::new()];
+    /// let gpx = Gpx::builder(GpxVersion::Gpx11).track(track).build();
+    ///
+    /// let documents = gpx.split_segments();
+    /// assert_eq!(documents.len(), 2);
+    /// assert_eq!(documents[0].tracks[0].name.as_deref(), Some("Tour"));
+    /// assert_eq!(documents[0].tracks[0].segments.len(), 1);
+    /// ```
+    pub fn split_segments(&self) -> Vec<Gpx> {
+        self.tracks
+            .iter()
+            .flat_map(|track| {
+                let header = Track {
+                    segments: Vec::new(),
+                    ..track.clone()
+                };
+                track.segments.iter().map(move |segment| Track {
+                    segments: vec![segment.clone()],
+                    ..header.clone()
+                })
+            })
+            .map(|track| self.with_track(track))
+            .collect()
+    }
+
+    /// Gives the bounds of all points of the document, those of its
+    /// waypoints, routes and tracks, if it has any. They are computed from
+    /// the points, whatever the bounds of the metadata say.
+    ///
+    /// ```
+    /// use gpx::{Gpx, Route, Waypoint};
+    ///
+    /// let mut gpx = Gpx::default();
+    /// assert_eq!(gpx.bounds(), None);
+    ///
+    /// gpx.waypoints.push(Waypoint::try_new(47.37, 8.54).unwrap());
+    /// let mut route = Route::new();
+    /// route.points.push(Waypoint::try_new(46.95, 7.44).unwrap());
+    /// gpx.routes.push(route);
+    ///
+    /// let bounds = gpx.bounds().unwrap();
+    /// assert_eq!((bounds.min().x, bounds.min().y), (7.44, 46.95));
+    /// assert_eq!((bounds.max().x, bounds.max().y), (8.54, 47.37));
+    /// ```
+    pub fn bounds(&self) -> Option<Rect<f64>> {
+        bounds(
+            self.waypoints
+                .iter()
+                .chain(self.routes.iter().flat_map(|route| &route.points))
+                .chain(
+                    self.tracks
+                        .iter()
+                        .flat_map(|track| &track.segments)
+                        .flat_map(|segment| &segment.points),
+                ),
+        )
+    }
+
+    /// Gives a document with the header of this one, holding only `track`.
+    fn with_track(&self, track: Track) -> Gpx {
+        Gpx {
+            version: self.version.clone(),
+            creator: self.creator.clone(),
+            namespaces: self.namespaces.clone(),
+            schema_location: self.schema_location.clone(),
+            metadata: self.metadata.clone().map(|metadata| Metadata {
+                bounds: None,
+                ..metadata
+            }),
+            waypoints: Vec::new(),
+            tracks: vec![track],
+            routes: Vec::new(),
+            extensions: self.extensions.clone(),
+        }
+    }
 }
 
 /// Metadata is information about the GPX file, author, and copyright restrictions.
@@ -41,6 +218,7 @@ pub struct Gpx {
 /// Providing rich, meaningful information about your GPX files allows others to
 /// search for and use your GPS data.
 #[derive(Clone, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Metadata {
     /// The name of the GPX file.
     pub name: Option<String>,
@@ -60,14 +238,47 @@ pub struct Metadata {
     /// Keywords associated with the file. Search engines or databases can use
     /// this information to classify the data.
     pub keywords: Option<String>,
-    /*copyright: GpxCopyrightType,*/
+
+    /// Copyright and license information governing use of the file.
+    pub copyright: Option<Copyright>,
+
     /// Bounds for the tracks in the GPX.
     pub bounds: Option<Rect<f64>>,
-    /*extensions: GpxExtensionsType,*/
+
+    /// Raw XML content of the `extensions` element, if present. Prefixes bound
+    /// outside of the element (e.g. on the root) are kept but not redeclared.
+    pub extensions: Option<String>,
+}
+
+impl Metadata {
+    /// Gives the individual keywords, splitting the comma-separated
+    /// `keywords` on commas and trimming surrounding whitespace.
+    ///
+    /// ```
+    /// use gpx::Metadata;
+    ///
+    /// let mut metadata: Metadata = Metadata::default();
+    /// assert!(metadata.keyword_list().is_empty());
+    ///
+    /// metadata.keywords = Some(String::from("hiking, Yosemite ,,waterfalls"));
+    /// assert_eq!(metadata.keyword_list(), vec!["hiking", "Yosemite", "waterfalls"]);
+    /// ```
+    pub fn keyword_list(&self) -> Vec<String> {
+        match self.keywords {
+            Some(ref keywords) => keywords
+                .split(',')
+                .map(str::trim)
+                .filter(|keyword| !keyword.is_empty())
+                .map(String::from)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
 }
 
 /// Route represents an ordered list of waypoints representing a series of turn points leading to a destination.
 #[derive(Clone, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Route {
     /// GPS name of route.
     pub name: Option<String>,
@@ -94,15 +305,26 @@ pub struct Route {
     /// Each Waypoint holds the coordinates, elevation, timestamp, and metadata
     /// for a single point in a track.
     pub points: Vec<Waypoint>,
+
+    /// Raw XML content of the `extensions` element, if present. Prefixes bound
+    /// outside of the element (e.g. on the root) are kept but not redeclared.
+    pub extensions: Option<String>,
 }
 
 impl Route {
-    /// Gives the linestring of the segment's points, the sequence of points that
-    /// comprises the track segment.
+    /// Gives the linestring of the route's points, the sequence of points that
+    /// comprises the route.
+    ///
+    /// Without any points, this is an empty linestring.
     pub fn linestring(&self) -> LineString<f64> {
         self.points.iter().map(|wpt| wpt.point()).collect()
     }
 
+    /// Gives the bounds of the route's points, if it has any.
+    pub fn bounds(&self) -> Option<Rect<f64>> {
+        bounds(self.points.iter())
+    }
+
     /// Creates a new Route with default values.
     ///
     /// ```
@@ -118,7 +340,7 @@ impl Route {
     ///     let point = Waypoint::new(Point::new(-121.97, 37.24));
     ///     route.points.push(point);
     /// }
-    ///
+    /// ```
     pub fn new() -> Route {
         Default::default()
     }
@@ -132,6 +354,7 @@ impl From<Route> for Geometry<f64> {
 
 /// Track represents an ordered list of points describing a path.
 #[derive(Clone, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Track {
     /// GPS name of track.
     pub name: Option<String>,
@@ -149,6 +372,9 @@ pub struct Track {
     /// Links to external information about the track.
     pub links: Vec<Link>,
 
+    /// GPS track number.
+    pub number: Option<u32>,
+
     /// Type (classification) of track.
     pub _type: Option<String>,
 
@@ -157,18 +383,24 @@ pub struct Track {
     /// was lost, or the GPS receiver was turned off, start a new Track Segment
     /// for each continuous span of track data.
     pub segments: Vec<TrackSegment>,
-    /* pub number: u8,*/
-    /* extensions */
-    /* trkSeg */
+    /// Raw XML content of the `extensions` element, if present. Prefixes bound
+    /// outside of the element (e.g. on the root) are kept but not redeclared.
+    pub extensions: Option<String>,
 }
 
 impl Track {
     /// Gives the multi-linestring that this track represents, which is multiple
-    /// linestrings.
+    /// linestrings. Segments without points give empty linestrings.
     pub fn multilinestring(&self) -> MultiLineString<f64> {
         self.segments.iter().map(|seg| seg.linestring()).collect()
     }
 
+    /// Gives the bounds of the points of all segments of the track, if it has
+    /// any.
+    pub fn bounds(&self) -> Option<Rect<f64>> {
+        bounds(self.segments.iter().flat_map(|segment| &segment.points))
+    }
+
     /// Creates a new Track with default values.
     ///
     /// ```
@@ -196,20 +428,42 @@ impl From<Track> for Geometry<f64> {
 /// was lost, or the GPS receiver was turned off, start a new Track Segment
 /// for each continuous span of track data.
 #[derive(Clone, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TrackSegment {
     /// Each Waypoint holds the coordinates, elevation, timestamp, and metadata
     /// for a single point in a track.
     pub points: Vec<Waypoint>,
-    /* extensions */
+
+    /// Raw XML content of the `extensions` element, if present. Prefixes bound
+    /// outside of the element (e.g. on the root) are kept but not redeclared.
+    pub extensions: Option<String>,
 }
 
 impl TrackSegment {
     /// Gives the linestring of the segment's points, the sequence of points that
     /// comprises the track segment.
+    ///
+    /// Without any points, this is an empty linestring.
     pub fn linestring(&self) -> LineString<f64> {
         self.points.iter().map(|wpt| wpt.point()).collect()
     }
 
+    /// Gives the bounds of the segment's points, if it has any.
+    ///
+    /// ```
+    /// use gpx::{TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// assert_eq!(segment.bounds(), None);
+    ///
+    /// segment.points.push(Waypoint::try_new(37.24, -121.97).unwrap());
+    /// let bounds = segment.bounds().unwrap();
+    /// assert_eq!(bounds.min(), bounds.max());
+    /// ```
+    pub fn bounds(&self) -> Option<Rect<f64>> {
+        bounds(self.points.iter())
+    }
+
     /// Creates a new TrackSegment with default values.
     ///
     /// ```
@@ -236,10 +490,36 @@ impl From<TrackSegment> for Geometry<f64> {
     }
 }
 
+/// Gives the bounds of points, if there are any.
+fn bounds<'a>(points: impl Iterator<Item = &'a Waypoint>) -> Option<Rect<f64>> {
+    let (min, max) = points.map(|waypoint| waypoint.point()).fold(
+        (
+            Coordinate::from((f64::INFINITY, f64::INFINITY)),
+            Coordinate::from((f64::NEG_INFINITY, f64::NEG_INFINITY)),
+        ),
+        |(min, max), point| {
+            (
+                Coordinate::from((min.x.min(point.x()), min.y.min(point.y()))),
+                Coordinate::from((max.x.max(point.x()), max.y.max(point.y()))),
+            )
+        },
+    );
+    if min.x <= max.x && min.y <= max.y {
+        Some(Rect::new(min, max))
+    } else {
+        None
+    }
+}
+
 // A Version of geo_types::Point that has the Default trait implemented, which
 // allows us to initialise the GpxPoint with default values compactly
 // in the Waypoint::new function below
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "Coordinates", try_from = "Coordinates")
+)]
 struct GpxPoint(Point<f64>);
 
 impl Default for GpxPoint {
@@ -248,9 +528,40 @@ impl Default for GpxPoint {
     }
 }
 
+/// Coordinates are how a point is serialized, named like the attributes of
+/// a waypoint so that latitude and longitude can't be mixed up.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Coordinates {
+    lat: f64,
+    lon: f64,
+}
+
+#[cfg(feature = "serde")]
+impl From<GpxPoint> for Coordinates {
+    fn from(point: GpxPoint) -> Coordinates {
+        Coordinates {
+            lat: point.0.lat(),
+            lon: point.0.lng(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<Coordinates> for GpxPoint {
+    type Error = Error;
+
+    fn try_from(coordinates: Coordinates) -> Result<GpxPoint> {
+        check_latitude(coordinates.lat)?;
+        check_longitude(coordinates.lon)?;
+        Ok(GpxPoint(Point::new(coordinates.lon, coordinates.lat)))
+    }
+}
+
 /// Waypoint represents a waypoint, point of interest, or named feature on a
 /// map.
 #[derive(Clone, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Waypoint {
     /// The geographical point.
     point: GpxPoint,
@@ -261,12 +572,20 @@ pub struct Waypoint {
     /// Speed (in meters per second) (only in GPX 1.0)
     pub speed: Option<f64>,
 
+    /// Instantaneous course (in degrees true) at the point (only in GPX 1.0)
+    pub course: Option<Degrees>,
+
     /// Creation/modification timestamp for element. Date and time in are in
     /// Univeral Coordinated Time (UTC), not local time! Conforms to ISO 8601
     /// specification for date/time representation. Fractional seconds are
     /// allowed for millisecond timing in tracklogs.
     pub time: Option<DateTime<Utc>>,
 
+    /// Offset from UTC (in seconds east) `time` was written with, to write
+    /// it with again. Only kept when reading with
+    /// `ParserOptions::preserve_offsets`.
+    pub time_offset: Option<i32>,
+
     /// The GPS name of the waypoint. This field will be transferred to and
     /// from the GPS. GPX does not place restrictions on the length of this
     /// field or the characters contained in it. It is up to the receiving
@@ -295,7 +614,9 @@ pub struct Waypoint {
     /// Type (classification) of the waypoint.
     pub _type: Option<String>,
 
-    // <magvar> degreesType </magvar> [0..1] ?
+    /// Magnetic variation (in degrees) at the point.
+    pub magvar: Option<Degrees>,
+
     /// Height of geoid in meters above WGS 84. This correspond to the sea level.
     pub geoidheight: Option<f64>,
 
@@ -324,8 +645,14 @@ pub struct Waypoint {
     pub dgps_age: Option<f64>,
 
     /// ID of DGPS station used in differential correction, in the range [0, 1023].
-    pub dgpsid: Option<u16>,
-    // <extensions> extensionsType </extensions> [0..1] ?
+    pub dgpsid: Option<DgpsStationId>,
+
+    /// Raw XML content of the `extensions` element, if present. Prefixes bound
+    /// outside of the element (e.g. on the root) are kept but not redeclared.
+    pub extensions: Option<String>,
+
+    /// Sensor data from a Garmin TrackPointExtension inside `extensions`.
+    pub track_point_extension: Option<TrackPointExtension>,
 }
 
 impl Waypoint {
@@ -344,14 +671,47 @@ impl Waypoint {
     ///     let point = wpt.point();
     ///
     ///     println!("waypoint latitude: {}, longitude: {}", point.lat(), point.lng());
+    ///
+    ///     // A default waypoint sits at (0, 0) rather than having no point.
+    ///     assert_eq!(Waypoint::default().point(), Point::new(0., 0.));
     /// }
     /// ```
     pub fn point(&self) -> Point<f64> {
         self.point.0 //.0 to extract the geo_types::Point from the tuple struct GpxPoint
     }
 
+    /// Gives the geographical point of the waypoint, or `None` if its
+    /// coordinates aren't a latitude and longitude, as may be the case for a
+    /// waypoint made with `Waypoint::new`.
+    ///
+    /// ```
+    /// extern crate geo_types;
+    /// extern crate gpx;
+    ///
+    /// use gpx::Waypoint;
+    /// use geo_types::Point;
+    ///
+    /// fn main() {
+    ///     let wpt = Waypoint::new(Point::new(-121.97, 37.24));
+    ///     assert_eq!(wpt.try_point(), Some(Point::new(-121.97, 37.24)));
+    ///
+    ///     let wpt = Waypoint::new(Point::new(f64::NAN, 37.24));
+    ///     assert_eq!(wpt.try_point(), None);
+    /// }
+    /// ```
+    pub fn try_point(&self) -> Option<Point<f64>> {
+        let point = self.point();
+        match (check_latitude(point.lat()), check_longitude(point.lng())) {
+            (Ok(()), Ok(())) => Some(point),
+            _ => None,
+        }
+    }
+
     /// Creates a new Waypoint from a given geographical point.
     ///
+    /// The point is taken as is; use `Waypoint::try_new` to have its
+    /// coordinates checked.
+    ///
     /// ```
     /// extern crate geo_types;
     /// extern crate gpx;
@@ -372,6 +732,88 @@ impl Waypoint {
             ..Default::default()
         }
     }
+
+    /// Creates a new Waypoint at the given latitude and longitude, failing if
+    /// the latitude is outside [-90, 90] or the longitude outside [-180, 180].
+    ///
+    /// ```
+    /// extern crate gpx;
+    ///
+    /// use gpx::Waypoint;
+    ///
+    /// fn main() {
+    ///     let wpt = Waypoint::try_new(37.24, -121.97).unwrap();
+    ///     assert_eq!(wpt.point().lat(), 37.24);
+    ///
+    ///     assert!(Waypoint::try_new(-121.97, 37.24).is_err());
+    /// }
+    /// ```
+    pub fn try_new(latitude: f64, longitude: f64) -> Result<Waypoint> {
+        let mut waypoint = Waypoint::default();
+        waypoint.set_point(Point::new(longitude, latitude))?;
+        Ok(waypoint)
+    }
+
+    /// Moves the waypoint to another geographical point, leaving it as is if
+    /// the point's coordinates are out of range.
+    pub fn set_point(&mut self, point: Point<f64>) -> Result<()> {
+        check_latitude(point.lat())?;
+        check_longitude(point.lng())?;
+        self.point = GpxPoint(point);
+        Ok(())
+    }
+
+    /// Changes the latitude of the waypoint, which must be in [-90, 90].
+    pub fn set_latitude(&mut self, latitude: f64) -> Result<()> {
+        check_latitude(latitude)?;
+        self.point.0.set_lat(latitude);
+        Ok(())
+    }
+
+    /// Changes the longitude of the waypoint, which must be in [-180, 180].
+    pub fn set_longitude(&mut self, longitude: f64) -> Result<()> {
+        check_longitude(longitude)?;
+        self.point.0.set_lng(longitude);
+        Ok(())
+    }
+
+    /// Gives the waypoint's symbol as a Garmin `Symbol`, if it has one.
+    ///
+    /// ```
+    /// extern crate geo_types;
+    /// extern crate gpx;
+    ///
+    /// use gpx::{Symbol, Waypoint};
+    /// use geo_types::Point;
+    ///
+    /// fn main() {
+    ///     let mut wpt = Waypoint::new(Point::new(-119.57, 37.74));
+    ///     wpt.symbol = Some(Symbol::Campground.to_string());
+    ///
+    ///     assert_eq!(wpt.garmin_symbol(), Some(Symbol::Campground));
+    /// }
+    /// ```
+    pub fn garmin_symbol(&self) -> Option<Symbol> {
+        self.symbol.as_deref().map(Symbol::from)
+    }
+}
+
+fn check_latitude(latitude: f64) -> Result<()> {
+    ensure!(
+        (-90. ..=90.).contains(&latitude),
+        "latitude {} is out of range [-90, 90]",
+        latitude
+    );
+    Ok(())
+}
+
+fn check_longitude(longitude: f64) -> Result<()> {
+    ensure!(
+        (-180. ..=180.).contains(&longitude),
+        "longitude {} is out of range [-180, 180]",
+        longitude
+    );
+    Ok(())
 }
 
 impl From<Waypoint> for Geometry<f64> {
@@ -380,24 +822,93 @@ impl From<Waypoint> for Geometry<f64> {
     }
 }
 
+/// TrackPointExtension holds the sensor readings that fitness devices attach
+/// to track points using Garmin's TrackPointExtension schema (v1 or v2).
+#[derive(Clone, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackPointExtension {
+    /// Air temperature in degrees Celsius.
+    pub atemp: Option<f64>,
+
+    /// Water temperature in degrees Celsius.
+    pub wtemp: Option<f64>,
+
+    /// Depth in meters.
+    pub depth: Option<f64>,
+
+    /// Heart rate in beats per minute.
+    pub hr: Option<u8>,
+
+    /// Cadence in revolutions per minute.
+    pub cad: Option<u8>,
+
+    /// Power in watts. It isn't part of Garmin's schema, but some
+    /// applications write it as a `power` child all the same.
+    pub power: Option<u16>,
+}
+
 /// Person represents a person or organization.
 #[derive(Clone, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Person {
     /// Name of person or organization.
     pub name: Option<String>,
 
     /// Email address.
-    pub email: Option<String>,
+    pub email: Option<Email>,
 
     /// Link to Web site or other external information about person.
     pub link: Option<Link>,
 }
 
+/// Email represents an email address, broken into two parts (id and domain)
+/// to help prevent email harvesting.
+///
+/// ```
+/// use gpx::Email;
+///
+/// let email: Email = "john.doe@example.com".parse().unwrap();
+/// assert_eq!(email.id, "john.doe");
+/// assert_eq!(email.domain, "example.com");
+/// assert_eq!(email.to_string(), "john.doe@example.com");
+/// ```
+#[derive(Clone, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Email {
+    /// Id half of the email address (john.doe).
+    pub id: String,
+
+    /// Domain half of the email address (example.com).
+    pub domain: String,
+}
+
+impl FromStr for Email {
+    type Err = Error;
+
+    /// Splits an address like `id@domain` at its last `@`.
+    fn from_str(email: &str) -> Result<Email> {
+        match email.rfind('@') {
+            Some(at) => Ok(Email {
+                id: email[..at].to_string(),
+                domain: email[at + 1..].to_string(),
+            }),
+            None => Err(format!("email address '{}' lacks an @", email).into()),
+        }
+    }
+}
+
+impl fmt::Display for Email {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}@{}", self.id, self.domain)
+    }
+}
+
 /// Link represents a link to an external resource.
 ///
 /// An external resource could be a web page, digital photo,
 /// video clip, etc., with additional information.
 #[derive(Clone, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Link {
     /// URL of hyperlink.
     pub href: String,
@@ -409,8 +920,127 @@ pub struct Link {
     pub _type: Option<String>,
 }
 
+/// Copyright represents information about the copyright holder and any
+/// license governing use of this file.
+///
+/// By linking to an appropriate license, you may place your data into the
+/// public domain or grant additional usage rights.
+#[derive(Clone, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Copyright {
+    /// Copyright holder.
+    pub author: String,
+
+    /// Year of copyright.
+    pub year: Option<i32>,
+
+    /// Link to external file containing license text.
+    pub license: Option<String>,
+}
+
+/// Degrees is an angle in degrees, in the range [0, 360).
+///
+/// ```
+/// use std::convert::TryFrom;
+/// use gpx::Degrees;
+///
+/// let magvar = Degrees::try_from(13.5).unwrap();
+/// assert_eq!(magvar.value(), 13.5);
+///
+/// assert!(Degrees::try_from(360.).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "f64", into = "f64")
+)]
+pub struct Degrees(f64);
+
+impl Degrees {
+    /// Gives the angle in degrees.
+    pub fn value(self) -> f64 {
+        self.0
+    }
+}
+
+impl TryFrom<f64> for Degrees {
+    type Error = Error;
+
+    fn try_from(value: f64) -> Result<Degrees> {
+        if (0. ..360.).contains(&value) {
+            Ok(Degrees(value))
+        } else {
+            Err(format!("{} degrees is out of range [0, 360)", value).into())
+        }
+    }
+}
+
+impl From<Degrees> for f64 {
+    fn from(degrees: Degrees) -> f64 {
+        degrees.0
+    }
+}
+
+impl fmt::Display for Degrees {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// DgpsStationId is the ID of a DGPS station, in the range [0, 1023].
+///
+/// ```
+/// use std::convert::TryFrom;
+/// use gpx::DgpsStationId;
+///
+/// let station = DgpsStationId::try_from(1023).unwrap();
+/// assert_eq!(station.value(), 1023);
+///
+/// assert!(DgpsStationId::try_from(1024).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u16", into = "u16")
+)]
+pub struct DgpsStationId(u16);
+
+impl DgpsStationId {
+    /// Gives the numeric station ID.
+    pub fn value(self) -> u16 {
+        self.0
+    }
+}
+
+impl TryFrom<u16> for DgpsStationId {
+    type Error = Error;
+
+    fn try_from(value: u16) -> Result<DgpsStationId> {
+        if value <= 1023 {
+            Ok(DgpsStationId(value))
+        } else {
+            Err(format!("DGPS station ID {} is out of range [0, 1023]", value).into())
+        }
+    }
+}
+
+impl From<DgpsStationId> for u16 {
+    fn from(id: DgpsStationId) -> u16 {
+        id.0
+    }
+}
+
+impl fmt::Display for DgpsStationId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 /// Type of the GPS fix.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Fix {
     /// The GPS had no fix. To signify "the fix info is unknown", leave out the Fix entirely.
     None,
@@ -425,3 +1055,106 @@ pub enum Fix {
     /// Other values that are not in the specification.
     Other(String),
 }
+
+macro_rules! symbols {
+    ($($variant:ident => $name:expr,)*) => {
+        /// Symbol is one of the common waypoint symbols of Garmin devices, as
+        /// found in the waypoint `sym` element.
+        ///
+        /// ```
+        /// use gpx::Symbol;
+        ///
+        /// assert_eq!(Symbol::from("Parking Area"), Symbol::ParkingArea);
+        /// assert_eq!(Symbol::from("parking area"), Symbol::ParkingArea);
+        /// assert_eq!(Symbol::ParkingArea.as_str(), "Parking Area");
+        ///
+        /// assert_eq!(Symbol::from("Lighthouse"), Symbol::Other("Lighthouse".to_string()));
+        /// ```
+        #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        pub enum Symbol {
+            $(
+                #[doc = concat!("The `", $name, "` symbol.")]
+                $variant,
+            )*
+            /// Any other symbol name, as found in the file.
+            Other(String),
+        }
+
+        impl Symbol {
+            /// Gives the name of the symbol, as spelled by Garmin devices.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(Symbol::$variant => $name,)*
+                    Symbol::Other(name) => name,
+                }
+            }
+        }
+
+        impl From<&str> for Symbol {
+            /// Matches a symbol name case-insensitively, falling back to
+            /// `Symbol::Other` for names that aren't known.
+            fn from(name: &str) -> Symbol {
+                $(
+                    if name.eq_ignore_ascii_case($name) {
+                        return Symbol::$variant;
+                    }
+                )*
+                Symbol::Other(name.to_string())
+            }
+        }
+    };
+}
+
+symbols! {
+    Airport => "Airport",
+    Anchor => "Anchor",
+    BikeTrail => "Bike Trail",
+    BoatRamp => "Boat Ramp",
+    Bridge => "Bridge",
+    Building => "Building",
+    Campground => "Campground",
+    Car => "Car",
+    Crossing => "Crossing",
+    DangerArea => "Danger Area",
+    DrinkingWater => "Drinking Water",
+    FishingArea => "Fishing Area",
+    Flag => "Flag",
+    FlagBlue => "Flag, Blue",
+    FlagGreen => "Flag, Green",
+    FlagRed => "Flag, Red",
+    Forest => "Forest",
+    GasStation => "Gas Station",
+    Geocache => "Geocache",
+    GeocacheFound => "Geocache Found",
+    Lodging => "Lodging",
+    MedicalFacility => "Medical Facility",
+    Museum => "Museum",
+    ParkingArea => "Parking Area",
+    PicnicArea => "Picnic Area",
+    PinBlue => "Pin, Blue",
+    PinGreen => "Pin, Green",
+    PinRed => "Pin, Red",
+    Residence => "Residence",
+    Restaurant => "Restaurant",
+    Restroom => "Restroom",
+    ScenicArea => "Scenic Area",
+    ShoppingCenter => "Shopping Center",
+    SkiingArea => "Skiing Area",
+    Summit => "Summit",
+    SwimmingArea => "Swimming Area",
+    TrailHead => "Trail Head",
+    Waypoint => "Waypoint",
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Symbol {
+        Symbol::from(name.as_str())
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
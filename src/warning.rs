@@ -0,0 +1,72 @@
+//! Warnings about suspicious content found while reading GPX, which doesn't
+//! keep it from being read.
+
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+
+use crate::errors::Location;
+
+/// Above this, a dilution of precision is taken to be garbage rather than
+/// a very poor fix.
+pub(crate) const MAX_DILUTION: f64 = 50.;
+
+/// ParseWarning is suspicious but valid content of a document, and where it
+/// was found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseWarning {
+    /// What was found.
+    pub kind: WarningKind,
+
+    /// Where it was found.
+    pub location: Location,
+}
+
+/// WarningKind tells what a `ParseWarning` is about.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WarningKind {
+    /// A track point has the same time as the one before it.
+    DuplicateTime(DateTime<Utc>),
+
+    /// A track segment has no points.
+    EmptySegment,
+
+    /// A dilution of precision (`hdop`, `vdop` or `pdop`) is not positive,
+    /// or too large to be meant.
+    DilutionOutOfRange(&'static str, f64),
+
+    /// A point's latitude and longitude, as read, were swapped back since
+    /// the latitude was out of range.
+    SwappedCoordinates(f64, f64),
+
+    /// A GPX 1.0 email address lacks an `@` to split it at, and was left out.
+    InvalidEmail(String),
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WarningKind::DuplicateTime(time) => {
+                write!(f, "point has the same time {} as the previous one", time)
+            }
+            WarningKind::EmptySegment => write!(f, "segment has no points"),
+            WarningKind::DilutionOutOfRange(element, value) => {
+                write!(f, "{} {} out of range", element, value)
+            }
+            WarningKind::SwappedCoordinates(latitude, longitude) => write!(
+                f,
+                "latitude {} and longitude {} swapped back",
+                latitude, longitude
+            ),
+            WarningKind::InvalidEmail(ref address) => {
+                write!(f, "email address '{}' lacks an @", address)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.kind, self.location)
+    }
+}
@@ -0,0 +1,217 @@
+//! Writes geometries as WKT and WKB (well-known text and binary).
+//!
+//! Waypoints become points, track segments line strings and tracks multi
+//! line strings, as their `Geometry` conversions do. Coordinates are
+//! longitude, latitude, and the elevation as a Z coordinate if all points
+//! have one. WKB is little-endian, with the ISO codes of Z geometries.
+
+use crate::{Track, TrackSegment, Waypoint};
+
+// WKB geometry types, to which 1000 is added for Z geometries.
+const WKB_POINT: u32 = 1;
+const WKB_LINE_STRING: u32 = 2;
+const WKB_MULTI_LINE_STRING: u32 = 5;
+
+impl Waypoint {
+    /// Gives the point as WKT.
+    ///
+    /// ```
+    /// use gpx::Waypoint;
+    ///
+    /// let mut wpt = Waypoint::try_new(37.24, -121.97).unwrap();
+    /// assert_eq!(wpt.to_wkt(), "POINT (-121.97 37.24)");
+    ///
+    /// wpt.elevation = Some(553.21);
+    /// assert_eq!(wpt.to_wkt(), "POINT Z (-121.97 37.24 553.21)");
+    /// ```
+    pub fn to_wkt(&self) -> String {
+        let z = has_z(std::slice::from_ref(self));
+        format!("POINT{} ({})", z_tag(z), wkt_coordinates(self, z))
+    }
+
+    /// Gives the point as WKB.
+    pub fn to_wkb(&self) -> Vec<u8> {
+        let z = has_z(std::slice::from_ref(self));
+        let mut wkb = wkb_header(WKB_POINT, z);
+        wkb_coordinates(self, z, &mut wkb);
+        wkb
+    }
+}
+
+impl TrackSegment {
+    /// Gives the segment's points as a WKT line string.
+    ///
+    /// ```
+    /// use gpx::{TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// assert_eq!(segment.to_wkt(), "LINESTRING EMPTY");
+    ///
+    /// segment.points.push(Waypoint::try_new(37.24, -121.97).unwrap());
+    /// segment.points.push(Waypoint::try_new(37.25, -121.96).unwrap());
+    /// assert_eq!(segment.to_wkt(), "LINESTRING (-121.97 37.24,-121.96 37.25)");
+    /// ```
+    pub fn to_wkt(&self) -> String {
+        let z = has_z(&self.points);
+        format!("LINESTRING{} {}", z_tag(z), wkt_line(&self.points, z))
+    }
+
+    /// Gives the segment's points as a WKB line string.
+    pub fn to_wkb(&self) -> Vec<u8> {
+        let z = has_z(&self.points);
+        let mut wkb = wkb_header(WKB_LINE_STRING, z);
+        wkb_line(&self.points, z, &mut wkb);
+        wkb
+    }
+}
+
+impl Track {
+    /// Gives the track as a WKT multi line string, with a line per segment.
+    ///
+    /// ```
+    /// use gpx::{Track, TrackSegment, Waypoint};
+    ///
+    /// let mut segment = TrackSegment::new();
+    /// segment.points.push(Waypoint::try_new(37.24, -121.97).unwrap());
+    /// let mut track = Track::new();
+    /// track.segments = vec![segment.clone(), segment];
+    ///
+    /// assert_eq!(track.to_wkt(), "MULTILINESTRING ((-121.97 37.24),(-121.97 37.24))");
+    /// ```
+    pub fn to_wkt(&self) -> String {
+        let z = self.has_z();
+        if self.segments.is_empty() {
+            return format!("MULTILINESTRING{} EMPTY", z_tag(z));
+        }
+        let lines: Vec<String> = self
+            .segments
+            .iter()
+            .map(|segment| wkt_line(&segment.points, z))
+            .collect();
+        format!("MULTILINESTRING{} ({})", z_tag(z), lines.join(","))
+    }
+
+    /// Gives the track as a WKB multi line string, with a line per segment.
+    pub fn to_wkb(&self) -> Vec<u8> {
+        let z = self.has_z();
+        let mut wkb = wkb_header(WKB_MULTI_LINE_STRING, z);
+        wkb.extend_from_slice(&(self.segments.len() as u32).to_le_bytes());
+        for segment in &self.segments {
+            wkb.extend(wkb_header(WKB_LINE_STRING, z));
+            wkb_line(&segment.points, z, &mut wkb);
+        }
+        wkb
+    }
+
+    fn has_z(&self) -> bool {
+        let mut points = self
+            .segments
+            .iter()
+            .flat_map(|segment| &segment.points)
+            .peekable();
+        points.peek().is_some() && points.all(|point| point.elevation.is_some())
+    }
+}
+
+/// Tells whether all of (and at least one of) the points have an elevation.
+fn has_z(points: &[Waypoint]) -> bool {
+    !points.is_empty() && points.iter().all(|point| point.elevation.is_some())
+}
+
+fn z_tag(z: bool) -> &'static str {
+    if z {
+        " Z"
+    } else {
+        ""
+    }
+}
+
+fn wkt_coordinates(waypoint: &Waypoint, z: bool) -> String {
+    let point = waypoint.point();
+    match waypoint.elevation {
+        Some(elevation) if z => format!("{} {} {}", point.lng(), point.lat(), elevation),
+        _ => format!("{} {}", point.lng(), point.lat()),
+    }
+}
+
+fn wkt_line(points: &[Waypoint], z: bool) -> String {
+    if points.is_empty() {
+        return String::from("EMPTY");
+    }
+    let coordinates: Vec<String> = points
+        .iter()
+        .map(|point| wkt_coordinates(point, z))
+        .collect();
+    format!("({})", coordinates.join(","))
+}
+
+/// Starts a WKB geometry: the byte order, then its type.
+fn wkb_header(geometry: u32, z: bool) -> Vec<u8> {
+    let mut wkb = vec![1];
+    let geometry = if z { geometry + 1000 } else { geometry };
+    wkb.extend_from_slice(&geometry.to_le_bytes());
+    wkb
+}
+
+fn wkb_coordinates(waypoint: &Waypoint, z: bool, wkb: &mut Vec<u8>) {
+    let point = waypoint.point();
+    wkb.extend_from_slice(&point.lng().to_le_bytes());
+    wkb.extend_from_slice(&point.lat().to_le_bytes());
+    if z {
+        wkb.extend_from_slice(&waypoint.elevation.unwrap_or_default().to_le_bytes());
+    }
+}
+
+fn wkb_line(points: &[Waypoint], z: bool, wkb: &mut Vec<u8>) {
+    wkb.extend_from_slice(&(points.len() as u32).to_le_bytes());
+    for point in points {
+        wkb_coordinates(point, z, wkb);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Track, TrackSegment, Waypoint};
+
+    #[test]
+    fn point_wkb() {
+        let wpt = Waypoint::try_new(2., 1.).unwrap();
+
+        let mut expected = vec![1, 1, 0, 0, 0];
+        expected.extend_from_slice(&1f64.to_le_bytes());
+        expected.extend_from_slice(&2f64.to_le_bytes());
+        assert_eq!(wpt.to_wkb(), expected);
+    }
+
+    #[test]
+    fn track_wkb_with_z() {
+        let mut wpt = Waypoint::try_new(2., 1.).unwrap();
+        wpt.elevation = Some(3.);
+        let mut segment = TrackSegment::new();
+        segment.points.push(wpt);
+        let mut track = Track::new();
+        track.segments.push(segment);
+
+        let wkb = track.to_wkb();
+        // Multi line string Z (1005) of one line string Z (1002) of one point.
+        assert_eq!(&wkb[..9], &[1, 0xed, 3, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(&wkb[9..18], &[1, 0xea, 3, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(wkb.len(), 18 + 3 * 8);
+        assert_eq!(track.to_wkt(), "MULTILINESTRING Z ((1 2 3))");
+    }
+
+    #[test]
+    fn mixed_elevations_without_z() {
+        let mut first = Waypoint::try_new(2., 1.).unwrap();
+        first.elevation = Some(3.);
+        let mut segment = TrackSegment::new();
+        segment.points = vec![first, Waypoint::try_new(4., 3.).unwrap()];
+
+        assert_eq!(segment.to_wkt(), "LINESTRING (1 2,3 4)");
+        assert_eq!(Track::new().to_wkt(), "MULTILINESTRING EMPTY");
+
+        let mut track = Track::new();
+        track.segments = vec![TrackSegment::new(), segment];
+        assert_eq!(track.to_wkt(), "MULTILINESTRING (EMPTY,(1 2,3 4))");
+    }
+}
@@ -2,8 +2,7 @@
 
 use std::io::Write;
 
-use chrono::{DateTime, Utc};
-use error_chain::bail;
+use chrono::{DateTime, FixedOffset, SubsecRound, Timelike, Utc};
 use geo_types::Rect;
 use xml::writer::{EmitterConfig, EventWriter, XmlEvent};
 
@@ -11,6 +10,128 @@ use crate::errors::*;
 use crate::types::*;
 use crate::{Gpx, GpxVersion};
 
+/// Creator written for documents which don't specify their own.
+const DEFAULT_CREATOR: &str = "https://github.com/georust/gpx";
+
+/// Namespace of the `xsi:schemaLocation` attribute.
+const XSI_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema-instance";
+
+/// WriterOptions controls how written GPX is formatted.
+///
+/// The default writes numbers as precisely as they are stored, indents
+/// elements with two spaces and starts with an XML declaration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WriterOptions {
+    /// Number of decimal places of latitudes and longitudes, or `None` to write
+    /// them in full.
+    pub coordinate_precision: Option<usize>,
+
+    /// Number of decimal places of elevations, or `None` to write them in full.
+    pub elevation_precision: Option<usize>,
+
+    /// Number of decimal places of the seconds of times, at most 9, or
+    /// `None` to write them in full, with 3, 6 or 9 decimal places if they
+    /// have a fraction at all.
+    pub time_precision: Option<usize>,
+
+    /// String each level of elements is indented with, or `None` to write all
+    /// of the document on a single line.
+    pub indent: Option<String>,
+
+    /// Whether to start the document with an XML declaration.
+    pub xml_declaration: bool,
+
+    /// Length (in characters) from which descriptions and comments are
+    /// wrapped in CDATA instead of being escaped, or `None` to always escape
+    /// them.
+    pub cdata_threshold: Option<usize>,
+
+    /// Whether to write the bounds of all points in the metadata, instead of
+    /// the bounds it holds.
+    pub compute_bounds: bool,
+
+    /// Version to write the document as, or `None` to keep its own version.
+    /// Writing GPX 1.0 drops what it has no room for, like the copyright or
+    /// all but the first link of an element.
+    pub version: Option<GpxVersion>,
+}
+
+impl Default for WriterOptions {
+    fn default() -> WriterOptions {
+        WriterOptions {
+            coordinate_precision: None,
+            elevation_precision: None,
+            time_precision: None,
+            indent: Some(String::from("  ")),
+            xml_declaration: true,
+            cdata_threshold: None,
+            compute_bounds: false,
+            version: None,
+        }
+    }
+}
+
+impl WriterOptions {
+    /// Gives the options for writing `gpx`, with the version settled.
+    fn for_document(&self, gpx: &Gpx) -> WriterOptions {
+        WriterOptions {
+            version: Some(self.version.clone().unwrap_or_else(|| gpx.version.clone())),
+            ..self.clone()
+        }
+    }
+
+    fn version(&self) -> GpxVersion {
+        self.version.clone().unwrap_or_default()
+    }
+
+    fn create_writer<W: Write>(&self, writer: W) -> EventWriter<W> {
+        let config = EmitterConfig::new().write_document_declaration(self.xml_declaration);
+        let config = match self.indent {
+            Some(ref indent) => config.perform_indent(true).indent_string(indent.clone()),
+            None => config.perform_indent(false),
+        };
+        config.create_writer(writer)
+    }
+
+    fn format_coordinate(&self, value: f64) -> String {
+        format_with_precision(value, self.coordinate_precision)
+    }
+
+    fn format_elevation(&self, value: f64) -> String {
+        format_with_precision(value, self.elevation_precision)
+    }
+
+    /// Formats a time in UTC, or with `offset` (in seconds east of UTC) if
+    /// any.
+    fn format_time(&self, time: &DateTime<Utc>, offset: Option<i32>) -> String {
+        let offset = offset
+            .and_then(FixedOffset::east_opt)
+            .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+        let time = time.with_timezone(&offset);
+        let precision = match self.time_precision {
+            Some(precision) => precision.min(9),
+            None => return time.to_rfc3339(),
+        };
+        let time = time.round_subsecs(precision as u16);
+        let mut text = time.format("%Y-%m-%dT%H:%M:%S").to_string();
+        if precision > 0 {
+            // Leap seconds are past 1_000_000_000 nanoseconds.
+            let nanoseconds = format!("{:09}", time.nanosecond() % 1_000_000_000);
+            text.push('.');
+            text.push_str(&nanoseconds[..precision]);
+        }
+        text.push_str(&time.format("%:z").to_string());
+        text
+    }
+}
+
+fn format_with_precision(value: f64, precision: Option<usize>) -> String {
+    match precision {
+        Some(precision) => format!("{:.*}", precision, value),
+        None => value.to_string(),
+    }
+}
+
 /// Writes an activity to GPX format.
 ///
 /// Takes any `std::io::Write` as its writer, and returns a
@@ -28,30 +149,425 @@ use crate::{Gpx, GpxVersion};
 /// write(&data, std::io::stdout()).unwrap();
 /// ```
 pub fn write<W: Write>(gpx: &Gpx, writer: W) -> Result<()> {
-    let mut writer = EmitterConfig::new()
-        .perform_indent(true)
-        .create_writer(writer);
-    write_xml_event(
-        XmlEvent::start_element("gpx")
-            .attr("version", version_to_version_string(gpx.version)?)
-            .attr("creator", "https://github.com/georust/gpx"),
-        &mut writer,
-    )?;
-    write_metadata(gpx, &mut writer)?;
-    for point in &gpx.waypoints {
-        write_waypoint("wpt", point, &mut writer)?;
+    write_with_options(gpx, writer, &WriterOptions::default())
+}
+
+/// Writes an activity to GPX format, formatted according to `options`.
+///
+/// ```
+/// use gpx::{write_with_options, Gpx, GpxVersion, Waypoint, WriterOptions};
+///
+/// let wpt = Waypoint::builder().lat(37.2412345).lon(-121.9712345).build().unwrap();
+/// let gpx = Gpx::builder(GpxVersion::Gpx11).waypoint(wpt).build();
+///
+/// let options = WriterOptions {
+///     coordinate_precision: Some(5),
+///     indent: None,
+///     ..Default::default()
+/// };
+/// let mut buffer = Vec::new();
+/// write_with_options(&gpx, &mut buffer, &options).unwrap();
+///
+/// let xml = String::from_utf8(buffer).unwrap();
+/// assert!(xml.contains("<wpt lat=\"37.24123\" lon=\"-121.97123\""));
+/// ```
+pub fn write_with_options<W: Write>(gpx: &Gpx, writer: W, options: &WriterOptions) -> Result<()> {
+    let options = options.for_document(gpx);
+    let mut writer = options.create_writer(writer);
+    write_gpx_start(gpx, false, &options, &mut writer)?;
+    write_gpx_end(gpx, &options, &mut writer)
+}
+
+/// Writes the opening `gpx` tag with everything in the document, leaving the
+/// tag open.
+///
+/// Prefixed namespaces are only declared if the document's extensions use
+/// them, unless `all_namespaces` is set.
+fn write_gpx_start<W: Write>(
+    gpx: &Gpx,
+    all_namespaces: bool,
+    options: &WriterOptions,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
+    write_gpx_head(gpx, all_namespaces, options, writer)?;
+    for piece in pieces(gpx) {
+        write_piece(piece, options, writer)?;
+    }
+    Ok(())
+}
+
+/// Writes the opening `gpx` tag and the metadata, like `write_gpx_start`.
+fn write_gpx_head<W: Write>(
+    gpx: &Gpx,
+    all_namespaces: bool,
+    options: &WriterOptions,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
+    let mut gpx_element = XmlEvent::start_element("gpx")
+        .attr("version", version_to_version_string(options.version())?)
+        .attr("creator", gpx.creator.as_deref().unwrap_or(DEFAULT_CREATOR))
+        .default_ns(version_to_namespace(options.version())?);
+    let extensions = if all_namespaces {
+        Vec::new()
+    } else {
+        extensions_of(gpx, options)
+    };
+    for (prefix, uri) in &gpx.namespaces {
+        // The default namespace always follows the version being written.
+        if prefix.is_empty() || (prefix == "xsi" && gpx.schema_location.is_some()) {
+            continue;
+        }
+        if all_namespaces
+            || extensions
+                .iter()
+                .any(|content| uses_prefix(content, prefix))
+        {
+            gpx_element = gpx_element.ns(prefix.as_str(), uri.as_str());
+        }
+    }
+    if let Some(ref schema_location) = gpx.schema_location {
+        gpx_element = gpx_element
+            .ns("xsi", XSI_NAMESPACE)
+            .attr("xsi:schemaLocation", schema_location);
+    }
+    write_xml_event(gpx_element, writer)?;
+    write_metadata(gpx, options, writer)?;
+    Ok(())
+}
+
+/// A piece of the waypoints, routes and tracks of a document, small enough
+/// to be written at once.
+enum Piece<'a> {
+    Waypoint(&'a Waypoint),
+    RouteStart(&'a Route),
+    RoutePoint(&'a Waypoint),
+    SegmentStart,
+    TrackPoint(&'a Waypoint),
+    SegmentEnd(&'a TrackSegment),
+    TrackStart(&'a Track),
+    End,
+}
+
+/// Gives the pieces of the waypoints, routes and tracks of `gpx`, in the
+/// order they are written.
+fn pieces(gpx: &Gpx) -> impl Iterator<Item = Piece<'_>> {
+    let once = std::iter::once;
+    let waypoints = gpx.waypoints.iter().map(Piece::Waypoint);
+    let routes = gpx.routes.iter().flat_map(move |route| {
+        once(Piece::RouteStart(route))
+            .chain(route.points.iter().map(Piece::RoutePoint))
+            .chain(once(Piece::End))
+    });
+    let tracks = gpx.tracks.iter().flat_map(move |track| {
+        let segments = track.segments.iter().flat_map(move |segment| {
+            once(Piece::SegmentStart)
+                .chain(segment.points.iter().map(Piece::TrackPoint))
+                .chain(once(Piece::SegmentEnd(segment)))
+        });
+        once(Piece::TrackStart(track))
+            .chain(segments)
+            .chain(once(Piece::End))
+    });
+    waypoints.chain(routes).chain(tracks)
+}
+
+fn write_piece<W: Write>(
+    piece: Piece,
+    options: &WriterOptions,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
+    match piece {
+        Piece::Waypoint(point) => write_waypoint("wpt", point, options, writer),
+        Piece::RouteStart(route) => write_route_head(route, options, writer),
+        Piece::RoutePoint(point) => write_waypoint("rtept", point, options, writer),
+        Piece::TrackStart(track) => write_track_head(track, options, writer),
+        Piece::SegmentStart => write_xml_event(XmlEvent::start_element("trkseg"), writer),
+        Piece::TrackPoint(point) => write_waypoint("trkpt", point, options, writer),
+        Piece::SegmentEnd(segment) => {
+            write_extensions_if_exists(&segment.extensions, options, writer)?;
+            write_xml_event(XmlEvent::end_element(), writer)
+        }
+        Piece::End => write_xml_event(XmlEvent::end_element(), writer),
     }
-    for track in &gpx.tracks {
-        write_track(track, &mut writer)?;
+}
+
+/// How many bytes `Chunks` serializes at a time, give or take a piece.
+#[cfg(feature = "async")]
+const CHUNK_SIZE: usize = 8192;
+
+/// Chunks serializes a document like `write_with_options` does, a chunk at
+/// a time, so that only a chunk is held in memory at once.
+#[cfg(feature = "async")]
+pub(crate) struct Chunks<'a> {
+    gpx: &'a Gpx,
+    options: WriterOptions,
+    writer: EventWriter<Vec<u8>>,
+    pieces: Box<dyn Iterator<Item = Piece<'a>> + 'a>,
+    started: bool,
+    done: bool,
+}
+
+#[cfg(feature = "async")]
+impl<'a> Chunks<'a> {
+    pub(crate) fn new(gpx: &'a Gpx, options: &WriterOptions) -> Chunks<'a> {
+        let options = options.for_document(gpx);
+        Chunks {
+            gpx,
+            writer: options.create_writer(Vec::with_capacity(CHUNK_SIZE)),
+            options,
+            pieces: Box::new(pieces(gpx)),
+            started: false,
+            done: false,
+        }
     }
-    for route in &gpx.routes {
-            write_route(route, &mut writer)?;
+
+    /// Serializes pieces until there's a chunk of them, or the document
+    /// ends.
+    fn fill(&mut self) -> Result<()> {
+        if !self.started {
+            self.started = true;
+            write_gpx_head(self.gpx, false, &self.options, &mut self.writer)?;
+        }
+        while self.writer.inner_mut().len() < CHUNK_SIZE {
+            match self.pieces.next() {
+                Some(piece) => write_piece(piece, &self.options, &mut self.writer)?,
+                None => {
+                    self.done = true;
+                    return write_gpx_end(self.gpx, &self.options, &mut self.writer);
+                }
+            }
+        }
+        Ok(())
     }
-    write_xml_event(XmlEvent::end_element(), &mut writer)?;
+}
+
+#[cfg(feature = "async")]
+impl<'a> Iterator for Chunks<'a> {
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if let Err(error) = self.fill() {
+            self.done = true;
+            return Some(Err(error));
+        }
+        Some(Ok(std::mem::take(self.writer.inner_mut())))
+    }
+}
+
+/// Writes what follows the tracks of the document and closes the `gpx` tag.
+fn write_gpx_end<W: Write>(
+    gpx: &Gpx,
+    options: &WriterOptions,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
+    write_extensions_if_exists(&gpx.extensions, options, writer)?;
+    write_xml_event(XmlEvent::end_element(), writer)?;
     Ok(())
 }
 
-fn write_xml_event<'a, W, E>(event: E, writer: &mut EventWriter<W>) -> Result<()>
+/// Gives the content of all extensions that will be written for the document.
+fn extensions_of<'a>(gpx: &'a Gpx, options: &WriterOptions) -> Vec<&'a str> {
+    // GPX 1.0 has no extensions element, so none are written.
+    if options.version() == GpxVersion::Gpx10 {
+        return Vec::new();
+    }
+    let waypoints = gpx
+        .waypoints
+        .iter()
+        .chain(gpx.routes.iter().flat_map(|route| &route.points))
+        .chain(
+            gpx.tracks
+                .iter()
+                .flat_map(|track| &track.segments)
+                .flat_map(|segment| &segment.points),
+        )
+        .map(|waypoint| &waypoint.extensions);
+    let segments = gpx
+        .tracks
+        .iter()
+        .flat_map(|track| &track.segments)
+        .map(|segment| &segment.extensions);
+
+    std::iter::once(&gpx.extensions)
+        .chain(gpx.metadata.iter().map(|metadata| &metadata.extensions))
+        .chain(gpx.routes.iter().map(|route| &route.extensions))
+        .chain(gpx.tracks.iter().map(|track| &track.extensions))
+        .chain(segments)
+        .chain(waypoints)
+        .filter_map(|extensions| extensions.as_deref())
+        .collect()
+}
+
+/// Tells whether raw XML `content` names an element or attribute with the
+/// given namespace prefix.
+fn uses_prefix(content: &str, prefix: &str) -> bool {
+    content.match_indices(prefix).any(|(index, _)| {
+        let before = content[..index].chars().next_back();
+        let after = content[index + prefix.len()..].chars().next();
+        after == Some(':')
+            && matches!(
+                before,
+                Some('<') | Some('/') | Some(' ') | Some('\n') | Some('\t')
+            )
+    })
+}
+
+/// The innermost element a `StreamingWriter` has left open.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum StreamingState {
+    Gpx,
+    Track,
+    Segment,
+}
+
+/// StreamingWriter writes a GPX document incrementally, one track point at a
+/// time, for example while logging GPS fixes as they arrive.
+///
+/// Only the elements currently open are kept track of, so the points written
+/// are never held in memory. The document is complete once `finish` is
+/// called; dropping the writer before leaves it truncated.
+///
+/// ```
+/// use gpx::{Gpx, GpxVersion, StreamingWriter, Track, Waypoint};
+///
+/// let header = Gpx::builder(GpxVersion::Gpx11).creator("my-logger").build();
+/// let mut writer = StreamingWriter::new(Vec::new(), &header).unwrap();
+///
+/// writer.start_track(&Track::builder().name("Morning ride").build()).unwrap();
+/// for (lat, lon) in vec![(37.24, -121.97), (37.25, -121.96)] {
+///     let fix = Waypoint::builder().lat(lat).lon(lon).build().unwrap();
+///     writer.write_point(&fix).unwrap();
+/// }
+///
+/// let buffer = writer.finish().unwrap();
+/// let gpx = gpx::read(buffer.as_slice()).unwrap();
+/// assert_eq!(gpx.tracks[0].segments[0].points.len(), 2);
+/// ```
+pub struct StreamingWriter<W: Write> {
+    writer: EventWriter<W>,
+    options: WriterOptions,
+    state: StreamingState,
+    // The root extensions of the header, which go last in the document.
+    extensions: Option<String>,
+}
+
+impl<W: Write> StreamingWriter<W> {
+    /// Starts a document on `writer`, immediately writing the header and
+    /// everything already in `gpx`: metadata, waypoints, routes and tracks.
+    /// Its extensions are written by `finish`.
+    ///
+    /// As the points to come aren't known yet, all namespaces of `gpx` are
+    /// declared, whether they're used or not.
+    pub fn new(writer: W, gpx: &Gpx) -> Result<StreamingWriter<W>> {
+        StreamingWriter::with_options(writer, gpx, WriterOptions::default())
+    }
+
+    /// Starts a document like `new`, formatted according to `options`.
+    pub fn with_options(
+        writer: W,
+        gpx: &Gpx,
+        options: WriterOptions,
+    ) -> Result<StreamingWriter<W>> {
+        let options = options.for_document(gpx);
+        let mut writer = options.create_writer(writer);
+        write_gpx_start(gpx, true, &options, &mut writer)?;
+        Ok(StreamingWriter {
+            writer,
+            options,
+            state: StreamingState::Gpx,
+            extensions: gpx.extensions.clone(),
+        })
+    }
+
+    /// Starts a new track, closing the current one, if any. The track's own
+    /// information and segments are written right away; further points go
+    /// into new segments.
+    pub fn start_track(&mut self, track: &Track) -> Result<()> {
+        self.end_track()?;
+        write_track_start(track, &self.options, &mut self.writer)?;
+        self.state = StreamingState::Track;
+        self.flush()
+    }
+
+    /// Starts a new segment in the current track, closing the current
+    /// segment, if any. Starts an empty track if there is none.
+    pub fn start_segment(&mut self) -> Result<()> {
+        match self.state {
+            StreamingState::Gpx => self.start_track(&Track::new())?,
+            StreamingState::Segment => self.end_element(StreamingState::Track)?,
+            StreamingState::Track => {}
+        }
+        write_xml_event(XmlEvent::start_element("trkseg"), &mut self.writer)?;
+        self.state = StreamingState::Segment;
+        Ok(())
+    }
+
+    /// Writes a point to the current segment, starting a segment (and track)
+    /// if there is none, then flushes the underlying writer.
+    pub fn write_point(&mut self, point: &Waypoint) -> Result<()> {
+        if self.state != StreamingState::Segment {
+            self.start_segment()?;
+        }
+        write_waypoint("trkpt", point, &self.options, &mut self.writer)?;
+        self.flush()
+    }
+
+    /// Closes all open elements, completing the document, and gives back the
+    /// underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.end_track()?;
+        write_extensions_if_exists(&self.extensions, &self.options, &mut self.writer)?;
+        write_xml_event(XmlEvent::end_element(), &mut self.writer)?;
+        self.flush()?;
+        Ok(self.writer.into_inner())
+    }
+
+    fn end_track(&mut self) -> Result<()> {
+        if self.state == StreamingState::Segment {
+            self.end_element(StreamingState::Track)?;
+        }
+        if self.state == StreamingState::Track {
+            self.end_element(StreamingState::Gpx)?;
+        }
+        Ok(())
+    }
+
+    fn end_element(&mut self, state: StreamingState) -> Result<()> {
+        write_xml_event(XmlEvent::end_element(), &mut self.writer)?;
+        self.state = state;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.writer
+            .inner_mut()
+            .flush()
+            .chain_err(|| "error while flushing gpx writer")
+    }
+}
+
+impl Gpx {
+    /// Writes the document to a string of GPX, like `write` does.
+    ///
+    /// ```
+    /// use gpx::{Gpx, GpxVersion};
+    ///
+    /// let gpx = Gpx::builder(GpxVersion::Gpx11).creator("my-app").build();
+    ///
+    /// let xml = gpx.to_string().unwrap();
+    /// assert!(xml.contains("creator=\"my-app\""));
+    /// ```
+    pub fn to_string(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        write(self, &mut buffer)?;
+        String::from_utf8(buffer).chain_err(|| "error while converting gpx to UTF-8")
+    }
+}
+
+pub(crate) fn write_xml_event<'a, W, E>(event: E, writer: &mut EventWriter<W>) -> Result<()>
 where
     W: Write,
     E: Into<XmlEvent<'a>>,
@@ -69,62 +585,101 @@ fn version_to_version_string(version: GpxVersion) -> Result<&'static str> {
     }
 }
 
-fn write_metadata<W: Write>(gpx: &Gpx, writer: &mut EventWriter<W>) -> Result<()> {
-    match gpx.version {
-        GpxVersion::Gpx10 => write_gpx10_metadata(gpx, writer),
-        GpxVersion::Gpx11 => write_gpx11_metadata(gpx, writer),
+fn version_to_namespace(version: GpxVersion) -> Result<&'static str> {
+    match version {
+        GpxVersion::Gpx10 => Ok("http://www.topografix.com/GPX/1/0"),
+        GpxVersion::Gpx11 => Ok("http://www.topografix.com/GPX/1/1"),
         version => Err(Error::from(format!("Unknown version {:?}", version))),
     }
 }
 
-fn write_gpx10_metadata<W: Write>(gpx: &Gpx, writer: &mut EventWriter<W>) -> Result<()> {
-    if gpx.metadata.is_none() {
-        return Ok(());
+fn write_metadata<W: Write>(
+    gpx: &Gpx,
+    options: &WriterOptions,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
+    let computed;
+    let metadata = if options.compute_bounds {
+        computed = Metadata {
+            bounds: gpx.bounds(),
+            ..gpx.metadata.clone().unwrap_or_default()
+        };
+        // Don't write empty metadata just for bounds that don't exist.
+        if computed == Metadata::default() {
+            return Ok(());
+        }
+        &computed
+    } else {
+        match gpx.metadata {
+            Some(ref metadata) => metadata,
+            None => return Ok(()),
+        }
+    };
+    match options.version() {
+        GpxVersion::Gpx10 => write_gpx10_metadata(metadata, options, writer),
+        GpxVersion::Gpx11 => write_gpx11_metadata(metadata, options, writer),
+        version => Err(Error::from(format!("Unknown version {:?}", version))),
     }
-    let metadata = gpx.metadata.as_ref().unwrap();
+}
+
+fn write_gpx10_metadata<W: Write>(
+    metadata: &Metadata,
+    options: &WriterOptions,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
     write_string_if_exists("name", &metadata.name, writer)?;
-    write_string_if_exists("description", &metadata.description, writer)?;
+    write_text_if_exists("desc", &metadata.description, options, writer)?;
     if let Some(author) = metadata.author.as_ref() {
         write_string_if_exists("author", &author.name, writer)?;
-        write_email_if_exists(&author.email, writer)?;
-        if let Some(link) = author.link.as_ref() {
-            write_string("url", &link.href, writer)?;
-            write_string_if_exists("urlname", &link.text, writer)?;
-        }
+        // GPX 1.0 has no split email type.
+        write_value_if_exists("email", &author.email, writer)?;
     }
+    // GPX 1.0 has a single url for the file; prefer the author's.
+    let link = metadata
+        .author
+        .as_ref()
+        .and_then(|author| author.link.as_ref())
+        .or_else(|| metadata.links.first());
+    write_gpx10_link_if_exists(link, writer)?;
+    write_time_if_exists(&metadata.time, None, options, writer)?;
     write_string_if_exists("keywords", &metadata.keywords, writer)?;
-    write_time_if_exists(&metadata.time, writer)?;
-    write_bounds_if_exists(&metadata.bounds, writer)?;
+    write_bounds_if_exists(&metadata.bounds, options, writer)?;
     Ok(())
 }
 
-fn write_gpx11_metadata<W: Write>(gpx: &Gpx, writer: &mut EventWriter<W>) -> Result<()> {
-    if gpx.metadata.is_none() {
-        return Ok(());
-    }
-    let metadata = gpx.metadata.as_ref().unwrap();
+fn write_gpx11_metadata<W: Write>(
+    metadata: &Metadata,
+    options: &WriterOptions,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
     write_xml_event(XmlEvent::start_element("metadata"), writer)?;
     write_string_if_exists("name", &metadata.name, writer)?;
-    write_string_if_exists("description", &metadata.description, writer)?;
+    write_text_if_exists("desc", &metadata.description, options, writer)?;
     write_person_if_exists("author", &metadata.author, writer)?;
-    write_string_if_exists("keywords", &metadata.keywords, writer)?;
-    write_time_if_exists(&metadata.time, writer)?;
+    write_copyright_if_exists(&metadata.copyright, writer)?;
     for link in &metadata.links {
-        write_link(&link, writer)?;
+        write_link(link, writer)?;
     }
-    write_bounds_if_exists(&metadata.bounds, writer)?;
+    write_time_if_exists(&metadata.time, None, options, writer)?;
+    write_string_if_exists("keywords", &metadata.keywords, writer)?;
+    write_bounds_if_exists(&metadata.bounds, options, writer)?;
+    write_extensions_if_exists(&metadata.extensions, options, writer)?;
     write_xml_event(XmlEvent::end_element(), writer)?;
     Ok(())
 }
 
-fn write_string<W: Write>(key: &str, value: &str, writer: &mut EventWriter<W>) -> Result<()> {
+pub(crate) fn write_string<W: Write>(
+    key: &str,
+    value: &str,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
     write_xml_event(XmlEvent::start_element(key), writer)?;
     write_xml_event(XmlEvent::characters(value), writer)?;
     write_xml_event(XmlEvent::end_element(), writer)?;
     Ok(())
 }
 
-fn write_string_if_exists<W: Write>(
+pub(crate) fn write_string_if_exists<W: Write>(
     key: &str,
     value: &Option<String>,
     writer: &mut EventWriter<W>,
@@ -135,6 +690,25 @@ fn write_string_if_exists<W: Write>(
     Ok(())
 }
 
+/// Writes a free-form text, like a description, in CDATA if it's as long as
+/// the threshold of the options.
+fn write_text_if_exists<W: Write>(
+    key: &str,
+    value: &Option<String>,
+    options: &WriterOptions,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
+    match (value, options.cdata_threshold) {
+        (Some(value), Some(threshold)) if value.chars().count() >= threshold => {
+            write_xml_event(XmlEvent::start_element(key), writer)?;
+            write_xml_event(XmlEvent::cdata(value), writer)?;
+            write_xml_event(XmlEvent::end_element(), writer)?;
+            Ok(())
+        }
+        _ => write_string_if_exists(key, value, writer),
+    }
+}
+
 fn write_value_if_exists<W: Write, T: ToString>(
     key: &str,
     value: &Option<T>,
@@ -150,23 +724,14 @@ fn write_value_if_exists<W: Write, T: ToString>(
 }
 
 fn write_email_if_exists<W: Write>(
-    email: &Option<String>,
+    email: &Option<Email>,
     writer: &mut EventWriter<W>,
 ) -> Result<()> {
     if let Some(ref email) = email {
-        let mut parts = email.split('@');
-        let id = parts.next().ok_or("Missing id part in email!")?;
-        let domain = parts.next().ok_or("Missing domain part in email!")?;
-        if parts.next().is_some() {
-            bail!(ErrorKind::Msg(format!(
-                "Email contains more than one @: {}",
-                email
-            )));
-        }
         write_xml_event(
             XmlEvent::start_element("email")
-                .attr("id", id)
-                .attr("domain", domain),
+                .attr("id", &email.id)
+                .attr("domain", &email.domain),
             writer,
         )?;
         write_xml_event(XmlEvent::end_element(), writer)?;
@@ -185,6 +750,35 @@ fn write_link<W: Write>(link: &Link, writer: &mut EventWriter<W>) -> Result<()>
     Ok(())
 }
 
+/// Writes the links of an element, of which GPX 1.0 only has room for the
+/// first.
+fn write_links<W: Write>(
+    links: &[Link],
+    options: &WriterOptions,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
+    match options.version() {
+        GpxVersion::Gpx10 => write_gpx10_link_if_exists(links.first(), writer),
+        _ => {
+            for link in links {
+                write_link(link, writer)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn write_gpx10_link_if_exists<W: Write>(
+    link: Option<&Link>,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
+    if let Some(link) = link {
+        write_string("url", &link.href, writer)?;
+        write_string_if_exists("urlname", &link.text, writer)?;
+    }
+    Ok(())
+}
+
 fn write_link_if_exists<W: Write>(link: &Option<Link>, writer: &mut EventWriter<W>) -> Result<()> {
     if let Some(ref link) = link {
         write_link(link, writer)?;
@@ -207,13 +801,32 @@ fn write_person_if_exists<W: Write>(
     Ok(())
 }
 
+fn write_copyright_if_exists<W: Write>(
+    copyright: &Option<Copyright>,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
+    if let Some(ref copyright) = copyright {
+        write_xml_event(
+            XmlEvent::start_element("copyright").attr("author", &copyright.author),
+            writer,
+        )?;
+        write_value_if_exists("year", &copyright.year, writer)?;
+        write_string_if_exists("license", &copyright.license, writer)?;
+        write_xml_event(XmlEvent::end_element(), writer)?;
+    }
+    Ok(())
+}
+
 fn write_time_if_exists<W: Write>(
     time: &Option<DateTime<Utc>>,
+    offset: Option<i32>,
+    options: &WriterOptions,
     writer: &mut EventWriter<W>,
 ) -> Result<()> {
     if let Some(ref time) = time {
+        let time = options.format_time(time, offset);
         write_xml_event(XmlEvent::start_element("time"), writer)?;
-        write_xml_event(XmlEvent::characters(&time.to_rfc3339()), writer)?;
+        write_xml_event(XmlEvent::characters(&time), writer)?;
         write_xml_event(XmlEvent::end_element(), writer)?;
     }
     Ok(())
@@ -221,15 +834,16 @@ fn write_time_if_exists<W: Write>(
 
 fn write_bounds_if_exists<W: Write>(
     bounds: &Option<Rect<f64>>,
+    options: &WriterOptions,
     writer: &mut EventWriter<W>,
 ) -> Result<()> {
     if let Some(ref bounds) = bounds {
         write_xml_event(
             XmlEvent::start_element("bounds")
-                .attr("minlat", &bounds.min().y.to_string())
-                .attr("maxlat", &bounds.max().y.to_string())
-                .attr("minlon", &bounds.min().x.to_string())
-                .attr("maxlon", &bounds.max().x.to_string()),
+                .attr("minlat", &options.format_coordinate(bounds.min().y))
+                .attr("maxlat", &options.format_coordinate(bounds.max().y))
+                .attr("minlon", &options.format_coordinate(bounds.min().x))
+                .attr("maxlon", &options.format_coordinate(bounds.max().x)),
             writer,
         )?;
         write_xml_event(XmlEvent::end_element(), writer)?;
@@ -237,6 +851,30 @@ fn write_bounds_if_exists<W: Write>(
     Ok(())
 }
 
+/// Writes an `extensions` element holding the raw XML `content` as it is.
+/// GPX 1.0 has no `extensions` element, so nothing is written for it.
+fn write_extensions_if_exists<W: Write>(
+    content: &Option<String>,
+    options: &WriterOptions,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
+    if options.version() == GpxVersion::Gpx10 {
+        return Ok(());
+    }
+    if let Some(ref content) = content {
+        write_xml_event(XmlEvent::start_element("extensions"), writer)?;
+        // xml-rs can't write raw XML, so close the start tag with an empty
+        // text event and write the content to the underlying writer.
+        write_xml_event(XmlEvent::characters(""), writer)?;
+        writer
+            .inner_mut()
+            .write_all(content.as_bytes())
+            .chain_err(|| "error while writing gpx extensions")?;
+        write_xml_event(XmlEvent::end_element(), writer)?;
+    }
+    Ok(())
+}
+
 fn write_fix_if_exists<W: Write>(fix: &Option<Fix>, writer: &mut EventWriter<W>) -> Result<()> {
     if let Some(ref fix) = fix {
         write_xml_event(XmlEvent::start_element("fix"), writer)?;
@@ -254,49 +892,72 @@ fn write_fix_if_exists<W: Write>(fix: &Option<Fix>, writer: &mut EventWriter<W>)
     Ok(())
 }
 
-fn write_track<W: Write>(track: &Track, writer: &mut EventWriter<W>) -> Result<()> {
+/// Writes the opening `trk` tag and everything in the track, leaving the tag
+/// open.
+fn write_track_start<W: Write>(
+    track: &Track,
+    options: &WriterOptions,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
+    write_track_head(track, options, writer)?;
+    for segment in &track.segments {
+        write_track_segment(segment, options, writer)?;
+    }
+    Ok(())
+}
+
+/// Writes the opening `trk` tag and the track's own information, before its
+/// segments.
+fn write_track_head<W: Write>(
+    track: &Track,
+    options: &WriterOptions,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
     write_xml_event(XmlEvent::start_element("trk"), writer)?;
     write_string_if_exists("name", &track.name, writer)?;
-    write_string_if_exists("cmt", &track.comment, writer)?;
-    write_string_if_exists("desc", &track.description, writer)?;
+    write_text_if_exists("cmt", &track.comment, options, writer)?;
+    write_text_if_exists("desc", &track.description, options, writer)?;
     write_string_if_exists("src", &track.source, writer)?;
-    for link in &track.links {
-        write_link(link, writer)?;
-    }
-    write_string_if_exists("type", &track._type, writer)?;
-    for segment in &track.segments {
-        write_track_segment(segment, writer)?;
+    write_links(&track.links, options, writer)?;
+    write_value_if_exists("number", &track.number, writer)?;
+    if options.version() != GpxVersion::Gpx10 {
+        write_string_if_exists("type", &track._type, writer)?;
     }
-    write_xml_event(XmlEvent::end_element(), writer)?;
+    write_extensions_if_exists(&track.extensions, options, writer)?;
     Ok(())
 }
 
-fn write_route<W: Write>(route: &Route, writer: &mut EventWriter<W>) -> Result<()> {
+/// Writes the opening `rte` tag and the route's own information, before its
+/// points.
+fn write_route_head<W: Write>(
+    route: &Route,
+    options: &WriterOptions,
+    writer: &mut EventWriter<W>,
+) -> Result<()> {
     write_xml_event(XmlEvent::start_element("rte"), writer)?;
     write_string_if_exists("name", &route.name, writer)?;
-    write_string_if_exists("cmt", &route.comment, writer)?;
-    write_string_if_exists("desc", &route.description, writer)?;
+    write_text_if_exists("cmt", &route.comment, options, writer)?;
+    write_text_if_exists("desc", &route.description, options, writer)?;
     write_string_if_exists("src", &route.source, writer)?;
-    for link in &route.links {
-        write_link(link, writer)?;
-    }
+    write_links(&route.links, options, writer)?;
     write_value_if_exists("number", &route.number, writer)?;
-    write_string_if_exists("type", &route._type, writer)?;
-    for point in &route.points {
-        write_waypoint("rtept", point, writer)?;
+    if options.version() != GpxVersion::Gpx10 {
+        write_string_if_exists("type", &route._type, writer)?;
     }
-    write_xml_event(XmlEvent::end_element(), writer)?;
+    write_extensions_if_exists(&route.extensions, options, writer)?;
     Ok(())
 }
 
 fn write_track_segment<W: Write>(
     segment: &TrackSegment,
+    options: &WriterOptions,
     writer: &mut EventWriter<W>,
 ) -> Result<()> {
     write_xml_event(XmlEvent::start_element("trkseg"), writer)?;
     for point in &segment.points {
-        write_waypoint("trkpt", point, writer)?;
+        write_waypoint("trkpt", point, options, writer)?;
     }
+    write_extensions_if_exists(&segment.extensions, options, writer)?;
     write_xml_event(XmlEvent::end_element(), writer)?;
     Ok(())
 }
@@ -304,25 +965,29 @@ fn write_track_segment<W: Write>(
 fn write_waypoint<W: Write>(
     tagname: &str,
     waypoint: &Waypoint,
+    options: &WriterOptions,
     writer: &mut EventWriter<W>,
 ) -> Result<()> {
     write_xml_event(
         XmlEvent::start_element(tagname)
-            .attr("lat", &waypoint.point().lat().to_string())
-            .attr("lon", &waypoint.point().lng().to_string()),
+            .attr("lat", &options.format_coordinate(waypoint.point().lat()))
+            .attr("lon", &options.format_coordinate(waypoint.point().lng())),
         writer,
     )?;
-    write_value_if_exists("ele", &waypoint.elevation, writer)?;
-    // TODO: write speed if GPX version == 1.0
-    write_time_if_exists(&waypoint.time, writer)?;
+    let elevation = waypoint.elevation.map(|ele| options.format_elevation(ele));
+    write_string_if_exists("ele", &elevation, writer)?;
+    write_time_if_exists(&waypoint.time, waypoint.time_offset, options, writer)?;
+    if options.version() == GpxVersion::Gpx10 {
+        write_value_if_exists("course", &waypoint.course, writer)?;
+        write_value_if_exists("speed", &waypoint.speed, writer)?;
+    }
+    write_value_if_exists("magvar", &waypoint.magvar, writer)?;
     write_value_if_exists("geoidheight", &waypoint.geoidheight, writer)?;
     write_string_if_exists("name", &waypoint.name, writer)?;
-    write_string_if_exists("cmt", &waypoint.comment, writer)?;
-    write_string_if_exists("desc", &waypoint.description, writer)?;
+    write_text_if_exists("cmt", &waypoint.comment, options, writer)?;
+    write_text_if_exists("desc", &waypoint.description, options, writer)?;
     write_string_if_exists("src", &waypoint.source, writer)?;
-    for link in &waypoint.links {
-        write_link(link, writer)?;
-    }
+    write_links(&waypoint.links, options, writer)?;
     write_string_if_exists("sym", &waypoint.symbol, writer)?;
     write_string_if_exists("type", &waypoint._type, writer)?;
     write_fix_if_exists(&waypoint.fix, writer)?;
@@ -332,6 +997,7 @@ fn write_waypoint<W: Write>(
     write_value_if_exists("pdop", &waypoint.pdop, writer)?;
     write_value_if_exists("ageofdgpsdata", &waypoint.dgps_age, writer)?;
     write_value_if_exists("dgpsid", &waypoint.dgpsid, writer)?;
+    write_extensions_if_exists(&waypoint.extensions, options, writer)?;
     write_xml_event(XmlEvent::end_element(), writer)?;
     Ok(())
 }
@@ -11,7 +11,7 @@ use geo::algorithm::haversine_distance::HaversineDistance;
 use geo::euclidean_length::EuclideanLength;
 use geo_types::{Geometry, Point};
 
-use gpx::{read, Fix};
+use gpx::{read, Fix, GpxVersion, Symbol};
 
 #[test]
 fn gpx_reader_read_test_badxml() {
@@ -35,11 +35,22 @@ fn gpx_reader_read_test_wikipedia() {
 
     let result = result.unwrap();
 
+    // The root declares the Garmin extension namespaces.
+    assert!(result.namespaces.contains(&(
+        String::from("gpxtpx"),
+        String::from("http://www.garmin.com/xmlschemas/TrackPointExtension/v1")
+    )));
+    assert!(result
+        .schema_location
+        .as_ref()
+        .unwrap()
+        .starts_with("http://www.topografix.com/GPX/1/1 "));
+
     // Check the metadata, of course; here it has a time.
     let metadata = result.metadata.unwrap();
     assert_eq!(
         metadata.time.unwrap(),
-        Utc.ymd(2009, 10, 17).and_hms(22, 58, 43)
+        Utc.with_ymd_and_hms(2009, 10, 17, 22, 58, 43).unwrap()
     );
 
     assert_eq!(metadata.links.len(), 1);
@@ -84,7 +95,7 @@ fn gpx_reader_read_test_gpsies() {
     let metadata = result.metadata.unwrap();
     assert_eq!(
         metadata.time.unwrap(),
-        Utc.ymd(2019, 09, 11).and_hms(17, 08, 31)
+        Utc.with_ymd_and_hms(2019, 9, 11, 17, 8, 31).unwrap()
     );
 
     assert_eq!(metadata.links.len(), 1);
@@ -120,11 +131,13 @@ fn gpx_reader_read_test_garmin_activity() {
     assert!(result.is_ok());
     let res = result.unwrap();
 
+    assert_eq!(res.creator, Some(String::from("Garmin Connect")));
+
     // Check the info on the metadata.
     let metadata = res.metadata.unwrap();
     assert_eq!(
         metadata.time.unwrap(),
-        Utc.ymd(2017, 7, 29).and_hms_micro(14, 46, 35, 000_000)
+        Utc.with_ymd_and_hms(2017, 7, 29, 14, 46, 35).unwrap()
     );
 
     assert_eq!(metadata.links.len(), 1);
@@ -147,6 +160,10 @@ fn gpx_reader_read_test_garmin_activity() {
     assert_eq!(track.segments.len(), 1);
     let segment = &track.segments[0];
 
+    let first = segment.points[0].track_point_extension.as_ref().unwrap();
+    assert_eq!(first.hr, Some(74));
+    assert_eq!(first.cad, Some(79));
+
     // Test for every single point in the file.
     for point in segment.points.iter() {
         // Elevation is between 90 and 220.
@@ -160,8 +177,8 @@ fn gpx_reader_read_test_garmin_activity() {
 
         // Time is between a day before and after.
         let time = point.time.unwrap();
-        assert!(time > Utc.ymd(2017, 7, 28).and_hms_micro(0, 0, 0, 000_000));
-        assert!(time < Utc.ymd(2017, 7, 30).and_hms_micro(0, 0, 0, 000_000));
+        assert!(time > Utc.with_ymd_and_hms(2017, 7, 28, 0, 0, 0).unwrap());
+        assert!(time < Utc.with_ymd_and_hms(2017, 7, 30, 0, 0, 0).unwrap());
 
         // Should coerce to Point.
         let geo: Geometry<f64> = point.clone().into();
@@ -178,6 +195,16 @@ fn gpx_reader_read_test_garmin_activity() {
         assert!(point.symbol.is_none());
         assert!(point._type.is_none());
         assert_eq!(point.links.len(), 0);
+
+        // But every point carries Garmin's heart rate and cadence extension.
+        let extensions = point.extensions.as_ref().unwrap();
+        assert!(extensions.starts_with("<ns3:TrackPointExtension>"));
+        assert!(extensions.contains("<ns3:hr>"));
+
+        let track_point_extension = point.track_point_extension.as_ref().unwrap();
+        assert!(track_point_extension.hr.is_some());
+        assert!(track_point_extension.cad.is_some());
+        assert!(track_point_extension.atemp.is_none());
     }
 }
 
@@ -210,6 +237,16 @@ fn gpx_reader_read_test_lovers_lane() {
     );
     assert_eq!(routes[0].points.len(), 139);
 
+    // Routes convert to the same geometries as track segments.
+    let linestring = routes[0].linestring();
+    assert_eq!(linestring.0.len(), 139);
+    assert!(linestring.euclidean_length() > 0.);
+    let geo: Geometry<f64> = routes[0].clone().into();
+    match geo {
+        Geometry::LineString(ls) => assert_eq!(ls, linestring),
+        _ => panic!("route.into() gave bad geometry"),
+    }
+
     // Test for every single point in the file.
     for point in routes[0].points.iter() {
         // Elevation is between 15 and 100
@@ -254,6 +291,7 @@ fn gpx_reader_read_test_with_accuracy() {
     let track = &res.tracks[0];
 
     assert_eq!(track.name, Some(String::from("Cycling")));
+    assert_eq!(track.number, Some(1));
 
     // Get the first track segment.
     assert_eq!(track.segments.len(), 1);
@@ -269,7 +307,7 @@ fn gpx_reader_read_test_with_accuracy() {
     assert_eq!(points[0].vdop.unwrap(), 6.2);
     assert_eq!(points[0].pdop.unwrap(), 728.);
     assert_eq!(points[0].dgps_age.unwrap(), 1.);
-    assert_eq!(points[0].dgpsid.unwrap(), 3);
+    assert_eq!(points[0].dgpsid.unwrap().value(), 3);
 
     assert_eq!(points[1].fix, Some(Fix::ThreeDimensional));
     assert_eq!(points[1].sat.unwrap(), 5);
@@ -277,10 +315,123 @@ fn gpx_reader_read_test_with_accuracy() {
     assert_eq!(points[1].vdop.unwrap(), 5.);
     assert_eq!(points[1].pdop.unwrap(), 619.1);
     assert_eq!(points[1].dgps_age.unwrap(), 2.01);
-    assert_eq!(points[1].dgpsid.unwrap(), 4);
+    assert_eq!(points[1].dgpsid.unwrap().value(), 4);
 
     assert_eq!(
         points[2].fix,
         Some(Fix::Other("something_not_in_the_spec".to_string()))
     );
 }
+
+#[test]
+fn gpx_reader_read_test_waypoints() {
+    let file = File::open("tests/fixtures/waypoints.gpx").unwrap();
+    let reader = BufReader::new(file);
+
+    let result = read(reader);
+    assert!(result.is_ok());
+    let res = result.unwrap();
+
+    let copyright = res.metadata.unwrap().copyright.unwrap();
+    assert_eq!(copyright.author, "National Park Service");
+    assert_eq!(copyright.year, Some(2018));
+    assert_eq!(
+        copyright.license,
+        Some(String::from("https://www.usa.gov/government-works"))
+    );
+
+    // Standalone waypoints live on the document itself, outside any track.
+    assert_eq!(res.tracks.len(), 0);
+    assert_eq!(res.routes.len(), 0);
+    assert_eq!(res.waypoints.len(), 3);
+
+    let trailhead = &res.waypoints[0];
+    assert_eq!(trailhead.point(), Point::new(-119.596601, 37.745914));
+    assert_eq!(trailhead.elevation, Some(1209.0));
+    assert_eq!(
+        trailhead.name,
+        Some(String::from("Yosemite Falls trailhead"))
+    );
+    assert_eq!(
+        trailhead.comment,
+        Some(String::from("Start of the upper falls trail"))
+    );
+    assert_eq!(trailhead.symbol, Some(String::from("Trail Head")));
+    assert_eq!(trailhead.garmin_symbol(), Some(Symbol::TrailHead));
+
+    let campground = &res.waypoints[1];
+    assert_eq!(
+        campground.description,
+        Some(String::from("Campground near Mirror Lake"))
+    );
+    assert_eq!(campground._type, Some(String::from("camping")));
+    assert_eq!(campground.garmin_symbol(), Some(Symbol::Campground));
+
+    let lake = &res.waypoints[2];
+    assert_eq!(lake.name, Some(String::from("Mirror Lake")));
+    assert_eq!(lake.garmin_symbol(), Some(Symbol::FlagBlue));
+    assert!(lake.elevation.is_none());
+    assert_eq!(lake.magvar.map(f64::from), Some(13.2));
+    assert_eq!(lake.geoidheight, Some(-32.1));
+}
+
+#[test]
+fn gpx_reader_read_test_gpx10() {
+    let file = File::open("tests/fixtures/gpx10_example.gpx").unwrap();
+    let reader = BufReader::new(file);
+
+    let result = read(reader);
+    assert!(result.is_ok());
+    let res = result.unwrap();
+
+    assert_eq!(res.version, GpxVersion::Gpx10);
+
+    // GPX 1.0 keeps the metadata directly on the root element.
+    let metadata = res.metadata.unwrap();
+    assert_eq!(metadata.name, Some(String::from("Morning ride")));
+    assert_eq!(
+        metadata.description,
+        Some(String::from("Commute along the lake shore"))
+    );
+    assert_eq!(metadata.keywords, Some(String::from("bike, commute")));
+    assert_eq!(metadata.keyword_list(), vec!["bike", "commute"]);
+    assert_eq!(
+        metadata.time.unwrap(),
+        Utc.with_ymd_and_hms(2004, 4, 18, 7, 12, 43).unwrap()
+    );
+    assert!(metadata.bounds.is_some());
+
+    let author = metadata.author.unwrap();
+    assert_eq!(author.name, Some(String::from("Jane Doe")));
+    let email = author.email.unwrap();
+    assert_eq!(email.id, "jane");
+    assert_eq!(email.domain, "example.com");
+    let link = author.link.unwrap();
+    assert_eq!(link.href, "http://example.com/rides");
+    assert_eq!(link.text, Some(String::from("Jane's rides")));
+
+    // url and urlname become links.
+    assert_eq!(res.waypoints.len(), 1);
+    let waypoint = &res.waypoints[0];
+    assert_eq!(waypoint.name, Some(String::from("5066")));
+    assert_eq!(waypoint.links.len(), 1);
+    assert_eq!(waypoint.links[0].href, "http://example.com/wpt/5066");
+    assert_eq!(waypoint.links[0].text, Some(String::from("Waypoint 5066")));
+
+    assert_eq!(res.routes.len(), 1);
+    assert_eq!(res.routes[0].number, Some(1));
+    assert_eq!(res.routes[0].points.len(), 2);
+
+    assert_eq!(res.tracks.len(), 1);
+    let track = &res.tracks[0];
+    assert_eq!(track.links.len(), 1);
+    assert_eq!(track.links[0].href, "http://example.com/rides/1");
+    assert!(track.links[0].text.is_none());
+
+    let points = &track.segments[0].points;
+    assert_eq!(points.len(), 2);
+    assert_eq!(points[0].speed, Some(5.2));
+    assert_eq!(points[0].course.map(f64::from), Some(41.2));
+    assert_eq!(points[1].speed, Some(5.6));
+    assert_eq!(points[1].course.map(f64::from), Some(43.9));
+}
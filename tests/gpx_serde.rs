@@ -0,0 +1,37 @@
+#![cfg(feature = "serde")]
+
+use std::fs::File;
+use std::io::BufReader;
+
+use gpx::{read, Gpx, Waypoint};
+
+#[test]
+fn gpx_serde_round_trip_garmin_activity() {
+    let file = File::open("tests/fixtures/garmin-activity.gpx").unwrap();
+    let gpx = read(BufReader::new(file)).unwrap();
+
+    let json = serde_json::to_string(&gpx).unwrap();
+    let deserialized: Gpx = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(deserialized, gpx);
+}
+
+#[test]
+fn gpx_serde_point_as_lat_lon() {
+    let wpt = Waypoint::try_new(37.24, -121.97).unwrap();
+
+    let json = serde_json::to_value(&wpt).unwrap();
+    assert_eq!(json["point"]["lat"], 37.24);
+    assert_eq!(json["point"]["lon"], -121.97);
+}
+
+#[test]
+fn gpx_serde_reject_out_of_range() {
+    let mut json = serde_json::to_value(Waypoint::default()).unwrap();
+    json["point"]["lat"] = serde_json::json!(121.97);
+    assert!(serde_json::from_value::<Waypoint>(json).is_err());
+
+    let mut json = serde_json::to_value(Waypoint::default()).unwrap();
+    json["magvar"] = serde_json::json!(360.);
+    assert!(serde_json::from_value::<Waypoint>(json).is_err());
+}
@@ -1,8 +1,15 @@
 use std::fs::File;
 use std::io::BufReader;
 
-use gpx::{read, write};
-use gpx::{Gpx, Link, Waypoint};
+use chrono::{TimeZone, Utc};
+use geo_types::{Coordinate, Rect};
+use gpx::{read, write, write_with_options};
+use gpx::{
+    Copyright, Degrees, DgpsStationId, Email, Fix, Gpx, GpxVersion, Link, Metadata, Person, Route,
+    StreamingWriter, Track, TrackSegment, Waypoint, WriterOptions,
+};
+use std::convert::TryFrom;
+use xml::reader::{EventReader, XmlEvent};
 
 #[test]
 fn gpx_writer_write_unknown_gpx_version() {
@@ -14,6 +21,22 @@ fn gpx_writer_write_unknown_gpx_version() {
     assert!(result.is_err());
 }
 
+#[test]
+fn gpx_writer_write_default_creator() {
+    let gpx = Gpx {
+        version: GpxVersion::Gpx11,
+        ..Default::default()
+    };
+    let mut writer: Vec<u8> = Vec::new();
+    write(&gpx, &mut writer).unwrap();
+
+    let written_gpx = read(writer.as_slice()).unwrap();
+    assert_eq!(
+        written_gpx.creator,
+        Some(String::from("https://github.com/georust/gpx"))
+    );
+}
+
 #[test]
 fn gpx_writer_write_test_wikipedia() {
     check_write_for_example_file("tests/fixtures/wikipedia_example.gpx");
@@ -24,15 +47,547 @@ fn gpx_writer_write_test_garmin_activity() {
     check_write_for_example_file("tests/fixtures/garmin-activity.gpx");
 }
 
+#[test]
+fn gpx_writer_write_test_lovers_lane() {
+    check_write_for_example_file("tests/fixtures/ecology-trail-and-lovers-lane-loop.gpx");
+}
+
+#[test]
+fn gpx_writer_write_test_gpsies() {
+    check_write_for_example_file("tests/fixtures/gpsies_example.gpx");
+}
+
+#[test]
+fn gpx_writer_write_test_waypoints() {
+    check_write_for_example_file("tests/fixtures/waypoints.gpx");
+}
+
 #[test]
 fn gpx_writer_write_test_with_accuracy() {
     check_write_for_example_file("tests/fixtures/with_accuracy.gpx");
 }
 
+#[test]
+fn gpx_writer_write_test_gpx10() {
+    check_write_for_example_file("tests/fixtures/gpx10_example.gpx");
+}
+
+#[test]
+fn gpx_writer_write_downgraded_to_gpx10() {
+    let reference_gpx = read_test_gpx_file("tests/fixtures/ecology-trail-and-lovers-lane-loop.gpx");
+    let options = WriterOptions {
+        version: Some(GpxVersion::Gpx10),
+        ..Default::default()
+    };
+    let mut buffer: Vec<u8> = Vec::new();
+    write_with_options(&reference_gpx, &mut buffer, &options).unwrap();
+
+    let written_gpx = read(buffer.as_slice()).unwrap();
+    assert_eq!(written_gpx.version, GpxVersion::Gpx10);
+    check_points_equal(&reference_gpx, &written_gpx);
+
+    // The metadata link becomes the url of the file.
+    let reference_link = &reference_gpx.metadata.unwrap().links[0];
+    let written_link = written_gpx.metadata.unwrap().author.unwrap().link.unwrap();
+    assert_eq!(reference_link.href, written_link.href);
+    assert_eq!(reference_link.text, written_link.text);
+}
+
+#[test]
+fn gpx_writer_write_gpx10_without_extensions() {
+    let reference_gpx = read_test_gpx_file("tests/fixtures/garmin-activity.gpx");
+    let options = WriterOptions {
+        version: Some(GpxVersion::Gpx10),
+        ..Default::default()
+    };
+    let mut buffer: Vec<u8> = Vec::new();
+    write_with_options(&reference_gpx, &mut buffer, &options).unwrap();
+
+    // GPX 1.0 has no extensions element to keep them in.
+    let written_gpx = read(buffer.as_slice()).unwrap();
+    let point = &written_gpx.tracks[0].segments[0].points[0];
+    assert!(point.extensions.is_none());
+    assert!(point.track_point_extension.is_none());
+}
+
+#[test]
+fn gpx_writer_write_computed_bounds() {
+    let reference_gpx = read_test_gpx_file("tests/fixtures/gpx10_example.gpx");
+    let options = WriterOptions {
+        compute_bounds: true,
+        ..Default::default()
+    };
+    let mut buffer: Vec<u8> = Vec::new();
+    write_with_options(&reference_gpx, &mut buffer, &options).unwrap();
+
+    // The stale bounds of the file are replaced by those of its points.
+    let written_gpx = read(buffer.as_slice()).unwrap();
+    let bounds = written_gpx.metadata.unwrap().bounds.unwrap();
+    assert_eq!(bounds.min(), Coordinate::from((-71.119277, 42.438878)));
+    assert_eq!(bounds.max(), Coordinate::from((-71.102973, 42.468655)));
+}
+
+#[test]
+fn gpx_writer_write_computed_bounds_without_points() {
+    let gpx = Gpx {
+        version: GpxVersion::Gpx11,
+        ..Default::default()
+    };
+    let options = WriterOptions {
+        compute_bounds: true,
+        ..Default::default()
+    };
+    let mut buffer: Vec<u8> = Vec::new();
+    write_with_options(&gpx, &mut buffer, &options).unwrap();
+
+    let written_gpx = read(buffer.as_slice()).unwrap();
+    assert!(written_gpx.metadata.is_none());
+}
+
+#[test]
+fn gpx_writer_write_used_namespaces() {
+    let reference_gpx = read_test_gpx_file("tests/fixtures/garmin-activity.gpx");
+    let written_gpx = write_and_reread_gpx(&reference_gpx);
+
+    // ns3 is used by the track point extensions, ns2 by nothing.
+    let prefixes: Vec<&str> = written_gpx
+        .namespaces
+        .iter()
+        .map(|(prefix, _)| prefix.as_str())
+        .collect();
+    assert!(prefixes.contains(&"ns3"));
+    assert!(!prefixes.contains(&"ns2"));
+}
+
+#[test]
+fn gpx_writer_write_tricky_text() {
+    let text = "<p>Fish & \"Chips\"</p>\n  <![CDATA[nested]]> it's\t done ";
+    let waypoint = Waypoint::builder()
+        .lat(1.)
+        .lon(2.)
+        .name(text)
+        .comment("short")
+        .description(text)
+        .build()
+        .unwrap();
+    let gpx = Gpx::builder(GpxVersion::Gpx11).waypoint(waypoint).build();
+
+    for cdata_threshold in [None, Some(10)] {
+        let options = WriterOptions {
+            cdata_threshold,
+            ..Default::default()
+        };
+        let mut buffer: Vec<u8> = Vec::new();
+        write_with_options(&gpx, &mut buffer, &options).unwrap();
+
+        let xml = String::from_utf8(buffer).unwrap();
+        assert_eq!(xml.contains("<desc><![CDATA["), cdata_threshold.is_some());
+        assert!(xml.contains("<cmt>short</cmt>"));
+
+        let written_gpx = read(xml.as_bytes()).unwrap();
+        let written = &written_gpx.waypoints[0];
+        assert_eq!(written.name.as_deref(), Some(text));
+        assert_eq!(written.comment.as_deref(), Some("short"));
+        assert_eq!(written.description.as_deref(), Some(text));
+    }
+}
+
+#[test]
+fn gpx_writer_write_compact() {
+    let reference_gpx = read_test_gpx_file("tests/fixtures/wikipedia_example.gpx");
+    let options = WriterOptions {
+        elevation_precision: Some(1),
+        indent: None,
+        xml_declaration: false,
+        ..Default::default()
+    };
+    let mut buffer: Vec<u8> = Vec::new();
+    write_with_options(&reference_gpx, &mut buffer, &options).unwrap();
+
+    let xml = String::from_utf8(buffer).unwrap();
+    assert!(xml.starts_with("<gpx "));
+    assert!(!xml.contains('\n'));
+    assert!(xml.contains("<ele>4.5</ele>"));
+
+    let written_gpx = read(xml.as_bytes()).unwrap();
+    let elevation = written_gpx.tracks[0].segments[0].points[0].elevation;
+    assert_eq!(elevation, Some(4.5));
+}
+
+#[test]
+fn gpx_writer_write_subsecond_times() {
+    let data = "<gpx version=\"1.1\" creator=\"a\"><trk><trkseg>
+        <trkpt lat=\"1\" lon=\"2\"><time>2020-06-01T12:00:00.1Z</time></trkpt>
+        <trkpt lat=\"1\" lon=\"2\"><time>2020-06-01T12:00:00.2Z</time></trkpt>
+        <trkpt lat=\"1\" lon=\"2\"><time>2020-06-01T12:00:00.123456789Z</time></trkpt>
+    </trkseg></trk></gpx>";
+    let reference_gpx = read(data.as_bytes()).unwrap();
+
+    // Times are kept as they are by default.
+    let written_gpx = write_and_reread_gpx(&reference_gpx);
+    assert_eq!(written_gpx.tracks, reference_gpx.tracks);
+
+    let options = WriterOptions {
+        time_precision: Some(2),
+        ..Default::default()
+    };
+    let mut buffer: Vec<u8> = Vec::new();
+    write_with_options(&reference_gpx, &mut buffer, &options).unwrap();
+    let xml = String::from_utf8(buffer).unwrap();
+    assert!(xml.contains("<time>2020-06-01T12:00:00.10+00:00</time>"));
+    assert!(xml.contains("<time>2020-06-01T12:00:00.20+00:00</time>"));
+    assert!(xml.contains("<time>2020-06-01T12:00:00.12+00:00</time>"));
+}
+
+#[test]
+fn gpx_writer_stream_garmin_activity() {
+    let reference_gpx = read_test_gpx_file("tests/fixtures/garmin-activity.gpx");
+
+    // Write the header up front, then every point on its own.
+    let header = Gpx {
+        tracks: Vec::new(),
+        ..reference_gpx.clone()
+    };
+    let mut writer = StreamingWriter::new(Vec::new(), &header).unwrap();
+    for track in &reference_gpx.tracks {
+        let empty_track = Track {
+            segments: Vec::new(),
+            ..track.clone()
+        };
+        writer.start_track(&empty_track).unwrap();
+        for segment in &track.segments {
+            writer.start_segment().unwrap();
+            for point in &segment.points {
+                writer.write_point(point).unwrap();
+            }
+        }
+    }
+    let buffer = writer.finish().unwrap();
+
+    let written_gpx = read(buffer.as_slice()).unwrap();
+    check_metadata_equal(&reference_gpx, &written_gpx);
+    check_points_equal(&reference_gpx, &written_gpx);
+}
+
+#[test]
+fn gpx_writer_stream_without_track() {
+    let header = Gpx {
+        version: GpxVersion::Gpx11,
+        ..Default::default()
+    };
+    let mut writer = StreamingWriter::new(Vec::new(), &header).unwrap();
+    writer.write_point(&Waypoint::default()).unwrap();
+    writer.start_segment().unwrap();
+    writer.write_point(&Waypoint::default()).unwrap();
+    let buffer = writer.finish().unwrap();
+
+    // Points outside of a track start one, with a segment of their own.
+    let written_gpx = read(buffer.as_slice()).unwrap();
+    assert_eq!(written_gpx.tracks.len(), 1);
+    assert_eq!(written_gpx.tracks[0].segments.len(), 2);
+}
+
+#[test]
+fn gpx_writer_write_split_tracks() {
+    let reference_gpx = read_test_gpx_file("tests/fixtures/garmin-activity.gpx");
+
+    let documents = reference_gpx.split_tracks();
+    assert_eq!(documents.len(), reference_gpx.tracks.len());
+    for (document, track) in documents.iter().zip(&reference_gpx.tracks) {
+        let written_gpx = write_and_reread_gpx(document);
+        check_metadata_equal(&reference_gpx, &written_gpx);
+        assert!(written_gpx.waypoints.is_empty());
+        assert_eq!(written_gpx.tracks.len(), 1);
+        assert_eq!(written_gpx.tracks[0].segments.len(), track.segments.len());
+    }
+}
+
+// The sequences of child elements from the GPX 1.1 schema.
+const GPX11_SEQUENCES: &[(&str, &[&str])] = &[
+    ("gpx", &["metadata", "wpt", "rte", "trk", "extensions"]),
+    (
+        "metadata",
+        &[
+            "name",
+            "desc",
+            "author",
+            "copyright",
+            "link",
+            "time",
+            "keywords",
+            "bounds",
+            "extensions",
+        ],
+    ),
+    ("author", &["name", "email", "link"]),
+    ("copyright", &["year", "license"]),
+    ("link", &["text", "type"]),
+    (
+        "wpt",
+        &[
+            "ele",
+            "time",
+            "magvar",
+            "geoidheight",
+            "name",
+            "cmt",
+            "desc",
+            "src",
+            "link",
+            "sym",
+            "type",
+            "fix",
+            "sat",
+            "hdop",
+            "vdop",
+            "pdop",
+            "ageofdgpsdata",
+            "dgpsid",
+            "extensions",
+        ],
+    ),
+    (
+        "rte",
+        &[
+            "name",
+            "cmt",
+            "desc",
+            "src",
+            "link",
+            "number",
+            "type",
+            "extensions",
+            "rtept",
+        ],
+    ),
+    (
+        "trk",
+        &[
+            "name",
+            "cmt",
+            "desc",
+            "src",
+            "link",
+            "number",
+            "type",
+            "extensions",
+            "trkseg",
+        ],
+    ),
+    ("trkseg", &["trkpt", "extensions"]),
+];
+
+// The sequences of child elements from the GPX 1.0 schema.
+const GPX10_SEQUENCES: &[(&str, &[&str])] = &[
+    (
+        "gpx",
+        &[
+            "name", "desc", "author", "email", "url", "urlname", "time", "keywords", "bounds",
+            "wpt", "rte", "trk",
+        ],
+    ),
+    (
+        "wpt",
+        &[
+            "ele",
+            "time",
+            "course",
+            "speed",
+            "magvar",
+            "geoidheight",
+            "name",
+            "cmt",
+            "desc",
+            "src",
+            "url",
+            "urlname",
+            "sym",
+            "type",
+            "fix",
+            "sat",
+            "hdop",
+            "vdop",
+            "pdop",
+            "ageofdgpsdata",
+            "dgpsid",
+        ],
+    ),
+    (
+        "rte",
+        &[
+            "name", "cmt", "desc", "src", "url", "urlname", "number", "rtept",
+        ],
+    ),
+    (
+        "trk",
+        &[
+            "name", "cmt", "desc", "src", "url", "urlname", "number", "trkseg",
+        ],
+    ),
+    ("trkseg", &["trkpt"]),
+];
+
+#[test]
+fn gpx_writer_write_gpx11_schema_order() {
+    let gpx = complete_gpx(GpxVersion::Gpx11);
+    let mut buffer: Vec<u8> = Vec::new();
+    write(&gpx, &mut buffer).unwrap();
+
+    check_schema_order(&buffer, GPX11_SEQUENCES);
+}
+
+#[test]
+fn gpx_writer_write_gpx10_schema_order() {
+    let gpx = complete_gpx(GpxVersion::Gpx10);
+    let mut buffer: Vec<u8> = Vec::new();
+    write(&gpx, &mut buffer).unwrap();
+
+    check_schema_order(&buffer, GPX10_SEQUENCES);
+}
+
+/// Gives a document with every field set, filled in back to front.
+fn complete_gpx(version: GpxVersion) -> Gpx {
+    let link = Link {
+        href: String::from("https://example.com"),
+        text: Some(String::from("Example")),
+        _type: Some(String::from("text/html")),
+    };
+    let waypoint = Waypoint::builder()
+        .extensions("<hr>74</hr>")
+        .dgpsid(DgpsStationId::try_from(12).unwrap())
+        .dgps_age(1.5)
+        .pdop(1.)
+        .vdop(1.)
+        .hdop(1.)
+        .sat(7u64)
+        .fix(Fix::ThreeDimensional)
+        ._type("Crossing")
+        .symbol("Flag, Blue")
+        .link(link.clone())
+        .source("GPS")
+        .description("Crossroads")
+        .comment("Busy")
+        .name("5066")
+        .geoidheight(-32.1)
+        .magvar(Degrees::try_from(13.2).unwrap())
+        .speed(5.2)
+        .course(Degrees::try_from(41.2).unwrap())
+        .time(Utc.with_ymd_and_hms(2004, 4, 18, 7, 12, 43).unwrap())
+        .elevation(44.5)
+        .lat(42.438878)
+        .lon(-71.119277)
+        .build()
+        .unwrap();
+    let segment = TrackSegment {
+        extensions: Some(String::from("<lap>1</lap>")),
+        points: vec![waypoint.clone()],
+    };
+    let track = Track::builder()
+        .extensions("<color>red</color>")
+        ._type("running")
+        .number(1u32)
+        .link(link.clone())
+        .source("GPS")
+        .description("Commute")
+        .comment("Slow")
+        .name("Morning ride")
+        .segment(segment)
+        .build();
+    let route = Route {
+        extensions: Some(String::from("<color>blue</color>")),
+        points: vec![waypoint.clone()],
+        _type: Some(String::from("cycling")),
+        number: Some(2),
+        links: vec![link.clone()],
+        source: Some(String::from("Planner")),
+        description: Some(String::from("Home to work")),
+        comment: Some(String::from("Fast")),
+        name: Some(String::from("Commute")),
+    };
+    let metadata = Metadata {
+        extensions: Some(String::from("<app>1</app>")),
+        bounds: Some(Rect::new((-71.2, 42.4), (-71.1, 42.5))),
+        keywords: Some(String::from("bike, commute")),
+        time: Some(Utc.with_ymd_and_hms(2004, 4, 18, 7, 0, 0).unwrap()),
+        links: vec![link.clone()],
+        copyright: Some(Copyright {
+            license: Some(String::from("CC0")),
+            year: Some(2004),
+            author: String::from("Jane Doe"),
+        }),
+        author: Some(Person {
+            link: Some(link),
+            email: Some(Email {
+                id: String::from("jane"),
+                domain: String::from("example.com"),
+            }),
+            name: Some(String::from("Jane Doe")),
+        }),
+        description: Some(String::from("Rides")),
+        name: Some(String::from("Morning rides")),
+    };
+
+    Gpx {
+        extensions: Some(String::from("<app>2</app>")),
+        ..Gpx::builder(version)
+            .route(route)
+            .track(track)
+            .waypoint(waypoint)
+            .metadata(metadata)
+            .build()
+    }
+}
+
+/// Checks that the children of every element listed in `sequences` come in
+/// the order of its sequence, and that none is missing.
+fn check_schema_order(xml: &[u8], sequences: &[(&str, &[&str])]) {
+    // The name of every open element, with the names of its children.
+    let mut open: Vec<(String, Vec<String>)> = Vec::new();
+    for event in EventReader::new(xml) {
+        match event.unwrap() {
+            XmlEvent::StartElement { name, .. } => {
+                if let Some((_, children)) = open.last_mut() {
+                    children.push(name.local_name.clone());
+                }
+                open.push((name.local_name, Vec::new()));
+            }
+            XmlEvent::EndElement { .. } => {
+                let (name, mut children) = open.pop().unwrap();
+                // Route and track points share the waypoint's sequence.
+                let name = match name.as_str() {
+                    "rtept" | "trkpt" => "wpt",
+                    name => name,
+                };
+                // The extensions are ours, so they don't have a sequence.
+                if open
+                    .last()
+                    .is_some_and(|(parent, _)| parent == "extensions")
+                {
+                    continue;
+                }
+                let sequence = match sequences.iter().find(|(n, _)| *n == name) {
+                    Some((_, sequence)) => sequence,
+                    None => continue,
+                };
+                children.dedup();
+                assert_eq!(&children, sequence, "children of {}", name);
+            }
+            _ => {}
+        }
+    }
+}
+
 fn check_write_for_example_file(filename: &str) {
     let reference_gpx = read_test_gpx_file(filename);
     let written_gpx = write_and_reread_gpx(&reference_gpx);
 
+    assert_eq!(reference_gpx.creator, written_gpx.creator);
+    // Only the namespaces in use are written.
+    for namespace in &written_gpx.namespaces {
+        assert!(reference_gpx.namespaces.contains(namespace));
+    }
+    assert_eq!(reference_gpx.schema_location, written_gpx.schema_location);
+    assert_eq!(reference_gpx.extensions, written_gpx.extensions);
     check_metadata_equal(&reference_gpx, &written_gpx);
     check_points_equal(&reference_gpx, &written_gpx);
 }
@@ -49,7 +604,7 @@ fn read_test_gpx_file(filename: &str) -> Gpx {
 
 fn write_and_reread_gpx(reference_gpx: &Gpx) -> Gpx {
     let mut buffer: Vec<u8> = Vec::new();
-    let result = write(&reference_gpx, &mut buffer);
+    let result = write(reference_gpx, &mut buffer);
     assert!(result.is_ok());
 
     let written_gpx = read(buffer.as_slice()).unwrap();
@@ -68,11 +623,16 @@ fn check_metadata_equal(reference_gpx: &Gpx, written_gpx: &Gpx) {
     let reference = reference.as_ref().unwrap();
     let written = written.as_ref().unwrap();
     assert_eq!(reference.name, written.name);
+    assert_eq!(reference.description, written.description);
     assert_eq!(reference.time, written.time);
+    assert_eq!(reference.keywords, written.keywords);
+    assert_eq!(reference.copyright, written.copyright);
+    assert_eq!(reference.author, written.author);
+    assert_eq!(reference.extensions, written.extensions);
     check_links_equal(&reference.links, &written.links);
 }
 
-fn check_links_equal(reference: &Vec<Link>, written: &Vec<Link>) {
+fn check_links_equal(reference: &[Link], written: &[Link]) {
     assert_eq!(reference.len(), written.len());
     for (r, w) in reference.iter().zip(written) {
         assert_eq!(r.href, w.href);
@@ -85,20 +645,32 @@ fn check_points_equal(reference: &Gpx, written: &Gpx) {
     assert_eq!(reference.tracks.len(), written.tracks.len());
     for (r_track, w_track) in reference.tracks.iter().zip(written.tracks.iter()) {
         assert_eq!(r_track.name, w_track.name);
+        assert_eq!(r_track.number, w_track.number);
+        assert_eq!(r_track.extensions, w_track.extensions);
         assert_eq!(r_track.segments.len(), w_track.segments.len());
         for (r_seg, w_seg) in r_track.segments.iter().zip(w_track.segments.iter()) {
+            assert_eq!(r_seg.extensions, w_seg.extensions);
             check_waypoints_equal(&r_seg.points, &w_seg.points);
         }
     }
+    assert_eq!(reference.routes.len(), written.routes.len());
+    for (r_route, w_route) in reference.routes.iter().zip(written.routes.iter()) {
+        assert_eq!(r_route.name, w_route.name);
+        assert_eq!(r_route.number, w_route.number);
+        assert_eq!(r_route.extensions, w_route.extensions);
+        check_waypoints_equal(&r_route.points, &w_route.points);
+    }
 }
 
-fn check_waypoints_equal(reference: &Vec<Waypoint>, written: &Vec<Waypoint>) {
+fn check_waypoints_equal(reference: &[Waypoint], written: &[Waypoint]) {
     assert_eq!(reference.len(), written.len());
     for (r_wp, w_wp) in reference.iter().zip(written) {
         assert_eq!(r_wp.point(), w_wp.point());
         assert_eq!(r_wp.elevation, w_wp.elevation);
         assert_eq!(r_wp.speed, w_wp.speed);
+        assert_eq!(r_wp.course, w_wp.course);
         assert_eq!(r_wp.time, w_wp.time);
+        assert_eq!(r_wp.magvar, w_wp.magvar);
         assert_eq!(r_wp.geoidheight, w_wp.geoidheight);
         assert_eq!(r_wp.name, w_wp.name);
         assert_eq!(r_wp.comment, w_wp.comment);
@@ -114,5 +686,7 @@ fn check_waypoints_equal(reference: &Vec<Waypoint>, written: &Vec<Waypoint>) {
         assert_eq!(r_wp.pdop, w_wp.pdop);
         assert_eq!(r_wp.dgps_age, w_wp.dgps_age);
         assert_eq!(r_wp.dgpsid, w_wp.dgpsid);
+        assert_eq!(r_wp.extensions, w_wp.extensions);
+        assert_eq!(r_wp.track_point_extension, w_wp.track_point_extension);
     }
 }